@@ -0,0 +1,12 @@
+#![no_main]
+// Bytes arbitrários no decodificador: nenhuma entrada pode causar panic, e toda instrução
+// aceita deve sobreviver a pack -> unpack sem mudar
+use cidacake_program::instruction::CakeInstruction;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(instruction) = CakeInstruction::unpack(data) {
+        let packed = instruction.pack();
+        assert_eq!(CakeInstruction::unpack(&packed).as_ref(), Ok(&instruction));
+    }
+});
@@ -0,0 +1,122 @@
+#![no_main]
+// Executa process_instruction com contas simuladas. As chaves e owners vêm de um conjunto pequeno
+// (programa, PDAs conhecidas, programas do sistema) para que as validações iniciais passem com
+// frequência e o fuzzer alcance o corpo das instruções
+use std::sync::OnceLock;
+
+use arbitrary::Arbitrary;
+use cidacake_program::{pda::*, process_instruction};
+use libfuzzer_sys::fuzz_target;
+use solana_program::{account_info::AccountInfo, entrypoint::MAX_PERMITTED_DATA_INCREASE, pubkey::Pubkey, sysvar};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+#[derive(Debug, Arbitrary)]
+enum FuzzAccount {
+    New { key: u8, owner: u8, lamports: u64, is_signer: bool, is_writable: bool, data: Vec<u8> },
+    // Repete uma conta já listada, como o runtime faz com contas duplicadas
+    Duplicate(u8),
+}
+
+#[derive(Debug, Arbitrary)]
+struct FuzzInput {
+    accounts: Vec<FuzzAccount>,
+    data: Vec<u8>,
+}
+
+// Mesmo layout da serialização do runtime: AccountInfo::original_data_len lê o u32 logo antes da
+// chave, e realloc grava o novo tamanho nos 8 bytes antes dos dados e cresce sobre a folga seguinte
+#[repr(C)]
+struct KeySlot {
+    _padding: u32,
+    original_data_len: u32,
+    key: Pubkey,
+}
+
+struct AccountSlot {
+    key: Box<KeySlot>,
+    owner: Pubkey,
+    lamports: u64,
+    buffer: Vec<u8>,
+    data_len: usize,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl AccountSlot {
+    fn new(key: Pubkey, owner: Pubkey, lamports: u64, is_signer: bool, is_writable: bool, data: &[u8]) -> Self {
+        let mut buffer = vec![0; 8 + data.len() + MAX_PERMITTED_DATA_INCREASE];
+        buffer[..8].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        buffer[8..8 + data.len()].copy_from_slice(data);
+        Self {
+            key: Box::new(KeySlot { _padding: 0, original_data_len: data.len() as u32, key }),
+            owner,
+            lamports,
+            buffer,
+            data_len: data.len(),
+            is_signer,
+            is_writable,
+        }
+    }
+
+    fn account_info(&mut self) -> AccountInfo<'_> {
+        // O slice é derivado do ponteiro do buffer inteiro para que realloc possa usar o cabeçalho e a folga
+        let data = unsafe { std::slice::from_raw_parts_mut(self.buffer.as_mut_ptr().add(8), self.data_len) };
+        AccountInfo::new(&self.key.key, self.is_signer, self.is_writable, &mut self.lamports, data, &self.owner, false, 0)
+    }
+}
+
+fn key_pool() -> &'static [Pubkey] {
+    static POOL: OnceLock<Vec<Pubkey>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        // As PDAs da loja são derivadas da loja 0, que também entra no conjunto
+        let store = find_store_address(0, &PROGRAM_ID).0;
+        vec![
+            PROGRAM_ID,
+            solana_system_interface::program::id(),
+            spl_token::id(),
+            sysvar::clock::id(),
+            store,
+            find_config_address(&store, &PROGRAM_ID).0,
+            find_global_stats_address(&PROGRAM_ID).0,
+            find_product_address(&store, 0, &PROGRAM_ID).0,
+            find_product_address(&store, 1, &PROGRAM_ID).0,
+            find_escrow_authority_address(&PROGRAM_ID).0,
+            find_treasury_authority_address(&PROGRAM_ID).0,
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+            Pubkey::new_from_array([3; 32]),
+        ]
+    })
+}
+
+fuzz_target!(|input: FuzzInput| {
+    let pool = key_pool();
+    let pick = |index: u8| pool[index as usize % pool.len()];
+
+    let mut slots: Vec<AccountSlot> = input
+        .accounts
+        .iter()
+        .filter_map(|account| match account {
+            FuzzAccount::New { key, owner, lamports, is_signer, is_writable, data } => {
+                Some(AccountSlot::new(pick(*key), pick(*owner), *lamports, *is_signer, *is_writable, data))
+            }
+            FuzzAccount::Duplicate(_) => None,
+        })
+        .collect();
+    let mut created = slots.iter_mut().map(AccountSlot::account_info);
+
+    let mut accounts: Vec<AccountInfo> = Vec::with_capacity(input.accounts.len());
+    for account in &input.accounts {
+        match account {
+            FuzzAccount::New { .. } => accounts.extend(created.next()),
+            FuzzAccount::Duplicate(index) if !accounts.is_empty() => {
+                let duplicate = accounts[*index as usize % accounts.len()].clone();
+                accounts.push(duplicate);
+            }
+            FuzzAccount::Duplicate(_) => {}
+        }
+    }
+
+    let _ = process_instruction(&PROGRAM_ID, &accounts, &input.data);
+});
@@ -0,0 +1,605 @@
+// CLI para administrar uma loja CidaCake: lê a carteira e a URL do RPC dos argumentos ou da
+// configuração do Solana CLI e monta as transações com os builders de cidacake_program::client
+use std::{error::Error, path::PathBuf};
+
+use cidacake_program::{
+    client::{self, SellAccounts, SellOptions, Store},
+    layout,
+    pda::{
+        find_buyer_history_address, find_buyer_history_counter_address, find_config_address, find_history_head_address,
+        find_product_address, find_product_tags_address, find_purchase_index_address, find_store_address, find_tag_index_address,
+        store_revenue_address,
+    },
+    to_ui_amount, BuyerHistoryCounter, CakeError, CakeState, Config, HistoryPageHead, Localization, Product, ProductPurchaseIndex,
+    ProductTags, PurchaseHistory, TagIndex, LANG_CODE_LEN, PAYMENT_MODE_CONFIDENTIAL, PAYMENT_MODE_SOL, PAYMENT_MODE_TOKEN, TAG_LEN,
+};
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use solana_client::{
+    client_error::ClientError,
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "cidacake", about = "Administra o estoque e as vendas de uma loja CidaCake")]
+struct Cli {
+    /// URL do RPC; padrão: json_rpc_url da configuração do Solana CLI
+    #[arg(short, long, global = true)]
+    url: Option<String>,
+    /// Carteira que assina e paga as transações; padrão: keypair_path da configuração do Solana CLI
+    #[arg(short, long, global = true)]
+    keypair: Option<PathBuf>,
+    /// Arquivo de configuração do Solana CLI; padrão: ~/.config/solana/cli/config.yml
+    #[arg(short = 'C', long, global = true)]
+    config: Option<PathBuf>,
+    /// Endereço do programa implantado
+    #[arg(long, env = "CIDACAKE_PROGRAM_ID", global = true)]
+    program_id: Option<Pubkey>,
+    /// Endereço da loja (PDA ["store", store_id] impressa pelo init)
+    #[arg(long, env = "CIDACAKE_STORE", global = true)]
+    store: Option<Pubkey>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Cria a loja na PDA ["store", store_id] com a carteira como owner
+    Init {
+        /// Identificador da loja; cada id gera uma loja com produtos, histórico e configuração próprios
+        #[arg(long, default_value_t = 0)]
+        store_id: u64,
+        /// Mint da conta de receita da loja, para onde vão os pagamentos das vendas
+        #[arg(long)]
+        revenue_mint: Pubkey,
+        /// Programa de token do mint de receita
+        #[arg(long, default_value_t = spl_token::id())]
+        token_program: Pubkey,
+    },
+    /// Cadastra um produto com o próximo id
+    AddProduct {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        #[arg(long)]
+        price: u64,
+        #[arg(long)]
+        stock: u64,
+        #[arg(long, default_value_t = 0)]
+        max_per_buyer: u64,
+        /// Tag do produto ("vegano", "sem-gluten"); pode ser repetida
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Lista os produtos cadastrados
+    ListProducts {
+        /// Mostra nome e descrição traduzidos para o idioma, quando houver tradução (por exemplo en ou pt-BR)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Só produtos com a tag; repetido, só os que têm todas
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Substitui as tags de um produto; sem --tag, remove todas
+    SetTags {
+        #[arg(long)]
+        product_id: u64,
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Cria ou substitui a tradução do nome e da descrição de um produto
+    SetLocalization {
+        #[arg(long)]
+        product_id: u64,
+        /// Idioma da tradução, por exemplo en ou pt-BR; só a língua principal é usada
+        #[arg(long)]
+        lang: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// Apaga a tradução de um produto e devolve o rent ao owner
+    RemoveLocalization {
+        #[arg(long)]
+        product_id: u64,
+        #[arg(long)]
+        lang: String,
+    },
+    /// Vende um produto; a carteira assina como authority e, sem --buyer, também como comprador
+    Sell {
+        #[arg(long)]
+        product_id: u64,
+        #[arg(long)]
+        amount: u64,
+        /// Keypair do comprador
+        #[arg(long)]
+        buyer: Option<PathBuf>,
+        /// A carteira da loja paga o rent do histórico em vez do comprador
+        #[arg(long, requires = "buyer")]
+        store_pays_rent: bool,
+        /// Mint do pagamento; padrão: payment_mint da configuração
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Programa de token do mint
+        #[arg(long, default_value_t = spl_token::id())]
+        token_program: Pubkey,
+        /// Conta de token do comprador; padrão: ATA do comprador
+        #[arg(long)]
+        buyer_token: Option<Pubkey>,
+        /// Conta de receita da loja; padrão: a do mint de pagamento
+        #[arg(long)]
+        owner_token: Option<Pubkey>,
+        /// Paga em SOL em vez de tokens
+        #[arg(long)]
+        sol: bool,
+        #[arg(long)]
+        coupon: Option<String>,
+        #[arg(long)]
+        variant_id: Option<u8>,
+        /// Retém o pagamento no cofre de escrow até a entrega
+        #[arg(long)]
+        escrow: bool,
+        /// Grava a venda no histórico paginado em vez de criar uma conta de histórico
+        #[arg(long, conflicts_with = "escrow")]
+        paged_history: bool,
+        /// Recado do comprador gravado junto com a compra, por exemplo o texto do bolo
+        #[arg(long, conflicts_with = "paged_history")]
+        memo: Option<String>,
+        /// Código de indicação; credita ao referrer a parte configurada da venda
+        #[arg(long, conflicts_with_all = ["escrow", "sol"])]
+        referral: Option<String>,
+        /// Gorjeta em unidades do mint, paga à parte para a conta de gorjetas da loja
+        #[arg(long, default_value_t = 0, conflicts_with_all = ["escrow", "sol", "paged_history"])]
+        tip: u64,
+        /// Arredonda o total para cima e doa a diferença para a conta de caridade da loja
+        #[arg(long, conflicts_with_all = ["escrow", "sol", "paged_history"])]
+        round_up: bool,
+        /// Paga com wSOL: deposita esses lamports na ATA wSOL do comprador e a fecha depois da compra
+        #[arg(long, conflicts_with_all = ["sol", "mint", "buyer_token"])]
+        wrap_sol: Option<u64>,
+        /// Falha se o total passar desse valor (unidades do mint ou lamports), por exemplo após uma mudança de preço
+        #[arg(long, default_value_t = 0)]
+        max_total_price: u64,
+    },
+    /// Repõe o estoque de um produto ou de uma variante
+    Restock {
+        #[arg(long)]
+        product_id: u64,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        variant_id: Option<u8>,
+    },
+    /// Lista o histórico de compras, opcionalmente de um comprador ou de um produto
+    History {
+        #[arg(long, conflicts_with = "product_id")]
+        buyer: Option<Pubkey>,
+        #[arg(long)]
+        product_id: Option<u64>,
+    },
+}
+
+// Campos usados do config.yml do Solana CLI
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SolanaConfig {
+    json_rpc_url: Option<String>,
+    keypair_path: Option<PathBuf>,
+}
+
+impl SolanaConfig {
+    // Sem -C, um config.yml ausente não é erro
+    fn load(path: Option<PathBuf>) -> CliResult<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path, true),
+            None => (home_dir().join(".config/solana/cli/config.yml"), false),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(serde_yaml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?),
+            Err(err) if explicit => Err(format!("não foi possível ler {}: {}", path.display(), err).into()),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+struct Context {
+    rpc: RpcClient,
+    payer: Keypair,
+    program_id: Pubkey,
+    store: Option<Pubkey>,
+}
+
+impl Context {
+    fn new(cli: &Cli) -> CliResult<Self> {
+        let solana_config = SolanaConfig::load(cli.config.clone())?;
+        let url = cli.url.clone().or(solana_config.json_rpc_url).unwrap_or_else(|| "https://api.devnet.solana.com".to_string());
+        let keypair_path =
+            cli.keypair.clone().or(solana_config.keypair_path).unwrap_or_else(|| home_dir().join(".config/solana/id.json"));
+        let payer = read_keypair(&keypair_path)?;
+        let program_id = cli.program_id.ok_or("informe --program-id ou CIDACAKE_PROGRAM_ID")?;
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+            payer,
+            program_id,
+            store: cli.store,
+        })
+    }
+
+    fn store_address(&self) -> CliResult<Pubkey> {
+        Ok(self.store.ok_or("informe --store ou CIDACAKE_STORE")?)
+    }
+
+    fn load_store(&self) -> CliResult<Store> {
+        let cake = self.store_address()?;
+        let data = self.rpc.get_account_data(&cake)?;
+        let state = match CakeState::unpack(&data) {
+            Ok(state) => state,
+            Err(_) => CakeState::unpack_legacy(&data)?,
+        };
+        // Sem a conta de configuração o programa usa os valores zerados
+        let config_address = find_config_address(&cake, &self.program_id).0;
+        let config = match self.rpc.get_account_with_commitment(&config_address, self.rpc.commitment())?.value {
+            Some(account) => Config::unpack_versioned(&account.data)?,
+            None => Config::unpack(&[0; Config::LEN])?,
+        };
+        Ok(Store { cake, state, config })
+    }
+
+    fn load_product(&self, product_id: u64) -> CliResult<Product> {
+        let data = self.rpc.get_account_data(&find_product_address(&self.store_address()?, product_id, &self.program_id).0)?;
+        Ok(Product::unpack_versioned(&data)?)
+    }
+
+    // Quantos históricos o comprador já tem; sem o contador ainda não houve compra
+    fn load_history_count(&self, buyer: &Pubkey) -> CliResult<u64> {
+        let counter = find_buyer_history_counter_address(&self.store_address()?, buyer, &self.program_id).0;
+        Ok(match self.rpc.get_account_with_commitment(&counter, self.rpc.commitment())?.value {
+            Some(account) => BuyerHistoryCounter::unpack(&account.data)?.count,
+            None => 0,
+        })
+    }
+
+    fn load_history_page_head(&self) -> CliResult<HistoryPageHead> {
+        let head = find_history_head_address(&self.store_address()?, &self.program_id).0;
+        Ok(match self.rpc.get_account_with_commitment(&head, self.rpc.commitment())?.value {
+            Some(account) => HistoryPageHead::unpack(&account.data)?,
+            None => HistoryPageHead::default(),
+        })
+    }
+
+    // Lê os históricos pelos endereços, em lotes do get_multiple_accounts; contas fechadas são ignoradas
+    fn load_histories(&self, addresses: &[Pubkey]) -> CliResult<Vec<(Pubkey, PurchaseHistory)>> {
+        let mut histories = Vec::new();
+        for chunk in addresses.chunks(100) {
+            for (address, account) in chunk.iter().zip(self.rpc.get_multiple_accounts(chunk)?) {
+                if let Some(history) = account.and_then(|account| PurchaseHistory::unpack_versioned(&account.data).ok()) {
+                    histories.push((*address, history));
+                }
+            }
+        }
+        Ok(histories)
+    }
+
+    fn send(&self, instructions: &[Instruction], extra_signers: &[&Keypair]) -> CliResult<()> {
+        let mut signers = vec![&self.payer];
+        signers.extend(extra_signers.iter().filter(|signer| signer.pubkey() != self.payer.pubkey()));
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.payer.pubkey()), &signers, blockhash);
+        let signature = self.rpc.send_and_confirm_transaction(&transaction).map_err(describe_error)?;
+        println!("Assinatura: {}", signature);
+        Ok(())
+    }
+}
+
+// Falhas do programa chegam como Custom(n); a tabela de CakeError traduz o código para a mensagem
+fn describe_error(err: ClientError) -> Box<dyn Error> {
+    if let Some(TransactionError::InstructionError(index, InstructionError::Custom(code))) = err.get_transaction_error() {
+        if let Some(error) = CakeError::from_code(code) {
+            return format!("instrução {} falhou com {:?} (código {}): {}", index, error, code, error).into();
+        }
+    }
+    err.into()
+}
+
+fn parse_lang(lang: &str) -> CliResult<[u8; LANG_CODE_LEN]> {
+    Ok(client::lang_code(lang).ok_or_else(|| format!("idioma inválido: {:?}; use um código como en ou pt-BR", lang))?)
+}
+
+fn parse_tag(tag: &str) -> CliResult<[u8; TAG_LEN]> {
+    Ok(client::tag(tag).ok_or_else(|| format!("tag inválida: {:?}; use de 1 a {} bytes", tag, TAG_LEN))?)
+}
+
+fn read_keypair(path: &PathBuf) -> CliResult<Keypair> {
+    read_keypair_file(path).map_err(|err| format!("não foi possível ler {}: {}", path.display(), err).into())
+}
+
+// name e description vêm da tradução escolhida ou do próprio produto
+fn print_product(product: &Product, name: &str, description: &str) {
+    println!("Produto #{}: {}", product.id, name);
+    if !description.is_empty() {
+        println!("  descrição:     {}", description);
+    }
+    if product.price_decimals > 0 {
+        println!("  preço:         {}", to_ui_amount(product.price, product.price_decimals));
+    } else {
+        println!("  preço:         {}", product.price);
+    }
+    if product.sol_price > 0 {
+        println!("  preço em SOL:  {} lamports", product.sol_price);
+    }
+    if product.usd_price_cents > 0 {
+        println!("  preço em USD:  {}.{:02}", product.usd_price_cents / 100, product.usd_price_cents % 100);
+    }
+    println!("  estoque:       {}", product.stock);
+    println!("  ativo:         {}", if product.active && product.available { "sim" } else { "não" });
+    if product.max_per_buyer > 0 {
+        println!("  limite:        {} por comprador", product.max_per_buyer);
+    }
+    println!("  vendidos:      {} (receita {}, {} lamports)", product.units_sold, product.revenue, product.sol_revenue);
+}
+
+fn print_history(address: &Pubkey, history: &PurchaseHistory) {
+    println!("Compra {}", address);
+    match history.bundle_id() {
+        Some(bundle_id) => println!("  combo:         #{}", bundle_id),
+        None => println!("  produto:       #{}", history.product_id),
+    }
+    println!("  comprador:     {}", history.buyer);
+    println!("  quantidade:    {} (reembolsados {})", history.quantity, history.refunded_quantity);
+    let unit = if history.payment_mode == PAYMENT_MODE_SOL { " lamports" } else { "" };
+    if history.payment_mode == PAYMENT_MODE_CONFIDENTIAL {
+        println!("  total:         confidencial");
+    } else {
+        println!("  total:         {}{} (taxa {})", history.total_price, unit, history.fee);
+    }
+    // Recibo gravado na venda; históricos antigos e checkouts não têm
+    if history.unit_price > 0 {
+        println!("  preço unit.:   {}{}", history.unit_price, unit);
+    }
+    if history.name_hash != [0; 32] {
+        println!("  nome (sha256): {}", history.name_hash.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    }
+    if history.tip > 0 {
+        println!("  gorjeta:       {}", history.tip);
+    }
+    if history.donation > 0 {
+        println!("  doação:        {}", history.donation);
+    }
+    println!("  status:        {}", history.status);
+    println!("  data:          {}", history.timestamp);
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let ctx = Context::new(&cli)?;
+    let program_id = ctx.program_id;
+    match cli.command {
+        Command::Init { store_id, revenue_mint, token_program } => {
+            let payer = ctx.payer.pubkey();
+            let ix = client::create_store_ix(&program_id, store_id, &payer, &payer, &revenue_mint, &token_program);
+            ctx.send(&[ix], &[])?;
+            let store = find_store_address(store_id, &program_id).0;
+            println!("Loja #{}: {}", store_id, store);
+            println!("Receita:  {}", store_revenue_address(&store, &revenue_mint, &token_program, &program_id));
+        }
+        Command::AddProduct { name, description, price, stock, max_per_buyer, tag } => {
+            let store = ctx.load_store()?;
+            let product_id = store.state.product_counter;
+            let tags = tag.iter().map(|tag| parse_tag(tag)).collect::<CliResult<Vec<_>>>()?;
+            let payer = ctx.payer.pubkey();
+            let ix = client::add_product_ix(&program_id, &store, &payer, &name, &description, price, stock, max_per_buyer, &tags);
+            ctx.send(&[ix], &[])?;
+            println!("Produto #{}: {}", product_id, find_product_address(&store.cake, product_id, &program_id).0);
+        }
+        Command::ListProducts { lang, tag } => {
+            let store = ctx.load_store()?;
+            let preferred: Vec<[u8; LANG_CODE_LEN]> = lang.map(|lang| parse_lang(&lang)).transpose()?.into_iter().collect();
+            let product_ids: Vec<u64> = if tag.is_empty() {
+                (0..store.state.product_counter).collect()
+            } else {
+                let tags = tag.iter().map(|tag| parse_tag(tag)).collect::<CliResult<Vec<_>>>()?;
+                let index_addresses: Vec<Pubkey> = tags.iter().map(|tag| find_tag_index_address(&store.cake, tag, &program_id).0).collect();
+                // Uma tag sem índice ainda não tem produtos
+                let mut indexes = Vec::new();
+                for account in ctx.rpc.get_multiple_accounts(&index_addresses)? {
+                    match account {
+                        Some(account) => indexes.push(TagIndex::unpack(&account.data)?),
+                        None => indexes.push(TagIndex { tag: [0; TAG_LEN], product_ids: Vec::new() }),
+                    }
+                }
+                client::products_with_tags(&indexes)
+            };
+            let addresses: Vec<Pubkey> = product_ids.iter().map(|id| find_product_address(&store.cake, *id, &program_id).0).collect();
+            // getMultipleAccounts aceita no máximo 100 contas por chamada
+            for chunk in addresses.chunks(100) {
+                let mut products = Vec::new();
+                for account in ctx.rpc.get_multiple_accounts(chunk)?.into_iter().flatten() {
+                    match Product::unpack_versioned(&account.data) {
+                        Ok(product) => products.push(product),
+                        Err(err) => eprintln!("Conta de produto ilegível: {}", err),
+                    }
+                }
+                let localization_addresses: Vec<Pubkey> = products
+                    .iter()
+                    .flat_map(|product| client::localization_addresses(&program_id, &store.cake, product.id, &preferred))
+                    .collect();
+                let mut localizations = Vec::new();
+                for localization_chunk in localization_addresses.chunks(100) {
+                    let accounts = ctx.rpc.get_multiple_accounts(localization_chunk)?;
+                    localizations.extend(accounts.into_iter().flatten().filter_map(|account| Localization::unpack(&account.data).ok()));
+                }
+                for product in &products {
+                    let (name, description) = client::resolve_localization(product, &localizations, &preferred);
+                    print_product(product, name, description);
+                }
+            }
+        }
+        Command::SetLocalization { product_id, lang, name, description } => {
+            let store = ctx.load_store()?;
+            let lang_code = parse_lang(&lang)?;
+            let ix = client::set_localization_ix(&program_id, &store, &ctx.payer.pubkey(), product_id, &lang_code, &name, &description);
+            ctx.send(&[ix], &[])?;
+            println!("Tradução {} do produto #{} gravada", String::from_utf8_lossy(&lang_code), product_id);
+        }
+        Command::SetTags { product_id, tag } => {
+            let store = ctx.load_store()?;
+            let tags = tag.iter().map(|tag| parse_tag(tag)).collect::<CliResult<Vec<_>>>()?;
+            let product_tags = find_product_tags_address(&store.cake, product_id, &program_id).0;
+            let current_tags = match ctx.rpc.get_account_with_commitment(&product_tags, ctx.rpc.commitment())?.value {
+                Some(account) => ProductTags::unpack(&account.data)?.tags,
+                None => Vec::new(),
+            };
+            ctx.send(&[client::set_product_tags_ix(&program_id, &store, &ctx.payer.pubkey(), product_id, &current_tags, &tags)], &[])?;
+            println!("Produto #{}: {} tag(s)", product_id, tags.len());
+        }
+        Command::RemoveLocalization { product_id, lang } => {
+            let store = ctx.load_store()?;
+            let lang_code = parse_lang(&lang)?;
+            ctx.send(&[client::remove_localization_ix(&program_id, &store, product_id, &lang_code)], &[])?;
+            println!("Tradução {} do produto #{} apagada", String::from_utf8_lossy(&lang_code), product_id);
+        }
+        Command::Sell {
+            product_id,
+            amount,
+            buyer,
+            store_pays_rent,
+            mint,
+            token_program,
+            buyer_token,
+            owner_token,
+            sol,
+            coupon,
+            variant_id,
+            escrow,
+            paged_history,
+            memo,
+            referral,
+            tip,
+            round_up,
+            wrap_sol,
+            max_total_price,
+        } => {
+            let store = ctx.load_store()?;
+            let product = ctx.load_product(product_id)?;
+            let buyer = buyer.map(|path| read_keypair(&path)).transpose()?;
+            let buyer_key = buyer.as_ref().map_or(ctx.payer.pubkey(), |buyer| buyer.pubkey());
+            let payment_mint =
+                if wrap_sol.is_some() { client::native_mint(&token_program) } else { mint.unwrap_or(store.config.payment_mint) };
+            let accounts = SellAccounts {
+                buyer: buyer_key,
+                payer: store_pays_rent.then(|| ctx.payer.pubkey()),
+                buyer_token: buyer_token
+                    .unwrap_or_else(|| get_associated_token_address_with_program_id(&buyer_key, &payment_mint, &token_program)),
+                owner_token: owner_token.unwrap_or_else(|| store_revenue_address(&store.cake, &payment_mint, &token_program, &program_id)),
+                token_program,
+                payment_mint,
+                gate_token: (product.gate_mint != Pubkey::default())
+                    .then(|| get_associated_token_address_with_program_id(&buyer_key, &product.gate_mint, &spl_token::id())),
+                price_feed: (sol && product.usd_price_cents != 0).then_some(product.price_feed),
+            };
+            let options = SellOptions {
+                coupon_code: coupon.map(String::into_bytes),
+                variant_id,
+                escrow,
+                payment_mode: if sol { PAYMENT_MODE_SOL } else { PAYMENT_MODE_TOKEN },
+                paged_history,
+                memo: memo.map(String::into_bytes),
+                referral_code: referral.map(String::into_bytes),
+                tip,
+                round_up,
+                sync_native: wrap_sol.is_some(),
+                close_wsol: wrap_sol.is_some(),
+                max_total_price,
+                ..Default::default()
+            };
+            let history_sequence =
+                if paged_history { ctx.load_history_page_head()?.write_page() } else { ctx.load_history_count(&buyer_key)? };
+            let mut instructions = match wrap_sol {
+                Some(lamports) => client::wrap_sol_ixs(&ctx.payer.pubkey(), &buyer_key, &token_program, lamports),
+                None => Vec::new(),
+            };
+            instructions.push(client::sell_ix(&program_id, &store, &product, amount, history_sequence, &accounts, &options));
+            let signers: Vec<&Keypair> = buyer.iter().collect();
+            ctx.send(&instructions, &signers)?;
+        }
+        Command::Restock { product_id, amount, variant_id } => {
+            let store = ctx.load_store()?;
+            ctx.send(&[client::restock_ix(&program_id, &store, &ctx.payer.pubkey(), product_id, amount, variant_id)], &[])?;
+            let product = ctx.load_product(product_id)?;
+            print_product(&product, &product.name, &product.description);
+        }
+        Command::History { buyer: Some(buyer), .. } => {
+            // Os endereços saem do contador do comprador, sem varrer as contas do programa; históricos
+            // anteriores ao contador só aparecem na listagem sem --buyer
+            let store = ctx.store_address()?;
+            let addresses: Vec<Pubkey> = (0..ctx.load_history_count(&buyer)?)
+                .map(|sequence| find_buyer_history_address(&store, &buyer, sequence, &program_id).0)
+                .collect();
+            let histories = ctx.load_histories(&addresses)?;
+            for (address, history) in &histories {
+                print_history(address, history);
+            }
+            println!("{} compra(s)", histories.len());
+        }
+        Command::History { product_id: Some(product_id), .. } => {
+            // O índice do produto guarda (comprador, sequência) de cada venda feita pelo sell
+            let store = ctx.store_address()?;
+            let index_address = find_purchase_index_address(&store, product_id, &program_id).0;
+            let index = match ctx.rpc.get_account_with_commitment(&index_address, ctx.rpc.commitment())?.value {
+                Some(account) => ProductPurchaseIndex::unpack(&account.data)?.entries,
+                None => Vec::new(),
+            };
+            let addresses: Vec<Pubkey> =
+                index.iter().map(|entry| find_buyer_history_address(&store, &entry.buyer, entry.sequence, &program_id).0).collect();
+            let histories = ctx.load_histories(&addresses)?;
+            for (address, history) in &histories {
+                print_history(address, history);
+            }
+            println!("{} compra(s)", histories.len());
+        }
+        Command::History { buyer: None, product_id: None } => {
+            // Filtra pelo discriminador; históricos antigos, gravados sem discriminador, não aparecem
+            let config = RpcProgramAccountsConfig {
+                filters: Some(layout::filters::purchase_history_filters()),
+                account_config: RpcAccountInfoConfig { commitment: Some(ctx.rpc.commitment()), ..Default::default() },
+                ..Default::default()
+            };
+            let mut histories: Vec<(Pubkey, PurchaseHistory)> = ctx
+                .rpc
+                .get_program_accounts_with_config(&program_id, config)?
+                .into_iter()
+                .filter_map(|(address, account)| PurchaseHistory::unpack_versioned(&account.data).ok().map(|history| (address, history)))
+                .collect();
+            histories.sort_by_key(|(_, history)| history.timestamp);
+            for (address, history) in &histories {
+                print_history(address, history);
+            }
+            println!("{} compra(s)", histories.len());
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Erro: {}", err);
+        std::process::exit(1);
+    }
+}
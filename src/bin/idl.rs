@@ -0,0 +1,25 @@
+// Gera o IDL em JSON a partir das anotações do shank em src/ (instruções, contas e tipos).
+// Uso: cargo run --features idl --bin idl -- [program_id] [arquivo de saída]
+use std::{env, fs, path::Path};
+
+use shank_idl::{extract_idl, ParseIdlOpts};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let program_id = args.next();
+    let out_path = args.next().unwrap_or_else(|| "idl/cidacake_program.json".to_string());
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_path = manifest_dir.join("src").join("lib.rs");
+    // O programa não usa declare_id!; o endereço entra no IDL apenas quando informado
+    let opts = ParseIdlOpts { require_program_address: false, program_address_override: program_id, ..Default::default() };
+    let idl = extract_idl(&lib_path.to_string_lossy(), opts)?.ok_or("nenhuma instrução anotada encontrada")?;
+
+    let out_path = manifest_dir.join(out_path);
+    if let Some(dir) = out_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&out_path, idl.try_into_json()?)?;
+    println!("IDL gerado em {}", out_path.display());
+    Ok(())
+}
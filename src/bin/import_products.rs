@@ -0,0 +1,285 @@
+// Importa um catálogo de produtos de um arquivo CSV ou JSON para uma loja CidaCake. Valida todas as
+// linhas antes de enviar, cria os produtos em lotes de AddProducts e repete os lotes que falharem
+use std::{error::Error, path::PathBuf, thread, time::Duration};
+
+use cidacake_program::{
+    client::{self, Store},
+    instruction::NewProduct,
+    pda::{find_config_address, find_product_address},
+    CakeState, Config, MAX_PRODUCT_DESCRIPTION_LEN, MAX_PRODUCT_NAME_LEN,
+};
+use clap::Parser;
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+// Contas fixas antes das PDAs dos produtos no AddProducts
+const ADD_PRODUCTS_FIXED_ACCOUNTS: usize = 4;
+
+#[derive(Parser)]
+#[command(name = "import-products", about = "Cadastra numa loja CidaCake os produtos de um arquivo CSV ou JSON")]
+struct Cli {
+    /// Catálogo: CSV com cabeçalho name,description,price,stock[,max_per_buyer] ou JSON com uma lista de objetos
+    /// com os mesmos campos
+    file: PathBuf,
+    /// URL do RPC; padrão: json_rpc_url da configuração do Solana CLI
+    #[arg(short, long)]
+    url: Option<String>,
+    /// Carteira do owner, que assina e paga as transações; padrão: keypair_path da configuração do Solana CLI
+    #[arg(short, long)]
+    keypair: Option<PathBuf>,
+    /// Arquivo de configuração do Solana CLI; padrão: ~/.config/solana/cli/config.yml
+    #[arg(short = 'C', long)]
+    config: Option<PathBuf>,
+    /// Endereço do programa implantado
+    #[arg(long, env = "CIDACAKE_PROGRAM_ID")]
+    program_id: Pubkey,
+    /// Endereço da loja (PDA ["store", store_id] impressa pelo init)
+    #[arg(long, env = "CIDACAKE_STORE")]
+    store: Pubkey,
+    /// Tentativas por lote antes de desistir
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+    /// Só valida o catálogo e mostra os endereços, sem enviar transações
+    #[arg(long)]
+    dry_run: bool,
+}
+
+// Campos usados do config.yml do Solana CLI
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SolanaConfig {
+    json_rpc_url: Option<String>,
+    keypair_path: Option<PathBuf>,
+}
+
+impl SolanaConfig {
+    // Sem -C, um config.yml ausente não é erro
+    fn load(path: Option<PathBuf>) -> CliResult<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path, true),
+            None => (home_dir().join(".config/solana/cli/config.yml"), false),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(serde_yaml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?),
+            Err(err) if explicit => Err(format!("não foi possível ler {}: {}", path.display(), err).into()),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+// Linha do catálogo; description e max_per_buyer podem faltar
+#[derive(Deserialize)]
+struct CatalogueEntry {
+    name: String,
+    #[serde(default)]
+    description: String,
+    price: u64,
+    stock: u64,
+    #[serde(default)]
+    max_per_buyer: u64,
+}
+
+impl CatalogueEntry {
+    fn validate(self, line: usize) -> CliResult<NewProduct> {
+        if self.name.is_empty() || self.name.len() > MAX_PRODUCT_NAME_LEN {
+            return Err(format!("item {}: name precisa ter de 1 a {} bytes", line, MAX_PRODUCT_NAME_LEN).into());
+        }
+        if self.description.len() > MAX_PRODUCT_DESCRIPTION_LEN {
+            return Err(format!("item {}: description passa de {} bytes", line, MAX_PRODUCT_DESCRIPTION_LEN).into());
+        }
+        if self.price == 0 {
+            return Err(format!("item {}: price precisa ser maior que zero", line).into());
+        }
+        Ok(NewProduct {
+            name: self.name,
+            description: self.description,
+            price: self.price,
+            stock: self.stock,
+            max_per_buyer: self.max_per_buyer,
+        })
+    }
+}
+
+// Campos de uma linha CSV; aspas permitem vírgulas no texto e "" vira uma aspa
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_csv(contents: &str) -> CliResult<Vec<CatalogueEntry>> {
+    let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or("catálogo vazio")?;
+    let header: Vec<String> = split_csv_line(header).into_iter().map(|field| field.trim().to_string()).collect();
+    let column = |name: &str| header.iter().position(|field| field == name);
+    let name_column = column("name").ok_or("cabeçalho sem a coluna name")?;
+    let price_column = column("price").ok_or("cabeçalho sem a coluna price")?;
+    let stock_column = column("stock").ok_or("cabeçalho sem a coluna stock")?;
+    let description_column = column("description");
+    let max_per_buyer_column = column("max_per_buyer");
+
+    let mut entries = Vec::new();
+    for (index, line) in lines {
+        let fields = split_csv_line(line);
+        if fields.len() != header.len() {
+            return Err(format!("linha {}: {} colunas, esperado {}", index + 1, fields.len(), header.len()).into());
+        }
+        let number = |column: usize| -> CliResult<u64> {
+            let value = fields[column].trim();
+            value.parse().map_err(|_| format!("linha {}: {} não é um inteiro: {:?}", index + 1, header[column], value).into())
+        };
+        entries.push(CatalogueEntry {
+            name: fields[name_column].trim().to_string(),
+            description: description_column.map(|column| fields[column].trim().to_string()).unwrap_or_default(),
+            price: number(price_column)?,
+            stock: number(stock_column)?,
+            max_per_buyer: max_per_buyer_column.map(number).transpose()?.unwrap_or(0),
+        });
+    }
+    Ok(entries)
+}
+
+// O formato vem da extensão; qualquer coisa além de .json é lida como CSV
+fn load_catalogue(path: &PathBuf) -> CliResult<Vec<NewProduct>> {
+    let contents = std::fs::read_to_string(path).map_err(|err| format!("não foi possível ler {}: {}", path.display(), err))?;
+    let entries = if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+        serde_json::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?
+    } else {
+        parse_csv(&contents)?
+    };
+    entries.into_iter().enumerate().map(|(index, entry)| entry.validate(index + 1)).collect()
+}
+
+struct Importer {
+    rpc: RpcClient,
+    owner: Keypair,
+    program_id: Pubkey,
+    store: Pubkey,
+}
+
+impl Importer {
+    fn load_store(&self) -> CliResult<Store> {
+        let data = self.rpc.get_account_data(&self.store)?;
+        let state = match CakeState::unpack(&data) {
+            Ok(state) => state,
+            Err(_) => CakeState::unpack_legacy(&data)?,
+        };
+        let config_address = find_config_address(&self.store, &self.program_id).0;
+        let config = match self.rpc.get_account_with_commitment(&config_address, self.rpc.commitment())?.value {
+            Some(account) => Config::unpack_versioned(&account.data)?,
+            None => Config::unpack(&[0; Config::LEN])?,
+        };
+        Ok(Store { cake: self.store, state, config })
+    }
+
+    fn send(&self, instruction: &Instruction) -> CliResult<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let instructions = std::slice::from_ref(instruction);
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.owner.pubkey()), &[&self.owner], blockhash);
+        let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+        println!("Assinatura: {}", signature);
+        Ok(())
+    }
+
+    // Reenvia o lote enquanto o product_counter não andar; se andou só o tamanho do lote, uma tentativa
+    // anterior foi confirmada depois do erro e o lote já está na loja
+    fn send_batch(&self, instruction: &Instruction, first_product_id: u64, count: u64, retries: u32) -> CliResult<()> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.send(instruction) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let product_counter = self.load_store()?.state.product_counter;
+            if product_counter == first_product_id + count {
+                return Ok(());
+            }
+            if product_counter != first_product_id {
+                return Err(
+                    format!("product_counter mudou para {} durante a importação; rode de novo com o restante", product_counter).into()
+                );
+            }
+            if attempt >= retries {
+                return Err(format!("lote a partir do produto #{} falhou após {} tentativa(s): {}", first_product_id, attempt, err).into());
+            }
+            eprintln!("Tentativa {} falhou: {}", attempt, err);
+            thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+            attempt += 1;
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let products = load_catalogue(&cli.file)?;
+    if products.is_empty() {
+        return Err("catálogo sem produtos".into());
+    }
+
+    let solana_config = SolanaConfig::load(cli.config)?;
+    let url = cli.url.or(solana_config.json_rpc_url).unwrap_or_else(|| "https://api.devnet.solana.com".to_string());
+    let keypair_path = cli.keypair.or(solana_config.keypair_path).unwrap_or_else(|| home_dir().join(".config/solana/id.json"));
+    let owner = read_keypair_file(&keypair_path).map_err(|err| format!("não foi possível ler {}: {}", keypair_path.display(), err))?;
+    let importer = Importer {
+        rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        owner,
+        program_id: cli.program_id,
+        store: cli.store,
+    };
+
+    let store = importer.load_store()?;
+    if store.state.owner != importer.owner.pubkey() {
+        return Err(format!("a carteira {} não é o owner da loja ({})", importer.owner.pubkey(), store.state.owner).into());
+    }
+
+    for (product_id, product) in (store.state.product_counter..).zip(&products) {
+        println!("Produto #{}: {} ({})", product_id, product.name, find_product_address(&store.cake, product_id, &cli.program_id).0);
+    }
+    if cli.dry_run {
+        println!("{} produto(s) válidos; nada foi enviado", products.len());
+        return Ok(());
+    }
+
+    let mut first_product_id = store.state.product_counter;
+    for instruction in client::add_products_ixs(&cli.program_id, &store, &importer.owner.pubkey(), &products) {
+        let count = (instruction.accounts.len() - ADD_PRODUCTS_FIXED_ACCOUNTS) as u64;
+        importer.send_batch(&instruction, first_product_id, count, cli.retries.max(1))?;
+        first_product_id += count;
+    }
+    println!("{} produto(s) importados", products.len());
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Erro: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,1916 @@
+// Montagem das instruções no lado do cliente: deriva as PDAs, ordena as contas como o processor
+// espera e serializa os dados com CakeInstruction::pack. As contas opcionais dependem do estado
+// on-chain (owner, taxas, divisão de receita, limites do produto), por isso os builders recebem
+// o estado já lido da rede em Store
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use solana_system_interface::{instruction as system_instruction, program as system_program};
+
+use crate::{
+    instruction::{CakeInstruction, NewProduct, SignedOrder},
+    pda::*,
+    state::{
+        is_valid_lang_code, Bundle, BundleItem, CakeState, Config, HappyHour, HappyHourWindow, Localization, Product, PurchaseHistory,
+        Referral, Subscription, TagIndex, LANG_CODE_LEN, MAX_PRODUCT_BATCH, PAYMENT_MODE_SOL, SELL_VERSION, TAG_LEN,
+    },
+};
+
+// Conta da loja (PDA ["store", store_id] ou, nas lojas legadas, conta criada pelo cliente) com o
+// estado e a configuração atuais; as PDAs da loja são derivadas a partir de cake
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Store {
+    pub cake: Pubkey,
+    pub state: CakeState,
+    pub config: Config,
+}
+
+// Contas de token e assinantes de uma venda; gate_token e price_feed só entram quando o produto exige.
+// owner_token é a conta de receita da loja para payment_mint (store_revenue_address)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SellAccounts {
+    pub buyer: Pubkey,
+    // Paga o rent do histórico e das contas criadas na venda e o recebe de volta quando o histórico é
+    // fechado ou arquivado; None = o próprio comprador
+    pub payer: Option<Pubkey>,
+    pub buyer_token: Pubkey,
+    pub owner_token: Pubkey,
+    pub token_program: Pubkey,
+    pub payment_mint: Pubkey,
+    pub gate_token: Option<Pubkey>,
+    pub price_feed: Option<Pubkey>,
+}
+
+impl SellAccounts {
+    pub fn rent_payer(&self) -> Pubkey {
+        self.payer.unwrap_or(self.buyer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SellOptions {
+    pub coupon_code: Option<Vec<u8>>,
+    pub variant_id: Option<u8>,
+    pub escrow: bool,
+    pub payment_mode: u8,
+    pub create_ata: bool,
+    pub gift_code: Option<Vec<u8>>,
+    pub mint_receipt: bool,
+    pub paged_history: bool,
+    // Recado do comprador, até MAX_MEMO_LEN bytes; não combina com paged_history
+    pub memo: Option<Vec<u8>>,
+    // Código de indicação; apenas no modo token e sem escrow
+    pub referral_code: Option<Vec<u8>>,
+    // Gorjeta em unidades do mint, enviada para Config::tip_account; apenas no modo token, sem escrow
+    // e sem paged_history
+    pub tip: u64,
+    // Arredonda o total até Config::round_up_unit e doa a diferença; mesmas restrições da gorjeta
+    pub round_up: bool,
+    // wSOL (payment_mint nativo): sync_native antes de cobrar e fechamento da conta do comprador depois
+    pub sync_native: bool,
+    pub close_wsol: bool,
+    // Total máximo aceito (por exemplo o total_price de uma cotação); 0 = sem limite
+    pub max_total_price: u64,
+}
+
+// Lojas legadas: cria a conta de estado com o tamanho de CakeState; deve assinar junto com initialize_ix
+pub fn create_state_account_ix(program_id: &Pubkey, payer: &Pubkey, cake: &Pubkey, rent_lamports: u64) -> Instruction {
+    system_instruction::create_account(payer, cake, rent_lamports, CakeState::LEN as u64, program_id)
+}
+
+// Contas que criam a conta de receita da loja para payment_mint, ao final do initialize e do create_store
+fn store_revenue_metas(program_id: &Pubkey, store: &Pubkey, payment_mint: &Pubkey, token_program: &Pubkey) -> [AccountMeta; 5] {
+    [
+        AccountMeta::new_readonly(*payment_mint, false),
+        AccountMeta::new(store_revenue_address(store, payment_mint, token_program, program_id), false),
+        AccountMeta::new_readonly(find_store_revenue_authority_address(store, program_id).0, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ]
+}
+
+pub fn initialize_ix(
+    program_id: &Pubkey,
+    cake: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    payment_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*cake, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(store_revenue_metas(program_id, cake, payment_mint, token_program));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::Initialize.pack(), accounts)
+}
+
+// Cria a loja na PDA ["store", store_id] e a conta de receita dela para payment_mint; o endereço é
+// find_store_address(store_id)
+pub fn create_store_ix(
+    program_id: &Pubkey,
+    store_id: u64,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    payment_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let store = find_store_address(store_id, program_id).0;
+    let mut accounts = vec![
+        AccountMeta::new(store, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(store_revenue_metas(program_id, &store, payment_mint, token_program));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::CreateStore { store_id }.pack(), accounts)
+}
+
+// Conta de receita para outro mint aceito (ou trocado na configuração); a ATA pode ser criada por qualquer um
+pub fn create_store_revenue_account_ix(
+    program_id: &Pubkey,
+    cake: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let (authority, _) = find_store_revenue_authority_address(cake, program_id);
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent(payer, &authority, mint, token_program)
+}
+
+// Cria ou substitui o conjunto de owners M-de-N; com o owner atual já sendo um conjunto, use
+// with_owner_approvals para incluir os membros que aprovam
+pub fn set_owner_set_ix(program_id: &Pubkey, store: &Store, payer: &Pubkey, threshold: u8, members: Vec<Pubkey>) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetOwnerSet { threshold, members }.pack(),
+        vec![
+            AccountMeta::new(store.cake, false),
+            AccountMeta::new(find_owner_set_address(&store.cake, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// Quando o owner da loja é o OwnerSet, a conta do owner deixa de assinar e os membros que aprovam
+// entram como assinantes ao final; serve para qualquer instrução administrativa dos builders
+pub fn with_owner_approvals(mut ix: Instruction, program_id: &Pubkey, store: &Store, approvers: &[Pubkey]) -> Instruction {
+    let (owner_set, _) = find_owner_set_address(&store.cake, program_id);
+    if store.state.owner != owner_set {
+        return ix;
+    }
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == owner_set) {
+        meta.is_signer = false;
+    }
+    ix.accounts.extend(approvers.iter().map(|approver| AccountMeta::new_readonly(*approver, true)));
+    ix
+}
+
+// O produto criado recebe o id product_counter do estado; com tags, entram a PDA product_tags e o índice
+// de cada tag
+#[allow(clippy::too_many_arguments)]
+pub fn add_product_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    name: &str,
+    description: &str,
+    price: u64,
+    stock: u64,
+    max_per_buyer: u64,
+    tags: &[[u8; TAG_LEN]],
+) -> Instruction {
+    let product_id = store.state.product_counter;
+    let data = CakeInstruction::AddProduct {
+        name: name.to_string(),
+        description: description.to_string(),
+        price,
+        stock,
+        max_per_buyer,
+        tags: tags.to_vec(),
+    };
+    let mut accounts = vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new_readonly(store.state.owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if !tags.is_empty() {
+        accounts.push(AccountMeta::new(find_product_tags_address(&store.cake, product_id, program_id).0, false));
+        accounts.extend(tags.iter().map(|tag| AccountMeta::new(find_tag_index_address(&store.cake, tag, program_id).0, false)));
+    }
+    Instruction::new_with_bytes(*program_id, &data.pack(), accounts)
+}
+
+// tags são as atuais do produto (ProductTags), para tirá-lo dos índices; o rent vai para o owner
+pub fn close_product_ix(program_id: &Pubkey, store: &Store, product_id: u64, force: bool, tags: &[[u8; TAG_LEN]]) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new(store.state.owner, true),
+        AccountMeta::new(find_product_tags_address(&store.cake, product_id, program_id).0, false),
+    ];
+    accounts.extend(tags.iter().map(|tag| AccountMeta::new(find_tag_index_address(&store.cake, tag, program_id).0, false)));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::CloseProduct { product_id, force }.pack(), accounts)
+}
+
+// Produtos criados a partir de first_product_id, que precisa ser o product_counter da loja na execução
+pub fn add_products_ix(program_id: &Pubkey, store: &Store, payer: &Pubkey, first_product_id: u64, products: &[NewProduct]) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new_readonly(store.state.owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    for product_id in first_product_id..first_product_id + products.len() as u64 {
+        accounts.push(AccountMeta::new(find_product_address(&store.cake, product_id, program_id).0, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::AddProducts { products: products.to_vec() }.pack(), accounts)
+}
+
+// Limite de tamanho de uma transação serializada
+const MAX_TRANSACTION_LEN: usize = 1232;
+
+// Tamanho de uma transação com owner e payer assinando só o AddProducts, com folga nos tamanhos compactos
+fn add_products_transaction_len(instruction: &Instruction) -> usize {
+    let accounts = instruction.accounts.len();
+    1 + 2 * 64 + 3 + 2 + (accounts + 1) * 32 + 32 + 2 + 1 + 2 + accounts + 2 + instruction.data.len()
+}
+
+// Divide o cardápio em AddProducts que cabem numa transação cada, com ids seguidos a partir do product_counter
+// da loja; as instruções precisam ser enviadas em ordem, uma por transação
+pub fn add_products_ixs(program_id: &Pubkey, store: &Store, payer: &Pubkey, products: &[NewProduct]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut first_product_id = store.state.product_counter;
+    let mut remaining = products;
+    while !remaining.is_empty() {
+        let mut count = remaining.len().min(MAX_PRODUCT_BATCH);
+        let mut ix = add_products_ix(program_id, store, payer, first_product_id, &remaining[..count]);
+        while count > 1 && add_products_transaction_len(&ix) > MAX_TRANSACTION_LEN {
+            count -= 1;
+            ix = add_products_ix(program_id, store, payer, first_product_id, &remaining[..count]);
+        }
+        instructions.push(ix);
+        first_product_id += count as u64;
+        remaining = &remaining[count..];
+    }
+    instructions
+}
+
+pub fn set_config_ix(program_id: &Pubkey, store: &Store, payer: &Pubkey, payment_mint: &Pubkey, price_decimals: u8) -> Instruction {
+    let (config, _) = find_config_address(&store.cake, program_id);
+    let accepted_mints = store.config.accepted_mints[..store.config.accepted_mint_count as usize].to_vec();
+    let data = CakeInstruction::SetConfig { payment_mint: *payment_mint, price_decimals, accepted_mints };
+    Instruction::new_with_bytes(
+        *program_id,
+        &data.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// Operadores precisam da PDA de papel logo após a authority
+fn push_stock_authority(accounts: &mut Vec<AccountMeta>, program_id: &Pubkey, store: &Store, authority: &Pubkey) {
+    if *authority != store.state.owner {
+        accounts.push(AccountMeta::new_readonly(find_operator_address(&store.cake, authority, program_id).0, false));
+    }
+}
+
+pub fn restock_ix(program_id: &Pubkey, store: &Store, authority: &Pubkey, product_id: u64, amount: u64, variant_id: Option<u8>) -> Instruction {
+    let (product, _) = find_product_address(&store.cake, product_id, program_id);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(product, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+    push_stock_authority(&mut accounts, program_id, store, authority);
+    if let Some(variant_id) = variant_id {
+        accounts.push(AccountMeta::new(find_variant_address(&store.cake, product_id, variant_id, program_id).0, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::Restock { product_id, amount, variant_id }.pack(), accounts)
+}
+
+pub fn update_price_ix(program_id: &Pubkey, store: &Store, product_id: u64, new_price: u64) -> Instruction {
+    let (product, _) = find_product_address(&store.cake, product_id, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::UpdatePrice { product_id, new_price }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(product, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+// Simule a transação e leia o return data com parse_quote
+pub fn get_quote_ix(program_id: &Pubkey, store: &Store, product_id: u64, amount: u64) -> Instruction {
+    let (product, _) = find_product_address(&store.cake, product_id, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::GetQuote { product_id, amount }.pack(),
+        vec![AccountMeta::new_readonly(store.cake, false), AccountMeta::new_readonly(product, false)],
+    )
+}
+
+// (total_price, unit_price) do return data do GetQuote
+pub fn parse_quote(data: &[u8]) -> Option<(u64, u64)> {
+    let total_price = u64::from_le_bytes(data.get(..8)?.try_into().ok()?);
+    let unit_price = u64::from_le_bytes(data.get(8..16)?.try_into().ok()?);
+    Some((total_price, unit_price))
+}
+
+// Orçamento completo no mint informado: simule e leia o return data com Quote::from_bytes
+pub fn get_full_quote_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    product_id: u64,
+    amount: u64,
+    payment_mint: &Pubkey,
+    coupon_code: Option<&[u8]>,
+    variant_id: Option<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new_readonly(find_product_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+        AccountMeta::new_readonly(*payment_mint, false),
+        AccountMeta::new_readonly(find_flash_sale_address(&store.cake, product_id, program_id).0, false),
+    ];
+    if let Some(variant_id) = variant_id {
+        accounts.push(AccountMeta::new_readonly(find_variant_address(&store.cake, product_id, variant_id, program_id).0, false));
+    }
+    if let Some(code) = coupon_code {
+        let code_hash = solana_program::hash::hash(code).to_bytes();
+        accounts.push(AccountMeta::new_readonly(find_coupon_address(&store.cake, &code_hash, program_id).0, false));
+    }
+    accounts.push(AccountMeta::new_readonly(find_happy_hour_address(&store.cake, product_id, program_id).0, false));
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::GetFullQuote { product_id, amount, coupon_code: coupon_code.map(<[u8]>::to_vec), variant_id }.pack(),
+        accounts,
+    )
+}
+
+pub fn update_stock_ix(program_id: &Pubkey, store: &Store, product_id: u64, delta: i64) -> Instruction {
+    let (product, _) = find_product_address(&store.cake, product_id, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::UpdateStock { product_id, delta }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(product, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn set_price_decimals_ix(program_id: &Pubkey, store: &Store, product_id: u64, price_decimals: u8) -> Instruction {
+    let (product, _) = find_product_address(&store.cake, product_id, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetPriceDecimals { product_id, price_decimals }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(product, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn set_localization_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    product_id: u64,
+    lang_code: &[u8; LANG_CODE_LEN],
+    name: &str,
+    description: &str,
+) -> Instruction {
+    let data = CakeInstruction::SetLocalization {
+        product_id,
+        lang_code: *lang_code,
+        name: name.to_string(),
+        description: description.to_string(),
+    };
+    Instruction::new_with_bytes(
+        *program_id,
+        &data.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new_readonly(find_product_address(&store.cake, product_id, program_id).0, false),
+            AccountMeta::new(find_localization_address(&store.cake, product_id, lang_code, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn remove_localization_ix(program_id: &Pubkey, store: &Store, product_id: u64, lang_code: &[u8; LANG_CODE_LEN]) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::RemoveLocalization { product_id, lang_code: *lang_code }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_localization_address(&store.cake, product_id, lang_code, program_id).0, false),
+            AccountMeta::new(store.state.owner, true),
+        ],
+    )
+}
+
+// Código de idioma de uma tag como "pt-BR", "en_US" ou "EN"; só a língua principal conta
+pub fn lang_code(tag: &str) -> Option<[u8; LANG_CODE_LEN]> {
+    let primary = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+    let lang_code: [u8; LANG_CODE_LEN] = primary.as_bytes().try_into().ok()?;
+    is_valid_lang_code(&lang_code).then_some(lang_code)
+}
+
+// PDAs das traduções do produto nos idiomas preferidos, para buscar com um getMultipleAccounts
+pub fn localization_addresses(program_id: &Pubkey, store: &Pubkey, product_id: u64, preferred: &[[u8; LANG_CODE_LEN]]) -> Vec<Pubkey> {
+    preferred.iter().map(|lang_code| find_localization_address(store, product_id, lang_code, program_id).0).collect()
+}
+
+// Nome e descrição no primeiro idioma preferido que tenha tradução do produto; sem nenhuma, os do cadastro.
+// Uma descrição traduzida vazia também cai para a do cadastro
+pub fn resolve_localization<'a>(
+    product: &'a Product,
+    localizations: &'a [Localization],
+    preferred: &[[u8; LANG_CODE_LEN]],
+) -> (&'a str, &'a str) {
+    let localization = preferred.iter().find_map(|lang_code| {
+        localizations.iter().find(|localization| localization.product_id == product.id && localization.lang_code == *lang_code)
+    });
+    match localization {
+        Some(localization) if localization.description.is_empty() => (&localization.name, &product.description),
+        Some(localization) => (&localization.name, &localization.description),
+        None => (&product.name, &product.description),
+    }
+}
+
+// Tag a partir do texto ("vegano"): de 1 a 16 bytes, sem NUL
+pub fn tag(text: &str) -> Option<[u8; TAG_LEN]> {
+    if text.is_empty() || text.len() > TAG_LEN || text.contains('\0') {
+        return None;
+    }
+    let mut tag = [0u8; TAG_LEN];
+    tag[..text.len()].copy_from_slice(text.as_bytes());
+    Some(tag)
+}
+
+// current_tags são as tags gravadas hoje em ProductTags (vazio quando a conta não existe); os índices
+// entram na ordem que o processor espera
+pub fn set_product_tags_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    product_id: u64,
+    current_tags: &[[u8; TAG_LEN]],
+    tags: &[[u8; TAG_LEN]],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new_readonly(find_product_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new(find_product_tags_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new_readonly(store.state.owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let removed = current_tags.iter().filter(|tag| !tags.contains(tag));
+    let added = tags.iter().filter(|tag| !current_tags.contains(tag));
+    accounts.extend(removed.chain(added).map(|tag| AccountMeta::new(find_tag_index_address(&store.cake, tag, program_id).0, false)));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::SetProductTags { product_id, tags: tags.to_vec() }.pack(), accounts)
+}
+
+// Produtos presentes em todos os índices lidos ("vegano" e "sem-gluten"), na ordem do primeiro
+pub fn products_with_tags(indexes: &[TagIndex]) -> Vec<u64> {
+    let Some((first, rest)) = indexes.split_first() else {
+        return Vec::new();
+    };
+    first.product_ids.iter().copied().filter(|product_id| rest.iter().all(|index| index.product_ids.contains(product_id))).collect()
+}
+
+// A venda grava o histórico na sequência history_sequence do comprador (BuyerHistoryCounter::count,
+// ou 0 quando o contador ainda não existe); com paged_history, history_sequence é a página
+// HistoryPageHead::write_page
+pub fn sell_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    product: &Product,
+    amount: u64,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+    options: &SellOptions,
+) -> Instruction {
+    let history = if options.paged_history {
+        find_history_page_address(&store.cake, history_sequence, program_id).0
+    } else {
+        find_buyer_history_address(&store.cake, &accounts.buyer, history_sequence, program_id).0
+    };
+    // O bump vai nos dados para o programa validar o produto sem find_program_address
+    let (product_address, product_bump) = find_product_address(&store.cake, product.id, program_id);
+    let sol = options.payment_mode == PAYMENT_MODE_SOL;
+
+    let mut metas = vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new(product_address, false),
+        AccountMeta::new(accounts.buyer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(history, false),
+        AccountMeta::new(accounts.rent_payer(), true),
+        AccountMeta::new(accounts.buyer_token, false),
+        AccountMeta::new(accounts.owner_token, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.payment_mint, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+    ];
+    if options.paged_history {
+        metas.push(AccountMeta::new(find_history_head_address(&store.cake, program_id).0, false));
+    } else {
+        metas.push(AccountMeta::new(find_buyer_history_counter_address(&store.cake, &accounts.buyer, program_id).0, false));
+        metas.push(AccountMeta::new(find_purchase_index_address(&store.cake, product.id, program_id).0, false));
+    }
+    metas.push(AccountMeta::new_readonly(find_blocklist_address(&store.cake, &accounts.buyer, program_id).0, false));
+    metas.push(AccountMeta::new(find_global_stats_address(program_id).0, false));
+    metas.push(AccountMeta::new_readonly(find_flash_sale_address(&store.cake, product.id, program_id).0, false));
+    metas.push(AccountMeta::new_readonly(find_happy_hour_address(&store.cake, product.id, program_id).0, false));
+    metas.push(AccountMeta::new(find_buyer_profile_address(&accounts.buyer, program_id).0, false));
+    if product.allowlist_required {
+        metas.push(AccountMeta::new_readonly(find_allowlist_address(&store.cake, &accounts.buyer, program_id).0, false));
+    }
+    if let Some(gate_token) = accounts.gate_token {
+        metas.push(AccountMeta::new_readonly(gate_token, false));
+    }
+    if sol {
+        if let Some(price_feed) = accounts.price_feed {
+            metas.push(AccountMeta::new_readonly(price_feed, false));
+        }
+    } else if let Some(variant_id) = options.variant_id {
+        metas.push(AccountMeta::new(find_variant_address(&store.cake, product.id, variant_id, program_id).0, false));
+    }
+    if let Some(code) = &options.coupon_code {
+        let code_hash = solana_program::hash::hash(code).to_bytes();
+        metas.push(AccountMeta::new(find_coupon_address(&store.cake, &code_hash, program_id).0, false));
+    }
+    if product.max_per_buyer > 0 {
+        metas.push(AccountMeta::new(find_buyer_purchase_address(&store.cake, product.id, &accounts.buyer, program_id).0, false));
+    }
+
+    if sol {
+        metas.push(AccountMeta::new(store.state.owner, false));
+    } else {
+        if options.create_ata {
+            metas.push(AccountMeta::new(store.state.owner, false));
+            metas.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+        }
+        if let Some(code) = &options.gift_code {
+            let code_hash = solana_program::hash::hash(code).to_bytes();
+            metas.push(AccountMeta::new(find_gift_card_address(&store.cake, &code_hash, program_id).0, false));
+            metas.push(AccountMeta::new(find_treasury_vault_address(&store.cake, &accounts.payment_mint, program_id).0, false));
+            metas.push(AccountMeta::new_readonly(find_treasury_authority_address(program_id).0, false));
+        }
+        if options.escrow {
+            metas.push(AccountMeta::new(find_history_vault_address(&history, program_id).0, false));
+            metas.push(AccountMeta::new_readonly(find_escrow_authority_address(program_id).0, false));
+        } else {
+            if store.state.commission_bps > 0 {
+                metas.push(AccountMeta::new(store.state.commission_account, false));
+            }
+            if let Some(code) = &options.referral_code {
+                let code_hash = solana_program::hash::hash(code).to_bytes();
+                metas.push(AccountMeta::new(find_referral_address(&store.cake, &code_hash, program_id).0, false));
+                metas.push(AccountMeta::new(find_treasury_vault_address(&store.cake, &accounts.payment_mint, program_id).0, false));
+            }
+            for split in &store.config.splits[..store.config.split_count as usize] {
+                metas.push(AccountMeta::new(split.token_account, false));
+            }
+        }
+        if store.state.fee_bps > 0 {
+            metas.push(AccountMeta::new(store.state.fee_destination, false));
+        }
+        if options.tip > 0 {
+            metas.push(AccountMeta::new(store.config.tip_account, false));
+        }
+        if options.round_up {
+            metas.push(AccountMeta::new(store.config.charity_account, false));
+        }
+    }
+
+    if options.mint_receipt {
+        let (receipt_mint, _) = find_receipt_mint_address(&history, program_id);
+        let receipt_token =
+            spl_associated_token_account::get_associated_token_address_with_program_id(&accounts.buyer, &receipt_mint, &spl_token::id());
+        metas.extend([
+            AccountMeta::new(receipt_mint, false),
+            AccountMeta::new(receipt_token, false),
+            AccountMeta::new_readonly(find_receipt_authority_address(program_id).0, false),
+            AccountMeta::new(mpl_token_metadata::accounts::Metadata::find_pda(&receipt_mint).0, false),
+            AccountMeta::new(mpl_token_metadata::accounts::MasterEdition::find_pda(&receipt_mint).0, false),
+            AccountMeta::new_readonly(mpl_token_metadata::ID, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ]);
+    }
+    if options.memo.is_some() {
+        metas.push(AccountMeta::new(find_note_address(&history, program_id).0, false));
+    }
+
+    let data = CakeInstruction::Sell {
+        product_id: product.id,
+        amount,
+        coupon_code: options.coupon_code.clone(),
+        variant_id: options.variant_id,
+        escrow: options.escrow,
+        payment_mode: options.payment_mode,
+        create_ata: options.create_ata,
+        gift_code: options.gift_code.clone(),
+        mint_receipt: options.mint_receipt,
+        product_bump: Some(product_bump),
+        paged_history: options.paged_history,
+        version: SELL_VERSION,
+        memo: options.memo.clone(),
+        referral_code: options.referral_code.clone(),
+        tip: options.tip,
+        round_up: options.round_up,
+        sync_native: options.sync_native,
+        close_wsol: options.close_wsol,
+        max_total_price: options.max_total_price,
+    };
+    Instruction::new_with_bytes(*program_id, &data.pack(), metas)
+}
+
+// O código fica só no cliente: a PDA e os dados da venda usam sha256(code)
+pub fn create_referral_ix(program_id: &Pubkey, store: &Store, payer: &Pubkey, code: &[u8], referrer: &Pubkey, mint: &Pubkey) -> Instruction {
+    let code_hash = solana_program::hash::hash(code).to_bytes();
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::CreateReferral { code_hash, referrer: *referrer, mint: *mint }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(find_referral_address(&store.cake, &code_hash, program_id).0, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn set_referral_bps_ix(program_id: &Pubkey, store: &Store, referral_bps: u16) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetReferralBps { referral_bps }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn set_tip_account_ix(program_id: &Pubkey, store: &Store, tip_account: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetTipAccount { tip_account: *tip_account }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+// Mint nativo do programa de token, usado para pagar com wSOL
+pub fn native_mint(token_program: &Pubkey) -> Pubkey {
+    if *token_program == spl_token_2022::id() {
+        spl_token_2022::native_mint::id()
+    } else {
+        spl_token::native_mint::id()
+    }
+}
+
+// Prepara a ATA wSOL do comprador para a venda: o payer cria a conta se faltar e o comprador deposita
+// os lamports; o sell com sync_native e close_wsol atualiza o saldo e depois devolve o que sobrar
+pub fn wrap_sol_ixs(payer: &Pubkey, buyer: &Pubkey, token_program: &Pubkey, lamports: u64) -> Vec<Instruction> {
+    let mint = native_mint(token_program);
+    let buyer_token = spl_associated_token_account::get_associated_token_address_with_program_id(buyer, &mint, token_program);
+    vec![
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(payer, buyer, &mint, token_program),
+        system_instruction::transfer(buyer, &buyer_token, lamports),
+    ]
+}
+
+// Aprovação que o comprador assina uma vez, em qualquer transação: o delegate da loja pode gastar até
+// `amount` da conta de token nas compras delegadas. Se o mint cobra taxa de transferência, cada compra
+// consome também transfer_fee_for_net_amount do total
+pub fn approve_purchase_delegate_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    accounts: &SellAccounts,
+    amount: u64,
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::instruction::approve_checked(
+        &accounts.token_program,
+        &accounts.buyer_token,
+        &accounts.payment_mint,
+        &find_purchase_delegate_address(&store.cake, program_id).0,
+        &accounts.buyer,
+        &[],
+        amount,
+        decimals,
+    )
+}
+
+// Assinatura recorrente: o comprador paga a conta e fixa o teto do preço unitário, em unidades do mint da
+// buyer_token, que process_subscription aceita cobrar
+#[allow(clippy::too_many_arguments)]
+pub fn create_subscription_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    buyer: &Pubkey,
+    buyer_token: &Pubkey,
+    product_id: u64,
+    quantity: u64,
+    interval: i64,
+    max_unit_price: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::CreateSubscription { product_id, quantity, interval, max_unit_price }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new_readonly(find_product_address(&store.cake, product_id, program_id).0, false),
+            AccountMeta::new(find_subscription_address(&store.cake, buyer, product_id, program_id).0, false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new_readonly(*buyer_token, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// Aprovação da buyer_token para o delegate da assinatura (produto, comprador) nesta loja; amount limita o
+// total das cobranças, que ainda não passam de max_unit_price por unidade
+#[allow(clippy::too_many_arguments)]
+pub fn approve_subscription_delegate_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    buyer: &Pubkey,
+    product_id: u64,
+    buyer_token: &Pubkey,
+    payment_mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    let subscription = find_subscription_address(&store.cake, buyer, product_id, program_id).0;
+    spl_token_2022::instruction::approve_checked(
+        token_program,
+        buyer_token,
+        payment_mint,
+        &find_subscription_delegate_address(&subscription, program_id).0,
+        buyer,
+        &[],
+        amount,
+        decimals,
+    )
+}
+
+// Cobrança de uma assinatura vencida, enviada por qualquer um; payer assina e paga o histórico.
+// history_sequence é o BuyerHistoryCounter::count do comprador
+pub fn process_subscription_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    subscription: &Subscription,
+    history_sequence: u64,
+    payer: &Pubkey,
+    payment_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let subscription_address = find_subscription_address(&store.cake, &subscription.buyer, subscription.product_id, program_id).0;
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::ProcessSubscription.pack(),
+        vec![
+            AccountMeta::new(store.cake, false),
+            AccountMeta::new(find_product_address(&store.cake, subscription.product_id, program_id).0, false),
+            AccountMeta::new(subscription_address, false),
+            AccountMeta::new(find_buyer_history_address(&store.cake, &subscription.buyer, history_sequence, program_id).0, false),
+            AccountMeta::new(subscription.buyer_token, false),
+            AccountMeta::new(store_revenue_address(&store.cake, payment_mint, token_program, program_id), false),
+            AccountMeta::new_readonly(find_subscription_delegate_address(&subscription_address, program_id).0, false),
+            AccountMeta::new_readonly(*payment_mint, false),
+            AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(find_buyer_history_counter_address(&store.cake, &subscription.buyer, program_id).0, false),
+        ],
+    )
+}
+
+// Contas de blocklist, allowlist e gate_mint que as compras conferem para cada produto, na ordem do processor
+fn buyer_gate_metas(program_id: &Pubkey, store: &Store, product: &Product, accounts: &SellAccounts) -> Vec<AccountMeta> {
+    let mut metas = vec![AccountMeta::new_readonly(find_blocklist_address(&store.cake, &accounts.buyer, program_id).0, false)];
+    if product.allowlist_required {
+        metas.push(AccountMeta::new_readonly(find_allowlist_address(&store.cake, &accounts.buyer, program_id).0, false));
+    }
+    if let Some(gate_token) = accounts.gate_token {
+        metas.push(AccountMeta::new_readonly(gate_token, false));
+    }
+    metas
+}
+
+// As 13 contas comuns à compra delegada e à ordem assinada
+fn delegated_purchase_metas(
+    program_id: &Pubkey,
+    store: &Store,
+    product_id: u64,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, product_id, program_id).0, false),
+        AccountMeta::new_readonly(accounts.buyer, false),
+        AccountMeta::new(find_buyer_history_address(&store.cake, &accounts.buyer, history_sequence, program_id).0, false),
+        AccountMeta::new(accounts.buyer_token, false),
+        AccountMeta::new(accounts.owner_token, false),
+        AccountMeta::new_readonly(find_purchase_delegate_address(&store.cake, program_id).0, false),
+        AccountMeta::new_readonly(accounts.payment_mint, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+        AccountMeta::new(accounts.payer.unwrap_or(store.state.owner), true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new(find_buyer_history_counter_address(&store.cake, &accounts.buyer, program_id).0, false),
+    ]
+}
+
+// Compra sem a assinatura do comprador, cobrada pelo delegate; o owner assina e accounts.payer paga o histórico
+// (o owner, quando payer é None, já que o comprador não assina)
+pub fn execute_delegated_purchase_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    product: &Product,
+    amount: u64,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+) -> Instruction {
+    let mut metas = delegated_purchase_metas(program_id, store, product.id, history_sequence, accounts);
+    metas.push(AccountMeta::new_readonly(store.state.owner, true));
+    metas.extend(buyer_gate_metas(program_id, store, product, accounts));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::ExecuteDelegatedPurchase { product_id: product.id, amount }.pack(), metas)
+}
+
+// Instrução do programa ed25519 que verifica a assinatura do comprador sobre a ordem; vai logo antes do
+// execute_signed_order_ix. Layout: cabeçalho de 16 bytes com os offsets, chave, assinatura e mensagem
+pub fn ed25519_verify_ix(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    let mut data = vec![1, 0];
+    for offset in [SIGNATURE_OFFSET, u16::MAX, PUBLIC_KEY_OFFSET, u16::MAX, MESSAGE_OFFSET, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(solana_program::ed25519_program::id(), &data, vec![])
+}
+
+// Executa a ordem que o comprador assinou off-chain; qualquer carteira pode enviar e pagar (accounts.payer,
+// ou o owner quando None). Precisa de ed25519_verify_ix(comprador, assinatura, order.message(store.cake))
+// imediatamente antes
+pub fn execute_signed_order_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    product: &Product,
+    order: &SignedOrder,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+) -> Instruction {
+    let mut metas = delegated_purchase_metas(program_id, store, order.product_id, history_sequence, accounts);
+    metas.push(AccountMeta::new(find_order_nonce_address(&store.cake, &accounts.buyer, order.nonce, program_id).0, false));
+    metas.push(AccountMeta::new_readonly(solana_program::sysvar::instructions::id(), false));
+    metas.extend(buyer_gate_metas(program_id, store, product, accounts));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::ExecuteSignedOrder { order: *order }.pack(), metas)
+}
+
+pub fn set_charity_ix(program_id: &Pubkey, store: &Store, charity_account: &Pubkey, round_up_unit: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetCharity { charity_account: *charity_account, round_up_unit }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+// Saca todo o saldo pendente do código para referrer_token, uma conta de token do mint da indicação
+pub fn claim_referral_rewards_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    referral: &Referral,
+    referrer_token: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::ClaimReferralRewards.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_referral_address(&store.cake, &referral.code_hash, program_id).0, false),
+            AccountMeta::new_readonly(referral.referrer, true),
+            AccountMeta::new(*referrer_token, false),
+            AccountMeta::new(find_treasury_vault_address(&store.cake, &referral.mint, program_id).0, false),
+            AccountMeta::new_readonly(find_treasury_authority_address(program_id).0, false),
+            AccountMeta::new_readonly(referral.mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+    )
+}
+
+// Reembolso total pelo owner, pago pela conta de receita da loja no mint; compras em SOL devolvem lamports
+// da carteira do owner para a do comprador. Com Token-2022 o mint vai ao final, seguido da variante vendida.
+// history_index é a sequência do comprador
+#[allow(clippy::too_many_arguments)]
+pub fn refund_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    history_index: u64,
+    history: &PurchaseHistory,
+    buyer_token: &Pubkey,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, history.product_id, program_id).0, false),
+        AccountMeta::new(find_buyer_history_address(&store.cake, &history.buyer, history_index, program_id).0, false),
+        AccountMeta::new(store.state.owner, true),
+        AccountMeta::new(store_revenue_address(&store.cake, mint, token_program, program_id), false),
+        AccountMeta::new_readonly(find_store_revenue_authority_address(&store.cake, program_id).0, false),
+        AccountMeta::new(*buyer_token, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+    if history.payment_mode == PAYMENT_MODE_SOL {
+        accounts.push(AccountMeta::new(history.buyer, false));
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    }
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    if let Some(variant_id) = history.variant() {
+        accounts.push(AccountMeta::new(find_variant_address(&store.cake, history.product_id, variant_id, program_id).0, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::Refund { history_index }.pack(), accounts)
+}
+
+pub fn set_arbiter_ix(program_id: &Pubkey, store: &Store, arbiter: &Pubkey, dispute_window: i64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetArbiter { arbiter: *arbiter, dispute_window }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn open_dispute_ix(program_id: &Pubkey, store: &Store, buyer: &Pubkey, payer: &Pubkey, history_index: u64) -> Instruction {
+    let history = find_buyer_history_address(&store.cake, buyer, history_index, program_id).0;
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::OpenDispute { history_index }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+            AccountMeta::new(history, false),
+            AccountMeta::new(find_dispute_address(&history, program_id).0, false),
+            AccountMeta::new_readonly(*buyer, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// Decisão do árbitro: destination_token é a conta do comprador com refund, senão a de receita da loja.
+// Com Token-2022 o mint vai ao final; com refund de uma variante, a PDA dela vem depois
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_dispute_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    arbiter: &Pubkey,
+    history_index: u64,
+    history: &PurchaseHistory,
+    refund: bool,
+    destination_token: &Pubkey,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let history_address = find_buyer_history_address(&store.cake, &history.buyer, history_index, program_id).0;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+        AccountMeta::new(find_product_address(&store.cake, history.product_id, program_id).0, false),
+        AccountMeta::new(history_address, false),
+        AccountMeta::new(find_dispute_address(&history_address, program_id).0, false),
+        AccountMeta::new_readonly(*arbiter, true),
+        AccountMeta::new(history.buyer, false),
+        AccountMeta::new(find_history_vault_address(&history_address, program_id).0, false),
+        AccountMeta::new(*destination_token, false),
+        AccountMeta::new_readonly(find_escrow_authority_address(program_id).0, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    if let (true, Some(variant_id)) = (refund, history.variant()) {
+        accounts.push(AccountMeta::new(find_variant_address(&store.cake, history.product_id, variant_id, program_id).0, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::ResolveDispute { history_index, refund }.pack(), accounts)
+}
+
+// Liberação de um escrow vencido, enviada por qualquer um (caller não assina). owner_token é a conta de
+// receita da loja no mint da compra; com Token-2022 o mint vai ao final
+#[allow(clippy::too_many_arguments)]
+pub fn release_expired_escrow_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    caller: &Pubkey,
+    history_index: u64,
+    history: &PurchaseHistory,
+    owner_token: &Pubkey,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let history_address = find_buyer_history_address(&store.cake, &history.buyer, history_index, program_id).0;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, history.product_id, program_id).0, false),
+        AccountMeta::new(history_address, false),
+        AccountMeta::new_readonly(*caller, false),
+        AccountMeta::new(history.buyer, false),
+        AccountMeta::new(find_history_vault_address(&history_address, program_id).0, false),
+        AccountMeta::new(*owner_token, false),
+        AccountMeta::new_readonly(find_escrow_authority_address(program_id).0, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::ReleaseExpiredEscrow { history_index }.pack(), accounts)
+}
+
+pub fn set_bundle_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    bundle_id: u64,
+    price: u64,
+    items: Vec<BundleItem>,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetBundle { bundle_id, price, items }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_bundle_address(&store.cake, bundle_id, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn close_bundle_ix(program_id: &Pubkey, store: &Store, bundle_id: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::CloseBundle { bundle_id }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_bundle_address(&store.cake, bundle_id, program_id).0, false),
+            AccountMeta::new(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn set_happy_hour_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    product_id: u64,
+    windows: Vec<HappyHourWindow>,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::SetHappyHour { product_id, windows }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new_readonly(find_product_address(&store.cake, product_id, program_id).0, false),
+            AccountMeta::new(find_happy_hour_address(&store.cake, product_id, program_id).0, false),
+            AccountMeta::new_readonly(store.state.owner, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn clear_happy_hour_ix(program_id: &Pubkey, store: &Store, product_id: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::ClearHappyHour { product_id }.pack(),
+        vec![
+            AccountMeta::new_readonly(store.cake, false),
+            AccountMeta::new(find_happy_hour_address(&store.cake, product_id, program_id).0, false),
+            AccountMeta::new(store.state.owner, true),
+        ],
+    )
+}
+
+pub fn withdraw_store_revenue_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    destination_token: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new_readonly(store.state.owner, true),
+        AccountMeta::new(store_revenue_address(&store.cake, mint, token_program, program_id), false),
+        AccountMeta::new_readonly(find_store_revenue_authority_address(&store.cake, program_id).0, false),
+        AccountMeta::new(*destination_token, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::WithdrawStoreRevenue { amount }.pack(), accounts)
+}
+
+// Instrução confidencial do Token-2022 (ConfigureAccount, ApplyPendingBalance ou Withdraw, com as provas
+// em contas de contexto) para a conta de receita do mint; extra_accounts são as contas que o Token-2022
+// espera entre a conta e o dono
+pub fn confidential_revenue_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    data: Vec<u8>,
+    extra_accounts: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new_readonly(store.state.owner, true),
+        AccountMeta::new(store_revenue_address(&store.cake, mint, &spl_token_2022::id(), program_id), false),
+        AccountMeta::new_readonly(find_store_revenue_authority_address(&store.cake, program_id).0, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(extra_accounts.iter().map(|account| AccountMeta::new_readonly(*account, false)));
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::ConfidentialRevenue { data }.pack(), accounts)
+}
+
+// Desconto do happy hour que uma venda de total_price receberia em now; sem a conta (None) não há desconto.
+// O sell aplica o maior entre ele e o da promoção relâmpago
+pub fn happy_hour_discount(happy_hour: Option<&HappyHour>, total_price: u64, now: i64) -> u64 {
+    happy_hour.map_or(0, |happy_hour| happy_hour.discount(total_price, now))
+}
+
+// products são os produtos do combo na ordem de bundle.items, lidos da rede para saber quais têm max_per_buyer
+pub fn sell_bundle_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    bundle: &Bundle,
+    products: &[Product],
+    quantity: u64,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+) -> Instruction {
+    let mut metas = vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new_readonly(find_bundle_address(&store.cake, bundle.bundle_id, program_id).0, false),
+        AccountMeta::new(accounts.buyer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(find_buyer_history_address(&store.cake, &accounts.buyer, history_sequence, program_id).0, false),
+        AccountMeta::new(accounts.rent_payer(), true),
+        AccountMeta::new(accounts.buyer_token, false),
+        AccountMeta::new(accounts.owner_token, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.payment_mint, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+        AccountMeta::new(find_buyer_history_counter_address(&store.cake, &accounts.buyer, program_id).0, false),
+    ];
+    metas.push(AccountMeta::new_readonly(find_blocklist_address(&store.cake, &accounts.buyer, program_id).0, false));
+    // Cada produto com gate_mint confere a ATA SPL Token do comprador para aquele mint
+    for product in products {
+        metas.push(AccountMeta::new(find_product_address(&store.cake, product.id, program_id).0, false));
+        if product.max_per_buyer > 0 {
+            metas.push(AccountMeta::new(find_buyer_purchase_address(&store.cake, product.id, &accounts.buyer, program_id).0, false));
+        }
+        if product.allowlist_required {
+            metas.push(AccountMeta::new_readonly(find_allowlist_address(&store.cake, &accounts.buyer, program_id).0, false));
+        }
+        if product.gate_mint != Pubkey::default() {
+            let gate_token = spl_associated_token_account::get_associated_token_address_with_program_id(
+                &accounts.buyer,
+                &product.gate_mint,
+                &spl_token::id(),
+            );
+            metas.push(AccountMeta::new_readonly(gate_token, false));
+        }
+    }
+    if store.state.fee_bps > 0 {
+        metas.push(AccountMeta::new(store.state.fee_destination, false));
+    }
+    Instruction::new_with_bytes(*program_id, &CakeInstruction::SellBundle { bundle_id: bundle.bundle_id, quantity }.pack(), metas)
+}
+
+// Contas de contexto das três provas da transferência confidencial e da prova do valor, criadas e verificadas
+// antes no programa ZK ElGamal pelo comprador
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidentialProofs {
+    pub equality: Pubkey,
+    pub ciphertext_validity: Pubkey,
+    pub range: Pubkey,
+    // ZeroCiphertext do texto cifrado da origem menos o total da compra
+    pub amount: Pubkey,
+}
+
+// Total da ConfidentialSell nas casas do mint (preço por faixa, sem descontos); é o valor que a prova
+// ZeroCiphertext de ConfidentialProofs::amount precisa fechar
+pub fn confidential_sell_total(store: &Store, product: &Product, quantity: u64, payment_mint: &Pubkey) -> Result<u64, ProgramError> {
+    let mint_decimals = store.config.mint_decimals(payment_mint).ok_or(crate::CakeError::InvalidPaymentMint)?;
+    let total = quantity.checked_mul(product.unit_price_for(quantity)).ok_or(crate::CakeError::ArithmeticOverflow)?;
+    crate::convert_price(total, product.price_decimals_or(store.config.price_decimals), mint_decimals)
+}
+
+// Venda paga por transferência confidencial do Token-2022; só o comprador e o payer assinam.
+// new_source_decryptable_balance é o novo saldo decifrável (AeCiphertext) da conta do comprador
+#[allow(clippy::too_many_arguments)]
+pub fn confidential_sell_ix(
+    program_id: &Pubkey,
+    store: &Store,
+    product: &Product,
+    quantity: u64,
+    history_sequence: u64,
+    accounts: &SellAccounts,
+    proofs: &ConfidentialProofs,
+    new_source_decryptable_balance: [u8; 36],
+) -> Instruction {
+    let history = find_buyer_history_address(&store.cake, &accounts.buyer, history_sequence, program_id).0;
+    let mut metas = vec![
+        AccountMeta::new(store.cake, false),
+        AccountMeta::new(find_product_address(&store.cake, product.id, program_id).0, false),
+        AccountMeta::new_readonly(accounts.buyer, true),
+        AccountMeta::new(history, false),
+        AccountMeta::new(find_buyer_history_counter_address(&store.cake, &accounts.buyer, program_id).0, false),
+        AccountMeta::new(accounts.buyer_token, false),
+        AccountMeta::new(accounts.owner_token, false),
+        AccountMeta::new_readonly(accounts.payment_mint, false),
+        AccountMeta::new_readonly(find_config_address(&store.cake, program_id).0, false),
+        AccountMeta::new(find_confidential_payment_address(&history, program_id).0, false),
+        AccountMeta::new_readonly(proofs.equality, false),
+        AccountMeta::new_readonly(proofs.ciphertext_validity, false),
+        AccountMeta::new_readonly(proofs.range, false),
+        AccountMeta::new_readonly(proofs.amount, false),
+        AccountMeta::new(accounts.rent_payer(), true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+    if product.max_per_buyer > 0 {
+        metas.push(AccountMeta::new(find_buyer_purchase_address(&store.cake, product.id, &accounts.buyer, program_id).0, false));
+    }
+    metas.extend(buyer_gate_metas(program_id, store, product, accounts));
+    Instruction::new_with_bytes(
+        *program_id,
+        &CakeInstruction::ConfidentialSell { product_id: product.id, quantity, new_source_decryptable_balance }.pack(),
+        metas,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AcceptedMint, SplitRecipient, ALL_WEEKDAYS, PAYMENT_MODE_TOKEN};
+
+    fn store(owner: Pubkey) -> Store {
+        Store {
+            cake: Pubkey::new_unique(),
+            state: CakeState {
+                owner,
+                product_counter: 3,
+                history_counter: 9,
+                max_price_change_bps: 0,
+                paused: false,
+                order_counter: 0,
+                category_counter: 0,
+                fee_bps: 0,
+                fee_destination: Pubkey::default(),
+                escrow_timeout: 0,
+                commission_bps: 0,
+                commission_authority: Pubkey::default(),
+                commission_account: Pubkey::default(),
+                is_initialized: true,
+            },
+            config: Config {
+                payment_mint: Pubkey::new_unique(),
+                price_decimals: 6,
+                accepted_mint_count: 0,
+                accepted_mints: [AcceptedMint::default(); 4],
+                split_count: 0,
+                splits: [SplitRecipient::default(); 4],
+                history_retention: 0,
+                referral_bps: 0,
+                tip_account: Pubkey::new_unique(),
+                charity_account: Pubkey::new_unique(),
+                round_up_unit: 1_000_000,
+                arbiter: Pubkey::default(),
+                dispute_window: 0,
+            },
+        }
+    }
+
+    fn product(id: u64) -> Product {
+        Product {
+            id,
+            name: "Bolo".to_string(),
+            description: String::new(),
+            price: 1_000_000,
+            stock: 10,
+            active: true,
+            available: true,
+            max_per_buyer: 0,
+            category_id: 0,
+            metadata_uri: [0; 200],
+            tier_count: 0,
+            tiers: Default::default(),
+            sol_price: 0,
+            usd_price_cents: 0,
+            price_feed: Pubkey::default(),
+            allowlist_required: false,
+            gate_mint: Pubkey::default(),
+            units_sold: 0,
+            revenue: 0,
+            sol_revenue: 0,
+            available_from: 0,
+            available_until: 0,
+            price_decimals: 0,
+        }
+    }
+
+    fn sell_accounts() -> SellAccounts {
+        SellAccounts {
+            buyer: Pubkey::new_unique(),
+            payer: Some(Pubkey::new_unique()),
+            buyer_token: Pubkey::new_unique(),
+            owner_token: Pubkey::new_unique(),
+            token_program: spl_token::id(),
+            payment_mint: Pubkey::new_unique(),
+            gate_token: None,
+            price_feed: None,
+        }
+    }
+
+    #[test]
+    fn test_sell_accounts() {
+        let program_id = Pubkey::new_unique();
+        let mut store = store(Pubkey::new_unique());
+        let accounts = sell_accounts();
+        let options = SellOptions { payment_mode: PAYMENT_MODE_TOKEN, ..Default::default() };
+
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 4, &accounts, &options);
+        assert_eq!(ix.accounts.len(), 18);
+        assert_eq!(ix.accounts[4].pubkey, find_buyer_history_address(&store.cake, &accounts.buyer, 4, &program_id).0);
+        assert_eq!(ix.accounts[11].pubkey, find_buyer_history_counter_address(&store.cake, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[12].pubkey, find_purchase_index_address(&store.cake, 1, &program_id).0);
+        assert_eq!(ix.accounts[13].pubkey, find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[16].pubkey, find_happy_hour_address(&store.cake, 1, &program_id).0);
+        // Só comprador e payer assinam; o owner não participa da venda
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, [accounts.buyer, accounts.rent_payer()]);
+        let product_bump = find_product_address(&store.cake, 1, &program_id).1;
+        assert!(matches!(CakeInstruction::unpack(&ix.data).unwrap(), CakeInstruction::Sell { product_bump: Some(bump), .. } if bump == product_bump));
+        assert!(matches!(CakeInstruction::unpack(&ix.data).unwrap(), CakeInstruction::Sell { version: SELL_VERSION, .. }));
+
+        // Sem payer o próprio comprador paga o rent do histórico
+        let buyer_pays = SellAccounts { payer: None, ..accounts };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 4, &buyer_pays, &options);
+        assert_eq!(ix.accounts[5].pubkey, accounts.buyer);
+        assert!(ix.accounts[5].is_signer && ix.accounts[5].is_writable);
+
+        // Comissão, dois destinatários e taxa entram nas posições esperadas
+        store.state.commission_bps = 100;
+        store.state.commission_account = Pubkey::new_unique();
+        store.state.fee_bps = 50;
+        store.state.fee_destination = Pubkey::new_unique();
+        store.config.split_count = 2;
+        store.config.splits[0].token_account = Pubkey::new_unique();
+        store.config.splits[1].token_account = Pubkey::new_unique();
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &sell_accounts(), &options);
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(keys.len(), 22);
+        assert_eq!(
+            keys[18..],
+            [
+                store.state.commission_account,
+                store.config.splits[0].token_account,
+                store.config.splits[1].token_account,
+                store.state.fee_destination
+            ]
+        );
+
+        // A indicação entra entre a comissão e a divisão de receita: PDA do código e cofre da tesouraria
+        let referral_options = SellOptions { referral_code: Some(b"ANA2024".to_vec()), ..options.clone() };
+        let accounts = sell_accounts();
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &accounts, &referral_options);
+        let code_hash = solana_program::hash::hash(b"ANA2024").to_bytes();
+        assert_eq!(ix.accounts.len(), 24);
+        assert_eq!(ix.accounts[19].pubkey, find_referral_address(&store.cake, &code_hash, &program_id).0);
+        assert_eq!(ix.accounts[20].pubkey, find_treasury_vault_address(&store.cake, &accounts.payment_mint, &program_id).0);
+        assert_eq!(ix.accounts[21].pubkey, store.config.splits[0].token_account);
+
+        // A conta das gorjetas vem depois da taxa
+        let tip_options = SellOptions { tip: 250_000, ..options.clone() };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &sell_accounts(), &tip_options);
+        assert_eq!(ix.accounts.len(), 23);
+        assert_eq!(ix.accounts[21].pubkey, store.state.fee_destination);
+        assert_eq!(ix.accounts[22].pubkey, store.config.tip_account);
+
+        // A conta da caridade vem logo depois da conta das gorjetas
+        let round_up_options = SellOptions { tip: 250_000, round_up: true, ..options.clone() };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &sell_accounts(), &round_up_options);
+        assert_eq!(ix.accounts.len(), 24);
+        assert_eq!(ix.accounts[23].pubkey, store.config.charity_account);
+
+        // Os sinalizadores de wSOL não acrescentam contas
+        let wsol_options = SellOptions { sync_native: true, close_wsol: true, ..options.clone() };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &sell_accounts(), &wsol_options);
+        assert_eq!(ix.accounts.len(), 22);
+
+        // O recado vai por último, na PDA derivada do histórico
+        let memo_options = SellOptions { memo: Some(b"Feliz aniversario, Ana".to_vec()), ..options.clone() };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 0, &sell_accounts(), &memo_options);
+        let history = find_buyer_history_address(&store.cake, &ix.accounts[2].pubkey, 0, &program_id).0;
+        assert_eq!(ix.accounts.last().unwrap().pubkey, find_note_address(&history, &program_id).0);
+
+        // No histórico paginado a conta de histórico é a página e o índice do produto sai da lista
+        let options = SellOptions { paged_history: true, ..options };
+        let ix = sell_ix(&program_id, &store, &product(1), 2, 5, &sell_accounts(), &options);
+        assert_eq!(ix.accounts[4].pubkey, find_history_page_address(&store.cake, 5, &program_id).0);
+        assert_eq!(ix.accounts[11].pubkey, find_history_head_address(&store.cake, &program_id).0);
+        assert_eq!(ix.accounts[12].pubkey, find_blocklist_address(&store.cake, &ix.accounts[2].pubkey, &program_id).0);
+    }
+
+    #[test]
+    fn test_owner_approvals() {
+        let program_id = Pubkey::new_unique();
+        let approvers = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        // Com owner comum a instrução não muda
+        let store = store(Pubkey::new_unique());
+        let ix = update_price_ix(&program_id, &store, 1, 2_000_000);
+        assert_eq!(with_owner_approvals(ix.clone(), &program_id, &store, &approvers), ix);
+
+        let mut store = store;
+        store.state.owner = find_owner_set_address(&store.cake, &program_id).0;
+        let ix = with_owner_approvals(update_price_ix(&program_id, &store, 1, 2_000_000), &program_id, &store, &approvers);
+        assert!(ix.accounts.iter().filter(|meta| meta.pubkey == store.state.owner).all(|meta| !meta.is_signer));
+        assert_eq!(ix.accounts[ix.accounts.len() - 2..].iter().map(|meta| (meta.pubkey, meta.is_signer)).collect::<Vec<_>>(), [
+            (approvers[0], true),
+            (approvers[1], true)
+        ]);
+    }
+
+    #[test]
+    fn test_add_products_batches() {
+        let program_id = Pubkey::new_unique();
+        let mut store = store(Pubkey::new_unique());
+        store.state.product_counter = 3;
+
+        let menu: Vec<NewProduct> = (0..40)
+            .map(|i| NewProduct {
+                name: format!("Bolo {i}"),
+                description: "Fatia com cobertura de chocolate".to_string(),
+                price: 1_000_000,
+                stock: 10,
+                max_per_buyer: 0,
+            })
+            .collect();
+        let instructions = add_products_ixs(&program_id, &store, &Pubkey::new_unique(), &menu);
+
+        // Cada lote cabe numa transação e as PDAs seguem o product_counter sem lacunas
+        let mut product_id = 3;
+        for ix in &instructions {
+            assert!(add_products_transaction_len(ix) <= MAX_TRANSACTION_LEN);
+            for meta in &ix.accounts[4..] {
+                assert_eq!(meta.pubkey, find_product_address(&store.cake, product_id, &program_id).0);
+                product_id += 1;
+            }
+        }
+        assert_eq!(product_id, 43);
+        assert!(instructions.len() < menu.len());
+    }
+
+    #[test]
+    fn test_delegated_purchase() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let accounts = sell_accounts();
+        let delegate = find_purchase_delegate_address(&store.cake, &program_id).0;
+
+        let approve = approve_purchase_delegate_ix(&program_id, &store, &accounts, 5_000_000, 6).unwrap();
+        assert_eq!(approve.accounts[0].pubkey, accounts.buyer_token);
+        assert_eq!(approve.accounts[2].pubkey, delegate);
+        assert_eq!(approve.accounts[3].pubkey, accounts.buyer);
+        assert!(approve.accounts[3].is_signer);
+
+        // O comprador entra sem assinar; só o payer e o owner assinam
+        let ix = execute_delegated_purchase_ix(&program_id, &store, &product(1), 2, 0, &accounts);
+        assert_eq!(ix.accounts.len(), 15);
+        assert_eq!(ix.accounts[14].pubkey, find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0);
+        assert!(!ix.accounts[2].is_signer);
+        assert_eq!(ix.accounts[6].pubkey, delegate);
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, [accounts.rent_payer(), store.state.owner]);
+        assert_eq!(ix.accounts[3].pubkey, find_buyer_history_address(&store.cake, &accounts.buyer, 0, &program_id).0);
+    }
+
+    #[test]
+    fn test_full_quote_accounts() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let mint = store.config.payment_mint;
+
+        // Nenhuma conta é gravável e ninguém assina
+        let ix = get_full_quote_ix(&program_id, &store, 4, 2, &mint, None, None);
+        assert_eq!(ix.accounts.len(), 6);
+        assert!(ix.accounts.iter().all(|meta| !meta.is_writable && !meta.is_signer));
+        assert_eq!(ix.accounts[4].pubkey, find_flash_sale_address(&store.cake, 4, &program_id).0);
+        assert_eq!(ix.accounts[5].pubkey, find_happy_hour_address(&store.cake, 4, &program_id).0);
+
+        let ix = get_full_quote_ix(&program_id, &store, 4, 2, &mint, Some(b"BOLO10"), Some(1));
+        let code_hash = solana_program::hash::hash(b"BOLO10").to_bytes();
+        assert_eq!(ix.accounts[5].pubkey, find_variant_address(&store.cake, 4, 1, &program_id).0);
+        assert_eq!(ix.accounts[6].pubkey, find_coupon_address(&store.cake, &code_hash, &program_id).0);
+        assert_eq!(ix.accounts[7].pubkey, find_happy_hour_address(&store.cake, 4, &program_id).0);
+    }
+
+    #[test]
+    fn test_happy_hour_discount() {
+        let window = HappyHourWindow { days: ALL_WEEKDAYS, start_minute: 15 * 60, end_minute: 17 * 60, discount_bps: 2_000 };
+        let happy_hour = HappyHour { product_id: 4, windows: vec![window] };
+        // 2023-11-14 16:00 UTC
+        let now = 1_699_920_000 + 16 * 3_600;
+        assert_eq!(happy_hour_discount(Some(&happy_hour), 1_000_000, now), 200_000);
+        assert_eq!(happy_hour_discount(Some(&happy_hour), 1_000_000, now + 3_600), 0);
+        assert_eq!(happy_hour_discount(None, 1_000_000, now), 0);
+    }
+
+    #[test]
+    fn test_signed_order() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let accounts = sell_accounts();
+        let order = SignedOrder { product_id: 1, quantity: 2, max_price: 3_000_000, expiry: 1_700_000_000, nonce: 7 };
+        let message = order.message(&store.cake);
+
+        // Os offsets apontam para a própria instrução ed25519
+        let verify = ed25519_verify_ix(&accounts.buyer, &[5; 64], &message);
+        let offset = |index: usize| u16::from_le_bytes([verify.data[2 + index * 2], verify.data[3 + index * 2]]) as usize;
+        assert_eq!(verify.data[0], 1);
+        assert_eq!(&verify.data[offset(0)..offset(0) + 64], &[5; 64]);
+        assert_eq!(&verify.data[offset(2)..offset(2) + 32], accounts.buyer.as_ref());
+        assert_eq!(&verify.data[offset(4)..offset(4) + offset(5)], &message);
+        assert_eq!([offset(1), offset(3), offset(6)], [u16::MAX as usize; 3]);
+
+        // Sem o owner: a PDA do nonce e a sysvar Instructions no lugar dele, e só o payer assina
+        let ix = execute_signed_order_ix(&program_id, &store, &product(1), &order, 0, &accounts);
+        assert_eq!(ix.accounts.len(), 16);
+        assert_eq!(ix.accounts[13].pubkey, find_order_nonce_address(&store.cake, &accounts.buyer, 7, &program_id).0);
+        assert_eq!(ix.accounts[14].pubkey, solana_program::sysvar::instructions::id());
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, [accounts.rent_payer()]);
+        assert_eq!(CakeInstruction::unpack(&ix.data).unwrap(), CakeInstruction::ExecuteSignedOrder { order });
+
+        // Produto com allowlist e gate_mint: blocklist, allowlist e conta do gate depois da sysvar
+        let gated = Product { allowlist_required: true, gate_mint: Pubkey::new_unique(), ..product(1) };
+        let accounts = SellAccounts { gate_token: Some(Pubkey::new_unique()), ..accounts };
+        let ix = execute_signed_order_ix(&program_id, &store, &gated, &order, 0, &accounts);
+        let tail: Vec<Pubkey> = ix.accounts[15..].iter().map(|meta| meta.pubkey).collect();
+        let blocklist = find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0;
+        let allowlist = find_allowlist_address(&store.cake, &accounts.buyer, &program_id).0;
+        assert_eq!(tail, [blocklist, allowlist, accounts.gate_token.unwrap()]);
+    }
+
+    #[test]
+    fn test_wrap_sol() {
+        let payer = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        for token_program in [spl_token::id(), spl_token_2022::id()] {
+            let mint = native_mint(&token_program);
+            let buyer_token = spl_associated_token_account::get_associated_token_address_with_program_id(&buyer, &mint, &token_program);
+            let instructions = wrap_sol_ixs(&payer, &buyer, &token_program, 5_000_000);
+            assert_eq!(instructions.len(), 2);
+            assert_eq!(instructions[0].accounts[1].pubkey, buyer_token);
+            assert_eq!(instructions[0].accounts[3].pubkey, mint);
+            assert_eq!(instructions[1].accounts[0].pubkey, buyer);
+            assert_eq!(instructions[1].accounts[1].pubkey, buyer_token);
+        }
+        assert_ne!(native_mint(&spl_token::id()), native_mint(&spl_token_2022::id()));
+    }
+    #[test]
+    fn test_localization() {
+        assert_eq!(lang_code("pt-BR"), Some(*b"pt"));
+        assert_eq!(lang_code("EN_us"), Some(*b"en"));
+        assert_eq!(lang_code("por"), None);
+        assert_eq!(lang_code("p1"), None);
+
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let ix = set_localization_ix(&program_id, &store, &store.state.owner, 2, b"en", "Carrot cake", "");
+        assert_eq!(ix.accounts[2].pubkey, find_localization_address(&store.cake, 2, b"en", &program_id).0);
+        assert_eq!(localization_addresses(&program_id, &store.cake, 2, &[*b"en"]), vec![ix.accounts[2].pubkey]);
+        let ix = remove_localization_ix(&program_id, &store, 2, b"en");
+        assert!(ix.accounts[2].is_writable && ix.accounts[2].is_signer);
+
+        let mut product = product(2);
+        product.description = "Com cobertura".to_string();
+        let localizations = [
+            Localization { product_id: 2, lang_code: *b"en", name: "Cake".to_string(), description: String::new() },
+            Localization { product_id: 2, lang_code: *b"es", name: "Pastel".to_string(), description: "Con cobertura".to_string() },
+            Localization { product_id: 3, lang_code: *b"fr", name: "Gâteau".to_string(), description: String::new() },
+        ];
+        assert_eq!(resolve_localization(&product, &localizations, &[*b"de", *b"es", *b"en"]), ("Pastel", "Con cobertura"));
+        assert_eq!(resolve_localization(&product, &localizations, &[*b"en"]), ("Cake", "Com cobertura"));
+        assert_eq!(resolve_localization(&product, &localizations, &[*b"fr"]), ("Bolo", "Com cobertura"));
+    }
+
+    #[test]
+    fn test_product_tags() {
+        assert_eq!(tag(""), None);
+        assert_eq!(tag("sem-gluten-e-sem-lactose"), None);
+        let (vegan, gluten_free, sugar_free) = (tag("vegano").unwrap(), tag("sem-gluten").unwrap(), tag("sem-acucar").unwrap());
+        assert_eq!(&vegan[..7], b"vegano\0");
+
+        // Removida primeiro, depois as novas; a mantida não entra
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let ix = set_product_tags_ix(&program_id, &store, &store.state.owner, 2, &[vegan, gluten_free], &[gluten_free, sugar_free]);
+        assert_eq!(ix.accounts.len(), 8);
+        assert_eq!(ix.accounts[6].pubkey, find_tag_index_address(&store.cake, &vegan, &program_id).0);
+        assert_eq!(ix.accounts[7].pubkey, find_tag_index_address(&store.cake, &sugar_free, &program_id).0);
+
+        // O produto novo entra nos índices das tags já no cadastro e sai deles ao ser fechado
+        let product_id = store.state.product_counter;
+        let ix = add_product_ix(&program_id, &store, &store.state.owner, "Bolo", "", 1_000, 1, 0, &[vegan, sugar_free]);
+        assert_eq!(ix.accounts.len(), 8);
+        assert_eq!(ix.accounts[5].pubkey, find_product_tags_address(&store.cake, product_id, &program_id).0);
+        assert_eq!(ix.accounts[7].pubkey, find_tag_index_address(&store.cake, &sugar_free, &program_id).0);
+        assert_eq!(add_product_ix(&program_id, &store, &store.state.owner, "Bolo", "", 1_000, 1, 0, &[]).accounts.len(), 5);
+        let ix = close_product_ix(&program_id, &store, 2, false, &[gluten_free]);
+        assert_eq!(ix.accounts.len(), 5);
+        assert_eq!(ix.accounts[3].pubkey, find_product_tags_address(&store.cake, 2, &program_id).0);
+        assert_eq!(ix.accounts[4].pubkey, find_tag_index_address(&store.cake, &gluten_free, &program_id).0);
+
+        let indexes = [
+            TagIndex { tag: vegan, product_ids: vec![1, 2, 5, 7] },
+            TagIndex { tag: gluten_free, product_ids: vec![7, 3, 2] },
+        ];
+        assert_eq!(products_with_tags(&indexes), vec![2, 7]);
+        assert_eq!(products_with_tags(&indexes[1..]), vec![7, 3, 2]);
+        assert!(products_with_tags(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_dispute_ixs() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let buyer = Pubkey::new_unique();
+        let open = open_dispute_ix(&program_id, &store, &buyer, &buyer, 4);
+        let history = find_buyer_history_address(&store.cake, &buyer, 4, &program_id).0;
+        assert_eq!(open.accounts[2].pubkey, history);
+        assert_eq!(open.accounts[3].pubkey, find_dispute_address(&history, &program_id).0);
+        assert!(open.accounts[4].is_signer && !open.accounts[4].is_writable);
+
+        let mut entry = PurchaseHistory::unpack_versioned(&[0u8; PurchaseHistory::BASE_BODY_LEN]).unwrap();
+        entry.buyer = buyer;
+        let (arbiter, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let destination = Pubkey::new_unique();
+        let resolve = resolve_dispute_ix(&program_id, &store, &arbiter, 4, &entry, true, &destination, &spl_token::id(), &mint);
+        assert_eq!(resolve.accounts.len(), 11);
+        assert_eq!(resolve.accounts[4].pubkey, open.accounts[3].pubkey);
+        assert_eq!(resolve.accounts[7].pubkey, find_history_vault_address(&history, &program_id).0);
+        let resolve = resolve_dispute_ix(&program_id, &store, &arbiter, 4, &entry, false, &destination, &spl_token_2022::id(), &mint);
+        assert_eq!(resolve.accounts.last().unwrap().pubkey, mint);
+        assert_eq!(CakeInstruction::unpack(&resolve.data).unwrap(), CakeInstruction::ResolveDispute { history_index: 4, refund: false });
+
+        // Só o reembolso de uma variante leva a PDA dela
+        entry.has_variant = true;
+        let variant = find_variant_address(&store.cake, entry.product_id, 0, &program_id).0;
+        let resolve = resolve_dispute_ix(&program_id, &store, &arbiter, 4, &entry, true, &destination, &spl_token::id(), &mint);
+        assert_eq!(resolve.accounts.len(), 12);
+        assert_eq!(resolve.accounts[11].pubkey, variant);
+        let resolve = resolve_dispute_ix(&program_id, &store, &arbiter, 4, &entry, false, &destination, &spl_token::id(), &mint);
+        assert_eq!(resolve.accounts.len(), 11);
+        let refund = refund_ix(&program_id, &store, 4, &entry, &destination, &spl_token_2022::id(), &mint);
+        assert_eq!(refund.accounts.len(), 10);
+        assert_eq!(refund.accounts[4].pubkey, store_revenue_address(&store.cake, &mint, &spl_token_2022::id(), &program_id));
+        assert_eq!(refund.accounts[5].pubkey, find_store_revenue_authority_address(&store.cake, &program_id).0);
+        assert_eq!(refund.accounts[8].pubkey, mint);
+        assert_eq!(refund.accounts[9].pubkey, variant);
+    }
+
+    #[test]
+    fn test_subscription_ixs() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let (buyer, buyer_token, mint, payer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let create = create_subscription_ix(&program_id, &store, &buyer, &buyer_token, 3, 2, 86_400, 900_000);
+        let address = find_subscription_address(&store.cake, &buyer, 3, &program_id).0;
+        assert_eq!(create.accounts[2].pubkey, address);
+        assert_eq!(
+            CakeInstruction::unpack(&create.data).unwrap(),
+            CakeInstruction::CreateSubscription { product_id: 3, quantity: 2, interval: 86_400, max_unit_price: 900_000 }
+        );
+
+        // O delegate é da assinatura, não global nem da loja
+        let delegate = find_subscription_delegate_address(&address, &program_id).0;
+        let approve =
+            approve_subscription_delegate_ix(&program_id, &store, &buyer, 3, &buyer_token, &mint, &spl_token::id(), 10, 6).unwrap();
+        assert_eq!(approve.accounts[2].pubkey, delegate);
+        let other = find_subscription_address(&store.cake, &buyer, 4, &program_id).0;
+        assert_ne!(delegate, find_subscription_delegate_address(&other, &program_id).0);
+
+        let subscription = Subscription {
+            buyer,
+            product_id: 3,
+            quantity: 2,
+            interval: 86_400,
+            next_due: 0,
+            buyer_token,
+            executions: 0,
+            max_unit_price: 900_000,
+        };
+        let process = process_subscription_ix(&program_id, &store, &subscription, 5, &payer, &mint, &spl_token::id());
+        assert_eq!(process.accounts[2].pubkey, address);
+        assert_eq!(process.accounts[3].pubkey, find_buyer_history_address(&store.cake, &buyer, 5, &program_id).0);
+        assert_eq!(process.accounts[5].pubkey, store_revenue_address(&store.cake, &mint, &spl_token::id(), &program_id));
+        assert_eq!(process.accounts[6].pubkey, delegate);
+        assert!(process.accounts[9].is_signer);
+    }
+
+    #[test]
+    fn test_sell_bundle_ix() {
+        let program_id = Pubkey::new_unique();
+        let mut store = store(Pubkey::new_unique());
+        store.state.fee_bps = 100;
+        store.state.fee_destination = Pubkey::new_unique();
+        let accounts = sell_accounts();
+        let mut limited = product(2);
+        limited.max_per_buyer = 1;
+        limited.allowlist_required = true;
+        let items = vec![BundleItem { product_id: 1, quantity: 2 }, BundleItem { product_id: 2, quantity: 1 }];
+        let bundle = Bundle { bundle_id: 4, price: 5_000_000, items };
+
+        let ix = sell_bundle_ix(&program_id, &store, &bundle, &[product(1), limited], 3, 0, &accounts);
+        assert_eq!(ix.accounts[1].pubkey, find_bundle_address(&store.cake, 4, &program_id).0);
+        // Blocklist, produto 1, produto 2 com o contador por comprador e a allowlist, e a conta de taxa por último
+        assert_eq!(ix.accounts.len(), 12 + 1 + 4 + 1);
+        assert_eq!(ix.accounts[12].pubkey, find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[13].pubkey, find_product_address(&store.cake, 1, &program_id).0);
+        assert_eq!(ix.accounts[15].pubkey, find_buyer_purchase_address(&store.cake, 2, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[16].pubkey, find_allowlist_address(&store.cake, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[17].pubkey, store.state.fee_destination);
+        assert_eq!(CakeInstruction::unpack(&ix.data).unwrap(), CakeInstruction::SellBundle { bundle_id: 4, quantity: 3 });
+    }
+
+    #[test]
+    fn test_confidential_sell_ix() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let accounts = sell_accounts();
+        let proofs = ConfidentialProofs {
+            equality: Pubkey::new_unique(),
+            ciphertext_validity: Pubkey::new_unique(),
+            range: Pubkey::new_unique(),
+            amount: Pubkey::new_unique(),
+        };
+        let mut limited = product(2);
+
+        let ix = confidential_sell_ix(&program_id, &store, &limited, 3, 1, &accounts, &proofs, [5; 36]);
+        assert_eq!(ix.accounts.len(), 18);
+        // Sem o owner: só o comprador e o payer, que paga histórico e compromissos, assinam
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, vec![accounts.buyer, accounts.rent_payer()]);
+        let history = find_buyer_history_address(&store.cake, &accounts.buyer, 1, &program_id).0;
+        assert_eq!(ix.accounts[9].pubkey, find_confidential_payment_address(&history, &program_id).0);
+        assert_eq!(ix.accounts[13].pubkey, proofs.amount);
+        assert_eq!(ix.accounts[16].pubkey, spl_token_2022::id());
+        assert_eq!(ix.accounts[17].pubkey, find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0);
+
+        limited.max_per_buyer = 1;
+        let ix = confidential_sell_ix(&program_id, &store, &limited, 3, 1, &accounts, &proofs, [5; 36]);
+        assert_eq!(ix.accounts[17].pubkey, find_buyer_purchase_address(&store.cake, 2, &accounts.buyer, &program_id).0);
+        assert_eq!(ix.accounts[18].pubkey, find_blocklist_address(&store.cake, &accounts.buyer, &program_id).0);
+
+        // Preço de 1_000_000 nas 6 casas da configuração, mint com as mesmas casas
+        let total = confidential_sell_total(&store, &limited, 3, &store.config.payment_mint).unwrap();
+        assert_eq!(total, 3_000_000);
+        assert!(confidential_sell_total(&store, &limited, 3, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn test_release_expired_escrow_ix() {
+        let program_id = Pubkey::new_unique();
+        let store = store(Pubkey::new_unique());
+        let mut entry = PurchaseHistory::unpack_versioned(&[0u8; PurchaseHistory::BASE_BODY_LEN]).unwrap();
+        entry.buyer = Pubkey::new_unique();
+        let (caller, owner_token, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = release_expired_escrow_ix(&program_id, &store, &caller, 2, &entry, &owner_token, &spl_token::id(), &mint);
+        // Ninguém assina: o crank pode ser enviado por qualquer pagador
+        assert!(ix.accounts.iter().all(|meta| !meta.is_signer));
+        assert_eq!(ix.accounts.len(), 9);
+        let history = find_buyer_history_address(&store.cake, &entry.buyer, 2, &program_id).0;
+        assert_eq!(ix.accounts[5].pubkey, find_history_vault_address(&history, &program_id).0);
+        let ix = release_expired_escrow_ix(&program_id, &store, &caller, 2, &entry, &owner_token, &spl_token_2022::id(), &mint);
+        assert_eq!(ix.accounts.last().unwrap().pubkey, mint);
+    }
+
+    #[test]
+    fn test_transfer_fee_for_net_amount() {
+        use spl_token_2022::{
+            extension::{transfer_fee::{TransferFee, TransferFeeConfig}, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut},
+            state::Mint,
+        };
+
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+        let mut data = vec![0u8; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let transfer_fee = TransferFee { epoch: 0.into(), maximum_fee: 1_000.into(), transfer_fee_basis_points: 100.into() };
+        let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = transfer_fee;
+        config.newer_transfer_fee = transfer_fee;
+        mint.base = Mint { decimals: 6, is_initialized: true, ..Mint::default() };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+
+        // 1%: para o owner receber 9_900, o comprador paga 10_000
+        assert_eq!(crate::transfer_fee_for_net_amount(&data, 5, 9_900), Ok(100));
+        assert_eq!(crate::transfer_fee_for_net_amount(&data, 5, 0), Ok(0));
+        // A taxa para em maximum_fee
+        assert_eq!(crate::transfer_fee_for_net_amount(&data, 5, 10_000_000), Ok(1_000));
+
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack(Mint { decimals: 6, is_initialized: true, ..Mint::default() }, &mut data).unwrap();
+        assert_eq!(crate::transfer_fee_for_net_amount(&data, 5, 9_900), Ok(0));
+    }
+
+    #[test]
+    fn test_store_revenue_ix() {
+        let program_id = Pubkey::new_unique();
+        let (owner, mint, destination) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let cake = find_store_address(3, &program_id).0;
+        let revenue = store_revenue_address(&cake, &mint, &spl_token::id(), &program_id);
+        let authority = find_store_revenue_authority_address(&cake, &program_id).0;
+
+        let ix = create_store_ix(&program_id, 3, &owner, &owner, &mint, &spl_token::id());
+        assert_eq!(ix.accounts.len(), 9);
+        assert_eq!((ix.accounts[5].pubkey, ix.accounts[6].pubkey), (revenue, authority));
+        assert!(ix.accounts[5].is_writable);
+        let legacy = Pubkey::new_unique();
+        let ix = initialize_ix(&program_id, &legacy, &owner, &owner, &mint, &spl_token::id());
+        assert_eq!(ix.accounts[5].pubkey, store_revenue_address(&legacy, &mint, &spl_token::id(), &program_id));
+        let ix = create_store_revenue_account_ix(&program_id, &cake, &owner, &mint, &spl_token::id());
+        assert_eq!((ix.accounts[1].pubkey, ix.accounts[2].pubkey), (revenue, authority));
+
+        let store = Store { cake, ..store(owner) };
+        let ix = withdraw_store_revenue_ix(&program_id, &store, &destination, &mint, &spl_token::id(), 100);
+        assert_eq!(ix.accounts.len(), 6);
+        assert_eq!((ix.accounts[0].pubkey, ix.accounts[2].pubkey, ix.accounts[3].pubkey), (cake, revenue, authority));
+        let ix = withdraw_store_revenue_ix(&program_id, &store, &destination, &mint, &spl_token_2022::id(), 100);
+        assert_eq!(ix.accounts.last().unwrap().pubkey, mint);
+        assert_ne!(ix.accounts[2].pubkey, revenue);
+
+        let ix = confidential_revenue_ix(&program_id, &store, &owner, &mint, vec![8, 0], &[]);
+        assert_eq!(ix.accounts[2].pubkey, store_revenue_address(&cake, &mint, &spl_token_2022::id(), &program_id));
+    }
+}
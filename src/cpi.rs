@@ -0,0 +1,119 @@
+// Composição on-chain: outro programa depende do crate com a feature `cpi` (que liga no-entrypoint), monta a
+// instrução com os builders de client e chama invoke_cidacake com as AccountInfos. As contas do cidacake lidas
+// pelo programa chamador passam pelos load_* abaixo, que conferem dono e endereço antes de confiar nos dados
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::CakeError,
+    pda::find_product_address,
+    state::{CakeState, Product, PurchaseHistory},
+};
+
+// account_infos inclui as contas da instrução em qualquer ordem; a conta do programa entra aqui. Uma PDA do
+// programa chamador que assina pela instrução (comprador, owner ou payer) passa as próprias seeds
+pub fn invoke_cidacake<'a>(
+    program: &AccountInfo<'a>,
+    ix: &Instruction,
+    account_infos: &[AccountInfo<'a>],
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    if ix.program_id != *program.key || !program.executable {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let mut infos = account_infos.to_vec();
+    infos.push(program.clone());
+    invoke_signed(ix, &infos, signer_seeds)
+}
+
+pub fn load_store(store: &AccountInfo, program_id: &Pubkey) -> Result<CakeState, ProgramError> {
+    if store.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    CakeState::unpack(&store.data.borrow())
+}
+
+pub fn load_product(product: &AccountInfo, store: &Pubkey, product_id: u64, program_id: &Pubkey) -> Result<Product, ProgramError> {
+    if product.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    if *product.key != find_product_address(store, product_id, program_id).0 {
+        return Err(CakeError::WrongPda.into());
+    }
+    Product::unpack_versioned(&product.data.borrow())
+}
+
+// O histórico não guarda a loja; quem precisa dela confere o endereço com find_buyer_history_address
+pub fn load_purchase_history(history: &AccountInfo, program_id: &Pubkey) -> Result<PurchaseHistory, ProgramError> {
+    if history.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    PurchaseHistory::unpack_versioned(&history.data.borrow())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account<'a>(key: &'a Pubkey, owner: &'a Pubkey, lamports: &'a mut u64, data: &'a mut [u8]) -> AccountInfo<'a> {
+        AccountInfo::new(key, false, false, lamports, data, owner, false, 0)
+    }
+
+    #[test]
+    fn test_load_store_checks_owner() {
+        let (program_id, key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let state = CakeState {
+            owner: Pubkey::new_unique(),
+            product_counter: 2,
+            history_counter: 5,
+            max_price_change_bps: 0,
+            paused: false,
+            order_counter: 0,
+            category_counter: 0,
+            fee_bps: 0,
+            fee_destination: Pubkey::default(),
+            escrow_timeout: 0,
+            commission_bps: 0,
+            commission_authority: Pubkey::default(),
+            commission_account: Pubkey::default(),
+            is_initialized: true,
+        };
+        let mut data = vec![0u8; CakeState::LEN];
+        CakeState::pack(state, &mut data).unwrap();
+
+        let mut lamports = 0;
+        assert_eq!(load_store(&account(&key, &program_id, &mut lamports, &mut data), &program_id), Ok(state));
+        let other = Pubkey::new_unique();
+        assert_eq!(
+            load_store(&account(&key, &other, &mut lamports, &mut data), &program_id),
+            Err(CakeError::IncorrectProgramId.into())
+        );
+    }
+
+    #[test]
+    fn test_load_product_checks_address() {
+        let (program_id, store) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let wrong = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [0u8; 8];
+        let product = account(&wrong, &program_id, &mut lamports, &mut data);
+        assert_eq!(load_product(&product, &store, 1, &program_id), Err(CakeError::WrongPda.into()));
+    }
+
+    #[test]
+    fn test_invoke_rejects_other_program() {
+        let (program_key, loader) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0;
+        let mut data = [];
+        let program = AccountInfo::new(&program_key, false, false, &mut lamports, &mut data, &loader, true, 0);
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[0], Vec::new());
+        assert_eq!(invoke_cidacake(&program, &ix, &[], &[]), Err(CakeError::IncorrectProgramId.into()));
+    }
+}
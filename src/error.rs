@@ -0,0 +1,314 @@
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+#[derive(Error, Debug, Copy, Clone)]
+pub enum CakeError {
+    #[error("Dados de instrução inválidos")]
+    InvalidInstructionData,
+    #[error("Programa incorreto")]
+    IncorrectProgramId,
+    #[error("Não autorizado")]
+    Unauthorized,
+    #[error("Estoque insuficiente")]
+    InsufficientStock,
+    #[error("Overflow aritmético")]
+    ArithmeticOverflow,
+    #[error("Produto inativo")]
+    ProductInactive,
+    #[error("Preço inválido")]
+    InvalidPrice,
+    #[error("Variação de preço acima do limite")]
+    PriceChangeTooLarge,
+    #[error("Programa pausado")]
+    ProgramPaused,
+    #[error("Produto indisponível")]
+    ProductUnavailable,
+    #[error("Compra já reembolsada")]
+    AlreadyRefunded,
+    #[error("Transição de estado do pedido inválida")]
+    InvalidOrderStatus,
+    #[error("Cupom inválido")]
+    InvalidCoupon,
+    #[error("Cupom expirado")]
+    CouponExpired,
+    #[error("Cupom esgotado")]
+    CouponExhausted,
+    #[error("Limite de compra por comprador excedido")]
+    PurchaseLimitExceeded,
+    #[error("Índice de categoria cheio")]
+    CategoryFull,
+    #[error("Conta precisa ser migrada para o layout atual")]
+    AccountNeedsMigration,
+    #[error("Prazo do escrow ainda não expirou")]
+    EscrowNotExpired,
+    #[error("Mint de pagamento não aceito")]
+    InvalidPaymentMint,
+    #[error("Conta de preço do oráculo inválida")]
+    InvalidOracleAccount,
+    #[error("Preço do oráculo desatualizado")]
+    StaleOraclePrice,
+    #[error("Intervalo de confiança do oráculo muito largo")]
+    OracleConfidenceTooWide,
+    #[error("Assinatura ainda não venceu")]
+    SubscriptionNotDue,
+    #[error("Cartão-presente inválido")]
+    InvalidGiftCard,
+    #[error("Cartão-presente expirado")]
+    GiftCardExpired,
+    #[error("Comprador fora da allowlist")]
+    BuyerNotAllowlisted,
+    #[error("Comprador bloqueado")]
+    BuyerBlocked,
+    #[error("Comprador não possui o token exigido")]
+    GateTokenRequired,
+    #[error("Produto ainda possui estoque")]
+    ProductHasStock,
+    #[error("Período de retenção do histórico ainda não terminou")]
+    HistoryRetentionActive,
+    #[error("Pré-encomendas encerradas para este lançamento")]
+    PreOrderClosed,
+    #[error("Lançamento ainda não chegou à data de liberação")]
+    LaunchNotReleased,
+    #[error("Produto fora da janela de venda")]
+    OutsideAvailabilityWindow,
+    #[error("Assinaturas insuficientes do conjunto de owners")]
+    NotEnoughApprovals,
+    #[error("Casas decimais do mint diferentes das configuradas")]
+    MintDecimalsMismatch,
+    #[error("Owner não assinou a transação")]
+    OwnerSignatureRequired,
+    #[error("Autoridade de estoque não assinou a transação")]
+    AuthoritySignatureRequired,
+    #[error("Comprador não assinou a transação")]
+    BuyerSignatureRequired,
+    #[error("Pagador do rent não assinou a transação")]
+    PayerSignatureRequired,
+    #[error("Conta já inicializada")]
+    AccountAlreadyInitialized,
+    #[error("Conta sem saldo para isenção de rent")]
+    NotRentExempt,
+    #[error("Conta de sysvar inválida")]
+    InvalidSysvarAccount,
+    #[error("Estado da loja já inicializado")]
+    AlreadyInitialized,
+    #[error("Código de indicação inválido")]
+    InvalidReferral,
+    #[error("Nenhuma recompensa de indicação pendente")]
+    NoReferralRewards,
+    #[error("Conta de token sem aprovação suficiente para o delegate de compras")]
+    DelegateNotApproved,
+    #[error("Assinatura da ordem inválida")]
+    InvalidOrderSignature,
+    #[error("Ordem expirada")]
+    OrderExpired,
+    #[error("Ordem já executada")]
+    OrderAlreadyExecuted,
+    #[error("Preço acima do máximo assinado na ordem")]
+    OrderPriceExceeded,
+    #[error("Total acima do máximo aceito pelo comprador")]
+    PriceSlippageExceeded,
+    #[error("Instrução sem dados")]
+    EmptyInstructionData,
+    #[error("Dados da instrução maiores que o layout")]
+    InstructionDataTooLong,
+    #[error("Texto não é UTF-8 válido")]
+    InvalidUtf8,
+    #[error("Texto com NUL no meio")]
+    InteriorNul,
+    #[error("Quantidade zero")]
+    ZeroQuantity,
+    #[error("Preço zero")]
+    ZeroPrice,
+    #[error("Conta do programa repetida na instrução")]
+    DuplicateAccount,
+    #[error("Código de idioma inválido")]
+    InvalidLanguageCode,
+    #[error("Tag vazia ou repetida")]
+    InvalidTag,
+    #[error("Índice da tag cheio")]
+    TagFull,
+    #[error("Disputas desativadas na loja")]
+    DisputesDisabled,
+    #[error("Prazo para abrir a disputa encerrado")]
+    DisputeWindowClosed,
+    #[error("Conta não é a PDA esperada")]
+    WrongPda,
+    #[error("Mint da conta de token diferente do esperado")]
+    WrongMint,
+    #[error("Plataforma não assinou a transação")]
+    PlatformSignatureRequired,
+    #[error("Conta menor que o layout esperado")]
+    AccountTooSmall,
+    #[error("Dados da conta fora do layout")]
+    InvalidAccountData,
+    #[error("Produto não encontrado")]
+    ProductNotFound,
+    #[error("Conta de token pertence a outra carteira")]
+    WrongTokenAccountOwner,
+    #[error("Conta de destino não pode receber os fundos")]
+    InvalidDestination,
+    #[error("Conta diferente da registrada na loja")]
+    UnexpectedAccount,
+    #[error("Compra não aceita reembolso")]
+    NotRefundable,
+    #[error("Quantidade fora do intervalo permitido")]
+    InvalidQuantity,
+    #[error("Percentual acima de 10.000 bps")]
+    InvalidBps,
+    #[error("Intervalo de datas inválido")]
+    InvalidTimeRange,
+    #[error("Valor da transferência confidencial diferente do total da compra")]
+    ConfidentialAmountMismatch,
+    #[error("Instrução confidencial não permitida na conta de receita")]
+    UnsupportedConfidentialInstruction,
+    #[error("Dados da instrução terminam no meio de um campo")]
+    InstructionDataTooShort,
+    #[error("Valor decimal inválido ou com mais casas que o mint")]
+    InvalidUiAmount,
+    #[error("Opções do sell incompatíveis entre si")]
+    IncompatibleSellOptions,
+    #[error("Novo tamanho da conta inválido ou não maior que o atual")]
+    InvalidAccountLength,
+    #[error("Threshold ou membros do conjunto de owners inválidos")]
+    InvalidOwnerSet,
+    #[error("Arredondamento sem conta de caridade")]
+    CharityAccountRequired,
+    #[error("Tradução sem nome")]
+    EmptyLocalizedName,
+    #[error("Combo com id acima do limite ou produto repetido")]
+    InvalidBundle,
+    #[error("Janela de happy hour inválida")]
+    InvalidHappyHourWindow,
+    #[error("Indicador não assinou a transação")]
+    ReferrerSignatureRequired,
+    #[error("Árbitro não assinou a transação")]
+    ArbiterSignatureRequired,
+}
+
+// Todos os erros viram ProgramError::Custom com o índice da variante, então cada falha tem um código próprio.
+// Variantes novas entram sempre no final para não mudar os códigos que clientes já conhecem
+impl From<CakeError> for ProgramError {
+    fn from(error: CakeError) -> Self {
+        ProgramError::Custom(error.code())
+    }
+}
+
+impl CakeError {
+    // Tabela de códigos para clientes: CakeError::ALL[n] é o erro de Custom(n)
+    pub const ALL: &'static [CakeError] = &[
+        CakeError::InvalidInstructionData,
+        CakeError::IncorrectProgramId,
+        CakeError::Unauthorized,
+        CakeError::InsufficientStock,
+        CakeError::ArithmeticOverflow,
+        CakeError::ProductInactive,
+        CakeError::InvalidPrice,
+        CakeError::PriceChangeTooLarge,
+        CakeError::ProgramPaused,
+        CakeError::ProductUnavailable,
+        CakeError::AlreadyRefunded,
+        CakeError::InvalidOrderStatus,
+        CakeError::InvalidCoupon,
+        CakeError::CouponExpired,
+        CakeError::CouponExhausted,
+        CakeError::PurchaseLimitExceeded,
+        CakeError::CategoryFull,
+        CakeError::AccountNeedsMigration,
+        CakeError::EscrowNotExpired,
+        CakeError::InvalidPaymentMint,
+        CakeError::InvalidOracleAccount,
+        CakeError::StaleOraclePrice,
+        CakeError::OracleConfidenceTooWide,
+        CakeError::SubscriptionNotDue,
+        CakeError::InvalidGiftCard,
+        CakeError::GiftCardExpired,
+        CakeError::BuyerNotAllowlisted,
+        CakeError::BuyerBlocked,
+        CakeError::GateTokenRequired,
+        CakeError::ProductHasStock,
+        CakeError::HistoryRetentionActive,
+        CakeError::PreOrderClosed,
+        CakeError::LaunchNotReleased,
+        CakeError::OutsideAvailabilityWindow,
+        CakeError::NotEnoughApprovals,
+        CakeError::MintDecimalsMismatch,
+        CakeError::OwnerSignatureRequired,
+        CakeError::AuthoritySignatureRequired,
+        CakeError::BuyerSignatureRequired,
+        CakeError::PayerSignatureRequired,
+        CakeError::AccountAlreadyInitialized,
+        CakeError::NotRentExempt,
+        CakeError::InvalidSysvarAccount,
+        CakeError::AlreadyInitialized,
+        CakeError::InvalidReferral,
+        CakeError::NoReferralRewards,
+        CakeError::DelegateNotApproved,
+        CakeError::InvalidOrderSignature,
+        CakeError::OrderExpired,
+        CakeError::OrderAlreadyExecuted,
+        CakeError::OrderPriceExceeded,
+        CakeError::PriceSlippageExceeded,
+        CakeError::EmptyInstructionData,
+        CakeError::InstructionDataTooLong,
+        CakeError::InvalidUtf8,
+        CakeError::InteriorNul,
+        CakeError::ZeroQuantity,
+        CakeError::ZeroPrice,
+        CakeError::DuplicateAccount,
+        CakeError::InvalidLanguageCode,
+        CakeError::InvalidTag,
+        CakeError::TagFull,
+        CakeError::DisputesDisabled,
+        CakeError::DisputeWindowClosed,
+        CakeError::WrongPda,
+        CakeError::WrongMint,
+        CakeError::PlatformSignatureRequired,
+        CakeError::AccountTooSmall,
+        CakeError::InvalidAccountData,
+        CakeError::ProductNotFound,
+        CakeError::WrongTokenAccountOwner,
+        CakeError::InvalidDestination,
+        CakeError::UnexpectedAccount,
+        CakeError::NotRefundable,
+        CakeError::InvalidQuantity,
+        CakeError::InvalidBps,
+        CakeError::InvalidTimeRange,
+        CakeError::ConfidentialAmountMismatch,
+        CakeError::UnsupportedConfidentialInstruction,
+        CakeError::InstructionDataTooShort,
+        CakeError::InvalidUiAmount,
+        CakeError::IncompatibleSellOptions,
+        CakeError::InvalidAccountLength,
+        CakeError::InvalidOwnerSet,
+        CakeError::CharityAccountRequired,
+        CakeError::EmptyLocalizedName,
+        CakeError::InvalidBundle,
+        CakeError::InvalidHappyHourWindow,
+        CakeError::ReferrerSignatureRequired,
+        CakeError::ArbiterSignatureRequired,
+    ];
+
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        assert_eq!(CakeError::ALL.len(), CakeError::ArbiterSignatureRequired.code() as usize + 1);
+        for (code, error) in CakeError::ALL.iter().enumerate() {
+            assert_eq!(error.code(), code as u32);
+            assert_eq!(CakeError::from_code(code as u32).map(CakeError::code), Some(code as u32));
+            assert_eq!(ProgramError::from(*error), ProgramError::Custom(code as u32));
+        }
+        assert!(CakeError::from_code(CakeError::ALL.len() as u32).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::CakeError, state::MAX_MEMO_LEN};
+
+// Eventos binários emitidos via sol_log_data ("Program data: <base64>" nos logs).
+// Cada evento é [discriminador 8][struct em Borsh]; o discriminador é sha256("event:<Nome>")[..8],
+// como no Anchor, e os campos só podem ser acrescentados ao final
+
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ProductAdded {
+    pub product_id: u64,
+    pub price: u64,
+    pub stock: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct Sold {
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub quantity: u64,
+    pub total_price: u64,
+    pub payment_mode: u8,
+    pub payment_mint: Pubkey,
+    // Sequência do histórico do comprador: PDA ["history", buyer, history_index]; com paged_history,
+    // página * HISTORY_PAGE_CAPACITY + posição na página
+    pub history_index: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct Refunded {
+    pub history_index: u64,
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub quantity: u64,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PriceChanged {
+    pub product_id: u64,
+    pub old_price: u64,
+    pub new_price: u64,
+}
+
+// Recado gravado na PDA ["note", history] junto com a venda; memo[..memo_len] são os bytes do recado
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct NoteAttached {
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub history_index: u64,
+    pub memo_len: u8,
+    pub memo: [u8; MAX_MEMO_LEN],
+}
+
+impl NoteAttached {
+    pub fn new(product_id: u64, buyer: Pubkey, history_index: u64, memo: &[u8]) -> Self {
+        let mut padded = [0u8; MAX_MEMO_LEN];
+        padded[..memo.len()].copy_from_slice(memo);
+        Self { product_id, buyer, history_index, memo_len: memo.len() as u8, memo: padded }
+    }
+
+    pub fn memo(&self) -> &[u8] {
+        &self.memo[..(self.memo_len as usize).min(MAX_MEMO_LEN)]
+    }
+}
+
+// Comprador contestou o escrow da compra; amount fica retido no cofre até a decisão
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct DisputeOpened {
+    pub history_index: u64,
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub amount: u64,
+}
+
+// Decisão do árbitro; status é DISPUTE_STATUS_REFUNDED (ao comprador) ou DISPUTE_STATUS_RELEASED (ao owner)
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct DisputeResolved {
+    pub history_index: u64,
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub arbiter: Pubkey,
+    pub status: u8,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CakeEvent {
+    ProductAdded(ProductAdded),
+    Sold(Sold),
+    Refunded(Refunded),
+    PriceChanged(PriceChanged),
+    NoteAttached(NoteAttached),
+    DisputeOpened(DisputeOpened),
+    DisputeResolved(DisputeResolved),
+}
+
+impl CakeEvent {
+    pub const PRODUCT_ADDED_DISCRIMINATOR: [u8; 8] = [148, 19, 172, 135, 92, 199, 234, 235];
+    pub const SOLD_DISCRIMINATOR: [u8; 8] = [205, 203, 210, 202, 96, 11, 192, 10];
+    pub const REFUNDED_DISCRIMINATOR: [u8; 8] = [35, 103, 149, 246, 196, 123, 221, 99];
+    pub const PRICE_CHANGED_DISCRIMINATOR: [u8; 8] = [251, 22, 83, 247, 153, 87, 138, 30];
+    pub const NOTE_ATTACHED_DISCRIMINATOR: [u8; 8] = [19, 10, 29, 14, 189, 43, 187, 141];
+    pub const DISPUTE_OPENED_DISCRIMINATOR: [u8; 8] = [239, 222, 102, 235, 193, 85, 1, 214];
+    pub const DISPUTE_RESOLVED_DISCRIMINATOR: [u8; 8] = [121, 64, 249, 153, 139, 128, 236, 187];
+
+    pub fn encode(&self) -> Vec<u8> {
+        let (discriminator, body) = match self {
+            CakeEvent::ProductAdded(event) => (Self::PRODUCT_ADDED_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::Sold(event) => (Self::SOLD_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::Refunded(event) => (Self::REFUNDED_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::PriceChanged(event) => (Self::PRICE_CHANGED_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::NoteAttached(event) => (Self::NOTE_ATTACHED_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::DisputeOpened(event) => (Self::DISPUTE_OPENED_DISCRIMINATOR, event.try_to_vec()),
+            CakeEvent::DisputeResolved(event) => (Self::DISPUTE_RESOLVED_DISCRIMINATOR, event.try_to_vec()),
+        };
+        let mut data = discriminator.to_vec();
+        data.extend_from_slice(&body.expect("eventos têm apenas campos de tamanho fixo"));
+        data
+    }
+
+    // Decodifica os bytes de um "Program data" já convertidos de base64; bytes extras ao final
+    // (campos de versões mais novas) são ignorados
+    pub fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < 8 {
+            return Err(CakeError::InvalidInstructionData.into());
+        }
+        let (discriminator, mut body) = data.split_at(8);
+        let invalid = |_| ProgramError::from(CakeError::InvalidInstructionData);
+        let event = match <[u8; 8]>::try_from(discriminator).unwrap() {
+            Self::PRODUCT_ADDED_DISCRIMINATOR => CakeEvent::ProductAdded(ProductAdded::deserialize(&mut body).map_err(invalid)?),
+            Self::SOLD_DISCRIMINATOR => CakeEvent::Sold(Sold::deserialize(&mut body).map_err(invalid)?),
+            Self::REFUNDED_DISCRIMINATOR => CakeEvent::Refunded(Refunded::deserialize(&mut body).map_err(invalid)?),
+            Self::PRICE_CHANGED_DISCRIMINATOR => CakeEvent::PriceChanged(PriceChanged::deserialize(&mut body).map_err(invalid)?),
+            Self::NOTE_ATTACHED_DISCRIMINATOR => CakeEvent::NoteAttached(NoteAttached::deserialize(&mut body).map_err(invalid)?),
+            Self::DISPUTE_OPENED_DISCRIMINATOR => CakeEvent::DisputeOpened(DisputeOpened::deserialize(&mut body).map_err(invalid)?),
+            Self::DISPUTE_RESOLVED_DISCRIMINATOR => {
+                CakeEvent::DisputeResolved(DisputeResolved::deserialize(&mut body).map_err(invalid)?)
+            }
+            _ => return Err(CakeError::InvalidInstructionData.into()),
+        };
+        Ok(event)
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.encode()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let events = [
+            CakeEvent::ProductAdded(ProductAdded { product_id: 1, price: 1_000_000, stock: 100 }),
+            CakeEvent::Sold(Sold {
+                product_id: 1,
+                buyer: Pubkey::new_from_array([1; 32]),
+                quantity: 2,
+                total_price: 2_000_000,
+                payment_mode: 0,
+                payment_mint: Pubkey::new_from_array([2; 32]),
+                history_index: 7,
+            }),
+            CakeEvent::Refunded(Refunded {
+                history_index: 7,
+                product_id: 1,
+                buyer: Pubkey::new_from_array([1; 32]),
+                quantity: 1,
+                amount: 1_000_000,
+            }),
+            CakeEvent::PriceChanged(PriceChanged { product_id: 1, old_price: 1_000_000, new_price: 1_100_000 }),
+            CakeEvent::NoteAttached(NoteAttached::new(1, Pubkey::new_from_array([1; 32]), 7, "Feliz aniversário, Ana".as_bytes())),
+            CakeEvent::DisputeOpened(DisputeOpened {
+                history_index: 7,
+                product_id: 1,
+                buyer: Pubkey::new_from_array([1; 32]),
+                amount: 2_000_000,
+            }),
+            CakeEvent::DisputeResolved(DisputeResolved {
+                history_index: 7,
+                product_id: 1,
+                buyer: Pubkey::new_from_array([1; 32]),
+                arbiter: Pubkey::new_from_array([3; 32]),
+                status: 1,
+                amount: 2_000_000,
+            }),
+        ];
+        for event in events {
+            assert_eq!(CakeEvent::decode(&event.encode()).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn test_discriminators() {
+        for (name, discriminator) in [
+            ("ProductAdded", CakeEvent::PRODUCT_ADDED_DISCRIMINATOR),
+            ("Sold", CakeEvent::SOLD_DISCRIMINATOR),
+            ("Refunded", CakeEvent::REFUNDED_DISCRIMINATOR),
+            ("PriceChanged", CakeEvent::PRICE_CHANGED_DISCRIMINATOR),
+            ("NoteAttached", CakeEvent::NOTE_ATTACHED_DISCRIMINATOR),
+            ("DisputeOpened", CakeEvent::DISPUTE_OPENED_DISCRIMINATOR),
+            ("DisputeResolved", CakeEvent::DISPUTE_RESOLVED_DISCRIMINATOR),
+        ] {
+            let hash = solana_program::hash::hash(format!("event:{}", name).as_bytes()).to_bytes();
+            assert_eq!(hash[..8], discriminator);
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_and_truncated() {
+        assert!(CakeEvent::decode(&[0; 4]).is_err());
+        assert!(CakeEvent::decode(&[0; 40]).is_err());
+        let data = CakeEvent::PriceChanged(PriceChanged { product_id: 1, old_price: 2, new_price: 3 }).encode();
+        assert!(CakeEvent::decode(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,2586 @@
+use shank::{ShankInstruction, ShankType};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{
+    error::CakeError,
+    state::{
+        AcceptedMint, BundleItem, HappyHourWindow, PriceTier, SplitRecipient, CHECKOUT_LEGACY_VERSION, CHECKOUT_VERSION,
+        MAX_ACCEPTED_MINTS, MAX_ARCHIVE_BATCH,
+        MAX_BUNDLE_ITEMS, MAX_CART_ITEMS, MAX_COUPON_CODE_LEN, MAX_HAPPY_HOUR_WINDOWS, MAX_MEMO_LEN, MAX_OWNER_SET_MEMBERS,
+        MAX_PRICE_TIERS, MAX_PRODUCT_BATCH, MAX_PRODUCT_DESCRIPTION_LEN, MAX_PRODUCT_NAME_LEN, MAX_PRODUCT_TAGS, MAX_SPLIT_RECIPIENTS,
+        PAYMENT_MODE_TOKEN, SELL_LEGACY_VERSION, SELL_VERSION, TAG_LEN,
+    },
+    validation::clean_text,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankType)]
+pub struct CartItem {
+    pub product_id: u64,
+    pub quantity: u64,
+}
+
+// Ordem assinada off-chain pelo comprador ("assina agora, envia depois"). A assinatura ed25519 cobre
+// message(), que amarra a ordem à loja; quem envia inclui a instrução do programa ed25519 logo antes
+#[derive(Debug, Clone, Copy, PartialEq, ShankType)]
+pub struct SignedOrder {
+    pub product_id: u64,
+    pub quantity: u64,
+    // Total máximo em unidades do mint
+    pub max_price: u64,
+    // Unix timestamp a partir do qual a ordem não vale mais
+    pub expiry: i64,
+    // Uma ordem por nonce: a execução cria a PDA ["order_nonce", cake, buyer, nonce]
+    pub nonce: u64,
+}
+
+pub const SIGNED_ORDER_DOMAIN: &[u8; 16] = b"cidacake-order-1";
+
+impl SignedOrder {
+    pub const MESSAGE_LEN: usize = 16 + 32 + 40;
+
+    // [domínio][store][product_id][quantity][max_price][expiry][nonce], inteiros em little-endian
+    pub fn message(&self, store: &Pubkey) -> [u8; Self::MESSAGE_LEN] {
+        let mut message = [0u8; Self::MESSAGE_LEN];
+        message[..16].copy_from_slice(SIGNED_ORDER_DOMAIN);
+        message[16..48].copy_from_slice(store.as_ref());
+        message[48..56].copy_from_slice(&self.product_id.to_le_bytes());
+        message[56..64].copy_from_slice(&self.quantity.to_le_bytes());
+        message[64..72].copy_from_slice(&self.max_price.to_le_bytes());
+        message[72..80].copy_from_slice(&self.expiry.to_le_bytes());
+        message[80..88].copy_from_slice(&self.nonce.to_le_bytes());
+        message
+    }
+}
+
+// Return data do GetFullQuote, em unidades do mint: amount_due = total_price + fee é o que o sell cobra
+// do comprador (sem gorjeta e sem arredondamento, que ele escolhe à parte). A taxa de transferência de
+// mints Token-2022 com TransferFeeConfig é debitada por cima (transfer_fee_for_net_amount)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Quote {
+    // Preço das unidades antes dos descontos
+    pub subtotal: u64,
+    // Promoção relâmpago e cupom somados
+    pub discount: u64,
+    pub total_price: u64,
+    // Taxa de serviço (fee_bps) sobre total_price
+    pub fee: u64,
+    pub amount_due: u64,
+}
+
+impl Quote {
+    pub const LEN: usize = 40;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        for (chunk, value) in data.chunks_exact_mut(8).zip([self.subtotal, self.discount, self.total_price, self.fee, self.amount_due]) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+        let value = |index: usize| u64::from_le_bytes(data[index * 8..index * 8 + 8].try_into().unwrap());
+        Some(Self { subtotal: value(0), discount: value(1), total_price: value(2), fee: value(3), amount_due: value(4) })
+    }
+}
+
+// Produto do AddProducts, com os mesmos campos do AddProduct
+#[derive(Debug, Clone, PartialEq, ShankType)]
+pub struct NewProduct {
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub stock: u64,
+    pub max_per_buyer: u64,
+}
+
+// Instruções do programa; o primeiro byte dos dados é o tag e os campos seguem em little-endian.
+// Os atributos #[account] descrevem as contas de cada instrução para o IDL gerado pelo shank; contas
+// opcionais são omitidas quando não se aplicam, sem deixar lugar vazio
+#[derive(Debug, Clone, PartialEq, ShankInstruction)]
+#[repr(u8)]
+pub enum CakeInstruction {
+    // 0: loja legada em conta criada fora do programa; lojas novas usam CreateStore. Cria também a conta de
+    // receita da loja para payment_mint
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner da loja")]
+    #[account(2, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(3, name = "system_program", desc = "System program")]
+    #[account(4, name = "payment_mint", desc = "Mint da conta de receita da loja")]
+    #[account(5, writable, name = "revenue_token", desc = "ATA da autoridade de receita para payment_mint; criada aqui")]
+    #[account(6, name = "revenue_authority", desc = "PDA [\"store_revenue\", cake]")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(8, name = "associated_token_program", desc = "Associated Token Account program")]
+    Initialize,
+    // 1: campos fixos [name 32][description 128], opcionalmente substituídos por textos longos e seguidos das tags
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_counter]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    #[account(5, writable, optional, name = "product_tags", desc = "PDA [\"product_tags\", cake, product_counter]; apenas com tags")]
+    #[account(6, writable, optional, name = "tag_indexes", desc = "PDAs [\"tag_index\", cake, tag], uma por tag, na ordem das tags")]
+    AddProduct { name: String, description: String, price: u64, stock: u64, max_per_buyer: u64, tags: Vec<[u8; 16]> },
+    // 2: somente leitura; devolve via return data [total_price u64][unit_price u64], com a faixa de preço aplicada
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    GetQuote { product_id: u64, amount: u64 },
+    // 3: delta positivo repõe e negativo baixa o estoque; não fica abaixo de zero
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    UpdateStock { product_id: u64, delta: i64 },
+    // 4: extensões opcionais após o amount, na ordem dos campos abaixo
+    #[legacy_optional_accounts_strategy]
+    #[account(0, optional, signer, name = "authority", desc = "Owner ou operador; apenas com version 1 ou 2")]
+    #[account(1, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(2, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(3, writable, signer, name = "buyer", desc = "Comprador")]
+    #[account(4, name = "system_program", desc = "System program")]
+    #[account(5, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count] ou, com paged_history, PDA [\"history_page\", cake, HistoryPageHead::write_page]")]
+    #[account(6, writable, signer, name = "payer", desc = "Paga o rent das contas criadas (normalmente o próprio comprador); gravado como rent_payer do histórico")]
+    #[account(7, optional, name = "clock", desc = "Sysvar Clock; apenas com version 1")]
+    #[account(8, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(9, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(10, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(11, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(12, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(13, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer] ou, com paged_history, PDA [\"history_head\", cake]; criada na primeira compra")]
+    #[account(14, writable, optional, name = "purchase_index", desc = "PDA [\"purchase_index\", cake, product_id]; criada na primeira venda do produto; apenas sem paged_history")]
+    #[account(15, optional, name = "operator", desc = "PDA [\"operator\", cake, authority]; apenas com authority que não é o owner")]
+    #[account(16, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(17, writable, name = "global_stats", desc = "PDA [\"global_stats\"]; pode não existir")]
+    #[account(18, name = "flash_sale", desc = "PDA [\"flash_sale\", cake, product_id]; pode não existir")]
+    #[account(19, optional, name = "happy_hour", desc = "PDA [\"happy_hour\", cake, product_id]; a partir da version 4; pode não existir")]
+    #[account(20, writable, name = "buyer_profile", desc = "PDA [\"buyer_profile\", buyer]; pode não existir")]
+    #[account(21, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(22, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    #[account(23, optional, name = "price_feed", desc = "Oráculo de preço; apenas no modo SOL com usd_price_cents")]
+    #[account(24, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas com variant_id")]
+    #[account(25, writable, optional, name = "coupon", desc = "PDA [\"coupon\", cake, sha256(coupon_code)]; apenas com coupon_code")]
+    #[account(26, writable, optional, name = "buyer_purchase", desc = "PDA [\"buyer_purchase\", cake, product_id, buyer]; apenas quando max_per_buyer > 0")]
+    #[account(27, writable, optional, name = "owner_wallet", desc = "Carteira do owner; no modo SOL ou com create_ata")]
+    #[account(28, optional, name = "associated_token_program", desc = "Associated Token program; apenas com create_ata")]
+    #[account(29, writable, optional, name = "gift_card", desc = "PDA [\"gift_card\", cake, sha256(gift_code)]; apenas com gift_code")]
+    #[account(30, writable, optional, name = "treasury_vault", desc = "PDA [\"treasury_vault\", cake, payment_mint]; apenas com gift_code")]
+    #[account(31, optional, name = "treasury_authority", desc = "PDA [\"treasury\"]; apenas com gift_code")]
+    #[account(32, writable, optional, name = "escrow_vault", desc = "PDA [\"history_vault\", history]; apenas com escrow")]
+    #[account(33, optional, name = "escrow_authority", desc = "PDA [\"escrow\"]; apenas com escrow")]
+    #[account(34, writable, optional, name = "commission_token", desc = "commission_account do estado; apenas sem escrow e com commission_bps > 0")]
+    #[account(35, writable, optional, name = "referral", desc = "PDA [\"referral\", cake, sha256(referral_code)]; apenas com referral_code")]
+    #[account(36, writable, optional, name = "referral_vault", desc = "PDA [\"treasury_vault\", cake, payment_mint]; apenas com referral_code")]
+    #[account(37, writable, optional, name = "split_recipient_tokens", desc = "Uma conta de token por destinatário de Config::splits, na ordem; apenas sem escrow")]
+    #[account(38, writable, optional, name = "fee_token", desc = "Conta de token de fee_destination; apenas quando fee_bps > 0")]
+    #[account(39, writable, optional, name = "tip_token", desc = "tip_account da configuração; apenas com tip > 0")]
+    #[account(40, writable, optional, name = "charity_token", desc = "charity_account da configuração; apenas com round_up")]
+    #[account(41, writable, optional, name = "receipt_mint", desc = "PDA [\"receipt_mint\", history]; apenas com mint_receipt")]
+    #[account(42, writable, optional, name = "receipt_token", desc = "ATA do comprador para o receipt_mint; apenas com mint_receipt")]
+    #[account(43, optional, name = "receipt_authority", desc = "PDA [\"receipt_authority\"]; apenas com mint_receipt")]
+    #[account(44, writable, optional, name = "metadata", desc = "Metadata do receipt_mint; apenas com mint_receipt")]
+    #[account(45, writable, optional, name = "master_edition", desc = "Master edition do receipt_mint; apenas com mint_receipt")]
+    #[account(46, optional, name = "token_metadata_program", desc = "Token Metadata program; apenas com mint_receipt")]
+    #[account(47, optional, name = "receipt_token_program", desc = "SPL Token; apenas com mint_receipt")]
+    #[account(48, optional, name = "receipt_associated_token_program", desc = "Associated Token program; apenas com mint_receipt")]
+    #[account(49, writable, optional, name = "note", desc = "PDA [\"note\", history]; apenas com memo")]
+    Sell {
+        product_id: u64,
+        amount: u64,
+        coupon_code: Option<Vec<u8>>,
+        variant_id: Option<u8>,
+        escrow: bool,
+        payment_mode: u8,
+        create_ata: bool,
+        gift_code: Option<Vec<u8>>,
+        mint_receipt: bool,
+        // Bump da PDA do produto calculado off-chain; evita o find_program_address no programa
+        product_bump: Option<u8>,
+        // Grava a venda na página atual do histórico paginado em vez de criar um PurchaseHistory
+        paged_history: bool,
+        // Layout de contas esperado (SELL_VERSION); dados sem o byte são da versão 1, com authority e Clock
+        version: u8,
+        // Recado do comprador (até MAX_MEMO_LEN bytes), gravado na PDA ["note", history]
+        memo: Option<Vec<u8>>,
+        // Código de indicação; credita Config::referral_bps do líquido da venda ao referrer
+        referral_code: Option<Vec<u8>>,
+        // Gorjeta em unidades do mint, transferida à parte para Config::tip_account; 0 = sem gorjeta
+        tip: u64,
+        // Arredonda o total para cima até Config::round_up_unit e doa a diferença a Config::charity_account
+        round_up: bool,
+        // wSOL: sincroniza o saldo da conta do comprador (sync_native) antes de cobrar
+        sync_native: bool,
+        // wSOL: fecha a conta do comprador depois do pagamento, devolvendo os lamports restantes a ele
+        close_wsol: bool,
+        // Total máximo aceito pelo comprador (unidades do mint ou lamports); 0 = sem limite
+        max_total_price: u64,
+    } = 4,
+    // 5
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    DeactivateProduct { product_id: u64 },
+    // 6
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "authority", desc = "Owner ou operador")]
+    #[account(3, optional, name = "operator", desc = "PDA [\"operator\", cake, authority]; apenas quando authority não é o owner")]
+    #[account(4, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas com variant_id")]
+    Restock { product_id: u64, amount: u64, variant_id: Option<u8> },
+    // 7
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    UpdatePrice { product_id: u64, new_price: u64 },
+    // 8
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetMaxPriceChange { max_price_change_bps: u16 },
+    // 9
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "operator_role", desc = "PDA [\"operator\", cake, operator]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    AddOperator { operator: Pubkey },
+    // 10
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "operator_role", desc = "PDA [\"operator\", cake, operator]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    RemoveOperator { operator: Pubkey },
+    // 11
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    Pause,
+    // 12
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    Resume,
+    // 13
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "authority", desc = "Owner ou operador")]
+    #[account(3, optional, name = "operator", desc = "PDA [\"operator\", cake, authority]; apenas quando authority não é o owner")]
+    SetAvailability { product_id: u64, available: bool },
+    // 14
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(3, writable, signer, name = "owner", desc = "Owner registrado no estado; paga o reembolso em SOL")]
+    #[account(4, writable, name = "revenue_token", desc = "Conta de receita da loja no mint da compra; paga o reembolso em token")]
+    #[account(5, name = "revenue_authority", desc = "PDA [\"store_revenue\", cake]")]
+    #[account(6, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(8, writable, optional, name = "buyer_wallet", desc = "Carteira do comprador; apenas em compras em SOL")]
+    #[account(9, optional, name = "system_program", desc = "System program; apenas em compras em SOL")]
+    #[account(10, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    #[account(11, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas em vendas de variante")]
+    Refund { history_index: u64 },
+    // 15
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "order", desc = "PDA [\"order\", cake, order_counter]")]
+    #[account(3, writable, signer, name = "buyer", desc = "Comprador; paga a criação das contas")]
+    #[account(4, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"order_vault\", cake, order_counter]")]
+    #[account(6, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(7, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(8, name = "system_program", desc = "System program")]
+    #[account(9, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(10, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(11, writable, optional, name = "buyer_purchase", desc = "PDA [\"buyer_purchase\", cake, product_id, buyer]; apenas quando max_per_buyer > 0")]
+    #[account(12, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(13, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(14, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    PlaceOrder { product_id: u64, quantity: u64 },
+    // 16
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "order", desc = "PDA [\"order\", cake, order_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, name = "buyer", desc = "Comprador do pedido; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"order_vault\", cake, order_id]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja (fulfill) ou do comprador (cancel)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    FulfillOrder { order_id: u64 },
+    // 17
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "order", desc = "PDA [\"order\", cake, order_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, name = "buyer", desc = "Comprador do pedido; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"order_vault\", cake, order_id]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja (fulfill) ou do comprador (cancel)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    CancelOrder { order_id: u64 },
+    // 18: version após os itens (CHECKOUT_VERSION); sem o byte, o layout 1
+    #[legacy_optional_accounts_strategy]
+    #[account(0, optional, signer, name = "authority", desc = "Owner ou operador; apenas com version 1")]
+    #[account(1, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(2, writable, signer, name = "buyer", desc = "Comprador")]
+    #[account(3, name = "system_program", desc = "System program")]
+    #[account(4, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(5, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(6, optional, name = "clock", desc = "Sysvar Clock; apenas com version 1")]
+    #[account(7, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(8, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(9, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(10, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(11, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(12, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    #[account(13, optional, name = "operator", desc = "PDA [\"operator\", cake, authority]; apenas com version 1 e authority que não é o owner")]
+    #[account(14, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(15, writable, name = "products", desc = "Para cada item, na ordem: PDA [\"product\", cake, product_id], PDA [\"buyer_purchase\", cake, product_id, buyer] se max_per_buyer > 0, PDA [\"allowlist\", cake, buyer] se o produto exige allowlist e conta de token do gate_mint se houver")]
+    #[account(16, writable, optional, name = "fee_token", desc = "Conta de token de fee_destination; apenas quando fee_bps > 0")]
+    Checkout { items: Vec<CartItem>, version: u8 },
+    // 19
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "coupon", desc = "PDA [\"coupon\", cake, code_hash]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    CreateCoupon { code_hash: [u8; 32], kind: u8, value: u64, expires_at: i64, max_uses: u64 },
+    // 20
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetMaxPerBuyer { product_id: u64, max_per_buyer: u64 },
+    // 21
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "category", desc = "PDA [\"category\", cake, category_counter]")]
+    #[account(2, writable, name = "category_index", desc = "PDA [\"category_index\", cake, category_counter]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    CreateCategory { name: [u8; 32] },
+    // 22
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, optional, name = "old_category_index", desc = "PDA [\"category_index\", cake, categoria atual]; apenas quando o produto já tem categoria")]
+    #[account(4, writable, optional, name = "new_category_index", desc = "PDA [\"category_index\", cake, category_id]; apenas quando category_id > 0")]
+    SetProductCategory { product_id: u64, category_id: u64 },
+    // 23
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    AddVariant { product_id: u64, variant_id: u8, name: [u8; 32], price: u64, stock: u64 },
+    // 24
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    MigrateProduct { product_id: u64 },
+    // 25
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetMetadataUri { product_id: u64, metadata_uri: [u8; 200] },
+    // 26
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetPriceTiers { product_id: u64, tiers: Vec<PriceTier> },
+    // 27
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetFee { fee_bps: u16, fee_destination: Pubkey },
+    // 28
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]; atualizado apenas em refund_escrow")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(3, signer, name = "authority", desc = "Comprador da compra")]
+    #[account(4, writable, name = "buyer", desc = "Comprador; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"history_vault\", history]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja ou do comprador (refund_escrow)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    ConfirmDelivery { history_index: u64 },
+    // 29
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]; atualizado apenas em refund_escrow")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(3, signer, name = "authority", desc = "Owner da compra")]
+    #[account(4, writable, name = "buyer", desc = "Comprador; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"history_vault\", history]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja ou do comprador (refund_escrow)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    ReleaseAfterTimeout { history_index: u64 },
+    // 30
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]; atualizado apenas em refund_escrow")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(3, signer, name = "authority", desc = "Owner da compra")]
+    #[account(4, writable, name = "buyer", desc = "Comprador; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"history_vault\", history]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja ou do comprador (refund_escrow)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    #[account(10, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas em vendas de variante")]
+    RefundEscrow { history_index: u64 },
+    // 31
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetEscrowTimeout { escrow_timeout: i64 },
+    // 32
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(3, writable, signer, name = "owner", desc = "Owner registrado no estado; paga o reembolso em SOL")]
+    #[account(4, writable, name = "revenue_token", desc = "Conta de receita da loja no mint da compra; paga o reembolso em token")]
+    #[account(5, name = "revenue_authority", desc = "PDA [\"store_revenue\", cake]")]
+    #[account(6, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(8, writable, optional, name = "buyer_wallet", desc = "Carteira do comprador; apenas em compras em SOL")]
+    #[account(9, optional, name = "system_program", desc = "System program; apenas em compras em SOL")]
+    #[account(10, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    #[account(11, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas em vendas de variante")]
+    PartialRefund { history_index: u64, quantity: u64 },
+    // 33
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetSolPrice { product_id: u64, sol_price: u64 },
+    // 34: sem a lista de mints, price_decimals = 0 e nenhum mint adicional
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    SetConfig { payment_mint: Pubkey, price_decimals: u8, accepted_mints: Vec<AcceptedMint> },
+    // 35
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetUsdPrice { product_id: u64, usd_price_cents: u64, price_feed: Pubkey },
+    // 36
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, signer, name = "owner", desc = "Owner registrado no estado; paga a criação das contas")]
+    #[account(2, writable, name = "treasury_vault", desc = "PDA [\"treasury_vault\", cake, mint]")]
+    #[account(3, name = "treasury_authority", desc = "PDA [\"treasury\"]")]
+    #[account(4, name = "mint", desc = "Mint do cofre")]
+    #[account(5, name = "system_program", desc = "System program")]
+    #[account(6, name = "token_program", desc = "SPL Token ou Token-2022")]
+    CreateTreasury,
+    // 37
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, name = "treasury_vault", desc = "PDA [\"treasury_vault\", cake, mint]")]
+    #[account(3, name = "treasury_authority", desc = "PDA [\"treasury\"]")]
+    #[account(4, writable, name = "destination_token", desc = "Conta de token de destino")]
+    #[account(5, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(6, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    WithdrawTreasury { amount: u64 },
+    // 38
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetRevenueSplit { splits: Vec<SplitRecipient> },
+    // 39
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, signer, name = "platform", desc = "Plataforma da comissão; vira commission_authority e precisa coincidir com a atual, se houver")]
+    SetCommission { commission_bps: u16, commission_account: Pubkey },
+    // 40: max_unit_price em unidades do mint da buyer_token; process_subscription não cobra acima dele
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "subscription", desc = "PDA [\"subscription\", cake, buyer, product_id]")]
+    #[account(3, writable, signer, name = "buyer", desc = "Comprador; paga a criação da conta")]
+    #[account(4, name = "buyer_token", desc = "Conta de token do comprador com aprovação para o delegate")]
+    #[account(5, name = "system_program", desc = "System program")]
+    CreateSubscription { product_id: u64, quantity: u64, interval: i64, max_unit_price: u64 },
+    // 41
+    #[account(0, writable, name = "subscription", desc = "PDA [\"subscription\", cake, buyer, product_id]")]
+    #[account(1, writable, signer, name = "buyer", desc = "Comprador; recebe o rent")]
+    CancelSubscription,
+    // 42
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "subscription", desc = "PDA [\"subscription\", cake, buyer, product_id]")]
+    #[account(3, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(4, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(5, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(6, name = "delegate", desc = "PDA [\"subscription_delegate\", subscription]")]
+    #[account(7, name = "payment_mint", desc = "Mint do pagamento")]
+    #[account(8, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(9, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(10, name = "system_program", desc = "System program")]
+    #[account(11, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(12, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    ProcessSubscription,
+    // 43
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, signer, name = "owner", desc = "Owner registrado no estado; paga a criação das contas")]
+    #[account(2, writable, name = "gift_card", desc = "PDA [\"gift_card\", cake, code_hash]")]
+    #[account(3, writable, name = "owner_token", desc = "Conta de token do owner")]
+    #[account(4, writable, name = "treasury_vault", desc = "PDA [\"treasury_vault\", cake, mint]")]
+    #[account(5, name = "mint", desc = "Mint do cartão-presente")]
+    #[account(6, name = "system_program", desc = "System program")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    CreateGiftCard { code_hash: [u8; 32], balance: u64, expires_at: i64 },
+    // 44
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    AddToAllowlist { buyer: Pubkey },
+    // 45
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    RemoveFromAllowlist { buyer: Pubkey },
+    // 46
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetAllowlistRequired { product_id: u64, required: bool },
+    // 47
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    AddToBlocklist { buyer: Pubkey },
+    // 48
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    RemoveFromBlocklist { buyer: Pubkey },
+    // 49
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetGateMint { product_id: u64, gate_mint: Pubkey },
+    // 50: aumenta uma conta do programa (produto, estado, ...) para new_len bytes
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "target", desc = "Conta do programa a aumentar")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    ExtendAccount { new_len: u64 },
+    // 51: force fecha mesmo com estoque
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    #[account(3, writable, name = "product_tags", desc = "PDA [\"product_tags\", cake, product_id]; pode não existir; fechada junto com o produto")]
+    #[account(4, writable, name = "tag_indexes", desc = "PDAs [\"tag_index\", cake, tag] das tags do produto, na ordem de ProductTags")]
+    CloseProduct { product_id: u64, force: bool },
+    // 52: segundos após a compra em que o histórico não pode ser fechado
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetHistoryRetention { history_retention: i64 },
+    // 53: o rent volta para o rent_payer gravado no histórico
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index]; históricos antigos usam [\"history\", buyer, product_id, history_index]")]
+    #[account(2, writable, signer, name = "authority", desc = "rent_payer do histórico ou owner, depois da retenção configurada (o rent_payer também antes, se o histórico foi reembolsado); recebe o rent quando é o rent_payer ou o histórico não tem um")]
+    #[account(3, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(4, writable, optional, name = "rent_payer", desc = "rent_payer do histórico; recebe o rent; apenas quando o owner fecha o histórico de uma venda paga por outra carteira")]
+    CloseHistory { history_index: u64 },
+    // 54
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "global_stats", desc = "PDA [\"global_stats\"]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    CreateGlobalStats,
+    // 55: acrescenta o discriminador ao estado de layout anterior
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(3, name = "system_program", desc = "System program")]
+    MigrateState,
+    // 56: [archive_id u64][count u8][history_index u64 * count]; fecha os históricos e guarda só a raiz merkle
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "archive", desc = "PDA [\"archive\", cake, archive_id]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; paga o arquivo e recebe o rent dos históricos que ele (ou ninguém registrado) pagou")]
+    #[account(3, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(4, name = "system_program", desc = "System program")]
+    #[account(5, writable, name = "histories", desc = "Um histórico por history_index, na mesma ordem")]
+    #[account(6, writable, name = "rent_payers", desc = "Depois dos históricos, cada rent_payer que não é o owner, uma vez; recebe o rent dos seus históricos")]
+    ArchiveHistory { archive_id: u64, history_indexes: Vec<u64> },
+    // 57: abre pré-encomendas de um produto até release_at; unit_price na escala de price_decimals
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "launch", desc = "PDA [\"launch\", cake, product_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    CreateLaunch { product_id: u64, release_at: i64, unit_price: u64, max_quantity: u64 },
+    // 58
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "launch", desc = "PDA [\"launch\", cake, product_id]")]
+    #[account(2, writable, name = "pre_order", desc = "PDA [\"pre_order\", cake, product_id, buyer]")]
+    #[account(3, writable, signer, name = "buyer", desc = "Comprador; paga a criação das contas")]
+    #[account(4, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"pre_order_vault\", pre_order]")]
+    #[account(6, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(7, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(8, name = "system_program", desc = "System program")]
+    #[account(9, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(10, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(11, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(12, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(13, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(14, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    PlacePreOrder { product_id: u64, quantity: u64 },
+    // 59
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "launch", desc = "PDA [\"launch\", cake, product_id]")]
+    #[account(2, writable, name = "pre_order", desc = "PDA [\"pre_order\", cake, product_id, buyer]")]
+    #[account(3, signer, name = "authority", desc = "Owner registrado no estado")]
+    #[account(4, writable, name = "buyer", desc = "Comprador da pré-encomenda; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"pre_order_vault\", pre_order]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(10, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(11, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]")]
+    #[account(12, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(13, name = "system_program", desc = "System program")]
+    #[account(14, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    ConvertPreOrder { product_id: u64, buyer: Pubkey },
+    // 60
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "launch", desc = "PDA [\"launch\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    CancelLaunch { product_id: u64 },
+    // 61: apenas com o lançamento cancelado
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "launch", desc = "PDA [\"launch\", cake, product_id]")]
+    #[account(2, writable, name = "pre_order", desc = "PDA [\"pre_order\", cake, product_id, buyer]")]
+    #[account(3, signer, name = "authority", desc = "Owner registrado no estado ou o próprio comprador")]
+    #[account(4, writable, name = "buyer", desc = "Comprador da pré-encomenda; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"pre_order_vault\", pre_order]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de token do comprador")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    RefundPreOrder { product_id: u64, buyer: Pubkey },
+    // 62: cria ou substitui a promoção do produto; discount_bps até 10_000 e start < end
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "flash_sale", desc = "PDA [\"flash_sale\", cake, product_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    ScheduleFlashSale { product_id: u64, discount_bps: u16, start: i64, end: i64 },
+    // 63
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "flash_sale", desc = "PDA [\"flash_sale\", cake, product_id]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    CancelFlashSale { product_id: u64 },
+    // 64: zero em available_from ou available_until remove o limite correspondente
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetAvailabilityWindow { product_id: u64, available_from: i64, available_until: i64 },
+    // 65: status é ORDER_STATUS_PREPARING ou ORDER_STATUS_OUT_FOR_DELIVERY, sempre avançando uma etapa
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "order", desc = "PDA [\"order\", cake, order_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    UpdateOrderStatus { order_id: u64, status: u8 },
+    // 66: o comprador confirma o recebimento e o cofre é pago ao owner
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "order", desc = "PDA [\"order\", cake, order_id]")]
+    #[account(3, signer, name = "buyer_signer", desc = "Comprador do pedido")]
+    #[account(4, writable, name = "buyer", desc = "Comprador do pedido; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"order_vault\", cake, order_id]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    ConfirmOrderDelivery { order_id: u64 },
+    // 67: cria ou atualiza o perfil do comprador; hashes calculados pelo cliente (ex.: sha256 do nome e do contato)
+    #[account(0, writable, name = "buyer_profile", desc = "PDA [\"buyer_profile\", buyer]")]
+    #[account(1, writable, signer, name = "buyer", desc = "Comprador; paga a criação da conta")]
+    #[account(2, name = "system_program", desc = "System program")]
+    SetBuyerProfile { display_name_hash: [u8; 32], preferred_mint: Pubkey, contact_hash: [u8; 32] },
+    // 68: cria a loja na PDA ["store", store_id]; as PDAs de produtos, histórico e configuração ficam sob ela.
+    // Cria também a conta de receita da loja para payment_mint
+    #[account(0, writable, name = "store", desc = "PDA [\"store\", store_id]")]
+    #[account(1, signer, name = "owner", desc = "Owner da loja")]
+    #[account(2, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(3, name = "system_program", desc = "System program")]
+    #[account(4, name = "payment_mint", desc = "Mint da conta de receita da loja")]
+    #[account(5, writable, name = "revenue_token", desc = "ATA da autoridade de receita para payment_mint; criada aqui")]
+    #[account(6, name = "revenue_authority", desc = "PDA [\"store_revenue\", store]")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(8, name = "associated_token_program", desc = "Associated Token Account program")]
+    CreateStore { store_id: u64 },
+    // 69: [threshold u8][count u8][member 32 * count]; cria ou substitui o conjunto de owners e aponta o
+    // owner da loja para ele. Os membros que aprovam assinam como contas extras ao final
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "owner_set", desc = "PDA [\"owner_set\", cake]")]
+    #[account(2, name = "owner", desc = "Owner registrado no estado; assina quando não é o conjunto de owners")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    SetOwnerSet { threshold: u8, members: Vec<Pubkey> },
+    // 70: casas decimais do preço do produto; zero volta a usar as da configuração
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetPriceDecimals { product_id: u64, price_decimals: u8 },
+    // 71: [count u8] e, por produto, [name_len u8][name][description_len u16][description][price u64][stock u64][max_per_buyer u64]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(3, name = "system_program", desc = "System program")]
+    #[account(4, writable, name = "products", desc = "Para cada produto, na ordem: PDA [\"product\", cake, product_counter + i]")]
+    AddProducts { products: Vec<NewProduct> },
+    // 72: code_hash = sha256 do código; as recompensas são pagas no mint informado
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, name = "referral", desc = "PDA [\"referral\", cake, code_hash]")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    CreateReferral { code_hash: [u8; 32], referrer: Pubkey, mint: Pubkey },
+    // 73: parte da venda, em bps, creditada ao referrer
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetReferralBps { referral_bps: u16 },
+    // 74: saca todo o saldo pendente do código
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "referral", desc = "PDA [\"referral\", cake, code_hash]")]
+    #[account(2, signer, name = "referrer", desc = "Referrer registrado no código")]
+    #[account(3, writable, name = "referrer_token", desc = "Conta de token do referrer")]
+    #[account(4, writable, name = "treasury_vault", desc = "PDA [\"treasury_vault\", cake, mint]")]
+    #[account(5, name = "treasury_authority", desc = "PDA [\"treasury\"]")]
+    #[account(6, name = "mint", desc = "Mint das recompensas")]
+    #[account(7, name = "token_program", desc = "SPL Token ou Token-2022")]
+    ClaimReferralRewards,
+    // 75: conta de token das gorjetas; Pubkey::default() desativa as gorjetas
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetTipAccount { tip_account: Pubkey },
+    // 76: conta de token da caridade e unidade de arredondamento; round_up_unit = 0 desativa
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetCharity { charity_account: Pubkey, round_up_unit: u64 },
+    // 77: compra paga pela aprovação prévia do comprador à PDA purchase_delegate, sem a assinatura dele;
+    // quem executa é o owner da loja
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, name = "buyer", desc = "Comprador; não assina")]
+    #[account(3, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(4, writable, name = "buyer_token", desc = "Conta de token do comprador com aprovação para o delegate")]
+    #[account(5, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(6, name = "delegate", desc = "PDA [\"purchase_delegate\", cake]")]
+    #[account(7, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(8, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(9, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(10, name = "system_program", desc = "System program")]
+    #[account(11, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(12, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    #[account(13, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(14, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(15, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(16, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    ExecuteDelegatedPurchase { product_id: u64, amount: u64 },
+    // 78: ordem assinada off-chain, paga pelo delegate da loja; qualquer um pode enviar
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, name = "buyer", desc = "Comprador que assinou a ordem; não assina a transação")]
+    #[account(3, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(4, writable, name = "buyer_token", desc = "Conta de token do comprador com aprovação para o delegate")]
+    #[account(5, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(6, name = "delegate", desc = "PDA [\"purchase_delegate\", cake]")]
+    #[account(7, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(8, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(9, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(10, name = "system_program", desc = "System program")]
+    #[account(11, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(12, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    #[account(13, writable, name = "order_nonce", desc = "PDA [\"order_nonce\", cake, buyer, nonce]; criada na execução")]
+    #[account(14, name = "instructions", desc = "Sysvar Instructions")]
+    #[account(15, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(16, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(17, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    ExecuteSignedOrder { order: SignedOrder },
+    // 79: somente leitura; devolve via return data o Quote da compra no mint informado, com variante,
+    // promoção relâmpago ou happy hour, cupom (sem consumir um uso), conversão de casas decimais e taxa de serviço
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(3, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(4, name = "flash_sale", desc = "PDA [\"flash_sale\", cake, product_id]; pode não existir")]
+    #[account(5, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas com variant_id")]
+    #[account(6, optional, name = "coupon", desc = "PDA [\"coupon\", cake, sha256(coupon_code)]; apenas com coupon_code")]
+    #[account(7, optional, name = "happy_hour", desc = "PDA [\"happy_hour\", cake, product_id]; pode não existir; sem ela a cotação ignora o happy hour")]
+    GetFullQuote { product_id: u64, amount: u64, coupon_code: Option<Vec<u8>>, variant_id: Option<u8> },
+    // 80: cria ou substitui a tradução do produto no idioma lang_code (ISO 639-1, minúsculas).
+    // Dados: [product_id u64][lang_code 2][name_len u8][name][description_len u16][description]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "localization", desc = "PDA [\"localization\", cake, product_id, lang_code]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    SetLocalization { product_id: u64, lang_code: [u8; 2], name: String, description: String },
+    // 81: fecha a tradução; o produto pode já ter sido fechado
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "localization", desc = "PDA [\"localization\", cake, product_id, lang_code]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    RemoveLocalization { product_id: u64, lang_code: [u8; 2] },
+    // 82: substitui as tags do produto e atualiza os índices. Dados: [product_id u64][count u8][tag 16 * count];
+    // sem tags, o produto sai de todos os índices. Os índices vêm na ordem das tags removidas (ordem atual)
+    // e depois na das tags novas (ordem dos dados)
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "product_tags", desc = "PDA [\"product_tags\", cake, product_id]; criada na primeira vez")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    #[account(6, writable, name = "tag_indexes", desc = "PDAs [\"tag_index\", cake, tag]: removidas e depois novas")]
+    SetProductTags { product_id: u64, tags: Vec<[u8; 16]> },
+    // 83: árbitro das disputas e prazo em segundos para abri-las; Pubkey::default() desativa as disputas
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    SetArbiter { arbiter: Pubkey, dispute_window: i64 },
+    // 84: o comprador contesta uma compra em escrow dentro do prazo; os fundos ficam presos no cofre
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index] ou o endereço antigo")]
+    #[account(3, writable, name = "dispute", desc = "PDA [\"dispute\", history]")]
+    #[account(4, signer, name = "buyer", desc = "Comprador da compra")]
+    #[account(5, writable, signer, name = "payer", desc = "Paga o rent da disputa")]
+    #[account(6, name = "system_program", desc = "System program")]
+    OpenDispute { history_index: u64 },
+    // 85: decisão do árbitro. Dados: [history_index u64][refund u8]; refund devolve ao comprador e repõe o
+    // estoque, senão o valor vai para o owner como no confirm_delivery
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(2, writable, name = "product", desc = "PDA [\"product\", cake, product_id]; atualizado apenas com refund")]
+    #[account(3, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index] ou o endereço antigo")]
+    #[account(4, writable, name = "dispute", desc = "PDA [\"dispute\", history]")]
+    #[account(5, signer, name = "arbiter", desc = "Árbitro da configuração")]
+    #[account(6, writable, name = "buyer", desc = "Comprador; recebe o rent do cofre")]
+    #[account(7, writable, name = "vault", desc = "PDA [\"history_vault\", history]")]
+    #[account(8, writable, name = "destination_token", desc = "Conta de token do comprador (refund) ou do owner")]
+    #[account(9, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(10, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(11, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    #[account(12, writable, optional, name = "variant", desc = "PDA [\"variant\", cake, product_id, variant_id]; apenas com refund de uma variante")]
+    ResolveDispute { history_index: u64, refund: bool },
+    // 86: libera ao owner uma compra em escrow depois de CakeState::escrow_timeout segundos sem confirmação
+    // nem disputa; qualquer um pode enviar, e o valor só vai para a conta de receita da loja
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]; não é alterado")]
+    #[account(2, writable, name = "history", desc = "PDA [\"history\", cake, buyer, history_index] ou o endereço antigo")]
+    #[account(3, name = "authority", desc = "Quem envia; não precisa assinar")]
+    #[account(4, writable, name = "buyer", desc = "Comprador; recebe o rent do cofre")]
+    #[account(5, writable, name = "vault", desc = "PDA [\"history_vault\", history]")]
+    #[account(6, writable, name = "destination_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(7, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    ReleaseExpiredEscrow { history_index: u64 },
+    // 87: entrega parte do pedido (ex.: 8 de 12) e paga ao owner a parte proporcional do cofre; o restante fica
+    // no cofre para fulfill_order/confirm_order_delivery ou cancel_order. quantity fica abaixo do que falta entregar
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "order", desc = "PDA [\"order\", cake, order_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, name = "vault", desc = "PDA [\"order_vault\", cake, order_id]")]
+    #[account(4, writable, name = "destination_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(5, name = "escrow_authority", desc = "PDA [\"escrow\"]")]
+    #[account(6, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(7, writable, signer, name = "payer", desc = "Paga o rent quando um pedido antigo precisa crescer")]
+    #[account(8, name = "system_program", desc = "System program")]
+    #[account(9, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    PartialFulfillOrder { order_id: u64, quantity: u64 },
+    // 88: [bundle_id u64][price u64][count u8][(product_id u64, quantity u64) * count]; cria ou substitui o combo.
+    // price fica nas casas decimais da configuração, bundle_id até MAX_BUNDLE_ID e os produtos não se repetem
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "bundle", desc = "PDA [\"bundle\", cake, bundle_id]")]
+    #[account(2, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(3, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(4, name = "system_program", desc = "System program")]
+    SetBundle { bundle_id: u64, price: u64, items: Vec<BundleItem> },
+    // 89
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "bundle", desc = "PDA [\"bundle\", cake, bundle_id]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    CloseBundle { bundle_id: u64 },
+    // 90: vende quantity combos; o estoque de todos os produtos cai de uma vez e o histórico único leva
+    // product_id = BUNDLE_PRODUCT_ID_FLAG | bundle_id
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "bundle", desc = "PDA [\"bundle\", cake, bundle_id]")]
+    #[account(2, writable, signer, name = "buyer", desc = "Comprador")]
+    #[account(3, name = "system_program", desc = "System program")]
+    #[account(4, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(5, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(6, writable, name = "buyer_token", desc = "Conta de token do comprador")]
+    #[account(7, writable, name = "owner_token", desc = "Conta de receita da loja para o mint (store_revenue_address)")]
+    #[account(8, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(9, name = "payment_mint", desc = "payment_mint da configuração ou mint aceito")]
+    #[account(10, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(11, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    #[account(12, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(13, writable, name = "products", desc = "Para cada item do combo, na ordem: PDA [\"product\", cake, product_id], PDA [\"buyer_purchase\", cake, product_id, buyer] se max_per_buyer > 0, PDA [\"allowlist\", cake, buyer] se o produto exige allowlist e conta de token do gate_mint se houver")]
+    #[account(14, writable, optional, name = "fee_token", desc = "Conta de token de fee_destination; apenas quando fee_bps > 0")]
+    SellBundle { bundle_id: u64, quantity: u64 },
+    // 91: [product_id u64][count u8][(days u8, start_minute u16, end_minute u16, discount_bps u16) * count]; cria ou
+    // substitui as janelas de happy hour do produto. Cada janela passa em HappyHourWindow::is_valid
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, writable, name = "happy_hour", desc = "PDA [\"happy_hour\", cake, product_id]")]
+    #[account(3, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(4, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(5, name = "system_program", desc = "System program")]
+    SetHappyHour { product_id: u64, windows: Vec<HappyHourWindow> },
+    // 92
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "happy_hour", desc = "PDA [\"happy_hour\", cake, product_id]")]
+    #[account(2, writable, signer, name = "owner", desc = "Owner registrado no estado; recebe o rent")]
+    ClearHappyHour { product_id: u64 },
+    // 93: saca da conta de receita da loja; a autoridade de receita assina pela conta
+    #[legacy_optional_accounts_strategy]
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, name = "revenue_token", desc = "ATA da autoridade de receita para o mint")]
+    #[account(3, name = "revenue_authority", desc = "PDA [\"store_revenue\", cake]")]
+    #[account(4, writable, name = "destination_token", desc = "Conta de token de destino")]
+    #[account(5, name = "token_program", desc = "SPL Token ou Token-2022")]
+    #[account(6, optional, name = "mint", desc = "Mint do token; apenas com Token-2022")]
+    WithdrawStoreRevenue { amount: u64 },
+    // 94: [product_id u64][quantity u64][new_source_decryptable_balance 36]; paga com a transferência
+    // confidencial do Token-2022. As provas vêm em contas de contexto já verificadas pelo programa ZK ElGamal;
+    // a prova ZeroCiphertext amarra o valor transferido ao total da compra
+    #[legacy_optional_accounts_strategy]
+    #[account(0, writable, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, writable, name = "product", desc = "PDA [\"product\", cake, product_id]")]
+    #[account(2, signer, name = "buyer", desc = "Comprador; dono de buyer_token")]
+    #[account(3, writable, name = "history", desc = "PDA [\"history\", cake, buyer, BuyerHistoryCounter::count]")]
+    #[account(4, writable, name = "history_counter", desc = "PDA [\"buyer_history\", cake, buyer]; criada na primeira compra")]
+    #[account(5, writable, name = "buyer_token", desc = "Conta de token do comprador com ConfidentialTransferAccount")]
+    #[account(6, writable, name = "owner_token", desc = "Conta de receita da loja, configurada com confidential_revenue")]
+    #[account(7, name = "payment_mint", desc = "Mint Token-2022 com ConfidentialTransferMint")]
+    #[account(8, name = "config", desc = "PDA [\"config\", cake]")]
+    #[account(9, writable, name = "confidential_payment", desc = "PDA [\"confidential_payment\", history]")]
+    #[account(10, name = "equality_proof", desc = "Contexto da prova de igualdade (CiphertextCommitmentEquality)")]
+    #[account(11, name = "ciphertext_validity_proof", desc = "Contexto da prova BatchedGroupedCiphertext3HandlesValidity")]
+    #[account(12, name = "range_proof", desc = "Contexto da prova BatchedRangeProofU128")]
+    #[account(13, name = "amount_proof", desc = "Contexto da prova ZeroCiphertext do valor da origem menos o total, com a chave do comprador")]
+    #[account(14, writable, signer, name = "payer", desc = "Paga o rent das contas criadas")]
+    #[account(15, name = "system_program", desc = "System program")]
+    #[account(16, name = "token_program", desc = "Token-2022")]
+    #[account(17, writable, optional, name = "buyer_purchase", desc = "PDA [\"buyer_purchase\", cake, product_id, buyer]; apenas com max_per_buyer")]
+    #[account(18, name = "blocklist", desc = "PDA [\"blocklist\", cake, buyer]; pode não existir")]
+    #[account(19, optional, name = "allowlist", desc = "PDA [\"allowlist\", cake, buyer]; apenas quando o produto exige allowlist")]
+    #[account(20, optional, name = "gate_token", desc = "Conta de token do comprador com o gate_mint; apenas quando o produto tem gate_mint")]
+    ConfidentialSell { product_id: u64, quantity: u64, new_source_decryptable_balance: [u8; 36] },
+    // 95: [instrução confidencial do Token-2022]; repassa à conta de receita da loja uma ConfigureAccount,
+    // ApplyPendingBalance ou Withdraw assinada pela autoridade de receita. As contas extras (mint, contextos
+    // das provas) seguem na ordem do Token-2022, entre a conta e o dono
+    #[account(0, name = "cake", desc = "Estado da loja (CakeState)")]
+    #[account(1, signer, name = "owner", desc = "Owner registrado no estado")]
+    #[account(2, writable, name = "revenue_token", desc = "ATA da autoridade de receita para o mint")]
+    #[account(3, name = "revenue_authority", desc = "PDA [\"store_revenue\", cake]")]
+    #[account(4, name = "token_program", desc = "Token-2022")]
+    #[account(5, writable, signer, name = "payer", desc = "Paga a realocação da conta no ConfigureAccount")]
+    #[account(6, name = "system_program", desc = "System program")]
+    ConfidentialRevenue { data: Vec<u8> },
+}
+
+fn invalid() -> ProgramError {
+    CakeError::InvalidInstructionData.into()
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], ProgramError> {
+    data.get(offset..offset + N).and_then(|bytes| bytes.try_into().ok()).ok_or_else(invalid)
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, ProgramError> {
+    data.get(offset).copied().ok_or_else(invalid)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ProgramError> {
+    read_bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    read_bytes(data, offset).map(u64::from_le_bytes)
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Result<i64, ProgramError> {
+    read_bytes(data, offset).map(i64::from_le_bytes)
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    read_bytes(data, offset).map(Pubkey::new_from_array)
+}
+
+// [code_len u8][code]; code_len = 0 significa ausente
+fn read_code(data: &[u8], offset: &mut usize) -> Result<Option<Vec<u8>>, ProgramError> {
+    let code_len = read_u8(data, *offset)? as usize;
+    *offset += 1;
+    if code_len > MAX_COUPON_CODE_LEN || data.len() < *offset + code_len {
+        return Err(invalid());
+    }
+    let code = data[*offset..*offset + code_len].to_vec();
+    *offset += code_len;
+    Ok(if code_len > 0 { Some(code) } else { None })
+}
+
+fn write_code(buf: &mut Vec<u8>, code: &Option<Vec<u8>>) {
+    let code = code.as_deref().unwrap_or_default();
+    buf.push(code.len() as u8);
+    buf.extend_from_slice(code);
+}
+
+// [count u8][tag 16 * count]
+fn read_tags(data: &[u8], offset: usize) -> Result<Vec<[u8; TAG_LEN]>, ProgramError> {
+    let count = read_u8(data, offset)? as usize;
+    if count > MAX_PRODUCT_TAGS {
+        return Err(invalid());
+    }
+    let tags = data.get(offset + 1..offset + 1 + count * TAG_LEN).ok_or_else(invalid)?;
+    Ok(tags.chunks_exact(TAG_LEN).map(|chunk| chunk.try_into().unwrap()).collect())
+}
+
+fn write_tags(buf: &mut Vec<u8>, tags: &[[u8; TAG_LEN]]) {
+    buf.push(tags.len() as u8);
+    for tag in tags {
+        buf.extend_from_slice(tag);
+    }
+}
+
+fn write_fixed(buf: &mut Vec<u8>, src: &str, len: usize) {
+    let start = buf.len();
+    buf.resize(start + len, 0);
+    buf[start..start + src.len()].copy_from_slice(src.as_bytes());
+}
+
+impl CakeInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, _) = input.split_first().ok_or_else(invalid)?;
+        Ok(match tag {
+            0 => Self::Initialize,
+            1 => {
+                let mut name = clean_text(&read_bytes::<32>(input, 1)?)?;
+                let mut description = clean_text(&read_bytes::<128>(input, 33)?)?;
+                let price = read_u64(input, 161)?;
+                let stock = read_u64(input, 169)?;
+                // Limite por comprador opcional; 0 = sem limite
+                let max_per_buyer = if input.len() >= 185 { read_u64(input, 177)? } else { 0 };
+                // Textos longos opcionais substituem os campos fixos:
+                // [name_len u8][name][description_len u16][description], seguidos das tags opcionais
+                let mut tags = Vec::new();
+                if input.len() > 185 {
+                    let name_len = input[185] as usize;
+                    let description_start = 186 + name_len;
+                    if name_len > MAX_PRODUCT_NAME_LEN {
+                        return Err(invalid());
+                    }
+                    let description_len = read_u16(input, description_start)? as usize;
+                    if description_len > MAX_PRODUCT_DESCRIPTION_LEN || input.len() < description_start + 2 + description_len {
+                        return Err(invalid());
+                    }
+                    name = clean_text(&input[186..description_start])?;
+                    description = clean_text(&input[description_start + 2..description_start + 2 + description_len])?;
+                    let tags_start = description_start + 2 + description_len;
+                    if input.len() > tags_start {
+                        tags = read_tags(input, tags_start)?;
+                    }
+                }
+                Self::AddProduct { name, description, price, stock, max_per_buyer, tags }
+            }
+            2 => Self::GetQuote { product_id: read_u64(input, 1)?, amount: read_u64(input, 9)? },
+            3 => Self::UpdateStock { product_id: read_u64(input, 1)?, delta: read_i64(input, 9)? },
+            4 => {
+                let product_id = read_u64(input, 1)?;
+                let amount = read_u64(input, 9)?;
+                let mut offset = 17;
+                let mut coupon_code = None;
+                if input.len() > offset {
+                    coupon_code = read_code(input, &mut offset)?;
+                }
+                let mut variant_id = None;
+                if input.len() >= offset + 2 {
+                    if input[offset] != 0 {
+                        variant_id = Some(input[offset + 1]);
+                    }
+                    offset += 2;
+                }
+                let mut escrow = false;
+                if input.len() > offset {
+                    escrow = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut payment_mode = PAYMENT_MODE_TOKEN;
+                if input.len() > offset {
+                    payment_mode = input[offset];
+                    offset += 1;
+                }
+                let mut create_ata = false;
+                if input.len() > offset {
+                    create_ata = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut gift_code = None;
+                if input.len() > offset {
+                    gift_code = read_code(input, &mut offset)?;
+                }
+                let mut mint_receipt = false;
+                if input.len() > offset {
+                    mint_receipt = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut product_bump = None;
+                if input.len() >= offset + 2 {
+                    if input[offset] != 0 {
+                        product_bump = Some(input[offset + 1]);
+                    }
+                    offset += 2;
+                }
+                let mut paged_history = false;
+                if input.len() > offset {
+                    paged_history = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut version = SELL_LEGACY_VERSION;
+                if input.len() > offset {
+                    version = input[offset];
+                    if !(SELL_LEGACY_VERSION..=SELL_VERSION).contains(&version) {
+                        return Err(invalid());
+                    }
+                    offset += 1;
+                }
+                // Recado do comprador: [memo_len u8][memo]; 0 = sem recado
+                let mut memo = None;
+                if input.len() > offset {
+                    let memo_len = input[offset] as usize;
+                    if memo_len > MAX_MEMO_LEN || input.len() < offset + 1 + memo_len {
+                        return Err(invalid());
+                    }
+                    if memo_len > 0 {
+                        memo = Some(input[offset + 1..offset + 1 + memo_len].to_vec());
+                    }
+                    offset += 1 + memo_len;
+                }
+                let mut referral_code = None;
+                if input.len() > offset {
+                    referral_code = read_code(input, &mut offset)?;
+                }
+                let mut tip = 0;
+                if input.len() > offset {
+                    tip = read_u64(input, offset)?;
+                    offset += 8;
+                }
+                let mut round_up = false;
+                if input.len() > offset {
+                    round_up = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut sync_native = false;
+                if input.len() > offset {
+                    sync_native = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut close_wsol = false;
+                if input.len() > offset {
+                    close_wsol = input[offset] != 0;
+                    offset += 1;
+                }
+                let mut max_total_price = 0;
+                if input.len() > offset {
+                    max_total_price = read_u64(input, offset)?;
+                }
+                Self::Sell {
+                    product_id,
+                    amount,
+                    coupon_code,
+                    variant_id,
+                    escrow,
+                    payment_mode,
+                    create_ata,
+                    gift_code,
+                    mint_receipt,
+                    product_bump,
+                    paged_history,
+                    version,
+                    memo,
+                    referral_code,
+                    tip,
+                    round_up,
+                    sync_native,
+                    close_wsol,
+                    max_total_price,
+                }
+            }
+            5 => Self::DeactivateProduct { product_id: read_u64(input, 1)? },
+            6 => Self::Restock {
+                product_id: read_u64(input, 1)?,
+                amount: read_u64(input, 9)?,
+                variant_id: input.get(17).copied(),
+            },
+            7 => Self::UpdatePrice { product_id: read_u64(input, 1)?, new_price: read_u64(input, 9)? },
+            8 => Self::SetMaxPriceChange { max_price_change_bps: read_u16(input, 1)? },
+            9 => Self::AddOperator { operator: read_pubkey(input, 1)? },
+            10 => Self::RemoveOperator { operator: read_pubkey(input, 1)? },
+            11 => Self::Pause,
+            12 => Self::Resume,
+            13 => Self::SetAvailability { product_id: read_u64(input, 1)?, available: read_u8(input, 9)? != 0 },
+            14 => Self::Refund { history_index: read_u64(input, 1)? },
+            15 => Self::PlaceOrder { product_id: read_u64(input, 1)?, quantity: read_u64(input, 9)? },
+            16 => Self::FulfillOrder { order_id: read_u64(input, 1)? },
+            17 => Self::CancelOrder { order_id: read_u64(input, 1)? },
+            18 => {
+                let item_count = read_u8(input, 1)? as usize;
+                if item_count == 0 || item_count > MAX_CART_ITEMS || input.len() < 2 + item_count * 16 {
+                    return Err(invalid());
+                }
+                let items = input[2..2 + item_count * 16]
+                    .chunks_exact(16)
+                    .map(|item| CartItem {
+                        product_id: u64::from_le_bytes(item[..8].try_into().unwrap()),
+                        quantity: u64::from_le_bytes(item[8..16].try_into().unwrap()),
+                    })
+                    .collect();
+                let version = input.get(2 + item_count * 16).copied().unwrap_or(CHECKOUT_LEGACY_VERSION);
+                if !(CHECKOUT_LEGACY_VERSION..=CHECKOUT_VERSION).contains(&version) {
+                    return Err(invalid());
+                }
+                Self::Checkout { items, version }
+            }
+            19 => Self::CreateCoupon {
+                code_hash: read_bytes(input, 1)?,
+                kind: read_u8(input, 33)?,
+                value: read_u64(input, 34)?,
+                expires_at: read_i64(input, 42)?,
+                max_uses: read_u64(input, 50)?,
+            },
+            20 => Self::SetMaxPerBuyer { product_id: read_u64(input, 1)?, max_per_buyer: read_u64(input, 9)? },
+            21 => Self::CreateCategory { name: read_bytes(input, 1)? },
+            22 => Self::SetProductCategory { product_id: read_u64(input, 1)?, category_id: read_u64(input, 9)? },
+            23 => Self::AddVariant {
+                product_id: read_u64(input, 1)?,
+                variant_id: read_u8(input, 9)?,
+                name: read_bytes(input, 10)?,
+                price: read_u64(input, 42)?,
+                stock: read_u64(input, 50)?,
+            },
+            24 => Self::MigrateProduct { product_id: read_u64(input, 1)? },
+            25 => Self::SetMetadataUri { product_id: read_u64(input, 1)?, metadata_uri: read_bytes(input, 9)? },
+            26 => {
+                let product_id = read_u64(input, 1)?;
+                let tier_count = read_u8(input, 9)? as usize;
+                if tier_count > MAX_PRICE_TIERS || input.len() < 10 + tier_count * 16 {
+                    return Err(invalid());
+                }
+                let tiers = input[10..10 + tier_count * 16]
+                    .chunks_exact(16)
+                    .map(|chunk| PriceTier {
+                        min_quantity: u64::from_le_bytes(chunk[..8].try_into().unwrap()),
+                        unit_price: u64::from_le_bytes(chunk[8..16].try_into().unwrap()),
+                    })
+                    .collect();
+                Self::SetPriceTiers { product_id, tiers }
+            }
+            27 => Self::SetFee { fee_bps: read_u16(input, 1)?, fee_destination: read_pubkey(input, 3)? },
+            28 => Self::ConfirmDelivery { history_index: read_u64(input, 1)? },
+            29 => Self::ReleaseAfterTimeout { history_index: read_u64(input, 1)? },
+            30 => Self::RefundEscrow { history_index: read_u64(input, 1)? },
+            31 => Self::SetEscrowTimeout { escrow_timeout: read_i64(input, 1)? },
+            32 => Self::PartialRefund { history_index: read_u64(input, 1)?, quantity: read_u64(input, 9)? },
+            33 => Self::SetSolPrice { product_id: read_u64(input, 1)?, sol_price: read_u64(input, 9)? },
+            34 => {
+                let payment_mint = read_pubkey(input, 1)?;
+                let mut price_decimals = 0;
+                let mut accepted_mints = Vec::new();
+                // [price_decimals u8][count u8][(mint 32, decimals u8) * count]
+                if input.len() > 33 {
+                    price_decimals = read_u8(input, 33)?;
+                    let count = read_u8(input, 34)? as usize;
+                    if count > MAX_ACCEPTED_MINTS || input.len() < 35 + count * 33 {
+                        return Err(invalid());
+                    }
+                    accepted_mints = input[35..35 + count * 33]
+                        .chunks_exact(33)
+                        .map(|chunk| AcceptedMint { mint: Pubkey::new_from_array(chunk[..32].try_into().unwrap()), decimals: chunk[32] })
+                        .collect();
+                }
+                Self::SetConfig { payment_mint, price_decimals, accepted_mints }
+            }
+            35 => Self::SetUsdPrice {
+                product_id: read_u64(input, 1)?,
+                usd_price_cents: read_u64(input, 9)?,
+                price_feed: read_pubkey(input, 17)?,
+            },
+            36 => Self::CreateTreasury,
+            37 => Self::WithdrawTreasury { amount: read_u64(input, 1)? },
+            38 => {
+                // [count u8][(token_account 32, share_bps u16) * count]
+                let count = read_u8(input, 1)? as usize;
+                if count > MAX_SPLIT_RECIPIENTS || input.len() < 2 + count * 34 {
+                    return Err(invalid());
+                }
+                let splits = input[2..2 + count * 34]
+                    .chunks_exact(34)
+                    .map(|chunk| SplitRecipient {
+                        token_account: Pubkey::new_from_array(chunk[..32].try_into().unwrap()),
+                        share_bps: u16::from_le_bytes(chunk[32..34].try_into().unwrap()),
+                    })
+                    .collect();
+                Self::SetRevenueSplit { splits }
+            }
+            39 => Self::SetCommission { commission_bps: read_u16(input, 1)?, commission_account: read_pubkey(input, 3)? },
+            40 => Self::CreateSubscription {
+                product_id: read_u64(input, 1)?,
+                quantity: read_u64(input, 9)?,
+                interval: read_i64(input, 17)?,
+                max_unit_price: read_u64(input, 25)?,
+            },
+            41 => Self::CancelSubscription,
+            42 => Self::ProcessSubscription,
+            43 => Self::CreateGiftCard {
+                code_hash: read_bytes(input, 1)?,
+                balance: read_u64(input, 33)?,
+                expires_at: read_i64(input, 41)?,
+            },
+            44 => Self::AddToAllowlist { buyer: read_pubkey(input, 1)? },
+            45 => Self::RemoveFromAllowlist { buyer: read_pubkey(input, 1)? },
+            46 => Self::SetAllowlistRequired { product_id: read_u64(input, 1)?, required: read_u8(input, 9)? != 0 },
+            47 => Self::AddToBlocklist { buyer: read_pubkey(input, 1)? },
+            48 => Self::RemoveFromBlocklist { buyer: read_pubkey(input, 1)? },
+            49 => Self::SetGateMint { product_id: read_u64(input, 1)?, gate_mint: read_pubkey(input, 9)? },
+            50 => Self::ExtendAccount { new_len: read_u64(input, 1)? },
+            51 => Self::CloseProduct { product_id: read_u64(input, 1)?, force: read_u8(input, 9)? != 0 },
+            52 => Self::SetHistoryRetention { history_retention: read_i64(input, 1)? },
+            53 => Self::CloseHistory { history_index: read_u64(input, 1)? },
+            54 => Self::CreateGlobalStats,
+            55 => Self::MigrateState,
+            56 => {
+                let archive_id = read_u64(input, 1)?;
+                let count = read_u8(input, 9)? as usize;
+                if count == 0 || count > MAX_ARCHIVE_BATCH || input.len() < 10 + count * 8 {
+                    return Err(invalid());
+                }
+                let history_indexes = input[10..10 + count * 8].chunks_exact(8).map(|index| u64::from_le_bytes(index.try_into().unwrap())).collect();
+                Self::ArchiveHistory { archive_id, history_indexes }
+            }
+            57 => Self::CreateLaunch {
+                product_id: read_u64(input, 1)?,
+                release_at: read_i64(input, 9)?,
+                unit_price: read_u64(input, 17)?,
+                max_quantity: read_u64(input, 25)?,
+            },
+            58 => Self::PlacePreOrder { product_id: read_u64(input, 1)?, quantity: read_u64(input, 9)? },
+            59 => Self::ConvertPreOrder { product_id: read_u64(input, 1)?, buyer: read_pubkey(input, 9)? },
+            60 => Self::CancelLaunch { product_id: read_u64(input, 1)? },
+            61 => Self::RefundPreOrder { product_id: read_u64(input, 1)?, buyer: read_pubkey(input, 9)? },
+            62 => Self::ScheduleFlashSale {
+                product_id: read_u64(input, 1)?,
+                discount_bps: read_u16(input, 9)?,
+                start: read_i64(input, 11)?,
+                end: read_i64(input, 19)?,
+            },
+            63 => Self::CancelFlashSale { product_id: read_u64(input, 1)? },
+            64 => Self::SetAvailabilityWindow {
+                product_id: read_u64(input, 1)?,
+                available_from: read_i64(input, 9)?,
+                available_until: read_i64(input, 17)?,
+            },
+            65 => Self::UpdateOrderStatus { order_id: read_u64(input, 1)?, status: read_u8(input, 9)? },
+            66 => Self::ConfirmOrderDelivery { order_id: read_u64(input, 1)? },
+            67 => Self::SetBuyerProfile {
+                display_name_hash: read_bytes(input, 1)?,
+                preferred_mint: read_pubkey(input, 33)?,
+                contact_hash: read_bytes(input, 65)?,
+            },
+            68 => Self::CreateStore { store_id: read_u64(input, 1)? },
+            69 => {
+                let threshold = read_u8(input, 1)?;
+                let count = read_u8(input, 2)? as usize;
+                if count > MAX_OWNER_SET_MEMBERS || input.len() < 3 + count * 32 {
+                    return Err(invalid());
+                }
+                let members = input[3..3 + count * 32].chunks_exact(32).map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap())).collect();
+                Self::SetOwnerSet { threshold, members }
+            }
+            70 => Self::SetPriceDecimals { product_id: read_u64(input, 1)?, price_decimals: read_u8(input, 9)? },
+            71 => {
+                let count = read_u8(input, 1)? as usize;
+                if count == 0 || count > MAX_PRODUCT_BATCH {
+                    return Err(invalid());
+                }
+                let mut offset = 2;
+                let mut products = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let name_len = read_u8(input, offset)? as usize;
+                    if name_len > MAX_PRODUCT_NAME_LEN || input.len() < offset + 1 + name_len {
+                        return Err(invalid());
+                    }
+                    let name = clean_text(&input[offset + 1..offset + 1 + name_len])?;
+                    offset += 1 + name_len;
+                    let description_len = read_u16(input, offset)? as usize;
+                    if description_len > MAX_PRODUCT_DESCRIPTION_LEN || input.len() < offset + 2 + description_len {
+                        return Err(invalid());
+                    }
+                    let description = clean_text(&input[offset + 2..offset + 2 + description_len])?;
+                    offset += 2 + description_len;
+                    products.push(NewProduct {
+                        name,
+                        description,
+                        price: read_u64(input, offset)?,
+                        stock: read_u64(input, offset + 8)?,
+                        max_per_buyer: read_u64(input, offset + 16)?,
+                    });
+                    offset += 24;
+                }
+                Self::AddProducts { products }
+            }
+            72 => Self::CreateReferral {
+                code_hash: read_bytes(input, 1)?,
+                referrer: read_pubkey(input, 33)?,
+                mint: read_pubkey(input, 65)?,
+            },
+            73 => Self::SetReferralBps { referral_bps: read_u16(input, 1)? },
+            74 => Self::ClaimReferralRewards,
+            75 => Self::SetTipAccount { tip_account: read_pubkey(input, 1)? },
+            76 => Self::SetCharity { charity_account: read_pubkey(input, 1)?, round_up_unit: read_u64(input, 33)? },
+            77 => Self::ExecuteDelegatedPurchase { product_id: read_u64(input, 1)?, amount: read_u64(input, 9)? },
+            78 => Self::ExecuteSignedOrder {
+                order: SignedOrder {
+                    product_id: read_u64(input, 1)?,
+                    quantity: read_u64(input, 9)?,
+                    max_price: read_u64(input, 17)?,
+                    expiry: read_i64(input, 25)?,
+                    nonce: read_u64(input, 33)?,
+                },
+            },
+            79 => {
+                let mut offset = 17;
+                let coupon_code = read_code(input, &mut offset)?;
+                // [has_variant u8][variant_id u8], como no Sell
+                let variant_id = read_u8(input, offset + 1)?;
+                let variant_id = (input[offset] != 0).then_some(variant_id);
+                Self::GetFullQuote { product_id: read_u64(input, 1)?, amount: read_u64(input, 9)?, coupon_code, variant_id }
+            }
+            80 => {
+                let name_len = read_u8(input, 11)? as usize;
+                if name_len > MAX_PRODUCT_NAME_LEN || input.len() < 12 + name_len {
+                    return Err(invalid());
+                }
+                let description_start = 12 + name_len;
+                let description_len = read_u16(input, description_start)? as usize;
+                if description_len > MAX_PRODUCT_DESCRIPTION_LEN || input.len() < description_start + 2 + description_len {
+                    return Err(invalid());
+                }
+                Self::SetLocalization {
+                    product_id: read_u64(input, 1)?,
+                    lang_code: read_bytes(input, 9)?,
+                    name: clean_text(&input[12..description_start])?,
+                    description: clean_text(&input[description_start + 2..description_start + 2 + description_len])?,
+                }
+            }
+            81 => Self::RemoveLocalization { product_id: read_u64(input, 1)?, lang_code: read_bytes(input, 9)? },
+            82 => Self::SetProductTags { product_id: read_u64(input, 1)?, tags: read_tags(input, 9)? },
+            83 => Self::SetArbiter { arbiter: read_pubkey(input, 1)?, dispute_window: read_i64(input, 33)? },
+            84 => Self::OpenDispute { history_index: read_u64(input, 1)? },
+            85 => Self::ResolveDispute { history_index: read_u64(input, 1)?, refund: read_u8(input, 9)? != 0 },
+            86 => Self::ReleaseExpiredEscrow { history_index: read_u64(input, 1)? },
+            87 => Self::PartialFulfillOrder { order_id: read_u64(input, 1)?, quantity: read_u64(input, 9)? },
+            88 => {
+                let item_count = read_u8(input, 17)? as usize;
+                if item_count == 0 || item_count > MAX_BUNDLE_ITEMS || input.len() < 18 + item_count * 16 {
+                    return Err(invalid());
+                }
+                let items = input[18..18 + item_count * 16]
+                    .chunks_exact(16)
+                    .map(|item| BundleItem {
+                        product_id: u64::from_le_bytes(item[..8].try_into().unwrap()),
+                        quantity: u64::from_le_bytes(item[8..16].try_into().unwrap()),
+                    })
+                    .collect();
+                Self::SetBundle { bundle_id: read_u64(input, 1)?, price: read_u64(input, 9)?, items }
+            }
+            89 => Self::CloseBundle { bundle_id: read_u64(input, 1)? },
+            90 => Self::SellBundle { bundle_id: read_u64(input, 1)?, quantity: read_u64(input, 9)? },
+            91 => {
+                let window_count = read_u8(input, 9)? as usize;
+                let len = HappyHourWindow::LEN;
+                if window_count == 0 || window_count > MAX_HAPPY_HOUR_WINDOWS || input.len() < 10 + window_count * len {
+                    return Err(invalid());
+                }
+                let windows = input[10..10 + window_count * len]
+                    .chunks_exact(len)
+                    .map(|window| HappyHourWindow {
+                        days: window[0],
+                        start_minute: u16::from_le_bytes(window[1..3].try_into().unwrap()),
+                        end_minute: u16::from_le_bytes(window[3..5].try_into().unwrap()),
+                        discount_bps: u16::from_le_bytes(window[5..7].try_into().unwrap()),
+                    })
+                    .collect();
+                Self::SetHappyHour { product_id: read_u64(input, 1)?, windows }
+            }
+            92 => Self::ClearHappyHour { product_id: read_u64(input, 1)? },
+            93 => Self::WithdrawStoreRevenue { amount: read_u64(input, 1)? },
+            94 => Self::ConfidentialSell {
+                product_id: read_u64(input, 1)?,
+                quantity: read_u64(input, 9)?,
+                new_source_decryptable_balance: read_bytes(input, 17)?,
+            },
+            95 if input.len() > 1 => Self::ConfidentialRevenue { data: input[1..].to_vec() },
+            _ => return Err(invalid()),
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Self::Initialize => buf.push(0),
+            Self::AddProduct { name, description, price, stock, max_per_buyer, tags } => {
+                buf.push(1);
+                // Textos que não cabem nos campos fixos, ou produtos com tags, vão no formato longo
+                let fits = name.len() <= 32 && description.len() <= 128 && tags.is_empty();
+                write_fixed(&mut buf, if fits { name } else { "" }, 32);
+                write_fixed(&mut buf, if fits { description } else { "" }, 128);
+                buf.extend_from_slice(&price.to_le_bytes());
+                buf.extend_from_slice(&stock.to_le_bytes());
+                buf.extend_from_slice(&max_per_buyer.to_le_bytes());
+                if !fits {
+                    buf.push(name.len() as u8);
+                    buf.extend_from_slice(name.as_bytes());
+                    buf.extend_from_slice(&(description.len() as u16).to_le_bytes());
+                    buf.extend_from_slice(description.as_bytes());
+                    if !tags.is_empty() {
+                        write_tags(&mut buf, tags);
+                    }
+                }
+            }
+            Self::Sell {
+                product_id,
+                amount,
+                coupon_code,
+                variant_id,
+                escrow,
+                payment_mode,
+                create_ata,
+                gift_code,
+                mint_receipt,
+                product_bump,
+                paged_history,
+                version,
+                memo,
+                referral_code,
+                tip,
+                round_up,
+                sync_native,
+                close_wsol,
+                max_total_price,
+            } => {
+                buf.push(4);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                write_code(&mut buf, coupon_code);
+                buf.push(variant_id.is_some() as u8);
+                buf.push(variant_id.unwrap_or_default());
+                buf.push(*escrow as u8);
+                buf.push(*payment_mode);
+                buf.push(*create_ata as u8);
+                write_code(&mut buf, gift_code);
+                buf.push(*mint_receipt as u8);
+                buf.push(product_bump.is_some() as u8);
+                buf.push(product_bump.unwrap_or_default());
+                buf.push(*paged_history as u8);
+                buf.push(*version);
+                let memo = memo.as_deref().unwrap_or_default();
+                buf.push(memo.len() as u8);
+                buf.extend_from_slice(memo);
+                write_code(&mut buf, referral_code);
+                buf.extend_from_slice(&tip.to_le_bytes());
+                buf.push(*round_up as u8);
+                buf.push(*sync_native as u8);
+                buf.push(*close_wsol as u8);
+                buf.extend_from_slice(&max_total_price.to_le_bytes());
+            }
+            Self::GetQuote { product_id, amount } => {
+                buf.push(2);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::UpdateStock { product_id, delta } => {
+                buf.push(3);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&delta.to_le_bytes());
+            }
+            Self::DeactivateProduct { product_id } => {
+                buf.push(5);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+            }
+            Self::Restock { product_id, amount, variant_id } => {
+                buf.push(6);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend(variant_id);
+            }
+            Self::UpdatePrice { product_id, new_price } => {
+                buf.push(7);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&new_price.to_le_bytes());
+            }
+            Self::SetMaxPriceChange { max_price_change_bps } => {
+                buf.push(8);
+                buf.extend_from_slice(&max_price_change_bps.to_le_bytes());
+            }
+            Self::AddOperator { operator } => {
+                buf.push(9);
+                buf.extend_from_slice(operator.as_ref());
+            }
+            Self::RemoveOperator { operator } => {
+                buf.push(10);
+                buf.extend_from_slice(operator.as_ref());
+            }
+            Self::Pause => buf.push(11),
+            Self::Resume => buf.push(12),
+            Self::SetAvailability { product_id, available } => {
+                buf.push(13);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(*available as u8);
+            }
+            Self::Refund { history_index } => {
+                buf.push(14);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::PlaceOrder { product_id, quantity } => {
+                buf.push(15);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Self::FulfillOrder { order_id } => {
+                buf.push(16);
+                buf.extend_from_slice(&order_id.to_le_bytes());
+            }
+            Self::CancelOrder { order_id } => {
+                buf.push(17);
+                buf.extend_from_slice(&order_id.to_le_bytes());
+            }
+            Self::Checkout { items, version } => {
+                buf.push(18);
+                buf.push(items.len() as u8);
+                for item in items {
+                    buf.extend_from_slice(&item.product_id.to_le_bytes());
+                    buf.extend_from_slice(&item.quantity.to_le_bytes());
+                }
+                buf.push(*version);
+            }
+            Self::CreateCoupon { code_hash, kind, value, expires_at, max_uses } => {
+                buf.push(19);
+                buf.extend_from_slice(code_hash);
+                buf.push(*kind);
+                buf.extend_from_slice(&value.to_le_bytes());
+                buf.extend_from_slice(&expires_at.to_le_bytes());
+                buf.extend_from_slice(&max_uses.to_le_bytes());
+            }
+            Self::SetMaxPerBuyer { product_id, max_per_buyer } => {
+                buf.push(20);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&max_per_buyer.to_le_bytes());
+            }
+            Self::CreateCategory { name } => {
+                buf.push(21);
+                buf.extend_from_slice(name);
+            }
+            Self::SetProductCategory { product_id, category_id } => {
+                buf.push(22);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&category_id.to_le_bytes());
+            }
+            Self::AddVariant { product_id, variant_id, name, price, stock } => {
+                buf.push(23);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(*variant_id);
+                buf.extend_from_slice(name);
+                buf.extend_from_slice(&price.to_le_bytes());
+                buf.extend_from_slice(&stock.to_le_bytes());
+            }
+            Self::MigrateProduct { product_id } => {
+                buf.push(24);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+            }
+            Self::SetMetadataUri { product_id, metadata_uri } => {
+                buf.push(25);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(metadata_uri);
+            }
+            Self::SetPriceTiers { product_id, tiers } => {
+                buf.push(26);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(tiers.len() as u8);
+                for tier in tiers {
+                    buf.extend_from_slice(&tier.min_quantity.to_le_bytes());
+                    buf.extend_from_slice(&tier.unit_price.to_le_bytes());
+                }
+            }
+            Self::SetFee { fee_bps, fee_destination } => {
+                buf.push(27);
+                buf.extend_from_slice(&fee_bps.to_le_bytes());
+                buf.extend_from_slice(fee_destination.as_ref());
+            }
+            Self::ConfirmDelivery { history_index } => {
+                buf.push(28);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::ReleaseAfterTimeout { history_index } => {
+                buf.push(29);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::RefundEscrow { history_index } => {
+                buf.push(30);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::SetEscrowTimeout { escrow_timeout } => {
+                buf.push(31);
+                buf.extend_from_slice(&escrow_timeout.to_le_bytes());
+            }
+            Self::PartialRefund { history_index, quantity } => {
+                buf.push(32);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Self::SetSolPrice { product_id, sol_price } => {
+                buf.push(33);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&sol_price.to_le_bytes());
+            }
+            Self::SetConfig { payment_mint, price_decimals, accepted_mints } => {
+                buf.push(34);
+                buf.extend_from_slice(payment_mint.as_ref());
+                buf.push(*price_decimals);
+                buf.push(accepted_mints.len() as u8);
+                for accepted in accepted_mints {
+                    buf.extend_from_slice(accepted.mint.as_ref());
+                    buf.push(accepted.decimals);
+                }
+            }
+            Self::SetUsdPrice { product_id, usd_price_cents, price_feed } => {
+                buf.push(35);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&usd_price_cents.to_le_bytes());
+                buf.extend_from_slice(price_feed.as_ref());
+            }
+            Self::CreateTreasury => buf.push(36),
+            Self::WithdrawTreasury { amount } => {
+                buf.push(37);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::SetRevenueSplit { splits } => {
+                buf.push(38);
+                buf.push(splits.len() as u8);
+                for split in splits {
+                    buf.extend_from_slice(split.token_account.as_ref());
+                    buf.extend_from_slice(&split.share_bps.to_le_bytes());
+                }
+            }
+            Self::SetCommission { commission_bps, commission_account } => {
+                buf.push(39);
+                buf.extend_from_slice(&commission_bps.to_le_bytes());
+                buf.extend_from_slice(commission_account.as_ref());
+            }
+            Self::CreateSubscription { product_id, quantity, interval, max_unit_price } => {
+                buf.push(40);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+                buf.extend_from_slice(&interval.to_le_bytes());
+                buf.extend_from_slice(&max_unit_price.to_le_bytes());
+            }
+            Self::CancelSubscription => buf.push(41),
+            Self::ProcessSubscription => buf.push(42),
+            Self::CreateGiftCard { code_hash, balance, expires_at } => {
+                buf.push(43);
+                buf.extend_from_slice(code_hash);
+                buf.extend_from_slice(&balance.to_le_bytes());
+                buf.extend_from_slice(&expires_at.to_le_bytes());
+            }
+            Self::AddToAllowlist { buyer } => {
+                buf.push(44);
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::RemoveFromAllowlist { buyer } => {
+                buf.push(45);
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::SetAllowlistRequired { product_id, required } => {
+                buf.push(46);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(*required as u8);
+            }
+            Self::AddToBlocklist { buyer } => {
+                buf.push(47);
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::RemoveFromBlocklist { buyer } => {
+                buf.push(48);
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::SetGateMint { product_id, gate_mint } => {
+                buf.push(49);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(gate_mint.as_ref());
+            }
+            Self::ExtendAccount { new_len } => {
+                buf.push(50);
+                buf.extend_from_slice(&new_len.to_le_bytes());
+            }
+            Self::CloseProduct { product_id, force } => {
+                buf.push(51);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(*force as u8);
+            }
+            Self::SetHistoryRetention { history_retention } => {
+                buf.push(52);
+                buf.extend_from_slice(&history_retention.to_le_bytes());
+            }
+            Self::CloseHistory { history_index } => {
+                buf.push(53);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::CreateGlobalStats => buf.push(54),
+            Self::MigrateState => buf.push(55),
+            Self::ArchiveHistory { archive_id, history_indexes } => {
+                buf.push(56);
+                buf.extend_from_slice(&archive_id.to_le_bytes());
+                buf.push(history_indexes.len() as u8);
+                for history_index in history_indexes {
+                    buf.extend_from_slice(&history_index.to_le_bytes());
+                }
+            }
+            Self::CreateLaunch { product_id, release_at, unit_price, max_quantity } => {
+                buf.push(57);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&release_at.to_le_bytes());
+                buf.extend_from_slice(&unit_price.to_le_bytes());
+                buf.extend_from_slice(&max_quantity.to_le_bytes());
+            }
+            Self::PlacePreOrder { product_id, quantity } => {
+                buf.push(58);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Self::ConvertPreOrder { product_id, buyer } => {
+                buf.push(59);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::CancelLaunch { product_id } => {
+                buf.push(60);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+            }
+            Self::RefundPreOrder { product_id, buyer } => {
+                buf.push(61);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(buyer.as_ref());
+            }
+            Self::ScheduleFlashSale { product_id, discount_bps, start, end } => {
+                buf.push(62);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&discount_bps.to_le_bytes());
+                buf.extend_from_slice(&start.to_le_bytes());
+                buf.extend_from_slice(&end.to_le_bytes());
+            }
+            Self::CancelFlashSale { product_id } => {
+                buf.push(63);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+            }
+            Self::SetAvailabilityWindow { product_id, available_from, available_until } => {
+                buf.push(64);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&available_from.to_le_bytes());
+                buf.extend_from_slice(&available_until.to_le_bytes());
+            }
+            Self::UpdateOrderStatus { order_id, status } => {
+                buf.push(65);
+                buf.extend_from_slice(&order_id.to_le_bytes());
+                buf.push(*status);
+            }
+            Self::ConfirmOrderDelivery { order_id } => {
+                buf.push(66);
+                buf.extend_from_slice(&order_id.to_le_bytes());
+            }
+            Self::SetBuyerProfile { display_name_hash, preferred_mint, contact_hash } => {
+                buf.push(67);
+                buf.extend_from_slice(display_name_hash);
+                buf.extend_from_slice(preferred_mint.as_ref());
+                buf.extend_from_slice(contact_hash);
+            }
+            Self::CreateStore { store_id } => {
+                buf.push(68);
+                buf.extend_from_slice(&store_id.to_le_bytes());
+            }
+            Self::SetOwnerSet { threshold, members } => {
+                buf.push(69);
+                buf.push(*threshold);
+                buf.push(members.len() as u8);
+                for member in members {
+                    buf.extend_from_slice(member.as_ref());
+                }
+            }
+            Self::SetPriceDecimals { product_id, price_decimals } => {
+                buf.push(70);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(*price_decimals);
+            }
+            Self::AddProducts { products } => {
+                buf.push(71);
+                buf.push(products.len() as u8);
+                for product in products {
+                    buf.push(product.name.len() as u8);
+                    buf.extend_from_slice(product.name.as_bytes());
+                    buf.extend_from_slice(&(product.description.len() as u16).to_le_bytes());
+                    buf.extend_from_slice(product.description.as_bytes());
+                    buf.extend_from_slice(&product.price.to_le_bytes());
+                    buf.extend_from_slice(&product.stock.to_le_bytes());
+                    buf.extend_from_slice(&product.max_per_buyer.to_le_bytes());
+                }
+            }
+            Self::CreateReferral { code_hash, referrer, mint } => {
+                buf.push(72);
+                buf.extend_from_slice(code_hash);
+                buf.extend_from_slice(referrer.as_ref());
+                buf.extend_from_slice(mint.as_ref());
+            }
+            Self::SetReferralBps { referral_bps } => {
+                buf.push(73);
+                buf.extend_from_slice(&referral_bps.to_le_bytes());
+            }
+            Self::ClaimReferralRewards => buf.push(74),
+            Self::SetTipAccount { tip_account } => {
+                buf.push(75);
+                buf.extend_from_slice(tip_account.as_ref());
+            }
+            Self::SetCharity { charity_account, round_up_unit } => {
+                buf.push(76);
+                buf.extend_from_slice(charity_account.as_ref());
+                buf.extend_from_slice(&round_up_unit.to_le_bytes());
+            }
+            Self::ExecuteDelegatedPurchase { product_id, amount } => {
+                buf.push(77);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::ExecuteSignedOrder { order } => {
+                buf.push(78);
+                buf.extend_from_slice(&order.product_id.to_le_bytes());
+                buf.extend_from_slice(&order.quantity.to_le_bytes());
+                buf.extend_from_slice(&order.max_price.to_le_bytes());
+                buf.extend_from_slice(&order.expiry.to_le_bytes());
+                buf.extend_from_slice(&order.nonce.to_le_bytes());
+            }
+            Self::GetFullQuote { product_id, amount, coupon_code, variant_id } => {
+                buf.push(79);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                write_code(&mut buf, coupon_code);
+                buf.push(variant_id.is_some() as u8);
+                buf.push(variant_id.unwrap_or_default());
+            }
+            Self::SetLocalization { product_id, lang_code, name, description } => {
+                buf.push(80);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(lang_code);
+                buf.push(name.len() as u8);
+                buf.extend_from_slice(name.as_bytes());
+                buf.extend_from_slice(&(description.len() as u16).to_le_bytes());
+                buf.extend_from_slice(description.as_bytes());
+            }
+            Self::RemoveLocalization { product_id, lang_code } => {
+                buf.push(81);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(lang_code);
+            }
+            Self::SetProductTags { product_id, tags } => {
+                buf.push(82);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                write_tags(&mut buf, tags);
+            }
+            Self::SetArbiter { arbiter, dispute_window } => {
+                buf.push(83);
+                buf.extend_from_slice(arbiter.as_ref());
+                buf.extend_from_slice(&dispute_window.to_le_bytes());
+            }
+            Self::OpenDispute { history_index } => {
+                buf.push(84);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::ResolveDispute { history_index, refund } => {
+                buf.push(85);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+                buf.push(*refund as u8);
+            }
+            Self::ReleaseExpiredEscrow { history_index } => {
+                buf.push(86);
+                buf.extend_from_slice(&history_index.to_le_bytes());
+            }
+            Self::PartialFulfillOrder { order_id, quantity } => {
+                buf.push(87);
+                buf.extend_from_slice(&order_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Self::SetBundle { bundle_id, price, items } => {
+                buf.push(88);
+                buf.extend_from_slice(&bundle_id.to_le_bytes());
+                buf.extend_from_slice(&price.to_le_bytes());
+                buf.push(items.len() as u8);
+                for item in items {
+                    buf.extend_from_slice(&item.product_id.to_le_bytes());
+                    buf.extend_from_slice(&item.quantity.to_le_bytes());
+                }
+            }
+            Self::CloseBundle { bundle_id } => {
+                buf.push(89);
+                buf.extend_from_slice(&bundle_id.to_le_bytes());
+            }
+            Self::SellBundle { bundle_id, quantity } => {
+                buf.push(90);
+                buf.extend_from_slice(&bundle_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Self::SetHappyHour { product_id, windows } => {
+                buf.push(91);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.push(windows.len() as u8);
+                for window in windows {
+                    buf.push(window.days);
+                    buf.extend_from_slice(&window.start_minute.to_le_bytes());
+                    buf.extend_from_slice(&window.end_minute.to_le_bytes());
+                    buf.extend_from_slice(&window.discount_bps.to_le_bytes());
+                }
+            }
+            Self::ClearHappyHour { product_id } => {
+                buf.push(92);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+            }
+            Self::WithdrawStoreRevenue { amount } => {
+                buf.push(93);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::ConfidentialSell { product_id, quantity, new_source_decryptable_balance } => {
+                buf.push(94);
+                buf.extend_from_slice(&product_id.to_le_bytes());
+                buf.extend_from_slice(&quantity.to_le_bytes());
+                buf.extend_from_slice(new_source_decryptable_balance);
+            }
+            Self::ConfidentialRevenue { data } => {
+                buf.push(95);
+                buf.extend_from_slice(data);
+            }
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Confere o round-trip e que faltar um byte dos campos obrigatórios é rejeitado
+    fn check(instruction: CakeInstruction, required_len: usize) {
+        let data = instruction.pack();
+        assert_eq!(CakeInstruction::unpack(&data).unwrap(), instruction);
+        assert_eq!(CakeInstruction::unpack(&data[..required_len - 1]), Err(CakeError::InvalidInstructionData.into()));
+    }
+
+    fn key(seed: u8) -> Pubkey {
+        Pubkey::new_from_array([seed; 32])
+    }
+
+    #[test]
+    fn test_empty_and_unknown_tag() {
+        assert_eq!(CakeInstruction::unpack(&[]), Err(CakeError::InvalidInstructionData.into()));
+        assert_eq!(CakeInstruction::unpack(&[56]), Err(CakeError::InvalidInstructionData.into()));
+        assert_eq!(CakeInstruction::unpack(&[95]), Err(CakeError::InvalidInstructionData.into()));
+    }
+
+    #[test]
+    fn test_unit_variants() {
+        for (instruction, tag) in [
+            (CakeInstruction::Initialize, 0),
+            (CakeInstruction::Pause, 11),
+            (CakeInstruction::Resume, 12),
+            (CakeInstruction::CreateTreasury, 36),
+            (CakeInstruction::CancelSubscription, 41),
+            (CakeInstruction::ProcessSubscription, 42),
+            (CakeInstruction::CreateGlobalStats, 54),
+            (CakeInstruction::MigrateState, 55),
+            (CakeInstruction::ClaimReferralRewards, 74),
+        ] {
+            assert_eq!(instruction.pack(), vec![tag]);
+            assert_eq!(CakeInstruction::unpack(&[tag]).unwrap(), instruction);
+        }
+    }
+
+    #[test]
+    fn test_add_product() {
+        let short = CakeInstruction::AddProduct {
+            name: "Bolo de cenoura".to_string(),
+            description: "Com cobertura de chocolate".to_string(),
+            price: 1_000_000,
+            stock: 100,
+            max_per_buyer: 5,
+            tags: Vec::new(),
+        };
+        check(short.clone(), 177);
+        assert_eq!(short.pack().len(), 185);
+
+        let long = CakeInstruction::AddProduct {
+            name: "B".repeat(MAX_PRODUCT_NAME_LEN),
+            description: "D".repeat(MAX_PRODUCT_DESCRIPTION_LEN),
+            price: 1,
+            stock: 2,
+            max_per_buyer: 0,
+            tags: Vec::new(),
+        };
+        check(long.clone(), 177);
+        let data = long.pack();
+        assert!(CakeInstruction::unpack(&data[..data.len() - 1]).is_err());
+
+        // Com tags, mesmo textos curtos vão no formato longo, e as tags vêm depois deles
+        let mut vegan = [0u8; TAG_LEN];
+        vegan[..6].copy_from_slice(b"vegano");
+        let tagged = CakeInstruction::AddProduct {
+            name: "Bolo de cenoura".to_string(),
+            description: "Com cobertura de chocolate".to_string(),
+            price: 1_000_000,
+            stock: 100,
+            max_per_buyer: 5,
+            tags: vec![vegan],
+        };
+        check(tagged.clone(), 177);
+        let data = tagged.pack();
+        assert_eq!(data.len(), 188 + 15 + 26 + 1 + TAG_LEN);
+        assert!(CakeInstruction::unpack(&data[..data.len() - 1]).is_err());
+
+        // Sem max_per_buyer, o limite é 0
+        let data = short.pack();
+        match CakeInstruction::unpack(&data[..177]).unwrap() {
+            CakeInstruction::AddProduct { max_per_buyer, .. } => assert_eq!(max_per_buyer, 0),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sell() {
+        let full = CakeInstruction::Sell {
+            product_id: 3,
+            amount: 2,
+            coupon_code: Some(b"BOLO10".to_vec()),
+            variant_id: Some(7),
+            escrow: true,
+            payment_mode: PAYMENT_MODE_TOKEN,
+            create_ata: true,
+            gift_code: Some(b"PRESENTE".to_vec()),
+            mint_receipt: true,
+            product_bump: Some(254),
+            paged_history: true,
+            version: SELL_VERSION,
+            memo: Some("Feliz aniversário, Ana".as_bytes().to_vec()),
+            referral_code: Some(b"ANA2024".to_vec()),
+            tip: 500_000,
+            round_up: true,
+            sync_native: true,
+            close_wsol: true,
+            max_total_price: 2_500_000,
+        };
+        check(full, 17);
+
+        let plain = CakeInstruction::Sell {
+            product_id: 3,
+            amount: 2,
+            coupon_code: None,
+            variant_id: None,
+            escrow: false,
+            payment_mode: PAYMENT_MODE_TOKEN,
+            create_ata: false,
+            gift_code: None,
+            mint_receipt: false,
+            product_bump: None,
+            paged_history: false,
+            version: SELL_VERSION,
+            memo: None,
+            referral_code: None,
+            tip: 0,
+            round_up: false,
+            sync_native: false,
+            close_wsol: false,
+            max_total_price: 0,
+        };
+        check(plain.clone(), 17);
+        // Todas as extensões são opcionais; sem o byte de versão os dados são do layout 1
+        match CakeInstruction::unpack(&plain.pack()[..17]).unwrap() {
+            CakeInstruction::Sell { version, .. } => assert_eq!(version, SELL_LEGACY_VERSION),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let mut data = plain.pack();
+        data[17] = (MAX_COUPON_CODE_LEN + 1) as u8;
+        assert!(CakeInstruction::unpack(&data).is_err());
+
+        let mut data = plain.pack();
+        let version_offset = data.len() - 22;
+        data[version_offset] = SELL_VERSION + 1;
+        assert!(CakeInstruction::unpack(&data).is_err());
+
+        let mut data = plain.pack();
+        data.truncate(data.len() - 20);
+        *data.last_mut().unwrap() = (MAX_MEMO_LEN + 1) as u8;
+        data.extend(vec![b'a'; MAX_MEMO_LEN + 1]);
+        assert!(CakeInstruction::unpack(&data).is_err());
+
+        let mut data = plain.pack();
+        data.truncate(data.len() - 19);
+        *data.last_mut().unwrap() = (MAX_COUPON_CODE_LEN + 1) as u8;
+        data.extend(vec![b'a'; MAX_COUPON_CODE_LEN + 1]);
+        assert!(CakeInstruction::unpack(&data).is_err());
+
+        // A gorjeta e o total máximo incompletos são rejeitados; sem os bytes finais não há arredondamento,
+        // wSOL nem limite de preço
+        let data = plain.pack();
+        assert!(CakeInstruction::unpack(&data[..data.len() - 12]).is_err());
+        assert!(CakeInstruction::unpack(&data[..data.len() - 1]).is_err());
+        match CakeInstruction::unpack(&data[..data.len() - 11]).unwrap() {
+            CakeInstruction::Sell { round_up, sync_native, close_wsol, max_total_price, .. } => {
+                assert!(!round_up && !sync_native && !close_wsol && max_total_price == 0)
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_product_updates() {
+        check(CakeInstruction::GetQuote { product_id: 1, amount: 3 }, 17);
+        check(CakeInstruction::UpdateStock { product_id: 1, delta: -4 }, 17);
+        check(CakeInstruction::DeactivateProduct { product_id: 1 }, 9);
+        check(CakeInstruction::Restock { product_id: 1, amount: 10, variant_id: None }, 17);
+        check(CakeInstruction::Restock { product_id: 1, amount: 10, variant_id: Some(2) }, 17);
+        check(CakeInstruction::UpdatePrice { product_id: 1, new_price: 500 }, 17);
+        check(CakeInstruction::SetMaxPriceChange { max_price_change_bps: 1_000 }, 3);
+        check(CakeInstruction::SetAvailability { product_id: 1, available: true }, 10);
+        check(CakeInstruction::SetMaxPerBuyer { product_id: 1, max_per_buyer: 3 }, 17);
+        check(CakeInstruction::MigrateProduct { product_id: 1 }, 9);
+        check(CakeInstruction::SetMetadataUri { product_id: 1, metadata_uri: [b'u'; 200] }, 209);
+        check(CakeInstruction::SetSolPrice { product_id: 1, sol_price: 10_000 }, 17);
+        check(CakeInstruction::SetUsdPrice { product_id: 1, usd_price_cents: 1_250, price_feed: key(9) }, 49);
+        check(CakeInstruction::SetAllowlistRequired { product_id: 1, required: true }, 10);
+        check(CakeInstruction::SetGateMint { product_id: 1, gate_mint: key(4) }, 41);
+        check(CakeInstruction::CloseProduct { product_id: 1, force: true }, 10);
+        check(CakeInstruction::SetAvailabilityWindow { product_id: 1, available_from: 1_700_000_000, available_until: 0 }, 25);
+        check(CakeInstruction::SetPriceDecimals { product_id: 1, price_decimals: 2 }, 10);
+    }
+
+    #[test]
+    fn test_operators_and_lists() {
+        check(CakeInstruction::SetBuyerProfile { display_name_hash: [1; 32], preferred_mint: key(2), contact_hash: [3; 32] }, 97);
+        check(CakeInstruction::CreateStore { store_id: 1 }, 9);
+        check(CakeInstruction::WithdrawStoreRevenue { amount: 500 }, 9);
+        check(CakeInstruction::AddOperator { operator: key(1) }, 33);
+        check(CakeInstruction::RemoveOperator { operator: key(1) }, 33);
+        check(CakeInstruction::AddToAllowlist { buyer: key(2) }, 33);
+        check(CakeInstruction::RemoveFromAllowlist { buyer: key(2) }, 33);
+        check(CakeInstruction::AddToBlocklist { buyer: key(3) }, 33);
+        check(CakeInstruction::RemoveFromBlocklist { buyer: key(3) }, 33);
+    }
+
+    #[test]
+    fn test_refunds_and_orders() {
+        check(CakeInstruction::Refund { history_index: 4 }, 9);
+        check(CakeInstruction::PartialRefund { history_index: 4, quantity: 1 }, 17);
+        check(CakeInstruction::PlaceOrder { product_id: 1, quantity: 2 }, 17);
+        check(CakeInstruction::FulfillOrder { order_id: 6 }, 9);
+        check(CakeInstruction::CancelOrder { order_id: 6 }, 9);
+        check(CakeInstruction::UpdateOrderStatus { order_id: 6, status: 3 }, 10);
+        check(CakeInstruction::ConfirmOrderDelivery { order_id: 6 }, 9);
+        check(CakeInstruction::PartialFulfillOrder { order_id: 6, quantity: 8 }, 17);
+        check(CakeInstruction::ConfirmDelivery { history_index: 4 }, 9);
+        check(CakeInstruction::ReleaseAfterTimeout { history_index: 4 }, 9);
+        check(CakeInstruction::RefundEscrow { history_index: 4 }, 9);
+        check(CakeInstruction::SetEscrowTimeout { escrow_timeout: 86_400 }, 9);
+        check(CakeInstruction::ReleaseExpiredEscrow { history_index: 4 }, 9);
+        check(CakeInstruction::SetHistoryRetention { history_retention: 2_592_000 }, 9);
+        check(CakeInstruction::CloseHistory { history_index: 4 }, 9);
+        check(CakeInstruction::ArchiveHistory { archive_id: 2, history_indexes: vec![4, 9, 12] }, 34);
+        assert!(CakeInstruction::unpack(&CakeInstruction::ArchiveHistory { archive_id: 2, history_indexes: Vec::new() }.pack()).is_err());
+        let too_many = vec![0; MAX_ARCHIVE_BATCH + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::ArchiveHistory { archive_id: 2, history_indexes: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_pre_orders() {
+        check(CakeInstruction::CreateLaunch { product_id: 1, release_at: 1_700_000_000, unit_price: 500, max_quantity: 40 }, 33);
+        check(CakeInstruction::PlacePreOrder { product_id: 1, quantity: 2 }, 17);
+        check(CakeInstruction::ConvertPreOrder { product_id: 1, buyer: key(2) }, 41);
+        check(CakeInstruction::CancelLaunch { product_id: 1 }, 9);
+        check(CakeInstruction::RefundPreOrder { product_id: 1, buyer: key(2) }, 41);
+    }
+
+    #[test]
+    fn test_checkout() {
+        let items = vec![CartItem { product_id: 1, quantity: 2 }, CartItem { product_id: 5, quantity: 1 }];
+        let checkout = CakeInstruction::Checkout { items: items.clone(), version: CHECKOUT_VERSION };
+        check(checkout.clone(), 34);
+        // Sem o byte de versão os dados são do layout 1
+        let legacy = CakeInstruction::unpack(&checkout.pack()[..34]).unwrap();
+        assert_eq!(legacy, CakeInstruction::Checkout { items, version: CHECKOUT_LEGACY_VERSION });
+        let mut data = checkout.pack();
+        *data.last_mut().unwrap() = CHECKOUT_VERSION + 1;
+        assert!(CakeInstruction::unpack(&data).is_err());
+
+        assert!(CakeInstruction::unpack(&CakeInstruction::Checkout { items: Vec::new(), version: CHECKOUT_VERSION }.pack()).is_err());
+        let too_many = vec![CartItem { product_id: 1, quantity: 1 }; MAX_CART_ITEMS + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::Checkout { items: too_many, version: CHECKOUT_VERSION }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_bundles() {
+        let items = vec![BundleItem { product_id: 1, quantity: 2 }, BundleItem { product_id: 5, quantity: 1 }];
+        check(CakeInstruction::SetBundle { bundle_id: 3, price: 12_000_000, items }, 50);
+        check(CakeInstruction::CloseBundle { bundle_id: 3 }, 9);
+        check(CakeInstruction::SellBundle { bundle_id: 3, quantity: 2 }, 17);
+
+        let set_bundle = |items| CakeInstruction::SetBundle { bundle_id: 3, price: 1, items }.pack();
+        assert!(CakeInstruction::unpack(&set_bundle(Vec::new())).is_err());
+        assert!(CakeInstruction::unpack(&set_bundle(vec![BundleItem { product_id: 1, quantity: 1 }; MAX_BUNDLE_ITEMS + 1])).is_err());
+    }
+
+    #[test]
+    fn test_happy_hour() {
+        let window = HappyHourWindow { days: 0x1f, start_minute: 900, end_minute: 1_020, discount_bps: 2_000 };
+        check(CakeInstruction::SetHappyHour { product_id: 2, windows: vec![window, window] }, 24);
+        check(CakeInstruction::ClearHappyHour { product_id: 2 }, 9);
+
+        let set_happy_hour = |windows| CakeInstruction::SetHappyHour { product_id: 2, windows }.pack();
+        assert!(CakeInstruction::unpack(&set_happy_hour(Vec::new())).is_err());
+        assert!(CakeInstruction::unpack(&set_happy_hour(vec![window; MAX_HAPPY_HOUR_WINDOWS + 1])).is_err());
+    }
+
+    #[test]
+    fn test_confidential_sell() {
+        check(CakeInstruction::ConfidentialSell { product_id: 1, quantity: 2, new_source_decryptable_balance: [7; 36] }, 53);
+        check(CakeInstruction::ConfidentialRevenue { data: vec![8, 1, 0, 0, 0, 0, 0, 0, 0] }, 2);
+    }
+
+    #[test]
+    fn test_add_products() {
+        let product = |name: &str| NewProduct {
+            name: name.to_string(),
+            description: "Fatia".to_string(),
+            price: 1_000,
+            stock: 5,
+            max_per_buyer: 0,
+        };
+        // 2 + 2 × (1 + 3 + 2 + 5 + 24)
+        check(CakeInstruction::AddProducts { products: vec![product("Bem"), product("Sol")] }, 72);
+
+        assert!(CakeInstruction::unpack(&CakeInstruction::AddProducts { products: Vec::new() }.pack()).is_err());
+        let too_many = vec![product("Bolo"); MAX_PRODUCT_BATCH + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::AddProducts { products: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_coupons_categories_and_variants() {
+        check(CakeInstruction::CreateCoupon { code_hash: [7; 32], kind: 1, value: 1_000, expires_at: -1, max_uses: 10 }, 58);
+        check(CakeInstruction::ScheduleFlashSale { product_id: 1, discount_bps: 2_500, start: 100, end: 200 }, 27);
+        check(CakeInstruction::CancelFlashSale { product_id: 1 }, 9);
+        check(CakeInstruction::CreateCategory { name: [b'c'; 32] }, 33);
+        check(CakeInstruction::SetProductCategory { product_id: 1, category_id: 2 }, 17);
+        check(CakeInstruction::AddVariant { product_id: 1, variant_id: 3, name: [b'v'; 32], price: 900, stock: 4 }, 58);
+    }
+
+    #[test]
+    fn test_price_tiers() {
+        let tiers = vec![PriceTier { min_quantity: 5, unit_price: 90 }, PriceTier { min_quantity: 10, unit_price: 80 }];
+        check(CakeInstruction::SetPriceTiers { product_id: 1, tiers }, 42);
+        check(CakeInstruction::SetPriceTiers { product_id: 1, tiers: Vec::new() }, 10);
+
+        let too_many = vec![PriceTier { min_quantity: 1, unit_price: 1 }; MAX_PRICE_TIERS + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::SetPriceTiers { product_id: 1, tiers: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_fees_and_commission() {
+        check(CakeInstruction::SetFee { fee_bps: 250, fee_destination: key(5) }, 35);
+        check(CakeInstruction::SetCommission { commission_bps: 500, commission_account: key(6) }, 35);
+        check(CakeInstruction::WithdrawTreasury { amount: 1_000 }, 9);
+    }
+
+    #[test]
+    fn test_extend_account() {
+        check(CakeInstruction::ExtendAccount { new_len: 1_024 }, 9);
+    }
+
+    #[test]
+    fn test_set_config() {
+        let accepted_mints = vec![AcceptedMint { mint: key(2), decimals: 9 }];
+        check(CakeInstruction::SetConfig { payment_mint: key(1), price_decimals: 6, accepted_mints }, 36);
+
+        // Apenas o payment_mint: sem mints adicionais
+        let data = CakeInstruction::SetConfig { payment_mint: key(1), price_decimals: 6, accepted_mints: Vec::new() }.pack();
+        assert_eq!(
+            CakeInstruction::unpack(&data[..33]).unwrap(),
+            CakeInstruction::SetConfig { payment_mint: key(1), price_decimals: 0, accepted_mints: Vec::new() }
+        );
+        assert!(CakeInstruction::unpack(&data[..34]).is_err());
+
+        let too_many = vec![AcceptedMint::default(); MAX_ACCEPTED_MINTS + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::SetConfig { payment_mint: key(1), price_decimals: 6, accepted_mints: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_set_revenue_split() {
+        let splits = vec![
+            SplitRecipient { token_account: key(1), share_bps: 7_000 },
+            SplitRecipient { token_account: key(2), share_bps: 3_000 },
+        ];
+        check(CakeInstruction::SetRevenueSplit { splits }, 70);
+        check(CakeInstruction::SetRevenueSplit { splits: Vec::new() }, 2);
+
+        let too_many = vec![SplitRecipient::default(); MAX_SPLIT_RECIPIENTS + 1];
+        assert!(CakeInstruction::unpack(&CakeInstruction::SetRevenueSplit { splits: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_set_owner_set() {
+        check(CakeInstruction::SetOwnerSet { threshold: 2, members: vec![key(1), key(2), key(3)] }, 99);
+
+        let too_many = (0..=MAX_OWNER_SET_MEMBERS as u8).map(key).collect();
+        assert!(CakeInstruction::unpack(&CakeInstruction::SetOwnerSet { threshold: 1, members: too_many }.pack()).is_err());
+    }
+
+    #[test]
+    fn test_subscriptions_and_gift_cards() {
+        check(CakeInstruction::CreateSubscription { product_id: 1, quantity: 2, interval: 604_800, max_unit_price: 900_000 }, 33);
+        check(CakeInstruction::CreateGiftCard { code_hash: [8; 32], balance: 5_000, expires_at: 0 }, 49);
+    }
+
+    #[test]
+    fn test_referrals() {
+        check(CakeInstruction::CreateReferral { code_hash: [9; 32], referrer: key(1), mint: key(2) }, 97);
+        check(CakeInstruction::SetReferralBps { referral_bps: 500 }, 3);
+    }
+
+    #[test]
+    fn test_set_tip_account() {
+        check(CakeInstruction::SetTipAccount { tip_account: key(3) }, 33);
+    }
+
+    #[test]
+    fn test_set_charity() {
+        check(CakeInstruction::SetCharity { charity_account: key(4), round_up_unit: 1_000_000 }, 41);
+    }
+
+    #[test]
+    fn test_execute_delegated_purchase() {
+        check(CakeInstruction::ExecuteDelegatedPurchase { product_id: 5, amount: 2 }, 17);
+    }
+
+    #[test]
+    fn test_get_full_quote() {
+        let plain = CakeInstruction::GetFullQuote { product_id: 1, amount: 3, coupon_code: None, variant_id: None };
+        check(plain.clone(), 20);
+        assert_eq!(plain.pack().len(), 20);
+        let full = CakeInstruction::GetFullQuote { product_id: 1, amount: 3, coupon_code: Some(b"BOLO10".to_vec()), variant_id: Some(2) };
+        check(full, 26);
+
+        let quote = Quote { subtotal: 3_000_000, discount: 300_000, total_price: 2_700_000, fee: 27_000, amount_due: 2_727_000 };
+        assert_eq!(Quote::from_bytes(&quote.to_bytes()), Some(quote));
+        assert_eq!(Quote::from_bytes(&quote.to_bytes()[..Quote::LEN - 1]), None);
+    }
+
+    #[test]
+    fn test_execute_signed_order() {
+        let order = SignedOrder { product_id: 5, quantity: 2, max_price: 3_000_000, expiry: 1_700_000_000, nonce: 42 };
+        check(CakeInstruction::ExecuteSignedOrder { order }, 41);
+
+        // A mensagem assinada muda com a loja e com cada campo da ordem
+        let store = key(9);
+        let message = order.message(&store);
+        assert_eq!(&message[..16], SIGNED_ORDER_DOMAIN);
+        assert_eq!(&message[16..48], store.as_ref());
+        assert_ne!(message, order.message(&key(8)));
+        assert_ne!(message, SignedOrder { nonce: 43, ..order }.message(&store));
+        assert_ne!(message, SignedOrder { max_price: 3_000_001, ..order }.message(&store));
+    }
+    #[test]
+    fn test_localization() {
+        let set = CakeInstruction::SetLocalization {
+            product_id: 4,
+            lang_code: *b"en",
+            name: "Carrot cake".to_string(),
+            description: "With chocolate frosting".to_string(),
+        };
+        // 1 + 8 + 2 + 1 + 11 + 2 + 23
+        check(set, 48);
+        check(CakeInstruction::RemoveLocalization { product_id: 4, lang_code: *b"en" }, 11);
+
+        let mut data = vec![80];
+        data.extend_from_slice(&4u64.to_le_bytes());
+        data.extend_from_slice(b"en");
+        data.push(MAX_PRODUCT_NAME_LEN as u8 + 1);
+        data.extend_from_slice(&[b'a'; MAX_PRODUCT_NAME_LEN + 1]);
+        data.extend_from_slice(&0u16.to_le_bytes());
+        assert_eq!(CakeInstruction::unpack(&data), Err(CakeError::InvalidInstructionData.into()));
+    }
+
+    #[test]
+    fn test_set_product_tags() {
+        let mut vegan = [0u8; TAG_LEN];
+        vegan[..6].copy_from_slice(b"vegano");
+        check(CakeInstruction::SetProductTags { product_id: 4, tags: vec![vegan, [b'g'; TAG_LEN]] }, 42);
+        check(CakeInstruction::SetProductTags { product_id: 4, tags: Vec::new() }, 10);
+
+        let too_many = CakeInstruction::SetProductTags { product_id: 4, tags: vec![vegan; MAX_PRODUCT_TAGS + 1] };
+        assert_eq!(CakeInstruction::unpack(&too_many.pack()), Err(CakeError::InvalidInstructionData.into()));
+    }
+
+    #[test]
+    fn test_disputes() {
+        check(CakeInstruction::SetArbiter { arbiter: key(5), dispute_window: 7 * 86_400 }, 41);
+        check(CakeInstruction::OpenDispute { history_index: 3 }, 9);
+        check(CakeInstruction::ResolveDispute { history_index: 3, refund: true }, 10);
+        check(CakeInstruction::ResolveDispute { history_index: 3, refund: false }, 10);
+    }
+}
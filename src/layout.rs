@@ -0,0 +1,304 @@
+// Posição em bytes de cada campo nas contas CakeState, Product e PurchaseHistory (layout atual, com
+// discriminador), para filtros memcmp de getProgramAccounts e indexadores que leem os bytes direto.
+// Os testes conferem cada constante contra o pack, então uma mudança de layout quebra aqui primeiro
+use crate::state::{MAX_PRICE_TIERS, PRODUCT_DISCRIMINATOR};
+
+pub const DISCRIMINATOR_OFFSET: usize = 0;
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+// CakeState: [discriminador 8][corpo 175][is_initialized 1]
+pub const CAKE_STATE_OWNER_OFFSET: usize = 8;
+pub const CAKE_STATE_PRODUCT_COUNTER_OFFSET: usize = 40;
+pub const CAKE_STATE_HISTORY_COUNTER_OFFSET: usize = 48;
+pub const CAKE_STATE_MAX_PRICE_CHANGE_BPS_OFFSET: usize = 56;
+pub const CAKE_STATE_PAUSED_OFFSET: usize = 58;
+pub const CAKE_STATE_ORDER_COUNTER_OFFSET: usize = 59;
+pub const CAKE_STATE_CATEGORY_COUNTER_OFFSET: usize = 67;
+pub const CAKE_STATE_FEE_BPS_OFFSET: usize = 75;
+pub const CAKE_STATE_FEE_DESTINATION_OFFSET: usize = 77;
+pub const CAKE_STATE_ESCROW_TIMEOUT_OFFSET: usize = 109;
+pub const CAKE_STATE_COMMISSION_BPS_OFFSET: usize = 117;
+pub const CAKE_STATE_COMMISSION_AUTHORITY_OFFSET: usize = 119;
+pub const CAKE_STATE_COMMISSION_ACCOUNT_OFFSET: usize = 151;
+pub const CAKE_STATE_IS_INITIALIZED_OFFSET: usize = 183;
+
+// Product: [discriminador 8][versão 1][id 8][name_len u32][name][description_len u32][description][campos fixos].
+// Só o cabeçalho tem posição absoluta; os campos fixos (ProductFixedFields) começam em
+// product_fixed_fields_offset e as constantes PRODUCT_*_OFFSET abaixo do cabeçalho contam a partir dali
+pub const PRODUCT_VERSION_OFFSET: usize = 8;
+pub const PRODUCT_ID_OFFSET: usize = 9;
+pub const PRODUCT_NAME_LEN_OFFSET: usize = 17;
+pub const PRODUCT_NAME_OFFSET: usize = 21;
+
+pub const PRODUCT_PRICE_OFFSET: usize = 0;
+pub const PRODUCT_STOCK_OFFSET: usize = 8;
+pub const PRODUCT_ACTIVE_OFFSET: usize = 16;
+pub const PRODUCT_AVAILABLE_OFFSET: usize = 17;
+pub const PRODUCT_MAX_PER_BUYER_OFFSET: usize = 18;
+pub const PRODUCT_CATEGORY_ID_OFFSET: usize = 26;
+pub const PRODUCT_METADATA_URI_OFFSET: usize = 34;
+pub const PRODUCT_TIER_COUNT_OFFSET: usize = 234;
+// MAX_PRICE_TIERS faixas de [min_quantity u64][unit_price u64]
+pub const PRODUCT_TIERS_OFFSET: usize = 235;
+pub const PRODUCT_SOL_PRICE_OFFSET: usize = PRODUCT_TIERS_OFFSET + MAX_PRICE_TIERS * 16;
+pub const PRODUCT_USD_PRICE_CENTS_OFFSET: usize = 307;
+pub const PRODUCT_PRICE_FEED_OFFSET: usize = 315;
+pub const PRODUCT_ALLOWLIST_REQUIRED_OFFSET: usize = 347;
+pub const PRODUCT_GATE_MINT_OFFSET: usize = 348;
+pub const PRODUCT_UNITS_SOLD_OFFSET: usize = 380;
+pub const PRODUCT_REVENUE_OFFSET: usize = 388;
+pub const PRODUCT_SOL_REVENUE_OFFSET: usize = 396;
+pub const PRODUCT_AVAILABLE_FROM_OFFSET: usize = 404;
+pub const PRODUCT_AVAILABLE_UNTIL_OFFSET: usize = 412;
+pub const PRODUCT_PRICE_DECIMALS_OFFSET: usize = 420;
+
+// Início dos campos fixos para nome e descrição com esses tamanhos em bytes
+pub const fn product_fixed_fields_offset(name_len: usize, description_len: usize) -> usize {
+    PRODUCT_NAME_OFFSET + name_len + 4 + description_len
+}
+
+// Mesmo cálculo lendo os tamanhos da própria conta; None fora do layout atual
+pub fn product_fixed_fields_offset_in(data: &[u8]) -> Option<usize> {
+    if data.get(..DISCRIMINATOR_LEN)? != PRODUCT_DISCRIMINATOR {
+        return None;
+    }
+    let read_len = |offset: usize| data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize);
+    let name_len = read_len(PRODUCT_NAME_LEN_OFFSET)?;
+    let description_len = read_len(PRODUCT_NAME_OFFSET + name_len)?;
+    Some(product_fixed_fields_offset(name_len, description_len))
+}
+
+// PurchaseHistory: [discriminador 8][corpo Borsh de tamanho fixo]
+pub const PURCHASE_HISTORY_PRODUCT_ID_OFFSET: usize = 8;
+pub const PURCHASE_HISTORY_QUANTITY_OFFSET: usize = 16;
+pub const PURCHASE_HISTORY_TOTAL_PRICE_OFFSET: usize = 24;
+pub const PURCHASE_HISTORY_BUYER_OFFSET: usize = 32;
+pub const PURCHASE_HISTORY_TIMESTAMP_OFFSET: usize = 64;
+pub const PURCHASE_HISTORY_STATUS_OFFSET: usize = 72;
+pub const PURCHASE_HISTORY_FEE_OFFSET: usize = 73;
+pub const PURCHASE_HISTORY_REFUNDED_QUANTITY_OFFSET: usize = 81;
+pub const PURCHASE_HISTORY_PAYMENT_MODE_OFFSET: usize = 89;
+pub const PURCHASE_HISTORY_RENT_PAYER_OFFSET: usize = 90;
+pub const PURCHASE_HISTORY_TIP_OFFSET: usize = 122;
+pub const PURCHASE_HISTORY_DONATION_OFFSET: usize = 130;
+pub const PURCHASE_HISTORY_UNIT_PRICE_OFFSET: usize = 138;
+pub const PURCHASE_HISTORY_NAME_HASH_OFFSET: usize = 146;
+pub const PURCHASE_HISTORY_HAS_VARIANT_OFFSET: usize = 178;
+pub const PURCHASE_HISTORY_VARIANT_ID_OFFSET: usize = 179;
+
+// Filtros de getProgramAccounts montados com as constantes acima. Filtram só pelo discriminador, sem
+// DataSize, para pegar também versões discriminadas mais curtas; contas antigas sem discriminador ficam de fora
+#[cfg(all(feature = "rpc-filters", not(target_os = "solana")))]
+pub mod filters {
+    use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    use crate::state::{CAKE_STATE_DISCRIMINATOR, PURCHASE_HISTORY_DISCRIMINATOR};
+
+    pub fn memcmp(offset: usize, bytes: &[u8]) -> RpcFilterType {
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, bytes.to_vec()))
+    }
+
+    pub fn store_filters() -> Vec<RpcFilterType> {
+        vec![memcmp(DISCRIMINATOR_OFFSET, &CAKE_STATE_DISCRIMINATOR)]
+    }
+
+    // Lojas de um owner (carteira ou OwnerSet)
+    pub fn stores_by_owner(owner: &Pubkey) -> Vec<RpcFilterType> {
+        let mut filters = store_filters();
+        filters.push(memcmp(CAKE_STATE_OWNER_OFFSET, owner.as_ref()));
+        filters
+    }
+
+    // Produtos de todas as lojas do programa; a loja está só no endereço da PDA
+    pub fn product_filters() -> Vec<RpcFilterType> {
+        vec![memcmp(DISCRIMINATOR_OFFSET, &PRODUCT_DISCRIMINATOR)]
+    }
+
+    pub fn products_by_id(product_id: u64) -> Vec<RpcFilterType> {
+        let mut filters = product_filters();
+        filters.push(memcmp(PRODUCT_ID_OFFSET, &product_id.to_le_bytes()));
+        filters
+    }
+
+    pub fn purchase_history_filters() -> Vec<RpcFilterType> {
+        vec![memcmp(DISCRIMINATOR_OFFSET, &PURCHASE_HISTORY_DISCRIMINATOR)]
+    }
+
+    pub fn purchase_histories_by_buyer(buyer: &Pubkey) -> Vec<RpcFilterType> {
+        let mut filters = purchase_history_filters();
+        filters.push(memcmp(PURCHASE_HISTORY_BUYER_OFFSET, buyer.as_ref()));
+        filters
+    }
+
+    pub fn purchase_histories_by_product(product_id: u64) -> Vec<RpcFilterType> {
+        let mut filters = purchase_history_filters();
+        filters.push(memcmp(PURCHASE_HISTORY_PRODUCT_ID_OFFSET, &product_id.to_le_bytes()));
+        filters
+    }
+
+    // status é um dos PURCHASE_STATUS_*; por exemplo, as compras ainda em escrow
+    pub fn purchase_histories_by_status(status: u8) -> Vec<RpcFilterType> {
+        let mut filters = purchase_history_filters();
+        filters.push(memcmp(PURCHASE_HISTORY_STATUS_OFFSET, &[status]));
+        filters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+    use super::*;
+    use crate::state::{
+        CakeState, PriceTier, Product, ProductFixedFields, PurchaseHistory, CAKE_STATE_DISCRIMINATOR, PURCHASE_HISTORY_DISCRIMINATOR,
+    };
+
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_cake_state_offsets() {
+        let state = CakeState {
+            owner: Pubkey::new_from_array([1; 32]),
+            product_counter: 2,
+            history_counter: 3,
+            max_price_change_bps: 4,
+            paused: true,
+            order_counter: 5,
+            category_counter: 6,
+            fee_bps: 7,
+            fee_destination: Pubkey::new_from_array([8; 32]),
+            escrow_timeout: 9,
+            commission_bps: 10,
+            commission_authority: Pubkey::new_from_array([11; 32]),
+            commission_account: Pubkey::new_from_array([12; 32]),
+            is_initialized: true,
+        };
+        let mut data = [0u8; CakeState::LEN];
+        CakeState::pack(state, &mut data).unwrap();
+
+        assert_eq!(data[DISCRIMINATOR_OFFSET..DISCRIMINATOR_LEN], CAKE_STATE_DISCRIMINATOR);
+        assert_eq!(data[CAKE_STATE_OWNER_OFFSET..CAKE_STATE_OWNER_OFFSET + 32], [1; 32]);
+        assert_eq!(read_u64(&data, CAKE_STATE_PRODUCT_COUNTER_OFFSET), 2);
+        assert_eq!(read_u64(&data, CAKE_STATE_HISTORY_COUNTER_OFFSET), 3);
+        assert_eq!(data[CAKE_STATE_MAX_PRICE_CHANGE_BPS_OFFSET..CAKE_STATE_MAX_PRICE_CHANGE_BPS_OFFSET + 2], 4u16.to_le_bytes());
+        assert_eq!(data[CAKE_STATE_PAUSED_OFFSET], 1);
+        assert_eq!(read_u64(&data, CAKE_STATE_ORDER_COUNTER_OFFSET), 5);
+        assert_eq!(read_u64(&data, CAKE_STATE_CATEGORY_COUNTER_OFFSET), 6);
+        assert_eq!(data[CAKE_STATE_FEE_BPS_OFFSET..CAKE_STATE_FEE_BPS_OFFSET + 2], 7u16.to_le_bytes());
+        assert_eq!(data[CAKE_STATE_FEE_DESTINATION_OFFSET..CAKE_STATE_FEE_DESTINATION_OFFSET + 32], [8; 32]);
+        assert_eq!(read_u64(&data, CAKE_STATE_ESCROW_TIMEOUT_OFFSET), 9);
+        assert_eq!(data[CAKE_STATE_COMMISSION_BPS_OFFSET..CAKE_STATE_COMMISSION_BPS_OFFSET + 2], 10u16.to_le_bytes());
+        assert_eq!(data[CAKE_STATE_COMMISSION_AUTHORITY_OFFSET..CAKE_STATE_COMMISSION_AUTHORITY_OFFSET + 32], [11; 32]);
+        assert_eq!(data[CAKE_STATE_COMMISSION_ACCOUNT_OFFSET..CAKE_STATE_COMMISSION_ACCOUNT_OFFSET + 32], [12; 32]);
+        assert_eq!(data[CAKE_STATE_IS_INITIALIZED_OFFSET], 1);
+        assert_eq!(CAKE_STATE_IS_INITIALIZED_OFFSET + 1, CakeState::LEN);
+    }
+
+    #[test]
+    fn test_product_offsets() {
+        let product = Product {
+            id: 7,
+            name: "Bolo".to_string(),
+            description: "De cenoura".to_string(),
+            price: 1,
+            stock: 2,
+            active: true,
+            available: true,
+            max_per_buyer: 3,
+            category_id: 4,
+            metadata_uri: [5; 200],
+            tier_count: 1,
+            tiers: [PriceTier { min_quantity: 6, unit_price: 7 }; MAX_PRICE_TIERS],
+            sol_price: 8,
+            usd_price_cents: 9,
+            price_feed: Pubkey::new_from_array([10; 32]),
+            allowlist_required: true,
+            gate_mint: Pubkey::new_from_array([11; 32]),
+            units_sold: 12,
+            revenue: 13,
+            sol_revenue: 14,
+            available_from: 15,
+            available_until: 16,
+            price_decimals: 17,
+        };
+        let mut data = vec![0u8; product.account_len().unwrap()];
+        Product::pack_discriminated(&product, &mut data).unwrap();
+
+        assert_eq!(data[PRODUCT_VERSION_OFFSET], 1);
+        assert_eq!(read_u64(&data, PRODUCT_ID_OFFSET), 7);
+        assert_eq!(data[PRODUCT_NAME_OFFSET..PRODUCT_NAME_OFFSET + 4], *b"Bolo");
+        let start = product_fixed_fields_offset(4, 10);
+        assert_eq!(product_fixed_fields_offset_in(&data), Some(start));
+        assert_eq!(data.len(), start + ProductFixedFields::LEN);
+        assert_eq!(product_fixed_fields_offset_in(&data[..8]), None);
+
+        let fixed = &data[start..];
+        assert_eq!(read_u64(fixed, PRODUCT_PRICE_OFFSET), 1);
+        assert_eq!(read_u64(fixed, PRODUCT_STOCK_OFFSET), 2);
+        assert_eq!((fixed[PRODUCT_ACTIVE_OFFSET], fixed[PRODUCT_AVAILABLE_OFFSET]), (1, 1));
+        assert_eq!(read_u64(fixed, PRODUCT_MAX_PER_BUYER_OFFSET), 3);
+        assert_eq!(read_u64(fixed, PRODUCT_CATEGORY_ID_OFFSET), 4);
+        assert_eq!(fixed[PRODUCT_METADATA_URI_OFFSET..PRODUCT_METADATA_URI_OFFSET + 200], [5; 200]);
+        assert_eq!(fixed[PRODUCT_TIER_COUNT_OFFSET], 1);
+        assert_eq!((read_u64(fixed, PRODUCT_TIERS_OFFSET), read_u64(fixed, PRODUCT_TIERS_OFFSET + 8)), (6, 7));
+        assert_eq!(read_u64(fixed, PRODUCT_SOL_PRICE_OFFSET), 8);
+        assert_eq!(read_u64(fixed, PRODUCT_USD_PRICE_CENTS_OFFSET), 9);
+        assert_eq!(fixed[PRODUCT_PRICE_FEED_OFFSET..PRODUCT_PRICE_FEED_OFFSET + 32], [10; 32]);
+        assert_eq!(fixed[PRODUCT_ALLOWLIST_REQUIRED_OFFSET], 1);
+        assert_eq!(fixed[PRODUCT_GATE_MINT_OFFSET..PRODUCT_GATE_MINT_OFFSET + 32], [11; 32]);
+        assert_eq!(read_u64(fixed, PRODUCT_UNITS_SOLD_OFFSET), 12);
+        assert_eq!(read_u64(fixed, PRODUCT_REVENUE_OFFSET), 13);
+        assert_eq!(read_u64(fixed, PRODUCT_SOL_REVENUE_OFFSET), 14);
+        assert_eq!(read_u64(fixed, PRODUCT_AVAILABLE_FROM_OFFSET), 15);
+        assert_eq!(read_u64(fixed, PRODUCT_AVAILABLE_UNTIL_OFFSET), 16);
+        assert_eq!(fixed[PRODUCT_PRICE_DECIMALS_OFFSET], 17);
+        assert_eq!(PRODUCT_PRICE_DECIMALS_OFFSET + 1, ProductFixedFields::LEN);
+    }
+
+    #[test]
+    fn test_purchase_history_offsets() {
+        let history = PurchaseHistory {
+            product_id: 1,
+            quantity: 2,
+            total_price: 3,
+            buyer: Pubkey::new_from_array([4; 32]),
+            timestamp: 5,
+            status: 6,
+            fee: 7,
+            refunded_quantity: 8,
+            payment_mode: 9,
+            rent_payer: Pubkey::new_from_array([10; 32]),
+            tip: 11,
+            donation: 12,
+            unit_price: 13,
+            name_hash: [14; 32],
+            has_variant: true,
+            variant_id: 15,
+        };
+        let mut data = [0u8; PurchaseHistory::LEN];
+        PurchaseHistory::pack(history, &mut data).unwrap();
+
+        assert_eq!(data[DISCRIMINATOR_OFFSET..DISCRIMINATOR_LEN], PURCHASE_HISTORY_DISCRIMINATOR);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_PRODUCT_ID_OFFSET), 1);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_QUANTITY_OFFSET), 2);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_TOTAL_PRICE_OFFSET), 3);
+        assert_eq!(data[PURCHASE_HISTORY_BUYER_OFFSET..PURCHASE_HISTORY_BUYER_OFFSET + 32], [4; 32]);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_TIMESTAMP_OFFSET), 5);
+        assert_eq!(data[PURCHASE_HISTORY_STATUS_OFFSET], 6);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_FEE_OFFSET), 7);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_REFUNDED_QUANTITY_OFFSET), 8);
+        assert_eq!(data[PURCHASE_HISTORY_PAYMENT_MODE_OFFSET], 9);
+        assert_eq!(data[PURCHASE_HISTORY_RENT_PAYER_OFFSET..PURCHASE_HISTORY_RENT_PAYER_OFFSET + 32], [10; 32]);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_TIP_OFFSET), 11);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_DONATION_OFFSET), 12);
+        assert_eq!(read_u64(&data, PURCHASE_HISTORY_UNIT_PRICE_OFFSET), 13);
+        assert_eq!(data[PURCHASE_HISTORY_NAME_HASH_OFFSET..PURCHASE_HISTORY_NAME_HASH_OFFSET + 32], [14; 32]);
+        assert_eq!(data[PURCHASE_HISTORY_HAS_VARIANT_OFFSET], 1);
+        assert_eq!(data[PURCHASE_HISTORY_VARIANT_ID_OFFSET], 15);
+        assert_eq!(PURCHASE_HISTORY_VARIANT_ID_OFFSET + 1, PurchaseHistory::LEN);
+    }
+}
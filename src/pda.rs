@@ -0,0 +1,219 @@
+use solana_program::pubkey::Pubkey;
+
+// Endereços das contas do programa; as seeds aqui são as mesmas usadas no invoke_signed do processor.
+// As contas de uma loja levam o endereço do estado dela (store) logo após o prefixo; autoridades,
+// estatísticas globais, perfis de comprador e contas derivadas de outra PDA não dependem da loja
+
+pub fn get_pda(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, program_id)
+}
+
+pub fn find_store_address(store_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"store", &store_id.to_le_bytes()], program_id)
+}
+
+pub fn find_config_address(store: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"config", store.as_ref()], program_id)
+}
+
+pub fn find_global_stats_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"global_stats"], program_id)
+}
+
+pub fn find_product_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"product", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+pub fn find_variant_address(store: &Pubkey, product_id: u64, variant_id: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"variant", store.as_ref(), &product_id.to_le_bytes(), &[variant_id]], program_id)
+}
+
+pub fn find_localization_address(store: &Pubkey, product_id: u64, lang_code: &[u8; 2], program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"localization", store.as_ref(), &product_id.to_le_bytes(), lang_code], program_id)
+}
+
+pub fn find_buyer_history_counter_address(store: &Pubkey, buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"buyer_history", store.as_ref(), buyer.as_ref()], program_id)
+}
+
+pub fn find_history_head_address(store: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"history_head", store.as_ref()], program_id)
+}
+
+pub fn find_history_page_address(store: &Pubkey, page: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"history_page", store.as_ref(), &page.to_le_bytes()], program_id)
+}
+
+pub fn find_history_archive_address(store: &Pubkey, archive_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"archive", store.as_ref(), &archive_id.to_le_bytes()], program_id)
+}
+
+pub fn find_purchase_index_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"purchase_index", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+// Históricos atuais: sequence vai de 0 a BuyerHistoryCounter::count - 1, então a carteira enumera as
+// próprias compras sem conhecer o history_counter global
+pub fn find_buyer_history_address(store: &Pubkey, buyer: &Pubkey, sequence: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"history", store.as_ref(), buyer.as_ref(), &sequence.to_le_bytes()], program_id)
+}
+
+// Históricos criados antes do contador por comprador; product_id = CART_PRODUCT_ID no histórico de um checkout
+pub fn find_history_address(store: &Pubkey, buyer: &Pubkey, product_id: u64, history_index: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"history", store.as_ref(), buyer.as_ref(), &product_id.to_le_bytes(), &history_index.to_le_bytes()], program_id)
+}
+
+pub fn find_history_vault_address(history: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"history_vault", history.as_ref()], program_id)
+}
+
+pub fn find_escrow_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"escrow"], program_id)
+}
+
+pub fn find_operator_address(store: &Pubkey, operator: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"operator", store.as_ref(), operator.as_ref()], program_id)
+}
+
+pub fn find_owner_set_address(store: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"owner_set", store.as_ref()], program_id)
+}
+
+pub fn find_allowlist_address(store: &Pubkey, buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"allowlist", store.as_ref(), buyer.as_ref()], program_id)
+}
+
+pub fn find_blocklist_address(store: &Pubkey, buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"blocklist", store.as_ref(), buyer.as_ref()], program_id)
+}
+
+pub fn find_buyer_purchase_address(store: &Pubkey, product_id: u64, buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"buyer_purchase", store.as_ref(), &product_id.to_le_bytes(), buyer.as_ref()], program_id)
+}
+
+pub fn find_order_address(store: &Pubkey, order_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"order", store.as_ref(), &order_id.to_le_bytes()], program_id)
+}
+
+pub fn find_order_vault_address(store: &Pubkey, order_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"order_vault", store.as_ref(), &order_id.to_le_bytes()], program_id)
+}
+
+pub fn find_launch_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"launch", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+pub fn find_pre_order_address(store: &Pubkey, product_id: u64, buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"pre_order", store.as_ref(), &product_id.to_le_bytes(), buyer.as_ref()], program_id)
+}
+
+pub fn find_pre_order_vault_address(pre_order: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"pre_order_vault", pre_order.as_ref()], program_id)
+}
+
+pub fn find_buyer_profile_address(buyer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"buyer_profile", buyer.as_ref()], program_id)
+}
+
+pub fn find_flash_sale_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"flash_sale", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+pub fn find_happy_hour_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"happy_hour", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+pub fn find_bundle_address(store: &Pubkey, bundle_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"bundle", store.as_ref(), &bundle_id.to_le_bytes()], program_id)
+}
+
+// code_hash = sha256 do código
+pub fn find_coupon_address(store: &Pubkey, code_hash: &[u8; 32], program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"coupon", store.as_ref(), code_hash], program_id)
+}
+
+pub fn find_gift_card_address(store: &Pubkey, code_hash: &[u8; 32], program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"gift_card", store.as_ref(), code_hash], program_id)
+}
+
+pub fn find_referral_address(store: &Pubkey, code_hash: &[u8; 32], program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"referral", store.as_ref(), code_hash], program_id)
+}
+
+pub fn find_category_address(store: &Pubkey, category_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"category", store.as_ref(), &category_id.to_le_bytes()], program_id)
+}
+
+pub fn find_category_index_address(store: &Pubkey, category_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"category_index", store.as_ref(), &category_id.to_le_bytes()], program_id)
+}
+
+pub fn find_product_tags_address(store: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"product_tags", store.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+pub fn find_tag_index_address(store: &Pubkey, tag: &[u8; 16], program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"tag_index", store.as_ref(), tag], program_id)
+}
+
+pub fn find_treasury_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"treasury"], program_id)
+}
+
+pub fn find_treasury_vault_address(store: &Pubkey, mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"treasury_vault", store.as_ref(), mint.as_ref()], program_id)
+}
+
+pub fn find_subscription_address(store: &Pubkey, buyer: &Pubkey, product_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"subscription", store.as_ref(), buyer.as_ref(), &product_id.to_le_bytes()], program_id)
+}
+
+// Delegate de uma assinatura; uma PDA por assinatura, para que a aprovação não valha para outras lojas nem
+// outras assinaturas do comprador
+pub fn find_subscription_delegate_address(subscription: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"subscription_delegate", subscription.as_ref()], program_id)
+}
+
+// Delegate das compras delegadas; uma PDA por loja, para que a aprovação valha só nela
+pub fn find_purchase_delegate_address(store: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"purchase_delegate", store.as_ref()], program_id)
+}
+
+pub fn find_order_nonce_address(store: &Pubkey, buyer: &Pubkey, nonce: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"order_nonce", store.as_ref(), buyer.as_ref(), &nonce.to_le_bytes()], program_id)
+}
+
+pub fn find_receipt_mint_address(history: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"receipt_mint", history.as_ref()], program_id)
+}
+
+pub fn find_receipt_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"receipt_authority"], program_id)
+}
+
+pub fn find_note_address(history: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"note", history.as_ref()], program_id)
+}
+
+// Disputa do escrow de uma compra; no máximo uma por histórico
+pub fn find_dispute_address(history: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"dispute", history.as_ref()], program_id)
+}
+
+// Dona das contas de receita da loja. É uma PDA também para lojas legadas, cuja conta de estado não
+// tem seeds para assinar
+pub fn find_store_revenue_authority_address(store: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"store_revenue", store.as_ref()], program_id)
+}
+
+// Conta de receita da loja: ATA da autoridade de receita para o mint, criada pelo initialize/create_store
+// para o payment_mint. É o único destino aceito para a receita das vendas
+pub fn store_revenue_address(store: &Pubkey, mint: &Pubkey, token_program: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    let (authority, _) = find_store_revenue_authority_address(store, program_id);
+    spl_associated_token_account::get_associated_token_address_with_program_id(&authority, mint, token_program)
+}
+
+// Compromissos do valor de uma compra confidencial; um por histórico
+pub fn find_confidential_payment_address(history: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    get_pda(&[b"confidential_payment", history.as_ref()], program_id)
+}
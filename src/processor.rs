@@ -0,0 +1,6591 @@
+use solana_program::{
+    account_info::{next_account_info, next_account_infos, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+    program_pack::Pack,
+    program::{invoke_signed, set_return_data},
+    sysvar::clock::Clock,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+use solana_system_interface::instruction as system_instruction;
+use spl_token_2022::extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::extension::confidential_transfer::instruction::ConfidentialTransferInstruction;
+
+use crate::{
+    error::CakeError,
+    events::{CakeEvent, DisputeOpened, DisputeResolved, NoteAttached, PriceChanged, ProductAdded, Refunded, Sold},
+    instruction::{CakeInstruction, CartItem, NewProduct, Quote, SignedOrder},
+    pda::*,
+    state::*,
+    validation,
+};
+
+// Converte um valor entre escalas decimais, arredondando para cima ao reduzir casas
+pub fn convert_price(amount: u64, from_decimals: u8, to_decimals: u8) -> Result<u64, ProgramError> {
+    if from_decimals == to_decimals {
+        return Ok(amount);
+    }
+    let converted = if to_decimals > from_decimals {
+        let factor = 10u128.checked_pow((to_decimals - from_decimals) as u32).ok_or(CakeError::ArithmeticOverflow)?;
+        (amount as u128).checked_mul(factor).ok_or(CakeError::ArithmeticOverflow)?
+    } else {
+        let factor = 10u128.checked_pow((from_decimals - to_decimals) as u32).ok_or(CakeError::ArithmeticOverflow)?;
+        (amount as u128).div_ceil(factor)
+    };
+    u64::try_from(converted).map_err(|_| CakeError::ArithmeticOverflow.into())
+}
+
+// Valor inteiro com `decimals` casas formatado para exibição: 1_250_000 com 6 casas vira "1.250000"
+pub fn to_ui_amount(amount: u64, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", amount, width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+// Inverso de to_ui_amount: "1.25" com 6 casas vira 1_250_000; mais casas do que `decimals` é erro
+pub fn from_ui_amount(value: &str, decimals: u8) -> Result<u64, ProgramError> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) || fraction.len() > decimals as usize {
+        return Err(CakeError::InvalidUiAmount.into());
+    }
+    format!("{}{:0<width$}", integer, fraction, width = decimals as usize).parse().map_err(|_| CakeError::ArithmeticOverflow.into())
+}
+
+// Conta de preço Pyth (v2): magic, tipo da conta, expoente, timestamp e preço agregado
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 240;
+
+pub const ORACLE_MAX_AGE_SECS: i64 = 60;
+pub const ORACLE_MAX_CONFIDENCE_BPS: u64 = 200;
+
+// Lê o preço agregado (price, expo) recusando valores desatualizados ou com confiança larga
+fn read_oracle_price(price_feed: &AccountInfo, now: i64) -> Result<(u64, i32), ProgramError> {
+    let data = price_feed.data.borrow();
+    if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN
+        || u32::from_le_bytes(data[0..4].try_into().unwrap()) != PYTH_MAGIC
+        || u32::from_le_bytes(data[8..12].try_into().unwrap()) != PYTH_ACCOUNT_TYPE_PRICE
+    {
+        return Err(CakeError::InvalidOracleAccount.into());
+    }
+
+    let expo = i32::from_le_bytes(data[20..24].try_into().unwrap());
+    let publish_time = i64::from_le_bytes(data[96..104].try_into().unwrap());
+    let price = i64::from_le_bytes(data[208..216].try_into().unwrap());
+    let conf = u64::from_le_bytes(data[216..224].try_into().unwrap());
+    let status = u32::from_le_bytes(data[224..228].try_into().unwrap());
+
+    if status != PYTH_STATUS_TRADING || now.saturating_sub(publish_time) > ORACLE_MAX_AGE_SECS {
+        return Err(CakeError::StaleOraclePrice.into());
+    }
+    if price <= 0 {
+        return Err(CakeError::InvalidOracleAccount.into());
+    }
+    let price = price as u64;
+    if (conf as u128) * 10_000 > (price as u128) * ORACLE_MAX_CONFIDENCE_BPS as u128 {
+        return Err(CakeError::OracleConfidenceTooWide.into());
+    }
+    Ok((price, expo))
+}
+
+// Converte centavos de dólar em unidades de um ativo com `decimals` casas cotado a price * 10^expo USD
+pub fn usd_cents_to_units(usd_cents: u64, price: u64, expo: i32, decimals: u8) -> Result<u64, ProgramError> {
+    // units = usd_cents * 10^(decimals - 2) / (price * 10^expo)
+    let exponent = decimals as i32 - 2 - expo;
+    let mut numerator = usd_cents as u128;
+    let mut denominator = price as u128;
+    let scale = 10u128.checked_pow(exponent.unsigned_abs()).ok_or(CakeError::ArithmeticOverflow)?;
+    if exponent >= 0 {
+        numerator = numerator.checked_mul(scale).ok_or(CakeError::ArithmeticOverflow)?;
+    } else {
+        denominator = denominator.checked_mul(scale).ok_or(CakeError::ArithmeticOverflow)?;
+    }
+    u64::try_from(numerator.div_ceil(denominator)).map_err(|_| CakeError::ArithmeticOverflow.into())
+}
+
+fn load_config(config_account: &AccountInfo, store: &Pubkey, program_id: &Pubkey) -> Result<Config, ProgramError> {
+    let (expected_config_account, _) = find_config_address(store, program_id);
+    if *config_account.key != expected_config_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    if config_account.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    Config::unpack_versioned(&config_account.data.borrow())
+}
+
+// Com o bump calculado off-chain a verificação custa um único create_program_address. Só serve para
+// contas que já existem e pertencem ao programa, porque elas sempre são criadas no bump canônico
+fn check_existing_pda(account: &AccountInfo, seeds: &[&[u8]], bump: Option<u8>, program_id: &Pubkey) -> ProgramResult {
+    let expected = match bump {
+        Some(bump) => {
+            if account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            let bump_seed = [bump];
+            let mut seeds_with_bump = seeds.to_vec();
+            seeds_with_bump.push(&bump_seed);
+            Pubkey::create_program_address(&seeds_with_bump, program_id).map_err(|_| CakeError::WrongPda)?
+        }
+        None => get_pda(seeds, program_id).0,
+    };
+    if *account.key != expected {
+        return Err(CakeError::WrongPda.into());
+    }
+    Ok(())
+}
+
+// Quem paga o rent das contas criadas precisa assinar a instrução
+fn check_payer(payer: &AccountInfo) -> ProgramResult {
+    if !payer.is_signer {
+        return Err(CakeError::PayerSignatureRequired.into());
+    }
+    Ok(())
+}
+
+// A PDA que vai ser criada ainda é do system program e não tem dados; sem isso o create_account
+// falharia dentro da CPI com um erro genérico
+fn check_uninitialized(account: &AccountInfo) -> ProgramResult {
+    if !account.data_is_empty() || *account.owner != solana_system_interface::program::id() {
+        return Err(CakeError::AccountAlreadyInitialized.into());
+    }
+    Ok(())
+}
+
+// Operações administrativas: o owner assina ou, quando o owner da loja é o OwnerSet dela, pelo menos
+// threshold membros assinam a instrução (em qualquer posição, normalmente como contas extras ao final)
+fn check_owner(cake_state: &CakeState, store: &Pubkey, owner: &AccountInfo, accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    if cake_state.owner != *owner.key {
+        return Err(CakeError::Unauthorized.into());
+    }
+    if owner.is_signer {
+        return Ok(());
+    }
+
+    // Fora o conjunto de owners, que aprova pelos membros, o owner precisa assinar
+    let (owner_set_account, _) = find_owner_set_address(store, program_id);
+    if *owner.key != owner_set_account || owner.owner != program_id {
+        return Err(CakeError::OwnerSignatureRequired.into());
+    }
+    let owner_set = OwnerSet::unpack(&owner.data.borrow())?;
+    let signers: Vec<Pubkey> = accounts.iter().filter(|account| account.is_signer).map(|account| *account.key).collect();
+    if !owner_set.is_approved(&signers) {
+        return Err(CakeError::NotEnoughApprovals.into());
+    }
+    Ok(())
+}
+
+// Operações de estoque aceitam o owner ou um operador com PDA de papel válido, assinando a instrução
+fn check_stock_authority<'a>(
+    cake_state: &CakeState,
+    store: &Pubkey,
+    authority: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(CakeError::AuthoritySignatureRequired.into());
+    }
+    if cake_state.owner == *authority.key {
+        return Ok(());
+    }
+
+    let operator_account = next_account_info(account_iter)?;
+    let (expected_operator_account, _) = find_operator_address(store, authority.key, program_id);
+    if *operator_account.key != expected_operator_account || operator_account.owner != program_id {
+        return Err(CakeError::Unauthorized.into());
+    }
+
+    let role = OperatorRole::unpack(&operator_account.data.borrow()).map_err(|_| CakeError::Unauthorized)?;
+    if role.operator != *authority.key {
+        return Err(CakeError::Unauthorized.into());
+    }
+    Ok(())
+}
+
+// Produtos com max_per_buyer > 0 exigem a PDA de contador (produto, comprador) como próxima conta
+#[allow(clippy::too_many_arguments)]
+fn enforce_buyer_limit<'a>(
+    store: &Pubkey,
+    product: &Product,
+    amount: u64,
+    buyer: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if product.max_per_buyer == 0 {
+        return Ok(());
+    }
+
+    let counter_account = next_account_info(account_iter)?;
+    let (expected_counter_account, bump) = find_buyer_purchase_address(store, product.id, buyer.key, program_id);
+
+    if *counter_account.key != expected_counter_account {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    let mut counter = if counter_account.data_is_empty() {
+        let rent = Rent::get()?;
+        let create_counter_account_ix = system_instruction::create_account(
+            payer.key,
+            counter_account.key,
+            rent.minimum_balance(BuyerPurchaseCounter::LEN),
+            BuyerPurchaseCounter::LEN as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_counter_account_ix,
+            &[payer.clone(), counter_account.clone(), system_program.clone()],
+            &[&[b"buyer_purchase", store.as_ref(), &product.id.to_le_bytes(), buyer.key.as_ref(), &[bump]]],
+        )?;
+
+        BuyerPurchaseCounter { buyer: *buyer.key, product_id: product.id, quantity: 0 }
+    } else {
+        if counter_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+        BuyerPurchaseCounter::unpack(&counter_account.data.borrow())?
+    };
+
+    counter.quantity = counter.quantity.checked_add(amount).ok_or(CakeError::ArithmeticOverflow)?;
+    if counter.quantity > product.max_per_buyer {
+        return Err(CakeError::PurchaseLimitExceeded.into());
+    }
+    BuyerPurchaseCounter::pack(counter, &mut counter_account.data.borrow_mut())?;
+    Ok(())
+}
+
+// A PDA ["blocklist", store, buyer] é sempre informada nas compras e precisa estar vazia
+fn check_not_blocked(store: &Pubkey, buyer: &Pubkey, blocklist_account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    let (expected_blocklist_account, _) = find_blocklist_address(store, buyer, program_id);
+    if *blocklist_account.key != expected_blocklist_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    if blocklist_account.owner == program_id && !blocklist_account.data_is_empty() {
+        return Err(CakeError::BuyerBlocked.into());
+    }
+    Ok(())
+}
+
+// Produtos com allowlist_required exigem a entrada do comprador na allowlist e produtos com gate_mint uma
+// conta de token do comprador com saldo desse mint; as contas vêm em account_iter nessa ordem, só quando exigidas
+fn check_product_gates<'a>(
+    store: &Pubkey,
+    buyer: &Pubkey,
+    product: &Product,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if product.allowlist_required {
+        let allowlist_account = next_account_info(account_iter)?;
+        let (expected_allowlist_account, _) = find_allowlist_address(store, buyer, program_id);
+        if *allowlist_account.key != expected_allowlist_account || allowlist_account.owner != program_id {
+            return Err(CakeError::BuyerNotAllowlisted.into());
+        }
+        if BuyerListEntry::unpack(&allowlist_account.data.borrow())?.buyer != *buyer {
+            return Err(CakeError::BuyerNotAllowlisted.into());
+        }
+    }
+
+    if product.gate_mint != Pubkey::default() {
+        let gate_token = next_account_info(account_iter)?;
+        let gate_token_data = unpack_token_account(gate_token)?;
+        if gate_token_data.owner != *buyer || gate_token_data.mint != product.gate_mint || gate_token_data.amount == 0 {
+            return Err(CakeError::GateTokenRequired.into());
+        }
+    }
+    Ok(())
+}
+
+// Blocklist, allowlist e gate_mint de uma compra de um produto: a PDA da blocklist vem em account_iter,
+// seguida das contas de check_product_gates
+fn check_buyer_gates<'a>(
+    store: &Pubkey,
+    buyer: &Pubkey,
+    product: &Product,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    check_not_blocked(store, buyer, next_account_info(account_iter)?, program_id)?;
+    check_product_gates(store, buyer, product, account_iter, program_id)
+}
+
+// A prova de validade aceita pelo Token-2022 traz o valor cifrado em metades baixa (16 bits) e alta; a prova
+// ZeroCiphertext, gerada pelo comprador com a chave da origem, mostra que esse valor menos o total é zero.
+// Devolve os compromissos de Pedersen das metades, o início de cada texto cifrado
+fn check_confidential_amount(
+    validity_proof: &AccountInfo,
+    amount_proof: &AccountInfo,
+    total_price: u64,
+) -> Result<([u8; 32], [u8; 32]), ProgramError> {
+    use spl_token_2022::solana_zk_sdk::{
+        encryption::pod::elgamal::PodElGamalCiphertext,
+        zk_elgamal_proof_program::{
+            self,
+            proof_data::{BatchedGroupedCiphertext3HandlesValidityProofContext, ProofType, ZeroCiphertextProofContext},
+            state::ProofContextState,
+        },
+    };
+    use bytemuck::Zeroable;
+    use spl_token_confidential_transfer_ciphertext_arithmetic as ciphertext_arithmetic;
+
+    if *amount_proof.owner != zk_elgamal_proof_program::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let validity_data = validity_proof.data.borrow();
+    let validity = bytemuck::try_from_bytes::<ProofContextState<BatchedGroupedCiphertext3HandlesValidityProofContext>>(&validity_data)
+        .map_err(|_| CakeError::InvalidAccountData)?;
+    let amount_data = amount_proof.data.borrow();
+    let zero = bytemuck::try_from_bytes::<ProofContextState<ZeroCiphertextProofContext>>(&amount_data)
+        .map_err(|_| CakeError::InvalidAccountData)?;
+    if zero.proof_type != ProofType::ZeroCiphertext.into() {
+        return Err(CakeError::InvalidAccountData.into());
+    }
+
+    // Índice 0: texto cifrado para a chave da origem (primeira chave da prova)
+    let context = &validity.proof_context;
+    let source_lo = context.grouped_ciphertext_lo.try_extract_ciphertext(0).map_err(|_| CakeError::InvalidAccountData)?;
+    let source_hi = context.grouped_ciphertext_hi.try_extract_ciphertext(0).map_err(|_| CakeError::InvalidAccountData)?;
+    // O texto cifrado zerado é o ponto identidade, então a soma dá o valor inteiro
+    let remainder = ciphertext_arithmetic::add_with_lo_hi(&PodElGamalCiphertext::zeroed(), &source_lo, &source_hi)
+        .and_then(|amount| ciphertext_arithmetic::subtract_from(&amount, total_price))
+        .ok_or(CakeError::InvalidAccountData)?;
+    if zero.proof_context.pubkey != context.first_pubkey || zero.proof_context.ciphertext != remainder {
+        return Err(CakeError::ConfidentialAmountMismatch.into());
+    }
+
+    let lo = bytemuck::bytes_of(&context.grouped_ciphertext_lo);
+    let hi = bytemuck::bytes_of(&context.grouped_ciphertext_hi);
+    Ok((<[u8; 32]>::try_from(&lo[..32]).unwrap(), <[u8; 32]>::try_from(&hi[..32]).unwrap()))
+}
+
+// Cria a PDA ["product", store, product_id] com um produto novo, ativo e sem categoria
+fn create_product<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    product_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    product_id: u64,
+    new_product: NewProduct,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_product_account, bump) = find_product_address(store, product_id, program_id);
+
+    if *product_account.key != expected_product_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    check_uninitialized(product_account)?;
+
+    let product = Product {
+        id: product_id,
+        name: new_product.name,
+        description: new_product.description,
+        price: new_product.price,
+        stock: new_product.stock,
+        active: true,
+        available: true,
+        max_per_buyer: new_product.max_per_buyer,
+        category_id: NO_CATEGORY,
+        metadata_uri: [0u8; 200],
+        tier_count: 0,
+        tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+        sol_price: 0,
+        usd_price_cents: 0,
+        price_feed: Pubkey::default(),
+        allowlist_required: false,
+        gate_mint: Pubkey::default(),
+        units_sold: 0,
+        revenue: 0,
+        sol_revenue: 0,
+        available_from: 0,
+        available_until: 0,
+        price_decimals: 0,
+    };
+
+    // A conta é dimensionada pelo tamanho serializado do produto
+    let product_len = product.account_len()?;
+    let rent = Rent::get()?;
+
+    let create_product_account_ix = system_instruction::create_account(
+        payer.key,
+        product_account.key,
+        rent.minimum_balance(product_len),
+        product_len as u64,
+        program_id,
+    );
+
+    invoke_signed(
+        &create_product_account_ix,
+        &[payer.clone(), product_account.clone(), system_program.clone()],
+        &[&[b"product", store.as_ref(), &product_id.to_le_bytes(), &[bump]]],
+    )?;
+
+    Product::pack_discriminated(&product, &mut product_account.data.borrow_mut())?;
+
+    CakeEvent::ProductAdded(ProductAdded { product_id, price: product.price, stock: product.stock }).emit();
+    Ok(())
+}
+
+// Tags atuais do produto; a PDA ["product_tags", store, product_id] é criada vazia na primeira vez
+fn load_or_create_product_tags<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    product_tags_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    product_id: u64,
+    program_id: &Pubkey,
+) -> Result<Vec<[u8; TAG_LEN]>, ProgramError> {
+    let (expected_product_tags_account, bump) = find_product_tags_address(store, product_id, program_id);
+    if *product_tags_account.key != expected_product_tags_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    if !product_tags_account.data_is_empty() {
+        if product_tags_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+        return Ok(ProductTags::unpack(&product_tags_account.data.borrow())?.tags);
+    }
+
+    let create_product_tags_account_ix = system_instruction::create_account(
+        payer.key,
+        product_tags_account.key,
+        Rent::get()?.minimum_balance(ProductTags::LEN),
+        ProductTags::LEN as u64,
+        program_id,
+    );
+
+    invoke_signed(
+        &create_product_tags_account_ix,
+        &[payer.clone(), product_tags_account.clone(), system_program.clone()],
+        &[&[b"product_tags", store.as_ref(), &product_id.to_le_bytes(), &[bump]]],
+    )?;
+    Ok(Vec::new())
+}
+
+// Tira o produto dos índices das tags, que vêm em account_iter na mesma ordem
+fn remove_from_tag_indexes<'a>(
+    store: &Pubkey,
+    product_id: u64,
+    tags: &[[u8; TAG_LEN]],
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    for tag in tags {
+        let index_account = next_account_info(account_iter)?;
+        let (expected_index, _) = find_tag_index_address(store, tag, program_id);
+        if *index_account.key != expected_index {
+            return Err(CakeError::WrongPda.into());
+        }
+        if index_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+
+        let mut index = TagIndex::unpack(&index_account.data.borrow())?;
+        index.product_ids.retain(|id| *id != product_id);
+        TagIndex::pack(index, &mut index_account.data.borrow_mut())?;
+    }
+    Ok(())
+}
+
+// Acrescenta o produto aos índices das tags, que vêm em account_iter na mesma ordem; o primeiro produto com
+// a tag cria o índice
+fn add_to_tag_indexes<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    system_program: &AccountInfo<'a>,
+    product_id: u64,
+    tags: &[[u8; TAG_LEN]],
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    for tag in tags {
+        let index_account = next_account_info(account_iter)?;
+        let (expected_index, index_bump) = find_tag_index_address(store, tag, program_id);
+        if *index_account.key != expected_index {
+            return Err(CakeError::WrongPda.into());
+        }
+
+        let mut index = if index_account.data_is_empty() {
+            let create_index_account_ix = system_instruction::create_account(
+                payer.key,
+                index_account.key,
+                Rent::get()?.minimum_balance(TagIndex::LEN),
+                TagIndex::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_index_account_ix,
+                &[payer.clone(), index_account.clone(), system_program.clone()],
+                &[&[b"tag_index", store.as_ref(), tag, &[index_bump]]],
+            )?;
+            TagIndex { tag: *tag, product_ids: Vec::new() }
+        } else if index_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        } else {
+            TagIndex::unpack(&index_account.data.borrow())?
+        };
+
+        if index.product_ids.len() >= MAX_TAG_PRODUCTS {
+            return Err(CakeError::TagFull.into());
+        }
+        index.product_ids.push(product_id);
+        TagIndex::pack(index, &mut index_account.data.borrow_mut())?;
+    }
+    Ok(())
+}
+
+// Cria o histórico na PDA ["history", store, buyer, sequence], com a sequência tirada do contador do comprador
+// (criado na primeira compra), e retorna a sequência usada
+fn create_history_account<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    buyer: &Pubkey,
+    history_account: &AccountInfo<'a>,
+    counter_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (expected_counter_account, counter_bump) = find_buyer_history_counter_address(store, buyer, program_id);
+    if *counter_account.key != expected_counter_account {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    let rent = Rent::get()?;
+    let mut counter = if counter_account.data_is_empty() {
+        let create_counter_account_ix = system_instruction::create_account(
+            payer.key,
+            counter_account.key,
+            rent.minimum_balance(BuyerHistoryCounter::LEN),
+            BuyerHistoryCounter::LEN as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_counter_account_ix,
+            &[payer.clone(), counter_account.clone(), system_program.clone()],
+            &[&[b"buyer_history", store.as_ref(), buyer.as_ref(), &[counter_bump]]],
+        )?;
+
+        BuyerHistoryCounter { buyer: *buyer, count: 0 }
+    } else {
+        if counter_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+        BuyerHistoryCounter::unpack(&counter_account.data.borrow())?
+    };
+
+    let sequence = counter.count;
+    let (expected_history_account, bump) = find_buyer_history_address(store, buyer, sequence, program_id);
+    if *history_account.key != expected_history_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    check_uninitialized(history_account)?;
+
+    let create_history_account_ix = system_instruction::create_account(
+        payer.key,
+        history_account.key,
+        rent.minimum_balance(PurchaseHistory::LEN),
+        PurchaseHistory::LEN as u64,
+        program_id,
+    );
+
+    invoke_signed(
+        &create_history_account_ix,
+        &[payer.clone(), history_account.clone(), system_program.clone()],
+        &[&[b"history", store.as_ref(), buyer.as_ref(), &sequence.to_le_bytes(), &[bump]]],
+    )?;
+
+    counter.count = counter.count.checked_add(1).ok_or(CakeError::ArithmeticOverflow)?;
+    BuyerHistoryCounter::pack(counter, &mut counter_account.data.borrow_mut())?;
+    Ok(sequence)
+}
+
+// Acrescenta a compra ao índice do produto, criando-o na primeira venda; a conta cresce uma entrada e
+// só a entrada nova é gravada
+fn append_purchase_index<'a>(
+    index_account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    store: &Pubkey,
+    product_id: u64,
+    entry: &PurchaseIndexEntry,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_index_account, bump) = find_purchase_index_address(store, product_id, program_id);
+    if *index_account.key != expected_index_account {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    if index_account.data_is_empty() {
+        let create_index_account_ix = system_instruction::create_account(
+            payer.key,
+            index_account.key,
+            Rent::get()?.minimum_balance(ProductPurchaseIndex::HEADER_LEN),
+            ProductPurchaseIndex::HEADER_LEN as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_index_account_ix,
+            &[payer.clone(), index_account.clone(), system_program.clone()],
+            &[&[b"purchase_index", store.as_ref(), &product_id.to_le_bytes(), &[bump]]],
+        )?;
+        ProductPurchaseIndex::pack_header(product_id, 0, &mut index_account.data.borrow_mut());
+    } else if index_account.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+
+    grow_account(index_account, payer, system_program, index_account.data_len() + ProductPurchaseIndex::ENTRY_LEN)?;
+    ProductPurchaseIndex::append(&mut index_account.data.borrow_mut(), product_id, entry)
+}
+
+// Grava a venda na última página do histórico paginado, abrindo uma página nova (que aponta para a
+// anterior) quando ela está cheia. Retorna o número global da entrada: página * HISTORY_PAGE_CAPACITY + posição
+fn append_history_page<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    head_account: &AccountInfo<'a>,
+    page_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    entry: &PurchaseHistory,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (expected_head_account, head_bump) = find_history_head_address(store, program_id);
+    if *head_account.key != expected_head_account {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    let rent = Rent::get()?;
+    let mut head = if head_account.data_is_empty() {
+        let create_head_account_ix = system_instruction::create_account(
+            payer.key,
+            head_account.key,
+            rent.minimum_balance(HistoryPageHead::LEN),
+            HistoryPageHead::LEN as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_head_account_ix,
+            &[payer.clone(), head_account.clone(), system_program.clone()],
+            &[&[b"history_head", store.as_ref(), &[head_bump]]],
+        )?;
+
+        HistoryPageHead::default()
+    } else {
+        if head_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+        HistoryPageHead::unpack(&head_account.data.borrow())?
+    };
+
+    let page = head.write_page();
+    let (expected_page_account, page_bump) = find_history_page_address(store, page, program_id);
+    if *page_account.key != expected_page_account {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    if page == head.page_count {
+        let create_page_account_ix = system_instruction::create_account(
+            payer.key,
+            page_account.key,
+            rent.minimum_balance(HistoryPage::LEN),
+            HistoryPage::LEN as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_page_account_ix,
+            &[payer.clone(), page_account.clone(), system_program.clone()],
+            &[&[b"history_page", store.as_ref(), &page.to_le_bytes(), &[page_bump]]],
+        )?;
+
+        let previous = if page == 0 { Pubkey::default() } else { find_history_page_address(store, page - 1, program_id).0 };
+        HistoryPage::pack_header(page, &previous, &mut page_account.data.borrow_mut());
+        head.page_count += 1;
+        head.current_len = 0;
+    } else if page_account.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+
+    HistoryPage::append(&mut page_account.data.borrow_mut(), entry)?;
+    let entry_index = page * HISTORY_PAGE_CAPACITY as u64 + head.current_len as u64;
+    head.current_len += 1;
+    HistoryPageHead::pack(head, &mut head_account.data.borrow_mut())?;
+    Ok(entry_index)
+}
+
+// history_index é a sequência do comprador; históricos anteriores ao contador por comprador ainda
+// são aceitos no endereço antigo, derivado do history_counter global
+fn check_history_address(
+    history_account: &AccountInfo,
+    store: &Pubkey,
+    history_entry: &PurchaseHistory,
+    history_index: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if *history_account.key == find_buyer_history_address(store, &history_entry.buyer, history_index, program_id).0 {
+        return Ok(());
+    }
+    let (legacy_history_account, _) =
+        find_history_address(store, &history_entry.buyer, history_entry.product_id, history_index, program_id);
+    if *history_account.key != legacy_history_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    Ok(())
+}
+
+fn check_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if *token_program.key != spl_token::id() && *token_program.key != spl_token_2022::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    Ok(())
+}
+
+// Contas de programa e sysvar são conferidas já na leitura, para que nenhuma CPI use um programa trocado
+fn next_token_program<'a, 'b>(account_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>) -> Result<&'b AccountInfo<'a>, ProgramError> {
+    let token_program = next_account_info(account_iter)?;
+    check_token_program(token_program)?;
+    Ok(token_program)
+}
+
+fn next_system_program<'a, 'b>(account_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>) -> Result<&'b AccountInfo<'a>, ProgramError> {
+    let system_program = next_account_info(account_iter)?;
+    if *system_program.key != solana_system_interface::program::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    Ok(system_program)
+}
+
+fn next_clock<'a, 'b>(account_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>) -> Result<&'b AccountInfo<'a>, ProgramError> {
+    let clock = next_account_info(account_iter)?;
+    if *clock.key != solana_program::sysvar::clock::id() {
+        return Err(CakeError::InvalidSysvarAccount.into());
+    }
+    Ok(clock)
+}
+
+// Lê a conta de token de qualquer um dos programas; contas Token-2022 podem ter extensões após a base
+fn unpack_token_account(account: &AccountInfo) -> Result<spl_token_2022::state::Account, ProgramError> {
+    if *account.owner != spl_token::id() && *account.owner != spl_token_2022::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let data = account.data.borrow();
+    Ok(StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?.base)
+}
+
+// As casas decimais que a configuração atribui ao mint precisam ser as da conta do mint; uma
+// configuração errada cobraria o preço em outra escala
+fn check_mint_decimals(mint: &AccountInfo, configured_decimals: u8) -> ProgramResult {
+    if *mint.owner != spl_token::id() && *mint.owner != spl_token_2022::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let decimals = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data.borrow())?.base.decimals;
+    if decimals != configured_decimals {
+        return Err(CakeError::MintDecimalsMismatch.into());
+    }
+    Ok(())
+}
+
+// Confere, pela sysvar Instructions, que a instrução anterior é do programa ed25519 e verificou a assinatura
+// de `signer` sobre `message`. Os offsets precisam apontar para dados da própria instrução ed25519
+// (índice u16::MAX); com outro índice, a chave e a mensagem conferidas aqui poderiam não ser as verificadas
+fn check_ed25519_signature(instructions_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8]) -> ProgramResult {
+    if *instructions_sysvar.key != solana_program::sysvar::instructions::id() {
+        return Err(CakeError::InvalidSysvarAccount.into());
+    }
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    if current_index == 0 {
+        return Err(CakeError::InvalidOrderSignature.into());
+    }
+    let verify_ix = load_instruction_at_checked(current_index - 1, instructions_sysvar)?;
+    if verify_ix.program_id != solana_program::ed25519_program::id() {
+        return Err(CakeError::InvalidOrderSignature.into());
+    }
+
+    // [num_signatures u8][padding u8] e os offsets da assinatura: signature_offset, signature_instruction_index,
+    // public_key_offset, public_key_instruction_index, message_data_offset, message_data_size, message_instruction_index
+    let data = &verify_ix.data;
+    if data.len() < 16 || data[0] != 1 {
+        return Err(CakeError::InvalidOrderSignature.into());
+    }
+    let offset = |index: usize| u16::from_le_bytes([data[2 + index * 2], data[3 + index * 2]]) as usize;
+    if [offset(1), offset(3), offset(6)].iter().any(|instruction_index| *instruction_index != u16::MAX as usize) {
+        return Err(CakeError::InvalidOrderSignature.into());
+    }
+    let public_key = data.get(offset(2)..offset(2) + 32);
+    let signed_message = data.get(offset(4)..offset(4) + offset(5));
+    if public_key != Some(signer.as_ref()) || signed_message != Some(message) {
+        return Err(CakeError::InvalidOrderSignature.into());
+    }
+    Ok(())
+}
+
+// wSOL: o mint nativo de cada programa de token
+fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::id() || *mint == spl_token_2022::native_mint::id()
+}
+
+// Atualiza o saldo de uma conta wSOL com os lamports depositados nela por fora do programa de token
+fn sync_native<'a>(token_program: &AccountInfo<'a>, token_account: &AccountInfo<'a>) -> ProgramResult {
+    let sync_ix = spl_token_2022::instruction::sync_native(token_program.key, token_account.key)?;
+    solana_program::program::invoke(&sync_ix, &[token_account.clone(), token_program.clone()])
+}
+
+// Fecha a conta wSOL do dono; o saldo que sobrou volta junto com o rent, em lamports
+fn close_wsol_account<'a>(token_program: &AccountInfo<'a>, token_account: &AccountInfo<'a>, owner: &AccountInfo<'a>) -> ProgramResult {
+    let close_ix = spl_token_2022::instruction::close_account(token_program.key, token_account.key, owner.key, owner.key, &[])?;
+    solana_program::program::invoke(&close_ix, &[token_account.clone(), owner.clone(), owner.clone(), token_program.clone()])
+}
+
+// Instruções sem conta de mint recebem o mint como conta extra ao final quando o programa é Token-2022
+fn next_mint_if_token_2022<'a, 'b>(
+    token_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> Result<Option<&'b AccountInfo<'a>>, ProgramError> {
+    if *token_program.key == spl_token_2022::id() {
+        Ok(Some(next_account_info(account_iter)?))
+    } else {
+        Ok(None)
+    }
+}
+
+// Transferência com transfer_checked quando o mint está disponível (obrigatório no Token-2022)
+fn transfer_tokens<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    mint: Option<&AccountInfo<'a>>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    check_token_program(token_program)?;
+
+    match mint {
+        Some(mint) => {
+            let decimals = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data.borrow())?.base.decimals;
+            let transfer_ix = spl_token_2022::instruction::transfer_checked(
+                token_program.key,
+                source.key,
+                mint.key,
+                destination.key,
+                authority.key,
+                &[],
+                amount,
+                decimals,
+            )?;
+            invoke_signed(
+                &transfer_ix,
+                &[source.clone(), mint.clone(), destination.clone(), authority.clone(), token_program.clone()],
+                signer_seeds,
+            )
+        }
+        None => {
+            if *token_program.key == spl_token_2022::id() {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            let transfer_ix = spl_token::instruction::transfer(
+                token_program.key,
+                source.key,
+                destination.key,
+                authority.key,
+                &[],
+                amount,
+            )?;
+            invoke_signed(
+                &transfer_ix,
+                &[source.clone(), destination.clone(), authority.clone(), token_program.clone()],
+                signer_seeds,
+            )
+        }
+    }
+}
+
+// Taxa de transferência (TransferFeeConfig do Token-2022) que, somada a amount, faz o destino receber amount
+// inteiro; zero para mints sem a extensão, inclusive os do SPL Token
+pub fn transfer_fee_for_net_amount(mint_data: &[u8], epoch: u64, amount: u64) -> Result<u64, ProgramError> {
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)?;
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(transfer_fee_config) => {
+            transfer_fee_config.calculate_inverse_epoch_fee(epoch, amount).ok_or(CakeError::ArithmeticOverflow.into())
+        }
+        Err(_) => Ok(0),
+    }
+}
+
+// transfer_fee_for_net_amount no processador: o SPL Token não tem extensões e sai antes de ler o mint e o
+// Clock, para não encarecer o caminho comum do sell
+fn mint_transfer_fee(token_program: &AccountInfo, mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    if *token_program.key != spl_token_2022::id() || amount == 0 {
+        return Ok(0);
+    }
+    transfer_fee_for_net_amount(&mint.data.borrow(), Clock::get()?.epoch, amount)
+}
+
+// Pagamentos do comprador (e o depósito do vale-presente): a taxa de transferência do mint é somada ao valor
+// debitado, para que owner, cofres e contas de taxa recebam exatamente amount e o histórico bata com os
+// saldos. Saídas de cofres e reembolsos usam transfer_tokens e pagam a taxa do próprio valor
+fn pay_tokens<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let transfer_fee = mint_transfer_fee(token_program, mint, amount)?;
+    if transfer_fee == 0 {
+        return transfer_tokens(token_program, source, destination, authority, Some(mint), amount, signer_seeds);
+    }
+    check_token_program(token_program)?;
+
+    let decimals = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data.borrow())?.base.decimals;
+    let transfer_ix = spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+        token_program.key,
+        source.key,
+        mint.key,
+        destination.key,
+        authority.key,
+        &[],
+        amount.checked_add(transfer_fee).ok_or(CakeError::ArithmeticOverflow)?,
+        decimals,
+        transfer_fee,
+    )?;
+    invoke_signed(
+        &transfer_ix,
+        &[source.clone(), mint.clone(), destination.clone(), authority.clone(), token_program.clone()],
+        signer_seeds,
+    )?;
+
+    msg!("transfer_fee: amount={}", transfer_fee);
+    Ok(())
+}
+
+// Tamanho da conta de token para o mint, incluindo as extensões exigidas pelo Token-2022
+fn token_account_len(token_program: &AccountInfo, mint: &AccountInfo) -> Result<usize, ProgramError> {
+    if *token_program.key != spl_token_2022::id() {
+        return Ok(spl_token::state::Account::LEN);
+    }
+    let mint_data = mint.data.borrow();
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let required_extensions = ExtensionType::get_required_init_account_extensions(&mint_state.get_extension_types()?);
+    ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&required_extensions)
+}
+
+fn service_fee(cake_state: &CakeState, total_price: u64) -> u64 {
+    ((total_price as u128) * (cake_state.fee_bps as u128) / 10_000) as u64
+}
+
+// Taxa de serviço cobrada do comprador além do total; a conta de destino é a próxima conta
+fn collect_fee<'a>(
+    cake_state: &CakeState,
+    total_price: u64,
+    buyer: &AccountInfo<'a>,
+    buyer_token: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+) -> Result<u64, ProgramError> {
+    if cake_state.fee_bps == 0 {
+        return Ok(0);
+    }
+
+    let fee_account = next_account_info(account_iter)?;
+    if *fee_account.key != cake_state.fee_destination {
+        return Err(CakeError::UnexpectedAccount.into());
+    }
+    let fee_account_data = unpack_token_account(fee_account)?;
+    if fee_account_data.mint != *mint.key {
+        return Err(CakeError::WrongMint.into());
+    }
+
+    let fee = service_fee(cake_state, total_price);
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    pay_tokens(token_program, buyer_token, fee_account, buyer, mint, fee, &[])?;
+
+    msg!("fee: amount={}", fee);
+    Ok(fee)
+}
+
+// A receita de uma venda só vai para a conta de receita da loja do mint da conta; com o owner fora da
+// transação, o destino não fica a critério de quem monta a instrução
+fn is_revenue_destination(
+    token_account: &AccountInfo,
+    token_data: &spl_token_2022::state::Account,
+    store: &Pubkey,
+    program_id: &Pubkey,
+) -> bool {
+    *token_account.key == store_revenue_address(store, &token_data.mint, token_account.owner, program_id)
+}
+
+// Cria a conta de receita da loja para o payment_mint se ainda não existir; as contas vêm ao final do
+// initialize e do create_store
+fn create_store_revenue_account<'a>(
+    payer: &AccountInfo<'a>,
+    store: &Pubkey,
+    system_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let payment_mint = next_account_info(account_iter)?;
+    let revenue_token = next_account_info(account_iter)?;
+    let revenue_authority = next_account_info(account_iter)?;
+    let token_program = next_token_program(account_iter)?;
+    let associated_token_program = next_account_info(account_iter)?;
+    if *associated_token_program.key != spl_associated_token_account::id() {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    if *revenue_authority.key != find_store_revenue_authority_address(store, program_id).0
+        || *revenue_token.key != store_revenue_address(store, payment_mint.key, token_program.key, program_id)
+    {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    solana_program::program::invoke(
+        &spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            payer.key,
+            revenue_authority.key,
+            payment_mint.key,
+            token_program.key,
+        ),
+        &[
+            payer.clone(),
+            revenue_token.clone(),
+            revenue_authority.clone(),
+            payment_mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            associated_token_program.clone(),
+        ],
+    )?;
+
+    msg!("store_revenue: mint={}, account={}", payment_mint.key, revenue_token.key);
+    Ok(())
+}
+
+// Comissão da plataforma descontada do total; a conta de comissão é a próxima conta
+fn collect_commission<'a>(
+    cake_state: &CakeState,
+    total_price: u64,
+    buyer: &AccountInfo<'a>,
+    buyer_token: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+) -> Result<u64, ProgramError> {
+    if cake_state.commission_bps == 0 {
+        return Ok(0);
+    }
+
+    let commission_account = next_account_info(account_iter)?;
+    if *commission_account.key != cake_state.commission_account {
+        return Err(CakeError::UnexpectedAccount.into());
+    }
+
+    let commission = ((total_price as u128) * (cake_state.commission_bps as u128) / 10_000) as u64;
+    if commission > 0 {
+        pay_tokens(token_program, buyer_token, commission_account, buyer, mint, commission, &[])?;
+    }
+
+    msg!("commission: gross={}, commission={}, net={}", total_price, commission, total_price - commission);
+    Ok(commission)
+}
+
+// Recompensa de indicação descontada do líquido da venda: vai para o cofre da tesouraria do mint e fica
+// pendente na PDA do código até o referrer sacar; a PDA do código e o cofre são as próximas contas
+#[allow(clippy::too_many_arguments)]
+fn credit_referral<'a>(
+    config: &Config,
+    store: &Pubkey,
+    code: &[u8],
+    net_price: u64,
+    buyer: &AccountInfo<'a>,
+    buyer_token: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let referral_account = next_account_info(account_iter)?;
+    let treasury_vault = next_account_info(account_iter)?;
+
+    let code_hash = solana_program::hash::hash(code).to_bytes();
+    let (expected_referral_account, _) = find_referral_address(store, &code_hash, program_id);
+    if *referral_account.key != expected_referral_account || referral_account.owner != program_id {
+        return Err(CakeError::InvalidReferral.into());
+    }
+    let (expected_treasury_vault, _) = find_treasury_vault_address(store, mint.key, program_id);
+    if *treasury_vault.key != expected_treasury_vault {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    // O comprador não pode indicar a si mesmo
+    let mut referral = Referral::unpack(&referral_account.data.borrow())?;
+    if referral.code_hash != code_hash || referral.mint != *mint.key || referral.referrer == *buyer.key {
+        return Err(CakeError::InvalidReferral.into());
+    }
+
+    let reward = Referral::reward(net_price, config.referral_bps);
+    if reward > 0 {
+        pay_tokens(token_program, buyer_token, treasury_vault, buyer, mint, reward, &[])?;
+        referral.pending = referral.pending.checked_add(reward).ok_or(CakeError::ArithmeticOverflow)?;
+        referral.total_earned = referral.total_earned.checked_add(reward).ok_or(CakeError::ArithmeticOverflow)?;
+        Referral::pack(referral, &mut referral_account.data.borrow_mut())?;
+    }
+
+    msg!("referral: reward={}, pending={}", reward, referral.pending);
+    Ok(reward)
+}
+
+// Aumenta uma conta do programa para new_len, completando o rent com lamports do payer
+fn grow_account<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+    if required_lamports > 0 {
+        solana_program::program::invoke(
+            &system_instruction::transfer(payer.key, account.key, required_lamports),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    account.resize(new_len)
+}
+
+// Cria a conta de token do cofre (PDA) controlada por uma autoridade PDA do programa (escrow ou tesouraria)
+// Grava o recado do comprador na PDA ["note", history], paga pelo payer
+fn create_purchase_note<'a>(
+    payer: &AccountInfo<'a>,
+    history_account: &AccountInfo<'a>,
+    note_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    memo: &[u8],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_note_account, note_bump) = find_note_address(history_account.key, program_id);
+    if *note_account.key != expected_note_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    check_uninitialized(note_account)?;
+
+    let create_note_account_ix = system_instruction::create_account(
+        payer.key,
+        note_account.key,
+        Rent::get()?.minimum_balance(PurchaseNote::LEN),
+        PurchaseNote::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_note_account_ix,
+        &[payer.clone(), note_account.clone(), system_program.clone()],
+        &[&[b"note", history_account.key.as_ref(), &[note_bump]]],
+    )?;
+
+    PurchaseNote::pack(PurchaseNote { history: *history_account.key, memo: memo.to_vec() }, &mut note_account.data.borrow_mut())
+}
+
+fn create_program_vault<'a>(
+    funder: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    vault_authority: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    vault_seeds: &[&[u8]],
+) -> ProgramResult {
+    check_token_program(token_program)?;
+    let rent = Rent::get()?;
+    let vault_len = token_account_len(token_program, mint)?;
+
+    let create_vault_ix = system_instruction::create_account(
+        funder.key,
+        vault.key,
+        rent.minimum_balance(vault_len),
+        vault_len as u64,
+        token_program.key,
+    );
+
+    invoke_signed(
+        &create_vault_ix,
+        &[funder.clone(), vault.clone(), system_program.clone()],
+        &[vault_seeds],
+    )?;
+
+    let init_vault_ix = spl_token_2022::instruction::initialize_account3(
+        token_program.key,
+        vault.key,
+        mint.key,
+        vault_authority.key,
+    )?;
+
+    solana_program::program::invoke(
+        &init_vault_ix,
+        &[vault.clone(), mint.clone(), token_program.clone()],
+    )
+}
+
+// Transfere `amount` do cofre e fecha a conta, devolvendo o aluguel para rent_destination
+#[allow(clippy::too_many_arguments)]
+fn release_escrow_vault<'a>(
+    vault: &AccountInfo<'a>,
+    destination_token: &AccountInfo<'a>,
+    rent_destination: &AccountInfo<'a>,
+    escrow_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    mint: Option<&AccountInfo<'a>>,
+    amount: u64,
+    escrow_bump: u8,
+) -> ProgramResult {
+    let escrow_seeds: &[&[u8]] = &[b"escrow", &[escrow_bump]];
+
+    transfer_tokens(token_program, vault, destination_token, escrow_authority, mint, amount, &[escrow_seeds])?;
+
+    let close_vault_ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        vault.key,
+        rent_destination.key,
+        escrow_authority.key,
+        &[],
+    )?;
+
+    invoke_signed(
+        &close_vault_ix,
+        &[vault.clone(), rent_destination.clone(), escrow_authority.clone(), token_program.clone()],
+        &[escrow_seeds],
+    )
+}
+
+// Liquida uma compra em escrow ou em disputa: com refund o valor volta ao comprador e o estoque é reposto,
+// senão vai para a conta de receita da loja. O cofre é fechado com o rent para buyer; o histórico sai
+// com o novo status e quem chama o grava
+#[allow(clippy::too_many_arguments)]
+fn settle_escrow<'a>(
+    store: &Pubkey,
+    product_account: &AccountInfo<'a>,
+    history_account: &AccountInfo<'a>,
+    history_entry: &mut PurchaseHistory,
+    buyer: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    destination_token: &AccountInfo<'a>,
+    escrow_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    mint: Option<&AccountInfo<'a>>,
+    refund: bool,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if *buyer.key != history_entry.buyer {
+        return Err(CakeError::UnexpectedAccount.into());
+    }
+
+    let (expected_vault, _) = find_history_vault_address(history_account.key, program_id);
+    let (expected_escrow_authority, escrow_bump) = find_escrow_authority_address(program_id);
+    if *vault.key != expected_vault || *escrow_authority.key != expected_escrow_authority {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    let vault_data = unpack_token_account(vault)?;
+    let destination_token_data = unpack_token_account(destination_token)?;
+    let valid_destination = if refund {
+        destination_token_data.owner == history_entry.buyer
+    } else {
+        is_revenue_destination(destination_token, &destination_token_data, store, program_id)
+    };
+    if destination_token_data.mint != vault_data.mint {
+        return Err(CakeError::WrongMint.into());
+    }
+    if !valid_destination {
+        return Err(CakeError::InvalidDestination.into());
+    }
+
+    release_escrow_vault(vault, destination_token, buyer, escrow_authority, token_program, mint, history_entry.total_price, escrow_bump)?;
+
+    if refund {
+        if product_account.owner != program_id {
+            return Err(CakeError::IncorrectProgramId.into());
+        }
+        let (expected_product_account, _) = find_product_address(store, history_entry.product_id, program_id);
+        if *product_account.key != expected_product_account {
+            return Err(CakeError::WrongPda.into());
+        }
+
+        restore_stock(store, product_account, history_entry, history_entry.quantity, account_iter, program_id)?;
+
+        history_entry.refunded_quantity = history_entry.quantity;
+        history_entry.status = PURCHASE_STATUS_REFUNDED;
+    } else {
+        history_entry.status = PURCHASE_STATUS_COMPLETED;
+    }
+    Ok(())
+}
+
+// Devolve ao estoque as unidades reembolsadas. Vendas de variante repõem a variante, cuja PDA é a próxima
+// conta; as demais repõem o produto
+fn restore_stock<'a>(
+    store: &Pubkey,
+    product_account: &AccountInfo<'a>,
+    history_entry: &PurchaseHistory,
+    quantity: u64,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let Some(variant_id) = history_entry.variant() else {
+        let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+        product.stock = product.stock.checked_add(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+        return Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut());
+    };
+
+    let variant_account = next_account_info(account_iter)?;
+    let (expected_variant_account, _) = find_variant_address(store, history_entry.product_id, variant_id, program_id);
+    if *variant_account.key != expected_variant_account {
+        return Err(CakeError::WrongPda.into());
+    }
+    if variant_account.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let mut variant = Variant::unpack(&variant_account.data.borrow())?;
+    variant.stock = variant.stock.checked_add(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+    Variant::pack(variant, &mut variant_account.data.borrow_mut())
+}
+
+// Recibo NFT (edição mestre com supply 0) para o comprador; a autoridade é a PDA receipt_authority.
+// Contas: mint do recibo, ATA do comprador, receipt_authority, metadata, master edition,
+// token metadata program, token program (SPL clássico) e associated token program
+#[allow(clippy::too_many_arguments)]
+fn mint_purchase_receipt<'a>(
+    payer: &AccountInfo<'a>,
+    buyer: &AccountInfo<'a>,
+    history_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    history_entry: &PurchaseHistory,
+    product: &Product,
+    history_index: u64,
+    account_iter: &mut std::slice::Iter<'_, AccountInfo<'a>>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let receipt_mint = next_account_info(account_iter)?;
+    let receipt_token = next_account_info(account_iter)?;
+    let receipt_authority = next_account_info(account_iter)?;
+    let metadata = next_account_info(account_iter)?;
+    let master_edition = next_account_info(account_iter)?;
+    let token_metadata_program = next_account_info(account_iter)?;
+    let token_program = next_token_program(account_iter)?;
+    let associated_token_program = next_account_info(account_iter)?;
+
+    let (expected_receipt_mint, mint_bump) = find_receipt_mint_address(history_account.key, program_id);
+    let (expected_receipt_authority, authority_bump) = find_receipt_authority_address(program_id);
+    if *receipt_mint.key != expected_receipt_mint
+        || *receipt_authority.key != expected_receipt_authority
+        || *metadata.key != mpl_token_metadata::accounts::Metadata::find_pda(receipt_mint.key).0
+        || *master_edition.key != mpl_token_metadata::accounts::MasterEdition::find_pda(receipt_mint.key).0
+    {
+        return Err(CakeError::WrongPda.into());
+    }
+    if *token_metadata_program.key != mpl_token_metadata::ID
+        || *token_program.key != spl_token::id()
+        || *associated_token_program.key != spl_associated_token_account::id()
+    {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    let authority_seeds: &[&[u8]] = &[b"receipt_authority", &[authority_bump]];
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            receipt_mint.key,
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            token_program.key,
+        ),
+        &[payer.clone(), receipt_mint.clone(), system_program.clone()],
+        &[&[b"receipt_mint", history_account.key.as_ref(), &[mint_bump]]],
+    )?;
+
+    solana_program::program::invoke(
+        &spl_token::instruction::initialize_mint2(token_program.key, receipt_mint.key, receipt_authority.key, Some(receipt_authority.key), 0)?,
+        &[receipt_mint.clone(), token_program.clone()],
+    )?;
+
+    let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        payer.key,
+        buyer.key,
+        receipt_mint.key,
+        token_program.key,
+    );
+    if create_ata_ix.accounts[1].pubkey != *receipt_token.key {
+        return Err(CakeError::WrongPda.into());
+    }
+    solana_program::program::invoke(
+        &create_ata_ix,
+        &[
+            payer.clone(),
+            receipt_token.clone(),
+            buyer.clone(),
+            receipt_mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            associated_token_program.clone(),
+        ],
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::mint_to(token_program.key, receipt_mint.key, receipt_token.key, receipt_authority.key, &[], 1)?,
+        &[receipt_mint.clone(), receipt_token.clone(), receipt_authority.clone(), token_program.clone()],
+        &[authority_seeds],
+    )?;
+
+    // Os dados da compra vão na query string da URI do produto para o renderizador off-chain
+    let uri_len = product.metadata_uri.iter().position(|b| *b == 0).unwrap_or(product.metadata_uri.len());
+    let base_uri = std::str::from_utf8(&product.metadata_uri[..uri_len]).map_err(|_| CakeError::InvalidUtf8)?;
+    let uri = format!(
+        "{}?product_id={}&quantity={}&total_price={}&timestamp={}",
+        base_uri, history_entry.product_id, history_entry.quantity, history_entry.total_price, history_entry.timestamp
+    );
+
+    mpl_token_metadata::instructions::CreateMetadataAccountV3CpiBuilder::new(token_metadata_program)
+        .metadata(metadata)
+        .mint(receipt_mint)
+        .mint_authority(receipt_authority)
+        .payer(payer)
+        .update_authority(receipt_authority, true)
+        .system_program(system_program)
+        .data(mpl_token_metadata::types::DataV2 {
+            name: format!("CidaCake Recibo #{}", history_index),
+            symbol: "CAKE".to_string(),
+            uri,
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        })
+        .is_mutable(false)
+        .invoke_signed(&[authority_seeds])?;
+
+    mpl_token_metadata::instructions::CreateMasterEditionV3CpiBuilder::new(token_metadata_program)
+        .edition(master_edition)
+        .mint(receipt_mint)
+        .update_authority(receipt_authority)
+        .mint_authority(receipt_authority)
+        .payer(payer)
+        .metadata(metadata)
+        .token_program(token_program)
+        .system_program(system_program)
+        .max_supply(0)
+        .invoke_signed(&[authority_seeds])?;
+
+    msg!("receipt: mint={}, history_index={}", receipt_mint.key, history_index);
+    Ok(())
+}
+
+// Compra sem a assinatura do comprador, paga pelo delegate da loja (ExecuteDelegatedPurchase e
+// ExecuteSignedOrder). As 13 primeiras contas são comuns; depois vem o owner ou, com ordem assinada,
+// a PDA do nonce e a sysvar Instructions, e por fim as contas de check_buyer_gates
+fn process_delegated_purchase(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    product_id: u64,
+    amount: u64,
+    order: Option<&SignedOrder>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let cake_account = next_account_info(account_iter)?;
+    let product_account = next_account_info(account_iter)?;
+    let buyer = next_account_info(account_iter)?;
+    let history_account = next_account_info(account_iter)?;
+    let buyer_token = next_account_info(account_iter)?;
+    let owner_token = next_account_info(account_iter)?;
+    let delegate = next_account_info(account_iter)?;
+    let payment_mint = next_account_info(account_iter)?;
+    let config_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program = next_system_program(account_iter)?;
+    let token_program = next_token_program(account_iter)?;
+    let history_counter_account = next_account_info(account_iter)?;
+
+    if cake_account.owner != program_id || product_account.owner != program_id {
+        return Err(CakeError::IncorrectProgramId.into());
+    }
+    if !payer.is_signer {
+        return Err(CakeError::PayerSignatureRequired.into());
+    }
+
+    let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+    if cake_state.paused {
+        return Err(CakeError::ProgramPaused.into());
+    }
+    // O comprador não assina: sem ordem, a aprovação ao delegate da loja autoriza só o owner a cobrar;
+    // com ordem, vale a assinatura ed25519 do comprador, uma vez por nonce e até a expiração
+    let now = Clock::get()?.unix_timestamp;
+    let order_nonce_account = match order {
+        None => {
+            let owner = next_account_info(account_iter)?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            None
+        }
+        Some(order) => {
+            let order_nonce_account = next_account_info(account_iter)?;
+            let instructions_sysvar = next_account_info(account_iter)?;
+            check_ed25519_signature(instructions_sysvar, buyer.key, &order.message(cake_account.key))?;
+            if now > order.expiry {
+                return Err(CakeError::OrderExpired.into());
+            }
+            Some(order_nonce_account)
+        }
+    };
+
+    let config = load_config(config_account, cake_account.key, program_id)?;
+    let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+
+    let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+    let (expected_delegate, delegate_bump) = find_purchase_delegate_address(cake_account.key, program_id);
+    if *product_account.key != expected_product_account || *delegate.key != expected_delegate {
+        return Err(CakeError::WrongPda.into());
+    }
+
+    let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+    if !product.active {
+        return Err(CakeError::ProductInactive.into());
+    }
+    if !product.available {
+        return Err(CakeError::ProductUnavailable.into());
+    }
+    if !product.is_available_at(now) {
+        return Err(CakeError::OutsideAvailabilityWindow.into());
+    }
+    if amount > product.stock {
+        return Err(CakeError::InsufficientStock.into());
+    }
+    check_buyer_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+    let unit_price = product.unit_price_for(amount);
+    let total_price = amount.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+    product.record_sale(amount, total_price, PAYMENT_MODE_TOKEN)?;
+    check_mint_decimals(payment_mint, mint_decimals)?;
+    let price_decimals = product.price_decimals_or(config.price_decimals);
+    let total_price = convert_price(total_price, price_decimals, mint_decimals)?;
+    let unit_price = convert_price(unit_price, price_decimals, mint_decimals)?;
+
+    let buyer_token_data = unpack_token_account(buyer_token)?;
+    let owner_token_data = unpack_token_account(owner_token)?;
+    if buyer_token_data.owner != *buyer.key {
+        return Err(CakeError::WrongTokenAccountOwner.into());
+    }
+    if buyer_token_data.mint != *payment_mint.key || owner_token_data.mint != *payment_mint.key {
+        return Err(CakeError::WrongMint.into());
+    }
+    if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+        return Err(CakeError::InvalidDestination.into());
+    }
+    if order.is_some_and(|order| total_price > order.max_price) {
+        return Err(CakeError::OrderPriceExceeded.into());
+    }
+    // A aprovação precisa cobrir também a taxa de transferência do mint, debitada junto
+    let transfer_fee = mint_transfer_fee(token_program, payment_mint, total_price)?;
+    let charged = total_price.checked_add(transfer_fee).ok_or(CakeError::ArithmeticOverflow)?;
+    if buyer_token_data.delegate != Some(expected_delegate).into() || buyer_token_data.delegated_amount < charged {
+        return Err(CakeError::DelegateNotApproved.into());
+    }
+
+    pay_tokens(
+        token_program,
+        buyer_token,
+        owner_token,
+        delegate,
+        payment_mint,
+        total_price,
+        &[&[b"purchase_delegate", cake_account.key.as_ref(), &[delegate_bump]]],
+    )?;
+
+    // A PDA do nonce marca a ordem como executada; se já existe, é uma repetição
+    if let (Some(order), Some(order_nonce_account)) = (order, order_nonce_account) {
+        let (expected_nonce_account, nonce_bump) = find_order_nonce_address(cake_account.key, buyer.key, order.nonce, program_id);
+        if *order_nonce_account.key != expected_nonce_account {
+            return Err(CakeError::WrongPda.into());
+        }
+        if order_nonce_account.lamports() > 0 {
+            return Err(CakeError::OrderAlreadyExecuted.into());
+        }
+        invoke_signed(
+            &system_instruction::create_account(payer.key, order_nonce_account.key, Rent::get()?.minimum_balance(0), 0, program_id),
+            &[payer.clone(), order_nonce_account.clone(), system_program.clone()],
+            &[&[b"order_nonce", cake_account.key.as_ref(), buyer.key.as_ref(), &order.nonce.to_le_bytes(), &[nonce_bump]]],
+        )?;
+    }
+
+    product.stock -= amount;
+    Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+    let history_index = create_history_account(
+        payer,
+        cake_account.key,
+        buyer.key,
+        history_account,
+        history_counter_account,
+        system_program,
+        program_id,
+    )?;
+
+    let history_entry = PurchaseHistory {
+        product_id,
+        quantity: amount,
+        total_price,
+        buyer: *buyer.key,
+        timestamp: now,
+        status: PURCHASE_STATUS_COMPLETED,
+        fee: 0,
+        refunded_quantity: 0,
+        payment_mode: PAYMENT_MODE_TOKEN,
+        rent_payer: *payer.key,
+        tip: 0,
+        donation: 0,
+        unit_price,
+        name_hash: product.name_hash(),
+        has_variant: false,
+        variant_id: 0,
+    };
+    PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+    cake_state.history_counter += 1;
+    CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+    CakeEvent::Sold(Sold {
+        product_id,
+        buyer: *buyer.key,
+        quantity: amount,
+        total_price,
+        payment_mode: PAYMENT_MODE_TOKEN,
+        payment_mint: *payment_mint.key,
+        history_index,
+    })
+    .emit();
+    Ok(())
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    validation::check_instruction_data(instruction_data)?;
+    let instruction = CakeInstruction::unpack(instruction_data)?;
+    validation::validate_instruction(&instruction, instruction_data, accounts, program_id)?;
+    let account_iter = &mut accounts.iter();
+
+    match instruction {
+        CakeInstruction::Initialize => {
+            msg!("Instrução: initialize");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            if cake_account.data.borrow().len() != CakeState::LEN {
+                return Err(CakeError::InvalidAccountData.into());
+            }
+
+            // A conta é criada fora do programa, então o saldo dela é conferido aqui
+            if !Rent::get()?.is_exempt(cake_account.lamports(), CakeState::LEN) {
+                return Err(CakeError::NotRentExempt.into());
+            }
+
+            if !owner.is_signer {
+                return Err(CakeError::OwnerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            // Sem isso, chamar initialize de novo trocaria o owner de uma loja existente
+            if CakeState::unpack_unchecked(&cake_account.data.borrow()).is_ok_and(|cake_state| cake_state.is_initialized) {
+                return Err(CakeError::AlreadyInitialized.into());
+            }
+
+            // A conta ainda não tem discriminador, então o estado é montado do zero
+            let cake_state = CakeState {
+                owner: *owner.key,
+                product_counter: 0,
+                history_counter: 0,
+                max_price_change_bps: 0,
+                paused: false,
+                order_counter: 0,
+                category_counter: 0,
+                fee_bps: 0,
+                fee_destination: Pubkey::default(),
+                escrow_timeout: 0,
+                commission_bps: 0,
+                commission_authority: Pubkey::default(),
+                commission_account: Pubkey::default(),
+                is_initialized: true,
+            };
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            create_store_revenue_account(payer, cake_account.key, system_program, account_iter, program_id)?;
+        }
+        CakeInstruction::AddProduct { name, description, price, stock, max_per_buyer, tags } => {
+            msg!("Instrução: add_product");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            check_payer(payer)?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let product_id = cake_state.product_counter;
+            let new_product = NewProduct { name, description, price, stock, max_per_buyer };
+            create_product(payer, cake_account.key, product_account, system_program, product_id, new_product, program_id)?;
+
+            // Com tags, a PDA product_tags e os índices de cada tag vêm após o system program
+            if !tags.is_empty() {
+                let product_tags_account = next_account_info(account_iter)?;
+                load_or_create_product_tags(payer, cake_account.key, product_tags_account, system_program, product_id, program_id)?;
+                add_to_tag_indexes(payer, cake_account.key, system_program, product_id, &tags, account_iter, program_id)?;
+                ProductTags::pack(ProductTags { product_id, tags }, &mut product_tags_account.data.borrow_mut())?;
+            }
+
+            cake_state.product_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::AddProducts { products } => {
+            msg!("Instrução: add_products");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            check_payer(payer)?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            // Uma PDA por produto, na ordem do payload, com ids sequenciais a partir do product_counter
+            let first_product_id = cake_state.product_counter;
+            for product in products {
+                let product_account = next_account_info(account_iter)?;
+                let product_id = cake_state.product_counter;
+                create_product(payer, cake_account.key, product_account, system_program, product_id, product, program_id)?;
+                cake_state.product_counter += 1;
+            }
+
+            msg!("add_products: first_product_id={}, count={}", first_product_id, cake_state.product_counter - first_product_id);
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::GetQuote { product_id, amount } => {
+            msg!("Instrução: get_quote");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+            if product.stock < amount {
+                return Err(CakeError::InsufficientStock.into());
+            }
+
+            let unit_price = product.unit_price_for(amount);
+            let total_price = amount.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+
+            // [total_price u64][unit_price u64], lido pelo cliente na simulação
+            let mut quote = [0u8; 16];
+            quote[..8].copy_from_slice(&total_price.to_le_bytes());
+            quote[8..].copy_from_slice(&unit_price.to_le_bytes());
+            set_return_data(&quote);
+
+            msg!("get_quote: product_id={}, amount={}, unit_price={}, total_price={}", product_id, amount, unit_price, total_price);
+        }
+        CakeInstruction::UpdateStock { product_id, delta } => {
+            msg!("Instrução: update_stock");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.stock = if delta >= 0 {
+                product.stock.checked_add(delta.unsigned_abs()).ok_or(CakeError::ArithmeticOverflow)?
+            } else {
+                product.stock.checked_sub(delta.unsigned_abs()).ok_or(CakeError::InsufficientStock)?
+            };
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("update_stock: product_id={}, delta={}, stock={}", product_id, delta, product.stock);
+        }
+        CakeInstruction::Sell {
+            product_id,
+            amount,
+            coupon_code,
+            variant_id,
+            escrow,
+            payment_mode,
+            create_ata,
+            gift_code,
+            mint_receipt,
+            product_bump,
+            paged_history,
+            version,
+            memo,
+            referral_code,
+            tip,
+            round_up,
+            sync_native: sync_wsol,
+            close_wsol,
+            max_total_price,
+        } => {
+            // Sem formatação no log: product_id e amount já vão no evento Sold
+            msg!("Instrução: sell");
+            // Até a versão 2 a venda era co-assinada pelo owner ou por um operador; a partir da 3 o
+            // comprador compra sozinho e o pagamento só pode ir para a conta de receita da loja
+            let authority = if version <= SELL_CLOCKLESS_VERSION { Some(next_account_info(account_iter)?) } else { None };
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            // Clientes da versão 1 ainda enviam a sysvar Clock aqui; o timestamp vem sempre de Clock::get
+            if version == SELL_LEGACY_VERSION {
+                next_clock(account_iter)?;
+            }
+            let buyer_token = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let usdt_mint = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let history_counter_account = next_account_info(account_iter)?;
+            // No histórico paginado a venda não tem PDA própria e não entra no índice do produto
+            let purchase_index_account = if paged_history { None } else { Some(next_account_info(account_iter)?) };
+
+            // O comprador autoriza o débito; o token program também confere, mas o erro fica explícito aqui
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(usdt_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+            if let Some(authority) = authority {
+                check_stock_authority(&cake_state, cake_account.key, authority, account_iter, program_id)?;
+            }
+            check_existing_pda(
+                product_account,
+                &[b"product", cake_account.key.as_ref(), &product_id.to_le_bytes()],
+                product_bump,
+                program_id,
+            )?;
+
+            // O clock é lido uma vez para o oráculo, cupom, cartão-presente e o histórico
+            let timestamp = Clock::get()?.unix_timestamp;
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+            if !product.is_available_at(timestamp) {
+                return Err(CakeError::OutsideAvailabilityWindow.into());
+            }
+
+            // A PDA da blocklist do comprador é sempre informada e precisa estar vazia
+            check_not_blocked(cake_account.key, buyer.key, next_account_info(account_iter)?, program_id)?;
+
+            // A PDA de estatísticas globais também é sempre informada; é atualizada quando já foi criada
+            let global_stats_account = next_account_info(account_iter)?;
+            let (expected_global_stats_account, _) = find_global_stats_address(program_id);
+            if *global_stats_account.key != expected_global_stats_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // A PDA da promoção relâmpago do produto também é sempre informada; vale só quando existe
+            let flash_sale_account = next_account_info(account_iter)?;
+            let (expected_flash_sale_account, _) = find_flash_sale_address(cake_account.key, product_id, program_id);
+            if *flash_sale_account.key != expected_flash_sale_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            let flash_sale = if flash_sale_account.owner == program_id && !flash_sale_account.data_is_empty() {
+                Some(FlashSale::unpack(&flash_sale_account.data.borrow())?)
+            } else {
+                None
+            };
+
+            // A partir da versão 4 a PDA do happy hour vem em seguida, com a mesma regra
+            let happy_hour = if version > SELL_BUYER_ONLY_VERSION {
+                let happy_hour_account = next_account_info(account_iter)?;
+                let (expected_happy_hour_account, _) = find_happy_hour_address(cake_account.key, product_id, program_id);
+                if *happy_hour_account.key != expected_happy_hour_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if happy_hour_account.owner == program_id && !happy_hour_account.data_is_empty() {
+                    Some(HappyHour::unpack(&happy_hour_account.data.borrow())?)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            // O perfil do comprador também é sempre informado; o gasto é acumulado quando ele foi criado
+            let buyer_profile_account = next_account_info(account_iter)?;
+            let (expected_buyer_profile_account, _) = find_buyer_profile_address(buyer.key, program_id);
+            if *buyer_profile_account.key != expected_buyer_profile_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Entrada da allowlist e conta de token do gate_mint, quando o produto exige
+            check_product_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+            // Extensões opcionais após o amount, na ordem:
+            //   [code_len u8][code] (code_len = 0 sem cupom), [has_variant u8][variant_id u8],
+            //   [escrow u8], [payment_mode u8], [create_ata u8], [gift_code_len u8][gift_code],
+            //   [mint_receipt u8], [has_product_bump u8][product_bump u8], [paged_history u8], [version u8],
+            //   [memo_len u8][memo], [referral_code_len u8][referral_code], [tip u64], [round_up u8],
+            //   [sync_native u8], [close_wsol u8], [max_total_price u64]
+            // Contas extras após as fixas: operador (authority que não é o owner), PDA da blocklist do comprador,
+            // PDA de estatísticas globais, PDA da promoção relâmpago, perfil do comprador, entrada da allowlist
+            // (allowlist_required), conta de token do gate_mint, variante (ou feed de preço Pyth no modo SOL
+            // com preço em USD), cupom, contador por comprador,
+            // e então carteira do owner (modo SOL) ou, no modo token, carteira do owner e associated
+            // token program (create_ata), cartão-presente, cofre e autoridade da tesouraria (quando
+            // há gift_code) seguidos de cofre e autoridade de escrow (modo escrow)
+            // ou da conta de comissão (quando commission_bps > 0), da PDA do código de indicação e do cofre
+            // da tesouraria (referral_code) e das contas da divisão de receita (quando configurada), a conta
+            // de taxa (quando fee_bps > 0), a conta das gorjetas (tip > 0), a conta da caridade (round_up),
+            // as contas do recibo NFT (mint_receipt) e por fim a PDA do recado (memo)
+            let coupon_code = coupon_code.as_deref();
+            let gift_code = gift_code.as_deref();
+            let referral_code = referral_code.as_deref();
+            if payment_mode > PAYMENT_MODE_SOL
+                || (payment_mode == PAYMENT_MODE_SOL
+                    && (escrow
+                        || variant_id.is_some()
+                        || create_ata
+                        || gift_code.is_some()
+                        || referral_code.is_some()
+                        || tip > 0
+                        || round_up
+                        || sync_wsol
+                        || close_wsol))
+                || (escrow && (gift_code.is_some() || referral_code.is_some() || tip > 0 || round_up))
+                || (paged_history && (escrow || mint_receipt || memo.is_some() || tip > 0 || round_up))
+            {
+                return Err(CakeError::IncompatibleSellOptions.into());
+            }
+
+            let mut selected_variant = None;
+            let unit_price = if payment_mode == PAYMENT_MODE_SOL {
+                if amount > product.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                // Preço em USD tem precedência: converte pelo feed SOL/USD no momento da venda
+                if product.usd_price_cents != 0 {
+                    let price_feed = next_account_info(account_iter)?;
+                    if *price_feed.key != product.price_feed {
+                        return Err(CakeError::InvalidOracleAccount.into());
+                    }
+                    let (price, expo) = read_oracle_price(price_feed, timestamp)?;
+                    let lamports = usd_cents_to_units(product.usd_price_cents, price, expo, 9)?;
+                    msg!("oracle: price={}, expo={}, unit_lamports={}", price, expo, lamports);
+                    lamports
+                } else if product.sol_price == 0 {
+                    return Err(CakeError::InvalidPrice.into());
+                } else {
+                    product.sol_price
+                }
+            } else if let Some(variant_id) = variant_id {
+                let variant_account = next_account_info(account_iter)?;
+                let (expected_variant_account, _) = find_variant_address(cake_account.key, product_id, variant_id, program_id);
+                if *variant_account.key != expected_variant_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if variant_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+
+                let variant = Variant::unpack(&variant_account.data.borrow())?;
+                if amount > variant.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                let price = variant.price;
+                selected_variant = Some((variant_account, variant));
+                price
+            } else {
+                if amount > product.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                product.unit_price_for(amount)
+            };
+
+            let mut total_price = amount.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+            // Preço de tabela para o recibo, na unidade cobrada
+            let mut receipt_unit_price = unit_price;
+
+            // A promoção relâmpago e o happy hour vêm antes do cupom, que incide sobre o preço já descontado.
+            // Os dois não se somam: vale o maior desconto
+            let flash_sale_discount = flash_sale.map_or(0, |flash_sale| flash_sale.discount(total_price, timestamp));
+            let happy_hour_discount = happy_hour.map_or(0, |happy_hour| happy_hour.discount(total_price, timestamp));
+            if flash_sale_discount >= happy_hour_discount && flash_sale_discount > 0 {
+                total_price -= flash_sale_discount;
+                msg!("flash_sale: discount={}", flash_sale_discount);
+            } else if happy_hour_discount > 0 {
+                total_price -= happy_hour_discount;
+                msg!("happy_hour: discount={}", happy_hour_discount);
+            }
+
+            if let Some(code) = coupon_code {
+                let code_hash = solana_program::hash::hash(code).to_bytes();
+                let coupon_account = next_account_info(account_iter)?;
+
+                let (expected_coupon_account, _) = find_coupon_address(cake_account.key, &code_hash, program_id);
+                if *coupon_account.key != expected_coupon_account || coupon_account.owner != program_id {
+                    return Err(CakeError::InvalidCoupon.into());
+                }
+
+                let mut coupon = Coupon::unpack(&coupon_account.data.borrow())?;
+                if coupon.code_hash != code_hash {
+                    return Err(CakeError::InvalidCoupon.into());
+                }
+                if coupon.expires_at != 0 && timestamp > coupon.expires_at {
+                    return Err(CakeError::CouponExpired.into());
+                }
+                if coupon.max_uses != 0 && coupon.uses >= coupon.max_uses {
+                    return Err(CakeError::CouponExhausted.into());
+                }
+
+                // Cupons de valor fixo são denominados no token de pagamento
+                if payment_mode == PAYMENT_MODE_SOL && coupon.kind == COUPON_KIND_AMOUNT {
+                    return Err(CakeError::InvalidCoupon.into());
+                }
+
+                let discount = coupon.discount(total_price)?;
+                total_price -= discount;
+                coupon.uses = coupon.uses.checked_add(1).ok_or(CakeError::ArithmeticOverflow)?;
+                Coupon::pack(coupon, &mut coupon_account.data.borrow_mut())?;
+
+                msg!("coupon: discount={}, uses={}", discount, coupon.uses);
+            }
+
+            enforce_buyer_limit(cake_account.key, &product, amount, buyer, payer, system_program, account_iter, program_id)?;
+            product.record_sale(amount, total_price, payment_mode)?;
+            if buyer_profile_account.owner == program_id && !buyer_profile_account.data_is_empty() {
+                let mut buyer_profile = BuyerProfile::unpack(&buyer_profile_account.data.borrow())?;
+                buyer_profile.record_purchase(total_price, payment_mode)?;
+                BuyerProfile::pack(buyer_profile, &mut buyer_profile_account.data.borrow_mut())?;
+            }
+
+            // O preço pode ter mudado depois da cotação; o limite vale para o total na unidade cobrada
+            let check_max_total_price = |total_price: u64| -> ProgramResult {
+                if max_total_price != 0 && total_price > max_total_price {
+                    return Err(CakeError::PriceSlippageExceeded.into());
+                }
+                Ok(())
+            };
+
+            let (fee, donation) = if payment_mode == PAYMENT_MODE_SOL {
+                check_max_total_price(total_price)?;
+                let owner_wallet = next_account_info(account_iter)?;
+                if *owner_wallet.key != cake_state.owner {
+                    return Err(CakeError::UnexpectedAccount.into());
+                }
+
+                solana_program::program::invoke(
+                    &system_instruction::transfer(buyer.key, owner_wallet.key, total_price),
+                    &[buyer.clone(), owner_wallet.clone(), system_program.clone()],
+                )?;
+                (0, 0)
+            } else {
+                check_mint_decimals(usdt_mint, mint_decimals)?;
+                let price_decimals = product.price_decimals_or(config.price_decimals);
+                total_price = convert_price(total_price, price_decimals, mint_decimals)?;
+                receipt_unit_price = convert_price(unit_price, price_decimals, mint_decimals)?;
+                check_max_total_price(total_price)?;
+
+                // Cria as ATAs ausentes de comprador e owner (pagas pelo payer) antes da transferência
+                if create_ata {
+                    let owner_wallet = next_account_info(account_iter)?;
+                    let associated_token_program = next_account_info(account_iter)?;
+                    if *owner_wallet.key != cake_state.owner {
+                        return Err(CakeError::UnexpectedAccount.into());
+                    }
+                    if *associated_token_program.key != spl_associated_token_account::id() {
+                        return Err(CakeError::IncorrectProgramId.into());
+                    }
+
+                    for (token_account, wallet) in [(buyer_token, buyer), (owner_token, owner_wallet)] {
+                        let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                            payer.key,
+                            wallet.key,
+                            usdt_mint.key,
+                            token_program.key,
+                        );
+                        if create_ata_ix.accounts[1].pubkey != *token_account.key {
+                            return Err(CakeError::WrongPda.into());
+                        }
+
+                        solana_program::program::invoke(
+                            &create_ata_ix,
+                            &[
+                                payer.clone(),
+                                token_account.clone(),
+                                wallet.clone(),
+                                usdt_mint.clone(),
+                                system_program.clone(),
+                                token_program.clone(),
+                                associated_token_program.clone(),
+                            ],
+                        )?;
+                    }
+                }
+
+                // wSOL: lamports depositados na conta do comprador nesta transação só contam depois do sync_native
+                if (sync_wsol || close_wsol) && !is_native_mint(usdt_mint.key) {
+                    return Err(CakeError::WrongMint.into());
+                }
+                if sync_wsol {
+                    sync_native(token_program, buyer_token)?;
+                }
+
+                // A conta do comprador não é lida: toda saída dela usa transfer_checked, que já confere o mint
+                let owner_token_data = unpack_token_account(owner_token)?;
+                if owner_token_data.mint != *usdt_mint.key {
+                    return Err(CakeError::WrongMint.into());
+                }
+                if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+                    return Err(CakeError::InvalidDestination.into());
+                }
+
+                // O saldo do cartão-presente (já depositado na tesouraria) cobre o total primeiro
+                let mut cash_price = total_price;
+                if let Some(code) = gift_code {
+                    let gift_card_account = next_account_info(account_iter)?;
+                    let treasury_vault = next_account_info(account_iter)?;
+                    let treasury_authority = next_account_info(account_iter)?;
+
+                    let code_hash = solana_program::hash::hash(code).to_bytes();
+                    let (expected_gift_card_account, _) = find_gift_card_address(cake_account.key, &code_hash, program_id);
+                    if *gift_card_account.key != expected_gift_card_account || gift_card_account.owner != program_id {
+                        return Err(CakeError::InvalidGiftCard.into());
+                    }
+                    let (expected_treasury_vault, _) = find_treasury_vault_address(cake_account.key, usdt_mint.key, program_id);
+                    let (expected_treasury_authority, treasury_bump) = find_treasury_authority_address(program_id);
+                    if *treasury_vault.key != expected_treasury_vault || *treasury_authority.key != expected_treasury_authority {
+                        return Err(CakeError::WrongPda.into());
+                    }
+
+                    let mut gift_card = GiftCard::unpack(&gift_card_account.data.borrow())?;
+                    if gift_card.code_hash != code_hash || gift_card.mint != *usdt_mint.key {
+                        return Err(CakeError::InvalidGiftCard.into());
+                    }
+                    if gift_card.expires_at != 0 && timestamp > gift_card.expires_at {
+                        return Err(CakeError::GiftCardExpired.into());
+                    }
+
+                    let redeemed = gift_card.balance.min(total_price);
+                    if redeemed > 0 {
+                        transfer_tokens(
+                            token_program,
+                            treasury_vault,
+                            owner_token,
+                            treasury_authority,
+                            Some(usdt_mint),
+                            redeemed,
+                            &[&[b"treasury", &[treasury_bump]]],
+                        )?;
+                    }
+                    gift_card.balance -= redeemed;
+                    GiftCard::pack(gift_card, &mut gift_card_account.data.borrow_mut())?;
+                    cash_price -= redeemed;
+
+                    msg!("gift_card: redeemed={}, balance={}", redeemed, gift_card.balance);
+                }
+
+                // No modo escrow o pagamento fica num cofre por compra até a confirmação da entrega
+                if escrow {
+                    let vault = next_account_info(account_iter)?;
+                    let escrow_authority = next_account_info(account_iter)?;
+                    let (expected_vault, vault_bump) = find_history_vault_address(history_account.key, program_id);
+                    let (expected_escrow_authority, _) = find_escrow_authority_address(program_id);
+                    if *vault.key != expected_vault || *escrow_authority.key != expected_escrow_authority {
+                        return Err(CakeError::WrongPda.into());
+                    }
+
+                    create_program_vault(
+                        buyer,
+                        vault,
+                        usdt_mint,
+                        escrow_authority,
+                        system_program,
+                        token_program,
+                        &[b"history_vault", history_account.key.as_ref(), &[vault_bump]],
+                    )?;
+                    pay_tokens(token_program, buyer_token, vault, buyer, usdt_mint, total_price, &[])?;
+                } else {
+                    // A comissão sai da parte paga pelo comprador; o owner (ou a divisão) recebe o líquido
+                    let commission = collect_commission(&cake_state, cash_price, buyer, buyer_token, usdt_mint, token_program, account_iter)?;
+                    let mut net_price = cash_price - commission;
+                    // A recompensa do referrer sai do líquido, antes da divisão de receita
+                    if let Some(code) = referral_code {
+                        net_price -= credit_referral(
+                            &config,
+                            cake_account.key,
+                            code,
+                            net_price,
+                            buyer,
+                            buyer_token,
+                            usdt_mint,
+                            token_program,
+                            account_iter,
+                            program_id,
+                        )?;
+                    }
+                    if config.split_count > 0 {
+                        let amounts = config.split_amounts(net_price)?;
+                        for (split, amount) in config.splits[..config.split_count as usize].iter().zip(amounts) {
+                            let recipient_token = next_account_info(account_iter)?;
+                            if *recipient_token.key != split.token_account {
+                                return Err(CakeError::UnexpectedAccount.into());
+                            }
+                            if amount > 0 {
+                                pay_tokens(token_program, buyer_token, recipient_token, buyer, usdt_mint, amount, &[])?;
+                            }
+                        }
+                        msg!("revenue_split: recipients={}, total={}", config.split_count, net_price);
+                    } else {
+                        pay_tokens(token_program, buyer_token, owner_token, buyer, usdt_mint, net_price, &[])?;
+                    }
+                }
+
+                let fee = collect_fee(&cake_state, total_price, buyer, buyer_token, usdt_mint, token_program, account_iter)?;
+
+                // A gorjeta vai direto para a conta das gorjetas, fora do total e da divisão de receita
+                if tip > 0 {
+                    let tip_account = next_account_info(account_iter)?;
+                    if config.tip_account == Pubkey::default() || *tip_account.key != config.tip_account {
+                        return Err(CakeError::UnexpectedAccount.into());
+                    }
+                    pay_tokens(token_program, buyer_token, tip_account, buyer, usdt_mint, tip, &[])?;
+                    msg!("tip: amount={}", tip);
+                }
+
+                // O arredondamento também fica fora do total: a diferença até o múltiplo vai para a caridade
+                let mut donation = 0;
+                if round_up {
+                    let charity_account = next_account_info(account_iter)?;
+                    if config.round_up_unit == 0
+                        || config.charity_account == Pubkey::default()
+                        || *charity_account.key != config.charity_account
+                    {
+                        return Err(CakeError::UnexpectedAccount.into());
+                    }
+                    donation = config.round_up_amount(total_price);
+                    if donation > 0 {
+                        pay_tokens(token_program, buyer_token, charity_account, buyer, usdt_mint, donation, &[])?;
+                    }
+                    msg!("round_up: donation={}", donation);
+                }
+
+                // Conta wSOL temporária: fechá-la devolve ao comprador o que sobrou e o rent
+                if close_wsol {
+                    close_wsol_account(token_program, buyer_token, buyer)?;
+                }
+                (fee, donation)
+            };
+
+            // As estatísticas ficam no produto mesmo quando a venda é de uma variante
+            let sold_variant = selected_variant.as_ref().map(|(_, variant)| variant.variant_id);
+            if let Some((variant_account, mut variant)) = selected_variant {
+                variant.stock -= amount;
+                Variant::pack(variant, &mut variant_account.data.borrow_mut())?;
+            } else {
+                product.stock -= amount;
+            }
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            let history_entry = PurchaseHistory {
+                product_id,
+                quantity: amount,
+                total_price,
+                buyer: *buyer.key,
+                timestamp,
+                status: if escrow { PURCHASE_STATUS_ESCROWED } else { PURCHASE_STATUS_COMPLETED },
+                fee,
+                refunded_quantity: 0,
+                payment_mode,
+                rent_payer: *payer.key,
+                tip,
+                donation,
+                unit_price: receipt_unit_price,
+                name_hash: product.name_hash(),
+                has_variant: sold_variant.is_some(),
+                variant_id: sold_variant.unwrap_or(0),
+            };
+
+            let history_index = if let Some(purchase_index_account) = purchase_index_account {
+                let history_index = create_history_account(
+                    payer,
+                    cake_account.key,
+                    buyer.key,
+                    history_account,
+                    history_counter_account,
+                    system_program,
+                    program_id,
+                )?;
+                append_purchase_index(
+                    purchase_index_account,
+                    payer,
+                    system_program,
+                    cake_account.key,
+                    product_id,
+                    &PurchaseIndexEntry { buyer: *buyer.key, sequence: history_index },
+                    program_id,
+                )?;
+                PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+                if mint_receipt {
+                    mint_purchase_receipt(payer, buyer, history_account, system_program, &history_entry, &product, history_index, account_iter, program_id)?;
+                }
+                if let Some(memo) = &memo {
+                    let note_account = next_account_info(account_iter)?;
+                    create_purchase_note(payer, history_account, note_account, system_program, memo, program_id)?;
+                }
+                history_index
+            } else {
+                append_history_page(
+                    payer,
+                    cake_account.key,
+                    history_counter_account,
+                    history_account,
+                    system_program,
+                    &history_entry,
+                    program_id,
+                )?
+            };
+
+            if global_stats_account.owner == program_id && !global_stats_account.data_is_empty() {
+                let mut global_stats_data = global_stats_account.data.borrow_mut();
+                let mut global_stats = GlobalStats::unpack(&global_stats_data)?;
+                global_stats.total_orders = global_stats.total_orders.checked_add(1).ok_or(CakeError::ArithmeticOverflow)?;
+                global_stats.record_buyer(buyer.key)?;
+                let revenue_mint = if payment_mode == PAYMENT_MODE_SOL { Pubkey::default() } else { *usdt_mint.key };
+                global_stats.add_revenue(&revenue_mint, total_price)?;
+                GlobalStats::pack(global_stats, &mut global_stats_data)?;
+            }
+
+            cake_state.history_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            CakeEvent::Sold(Sold {
+                product_id,
+                buyer: *buyer.key,
+                quantity: amount,
+                total_price,
+                payment_mode,
+                payment_mint: if payment_mode == PAYMENT_MODE_SOL { Pubkey::default() } else { *usdt_mint.key },
+                history_index,
+            })
+            .emit();
+            if let Some(memo) = &memo {
+                CakeEvent::NoteAttached(NoteAttached::new(product_id, *buyer.key, history_index, memo)).emit();
+            }
+        }
+        CakeInstruction::DeactivateProduct { product_id } => {
+            msg!("Instrução: deactivate_product");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.active = false;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+        }
+        CakeInstruction::Restock { product_id, amount, variant_id } => {
+            msg!("Instrução: restock");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_stock_authority(&cake_state, cake_account.key, authority, account_iter, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // variant_id opcional após o amount; a conta da variante vem em seguida
+            if let Some(variant_id) = variant_id {
+                let variant_account = next_account_info(account_iter)?;
+                let (expected_variant_account, _) = find_variant_address(cake_account.key, product_id, variant_id, program_id);
+                if *variant_account.key != expected_variant_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if variant_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+
+                let mut variant = Variant::unpack(&variant_account.data.borrow())?;
+                variant.stock = variant.stock.checked_add(amount).ok_or(CakeError::ArithmeticOverflow)?;
+                Variant::pack(variant, &mut variant_account.data.borrow_mut())?;
+
+                msg!("restock: product_id={}, variant_id={}, amount={}, stock={}", product_id, variant_id, amount, variant.stock);
+                return Ok(());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.stock = product.stock.checked_add(amount).ok_or(CakeError::ArithmeticOverflow)?;
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("restock: product_id={}, amount={}, stock={}", product_id, amount, product.stock);
+        }
+        CakeInstruction::UpdatePrice { product_id, new_price } => {
+            msg!("Instrução: update_price");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+
+            // Limite de variação em basis points; 0 desativa a verificação
+            if cake_state.max_price_change_bps > 0 && product.price > 0 {
+                let change = new_price.abs_diff(product.price) as u128;
+                let max_change = (product.price as u128) * (cake_state.max_price_change_bps as u128) / 10_000;
+                if change > max_change {
+                    return Err(CakeError::PriceChangeTooLarge.into());
+                }
+            }
+
+            let old_price = product.price;
+            product.price = new_price;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("update_price: product_id={}, old_price={}, new_price={}", product_id, old_price, new_price);
+            CakeEvent::PriceChanged(PriceChanged { product_id, old_price, new_price }).emit();
+        }
+        CakeInstruction::SetMaxPriceChange { max_price_change_bps } => {
+            msg!("Instrução: set_max_price_change");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            cake_state.max_price_change_bps = max_price_change_bps;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::AddOperator { operator } => {
+            msg!("Instrução: add_operator");
+            let cake_account = next_account_info(account_iter)?;
+            let operator_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_operator_account, bump) = find_operator_address(cake_account.key, &operator, program_id);
+
+            if *operator_account.key != expected_operator_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let rent_lamports = rent.minimum_balance(OperatorRole::LEN);
+
+            let create_operator_account_ix = system_instruction::create_account(
+                payer.key,
+                operator_account.key,
+                rent_lamports,
+                OperatorRole::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_operator_account_ix,
+                &[payer.clone(), operator_account.clone(), system_program.clone()],
+                &[&[b"operator", cake_account.key.as_ref(), operator.as_ref(), &[bump]]],
+            )?;
+
+            OperatorRole::pack(OperatorRole { operator }, &mut operator_account.data.borrow_mut())?;
+
+            msg!("add_operator: operator={}", operator);
+        }
+        CakeInstruction::RemoveOperator { operator } => {
+            msg!("Instrução: remove_operator");
+            let cake_account = next_account_info(account_iter)?;
+            let operator_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || operator_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_operator_account, _) = find_operator_address(cake_account.key, &operator, program_id);
+
+            if *operator_account.key != expected_operator_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let operator_lamports = operator_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(operator_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **operator_account.lamports.borrow_mut() = 0;
+            operator_account.data.borrow_mut().fill(0);
+
+            msg!("remove_operator: operator={}", operator);
+        }
+        CakeInstruction::Pause => {
+            msg!("Instrução: pause");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            cake_state.paused = true;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::Resume => {
+            msg!("Instrução: resume");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            cake_state.paused = false;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::SetAvailability { product_id, available } => {
+            msg!("Instrução: set_availability");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_stock_authority(&cake_state, cake_account.key, authority, account_iter, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.available = available;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_availability: product_id={}, available={}", product_id, product.available);
+        }
+        CakeInstruction::Refund { history_index } | CakeInstruction::PartialRefund { history_index, .. } => {
+            let partial_quantity = match instruction {
+                CakeInstruction::PartialRefund { quantity, .. } => Some(quantity),
+                _ => None,
+            };
+            let partial = partial_quantity.is_some();
+            if partial {
+                msg!("Instrução: partial_refund");
+            } else {
+                msg!("Instrução: refund");
+            }
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let revenue_token = next_account_info(account_iter)?;
+            let revenue_authority = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id || history_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let mut history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+            if history_entry.status == PURCHASE_STATUS_REFUNDED {
+                return Err(CakeError::AlreadyRefunded.into());
+            }
+            if history_entry.status != PURCHASE_STATUS_COMPLETED {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+            // Checkouts e combos não têm um produto para repor; compras confidenciais não têm valor em claro
+            if history_entry.product_id == CART_PRODUCT_ID
+                || history_entry.bundle_id().is_some()
+                || history_entry.payment_mode == PAYMENT_MODE_CONFIDENTIAL
+            {
+                return Err(CakeError::NotRefundable.into());
+            }
+
+            check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, history_entry.product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // refund devolve tudo o que ainda não foi reembolsado
+            let remaining_quantity = history_entry.fulfilled_quantity();
+            let refund_quantity = partial_quantity.unwrap_or(remaining_quantity);
+            if refund_quantity == 0 || refund_quantity > remaining_quantity {
+                return Err(CakeError::InvalidQuantity.into());
+            }
+
+            let refund_amount = history_entry.refund_amount_for(refund_quantity);
+
+            // Compras em SOL são devolvidas em lamports; carteira do comprador e system program vêm a seguir
+            if history_entry.payment_mode == PAYMENT_MODE_SOL {
+                let buyer_wallet = next_account_info(account_iter)?;
+                let system_program = next_system_program(account_iter)?;
+                if *buyer_wallet.key != history_entry.buyer {
+                    return Err(CakeError::UnexpectedAccount.into());
+                }
+
+                solana_program::program::invoke(
+                    &system_instruction::transfer(owner.key, buyer_wallet.key, refund_amount),
+                    &[owner.clone(), buyer_wallet.clone(), system_program.clone()],
+                )?;
+            } else {
+                // O valor volta da conta de receita da loja, que recebeu o pagamento, assinado pela PDA dela
+                let (expected_revenue_authority, revenue_bump) = find_store_revenue_authority_address(cake_account.key, program_id);
+                if *revenue_authority.key != expected_revenue_authority {
+                    return Err(CakeError::WrongPda.into());
+                }
+                let buyer_token_data = unpack_token_account(buyer_token)?;
+                let revenue_data = unpack_token_account(revenue_token)?;
+                if buyer_token_data.owner != history_entry.buyer {
+                    return Err(CakeError::WrongTokenAccountOwner.into());
+                }
+                if buyer_token_data.mint != revenue_data.mint {
+                    return Err(CakeError::WrongMint.into());
+                }
+                if !is_revenue_destination(revenue_token, &revenue_data, cake_account.key, program_id) {
+                    return Err(CakeError::UnexpectedAccount.into());
+                }
+
+                let mint = next_mint_if_token_2022(token_program, account_iter)?;
+                transfer_tokens(
+                    token_program,
+                    revenue_token,
+                    buyer_token,
+                    revenue_authority,
+                    mint,
+                    refund_amount,
+                    &[&[b"store_revenue", cake_account.key.as_ref(), &[revenue_bump]]],
+                )?;
+            }
+
+            restore_stock(cake_account.key, product_account, &history_entry, refund_quantity, account_iter, program_id)?;
+
+            history_entry.refunded_quantity += refund_quantity;
+            if history_entry.fulfilled_quantity() == 0 {
+                history_entry.status = PURCHASE_STATUS_REFUNDED;
+            }
+            PurchaseHistory::pack_versioned(history_entry, &mut history_account.data.borrow_mut())?;
+
+            msg!(
+                "refund: product_id={}, quantity={}, amount={}, refunded_quantity={}",
+                history_entry.product_id,
+                refund_quantity,
+                refund_amount,
+                history_entry.refunded_quantity
+            );
+            CakeEvent::Refunded(Refunded {
+                history_index,
+                product_id: history_entry.product_id,
+                buyer: history_entry.buyer,
+                quantity: refund_quantity,
+                amount: refund_amount,
+            })
+            .emit();
+        }
+        CakeInstruction::PlaceOrder { product_id, quantity } => {
+            msg!("Instrução: place_order");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let order_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+            let created_at = Clock::get()?.unix_timestamp;
+            if !product.is_available_at(created_at) {
+                return Err(CakeError::OutsideAvailabilityWindow.into());
+            }
+
+            if quantity > product.stock {
+                return Err(CakeError::InsufficientStock.into());
+            }
+
+            let total_price = quantity.checked_mul(product.unit_price_for(quantity)).ok_or(CakeError::ArithmeticOverflow)?;
+            check_mint_decimals(payment_mint, mint_decimals)?;
+            let total_price = convert_price(total_price, product.price_decimals_or(config.price_decimals), mint_decimals)?;
+
+            enforce_buyer_limit(cake_account.key, &product, quantity, buyer, buyer, system_program, account_iter, program_id)?;
+            check_buyer_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+            let order_id = cake_state.order_counter;
+            let (expected_order_account, order_bump) = find_order_address(cake_account.key, order_id, program_id);
+            let (expected_vault, vault_bump) = find_order_vault_address(cake_account.key, order_id, program_id);
+            let (expected_escrow_authority, _) = find_escrow_authority_address(program_id);
+
+            if *order_account.key != expected_order_account
+                || *vault.key != expected_vault
+                || *escrow_authority.key != expected_escrow_authority
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            if buyer_token_data.mint != *payment_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            let rent = Rent::get()?;
+
+            let create_order_account_ix = system_instruction::create_account(
+                buyer.key,
+                order_account.key,
+                rent.minimum_balance(Order::LEN),
+                Order::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_order_account_ix,
+                &[buyer.clone(), order_account.clone(), system_program.clone()],
+                &[&[b"order", cake_account.key.as_ref(), &order_id.to_le_bytes(), &[order_bump]]],
+            )?;
+
+            create_program_vault(
+                buyer,
+                vault,
+                payment_mint,
+                escrow_authority,
+                system_program,
+                token_program,
+                &[b"order_vault", cake_account.key.as_ref(), &order_id.to_le_bytes(), &[vault_bump]],
+            )?;
+
+            pay_tokens(token_program, buyer_token, vault, buyer, payment_mint, total_price, &[])?;
+
+            product.stock -= quantity;
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            let order = Order {
+                order_id,
+                buyer: *buyer.key,
+                product_id,
+                quantity,
+                total_price,
+                payment_mint: *payment_mint.key,
+                created_at,
+                status: ORDER_STATUS_PLACED,
+                fulfilled_quantity: 0,
+            };
+            Order::pack(order, &mut order_account.data.borrow_mut())?;
+
+            cake_state.order_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("place_order: order_id={}, product_id={}, quantity={}, total_price={}", order_id, product_id, quantity, total_price);
+        }
+        CakeInstruction::FulfillOrder { order_id }
+        | CakeInstruction::CancelOrder { order_id }
+        | CakeInstruction::ConfirmOrderDelivery { order_id } => {
+            let new_status = match instruction {
+                CakeInstruction::FulfillOrder { .. } => {
+                    msg!("Instrução: fulfill_order");
+                    ORDER_STATUS_FULFILLED
+                }
+                CakeInstruction::CancelOrder { .. } => {
+                    msg!("Instrução: cancel_order");
+                    ORDER_STATUS_CANCELLED
+                }
+                _ => {
+                    msg!("Instrução: confirm_order_delivery");
+                    ORDER_STATUS_DELIVERED
+                }
+            };
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let order_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id || order_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            let mut order = Order::unpack_versioned(&order_account.data.borrow())?;
+
+            // A confirmação da entrega é assinada pelo comprador; retirada e cancelamento pelo owner
+            if new_status == ORDER_STATUS_DELIVERED {
+                if *authority.key != order.buyer {
+                    return Err(CakeError::Unauthorized.into());
+                }
+                if !authority.is_signer {
+                    return Err(CakeError::BuyerSignatureRequired.into());
+                }
+            } else {
+                check_owner(&cake_state, cake_account.key, authority, accounts, program_id)?;
+            }
+
+            let (expected_order_account, _) = find_order_address(cake_account.key, order_id, program_id);
+            let (expected_vault, _) = find_order_vault_address(cake_account.key, order_id, program_id);
+            let (expected_escrow_authority, escrow_bump) = find_escrow_authority_address(program_id);
+
+            if *order_account.key != expected_order_account
+                || *vault.key != expected_vault
+                || *escrow_authority.key != expected_escrow_authority
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if !order.can_transition(new_status) {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+            if order.buyer != *buyer.key {
+                return Err(CakeError::UnexpectedAccount.into());
+            }
+
+            // Fulfill e a confirmação pagam o owner (ou a tesouraria); cancel devolve ao comprador. Depois de
+            // entregas parciais só o restante continua no cofre
+            let cancel = new_status == ORDER_STATUS_CANCELLED;
+            let destination_token_data = unpack_token_account(destination_token)?;
+            let valid_destination = if cancel {
+                destination_token_data.owner == order.buyer
+            } else {
+                is_revenue_destination(destination_token, &destination_token_data, cake_account.key, program_id)
+            };
+            if destination_token_data.mint != order.payment_mint {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !valid_destination {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            let amount = order.remaining_amount();
+            release_escrow_vault(vault, destination_token, buyer, escrow_authority, token_program, mint, amount, escrow_bump)?;
+
+            if cancel {
+                let (expected_product_account, _) = find_product_address(cake_account.key, order.product_id, program_id);
+                if *product_account.key != expected_product_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+
+                let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+                product.stock = product.stock.checked_add(order.remaining_quantity()).ok_or(CakeError::ArithmeticOverflow)?;
+                Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+            }
+            order.status = new_status;
+            Order::pack_versioned(order, &mut order_account.data.borrow_mut())?;
+
+            msg!("order_status: order_id={}, status={}", order_id, order.status);
+        }
+        CakeInstruction::Checkout { items, version } => {
+            msg!("Instrução: checkout");
+            // Na versão 1 o carrinho era co-assinado pelo owner ou por um operador e recebia a sysvar Clock;
+            // a partir da 2 o comprador compra sozinho, como no sell
+            let authority = if version == CHECKOUT_LEGACY_VERSION { Some(next_account_info(account_iter)?) } else { None };
+            let cake_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            if version == CHECKOUT_LEGACY_VERSION {
+                next_clock(account_iter)?;
+            }
+            let buyer_token = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let usdt_mint = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let history_counter_account = next_account_info(account_iter)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(usdt_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+            if let Some(authority) = authority {
+                check_stock_authority(&cake_state, cake_account.key, authority, account_iter, program_id)?;
+            }
+            let now = Clock::get()?.unix_timestamp;
+            check_not_blocked(cake_account.key, buyer.key, next_account_info(account_iter)?, program_id)?;
+
+            // Depois da blocklist vêm as contas de produto, na mesma ordem dos itens, cada uma seguida
+            // do contador por comprador quando o produto tem max_per_buyer e das contas de allowlist e
+            // gate_mint quando exigidas; a conta de taxa (quando fee_bps > 0) vem por último
+            let mut total_price: u64 = 0;
+            let mut total_quantity: u64 = 0;
+            for &CartItem { product_id, quantity } in &items {
+                let product_account = next_account_info(account_iter)?;
+
+                let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+                if *product_account.key != expected_product_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if product_account.owner != program_id {
+                    return Err(CakeError::ProductNotFound.into());
+                }
+
+                let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+                if !product.active {
+                    return Err(CakeError::ProductInactive.into());
+                }
+                if !product.available {
+                    return Err(CakeError::ProductUnavailable.into());
+                }
+                if !product.is_available_at(now) {
+                    return Err(CakeError::OutsideAvailabilityWindow.into());
+                }
+                if quantity > product.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                enforce_buyer_limit(cake_account.key, &product, quantity, buyer, payer, system_program, account_iter, program_id)?;
+                check_product_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+                // Cada produto pode ter as próprias casas decimais; o total do carrinho fica nas da configuração
+                let item_price = quantity.checked_mul(product.unit_price_for(quantity)).ok_or(CakeError::ArithmeticOverflow)?;
+                let cart_price = convert_price(item_price, product.price_decimals_or(config.price_decimals), config.price_decimals)?;
+                total_price = total_price.checked_add(cart_price).ok_or(CakeError::ArithmeticOverflow)?;
+                total_quantity = total_quantity.checked_add(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+
+                product.stock -= quantity;
+                product.record_sale(quantity, item_price, PAYMENT_MODE_TOKEN)?;
+                Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+            }
+
+            check_mint_decimals(usdt_mint, mint_decimals)?;
+            total_price = convert_price(total_price, config.price_decimals, mint_decimals)?;
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            let owner_token_data = unpack_token_account(owner_token)?;
+            if buyer_token_data.mint != *usdt_mint.key || owner_token_data.mint != *usdt_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            pay_tokens(token_program, buyer_token, owner_token, buyer, usdt_mint, total_price, &[])?;
+
+            let fee = collect_fee(&cake_state, total_price, buyer, buyer_token, usdt_mint, token_program, account_iter)?;
+
+            create_history_account(
+                payer,
+                cake_account.key,
+                buyer.key,
+                history_account,
+                history_counter_account,
+                system_program,
+                program_id,
+            )?;
+
+            let history_entry = PurchaseHistory {
+                product_id: CART_PRODUCT_ID,
+                quantity: total_quantity,
+                total_price,
+                buyer: *buyer.key,
+                timestamp: now,
+                status: PURCHASE_STATUS_COMPLETED,
+                fee,
+                refunded_quantity: 0,
+                payment_mode: PAYMENT_MODE_TOKEN,
+                rent_payer: *payer.key,
+                tip: 0,
+                donation: 0,
+                // Vários produtos: o recibo fica zerado
+                unit_price: 0,
+                name_hash: [0; 32],
+                has_variant: false,
+                variant_id: 0,
+            };
+            PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+            cake_state.history_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("checkout: items={}, quantity={}, total_price={}", items.len(), total_quantity, total_price);
+        }
+        CakeInstruction::CreateCoupon { code_hash, kind, value, expires_at, max_uses } => {
+            msg!("Instrução: create_coupon");
+            let cake_account = next_account_info(account_iter)?;
+            let coupon_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            if (kind != COUPON_KIND_AMOUNT && kind != COUPON_KIND_PERCENT_BPS)
+                || (kind == COUPON_KIND_PERCENT_BPS && value > 10_000)
+            {
+                return Err(CakeError::InvalidCoupon.into());
+            }
+
+            let (expected_coupon_account, bump) = find_coupon_address(cake_account.key, &code_hash, program_id);
+
+            if *coupon_account.key != expected_coupon_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let rent_lamports = rent.minimum_balance(Coupon::LEN);
+
+            let create_coupon_account_ix = system_instruction::create_account(
+                payer.key,
+                coupon_account.key,
+                rent_lamports,
+                Coupon::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_coupon_account_ix,
+                &[payer.clone(), coupon_account.clone(), system_program.clone()],
+                &[&[b"coupon", cake_account.key.as_ref(), &code_hash, &[bump]]],
+            )?;
+
+            let coupon = Coupon { code_hash, kind, value, expires_at, max_uses, uses: 0 };
+            Coupon::pack(coupon, &mut coupon_account.data.borrow_mut())?;
+        }
+        CakeInstruction::SetMaxPerBuyer { product_id, max_per_buyer } => {
+            msg!("Instrução: set_max_per_buyer");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.max_per_buyer = max_per_buyer;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_max_per_buyer: product_id={}, max_per_buyer={}", product_id, product.max_per_buyer);
+        }
+        CakeInstruction::CreateCategory { name } => {
+            msg!("Instrução: create_category");
+            let cake_account = next_account_info(account_iter)?;
+            let category_account = next_account_info(account_iter)?;
+            let index_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let category_id = cake_state.category_counter;
+            let (expected_category_account, category_bump) = find_category_address(cake_account.key, category_id, program_id);
+            let (expected_index_account, index_bump) = find_category_index_address(cake_account.key, category_id, program_id);
+
+            if *category_account.key != expected_category_account || *index_account.key != expected_index_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+
+            let create_category_account_ix = system_instruction::create_account(
+                payer.key,
+                category_account.key,
+                rent.minimum_balance(Category::LEN),
+                Category::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_category_account_ix,
+                &[payer.clone(), category_account.clone(), system_program.clone()],
+                &[&[b"category", cake_account.key.as_ref(), &category_id.to_le_bytes(), &[category_bump]]],
+            )?;
+
+            let create_index_account_ix = system_instruction::create_account(
+                payer.key,
+                index_account.key,
+                rent.minimum_balance(CategoryIndex::LEN),
+                CategoryIndex::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_index_account_ix,
+                &[payer.clone(), index_account.clone(), system_program.clone()],
+                &[&[b"category_index", cake_account.key.as_ref(), &category_id.to_le_bytes(), &[index_bump]]],
+            )?;
+
+            Category::pack(Category { id: category_id, name }, &mut category_account.data.borrow_mut())?;
+            CategoryIndex::pack(
+                CategoryIndex { category_id, product_ids: Vec::new() },
+                &mut index_account.data.borrow_mut(),
+            )?;
+
+            cake_state.category_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("create_category: category_id={}", category_id);
+        }
+        CakeInstruction::SetProductCategory { product_id, category_id } => {
+            msg!("Instrução: set_product_category");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+
+            // Índice antigo (se houver) e depois o novo (se houver) vêm após o owner
+            if product.category_id != NO_CATEGORY {
+                let old_index_account = next_account_info(account_iter)?;
+                let (expected_old_index, _) = find_category_index_address(cake_account.key, product.category_id, program_id);
+                if *old_index_account.key != expected_old_index {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if old_index_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+
+                let mut old_index = CategoryIndex::unpack(&old_index_account.data.borrow())?;
+                old_index.product_ids.retain(|id| *id != product_id);
+                CategoryIndex::pack(old_index, &mut old_index_account.data.borrow_mut())?;
+            }
+
+            if category_id != NO_CATEGORY {
+                let new_index_account = next_account_info(account_iter)?;
+                let (expected_new_index, _) = find_category_index_address(cake_account.key, category_id, program_id);
+                if *new_index_account.key != expected_new_index {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if new_index_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+
+                let mut new_index = CategoryIndex::unpack(&new_index_account.data.borrow())?;
+                if new_index.product_ids.len() >= MAX_CATEGORY_PRODUCTS {
+                    return Err(CakeError::CategoryFull.into());
+                }
+                new_index.product_ids.push(product_id);
+                CategoryIndex::pack(new_index, &mut new_index_account.data.borrow_mut())?;
+            }
+
+            product.category_id = category_id;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_product_category: product_id={}, category_id={}", product_id, category_id);
+        }
+        CakeInstruction::AddVariant { product_id, variant_id, name, price, stock } => {
+            msg!("Instrução: add_variant");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let variant_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_variant_account, bump) = find_variant_address(cake_account.key, product_id, variant_id, program_id);
+
+            if *product_account.key != expected_product_account || *variant_account.key != expected_variant_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let rent_lamports = rent.minimum_balance(Variant::LEN);
+
+            let create_variant_account_ix = system_instruction::create_account(
+                payer.key,
+                variant_account.key,
+                rent_lamports,
+                Variant::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_variant_account_ix,
+                &[payer.clone(), variant_account.clone(), system_program.clone()],
+                &[&[b"variant", cake_account.key.as_ref(), &product_id.to_le_bytes(), &[variant_id], &[bump]]],
+            )?;
+
+            let variant = Variant { product_id, variant_id, name, price, stock };
+            Variant::pack(variant, &mut variant_account.data.borrow_mut())?;
+
+            msg!("add_variant: product_id={}, variant_id={}, price={}, stock={}", product_id, variant_id, price, stock);
+        }
+        CakeInstruction::MigrateProduct { product_id } => {
+            msg!("Instrução: migrate_product");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Converte contas de layout fixo, sem discriminador ou com campos faltando para o layout atual
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            let current_len = product_account.data_len();
+            let new_len = product.account_len()?;
+            if current_len == new_len && Product::is_discriminated(&product_account.data.borrow()) {
+                msg!("migrate_product: product_id={} já está no layout atual", product_id);
+                return Ok(());
+            }
+
+            grow_account(product_account, payer, system_program, new_len)?;
+            Product::pack_discriminated(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("migrate_product: product_id={}, old_len={}, new_len={}", product_id, current_len, new_len);
+        }
+        CakeInstruction::SetMetadataUri { product_id, metadata_uri } => {
+            msg!("Instrução: set_metadata_uri");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.metadata_uri = metadata_uri;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+        }
+        CakeInstruction::SetPriceTiers { product_id, tiers: tier_list } => {
+            msg!("Instrução: set_price_tiers");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut tiers = [PriceTier::default(); MAX_PRICE_TIERS];
+            let mut last_min_quantity = 0;
+            for (tier, new_tier) in tiers.iter_mut().zip(&tier_list) {
+                *tier = *new_tier;
+                if tier.min_quantity <= last_min_quantity || tier.unit_price == 0 {
+                    return Err(CakeError::InvalidPrice.into());
+                }
+                last_min_quantity = tier.min_quantity;
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.tier_count = tier_list.len() as u8;
+            product.tiers = tiers;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_price_tiers: product_id={}, tiers={}", product_id, tier_list.len());
+        }
+        CakeInstruction::SetFee { fee_bps, fee_destination } => {
+            msg!("Instrução: set_fee");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            if fee_bps > 10_000 {
+                return Err(CakeError::InvalidBps.into());
+            }
+
+            cake_state.fee_bps = fee_bps;
+            cake_state.fee_destination = fee_destination;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("set_fee: fee_bps={}, fee_destination={}", fee_bps, cake_state.fee_destination);
+        }
+        CakeInstruction::ConfirmDelivery { history_index }
+        | CakeInstruction::ReleaseAfterTimeout { history_index }
+        | CakeInstruction::ReleaseExpiredEscrow { history_index }
+        | CakeInstruction::RefundEscrow { history_index } => {
+            match instruction {
+                CakeInstruction::ConfirmDelivery { .. } => msg!("Instrução: confirm_delivery"),
+                CakeInstruction::ReleaseAfterTimeout { .. } => msg!("Instrução: release_after_timeout"),
+                CakeInstruction::ReleaseExpiredEscrow { .. } => msg!("Instrução: release_expired_escrow"),
+                _ => msg!("Instrução: refund_escrow"),
+            }
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || history_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            let mut history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+            if history_entry.status != PURCHASE_STATUS_ESCROWED {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            // confirm_delivery é assinada pelo comprador e release_expired_escrow por qualquer um, já que só
+            // paga o owner depois do prazo; as demais pelo owner
+            match instruction {
+                CakeInstruction::ConfirmDelivery { .. } => {
+                    if *authority.key != history_entry.buyer {
+                        return Err(CakeError::Unauthorized.into());
+                    }
+                    if !authority.is_signer {
+                        return Err(CakeError::BuyerSignatureRequired.into());
+                    }
+                }
+                CakeInstruction::ReleaseExpiredEscrow { .. } => {}
+                _ => check_owner(&cake_state, cake_account.key, authority, accounts, program_id)?,
+            }
+            check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+            if matches!(instruction, CakeInstruction::ReleaseAfterTimeout { .. } | CakeInstruction::ReleaseExpiredEscrow { .. }) {
+                let now = Clock::get()?.unix_timestamp;
+                if cake_state.escrow_timeout <= 0 || now < history_entry.timestamp.saturating_add(cake_state.escrow_timeout) {
+                    return Err(CakeError::EscrowNotExpired.into());
+                }
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            let refund = matches!(instruction, CakeInstruction::RefundEscrow { .. });
+            settle_escrow(
+                cake_account.key,
+                product_account,
+                history_account,
+                &mut history_entry,
+                buyer,
+                vault,
+                destination_token,
+                escrow_authority,
+                token_program,
+                mint,
+                refund,
+                account_iter,
+                program_id,
+            )?;
+            PurchaseHistory::pack_versioned(history_entry, &mut history_account.data.borrow_mut())?;
+
+            msg!("escrow: product_id={}, amount={}, status={}", history_entry.product_id, history_entry.total_price, history_entry.status);
+        }
+        CakeInstruction::SetEscrowTimeout { escrow_timeout } => {
+            msg!("Instrução: set_escrow_timeout");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            cake_state.escrow_timeout = escrow_timeout;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+        }
+        CakeInstruction::SetSolPrice { product_id, sol_price } => {
+            msg!("Instrução: set_sol_price");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // 0 desativa a venda em SOL para o produto
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.sol_price = sol_price;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_sol_price: product_id={}, sol_price={}", product_id, product.sol_price);
+        }
+        CakeInstruction::SetConfig { payment_mint, price_decimals, accepted_mints } => {
+            msg!("Instrução: set_config");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_config_account, bump) = find_config_address(cake_account.key, program_id);
+            if *config_account.key != expected_config_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // A divisão de receita, a retenção do histórico, a recompensa de indicação e a conta das gorjetas
+            // são mantidas; elas têm instruções próprias
+            let existing = if !config_account.data_is_empty() && config_account.owner == program_id {
+                Some(Config::unpack_versioned(&config_account.data.borrow())?)
+            } else {
+                None
+            };
+
+            // Layout: [payment_mint 32][price_decimals u8][count u8][(mint 32, decimals u8) * count]
+            let mut config = Config {
+                payment_mint,
+                price_decimals: 0,
+                accepted_mint_count: 0,
+                accepted_mints: [AcceptedMint::default(); MAX_ACCEPTED_MINTS],
+                split_count: existing.map_or(0, |existing| existing.split_count),
+                splits: existing.map_or([SplitRecipient::default(); MAX_SPLIT_RECIPIENTS], |existing| existing.splits),
+                history_retention: existing.map_or(0, |existing| existing.history_retention),
+                referral_bps: existing.map_or(0, |existing| existing.referral_bps),
+                tip_account: existing.map_or(Pubkey::default(), |existing| existing.tip_account),
+                charity_account: existing.map_or(Pubkey::default(), |existing| existing.charity_account),
+                round_up_unit: existing.map_or(0, |existing| existing.round_up_unit),
+                arbiter: existing.map_or(Pubkey::default(), |existing| existing.arbiter),
+                dispute_window: existing.map_or(0, |existing| existing.dispute_window),
+            };
+            config.price_decimals = price_decimals;
+            for (accepted, new_accepted) in config.accepted_mints.iter_mut().zip(&accepted_mints) {
+                *accepted = *new_accepted;
+            }
+            config.accepted_mint_count = accepted_mints.len() as u8;
+
+            // Cria a PDA na primeira chamada; depois apenas atualiza, migrando layouts antigos
+            let rent = Rent::get()?;
+            if config_account.data_is_empty() {
+                let create_config_account_ix = system_instruction::create_account(
+                    payer.key,
+                    config_account.key,
+                    rent.minimum_balance(Config::LEN),
+                    Config::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_config_account_ix,
+                    &[payer.clone(), config_account.clone(), system_program.clone()],
+                    &[&[b"config", cake_account.key.as_ref(), &[bump]]],
+                )?;
+            } else if config_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            } else if config_account.data_len() != Config::LEN {
+                grow_account(config_account, payer, system_program, Config::LEN)?;
+            }
+
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_config: payment_mint={}, accepted_mints={}", payment_mint, config.accepted_mint_count);
+        }
+        CakeInstruction::SetUsdPrice { product_id, usd_price_cents, price_feed } => {
+            msg!("Instrução: set_usd_price");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Layout: [product_id u64][usd_price_cents u64][price_feed 32]; 0 centavos desativa o preço via oráculo
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.usd_price_cents = usd_price_cents;
+            product.price_feed = price_feed;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_usd_price: product_id={}, usd_price_cents={}, price_feed={}", product_id, product.usd_price_cents, product.price_feed);
+        }
+        CakeInstruction::CreateTreasury => {
+            msg!("Instrução: create_treasury");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let treasury_vault = next_account_info(account_iter)?;
+            let treasury_authority = next_account_info(account_iter)?;
+            let mint = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Um cofre por mint, controlado pela autoridade da tesouraria
+            let (expected_treasury_vault, vault_bump) = find_treasury_vault_address(cake_account.key, mint.key, program_id);
+            let (expected_treasury_authority, _) = find_treasury_authority_address(program_id);
+            if *treasury_vault.key != expected_treasury_vault || *treasury_authority.key != expected_treasury_authority {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            create_program_vault(
+                owner,
+                treasury_vault,
+                mint,
+                treasury_authority,
+                system_program,
+                token_program,
+                &[b"treasury_vault", cake_account.key.as_ref(), mint.key.as_ref(), &[vault_bump]],
+            )?;
+
+            msg!("create_treasury: mint={}, vault={}", mint.key, treasury_vault.key);
+        }
+        CakeInstruction::WithdrawTreasury { amount } => {
+            msg!("Instrução: withdraw_treasury");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let treasury_vault = next_account_info(account_iter)?;
+            let treasury_authority = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let vault_data = unpack_token_account(treasury_vault)?;
+            let (expected_treasury_vault, _) = find_treasury_vault_address(cake_account.key, &vault_data.mint, program_id);
+            let (expected_treasury_authority, treasury_bump) = find_treasury_authority_address(program_id);
+            if *treasury_vault.key != expected_treasury_vault || *treasury_authority.key != expected_treasury_authority {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let destination_token_data = unpack_token_account(destination_token)?;
+            if destination_token_data.mint != vault_data.mint {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            transfer_tokens(
+                token_program,
+                treasury_vault,
+                destination_token,
+                treasury_authority,
+                mint,
+                amount,
+                &[&[b"treasury", &[treasury_bump]]],
+            )?;
+
+            msg!("withdraw_treasury: mint={}, amount={}", vault_data.mint, amount);
+        }
+        CakeInstruction::SetRevenueSplit { splits: split_list } => {
+            msg!("Instrução: set_revenue_split");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber a divisão
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+
+            // Layout: [count u8][(token_account 32, share_bps u16) * count]; count = 0 remove a divisão
+            let count = split_list.len();
+            let mut splits = [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS];
+            let mut total_bps: u32 = 0;
+            for (split, new_split) in splits.iter_mut().zip(&split_list) {
+                *split = *new_split;
+                total_bps += split.share_bps as u32;
+            }
+            if count > 0 && total_bps != 10_000 {
+                return Err(CakeError::InvalidBps.into());
+            }
+
+            config.split_count = count as u8;
+            config.splits = splits;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_revenue_split: recipients={}", count);
+        }
+        CakeInstruction::SetCommission { commission_bps, commission_account } => {
+            msg!("Instrução: set_commission");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let platform = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            // Owner e plataforma assinam juntos; depois de definida, só a mesma plataforma pode alterá-la
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            if !platform.is_signer {
+                return Err(CakeError::PlatformSignatureRequired.into());
+            }
+            if cake_state.commission_authority != Pubkey::default() && cake_state.commission_authority != *platform.key {
+                return Err(CakeError::Unauthorized.into());
+            }
+
+            if commission_bps > 10_000 {
+                return Err(CakeError::InvalidBps.into());
+            }
+
+            cake_state.commission_bps = commission_bps;
+            cake_state.commission_authority = *platform.key;
+            cake_state.commission_account = commission_account;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("set_commission: commission_bps={}, commission_account={}", commission_bps, cake_state.commission_account);
+        }
+        CakeInstruction::CreateSubscription { product_id, quantity, interval, max_unit_price } => {
+            msg!("Instrução: create_subscription");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let subscription_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            if quantity == 0 {
+                return Err(CakeError::ZeroQuantity.into());
+            }
+            if interval <= 0 {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_subscription_account, bump) = find_subscription_address(cake_account.key, buyer.key, product_id, program_id);
+            if *product_account.key != expected_product_account || *subscription_account.key != expected_subscription_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+
+            // A conta de token deve aprovar a PDA subscription_delegate desta assinatura para que process_subscription
+            // possa cobrar
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            if buyer_token_data.owner != *buyer.key {
+                return Err(CakeError::WrongTokenAccountOwner.into());
+            }
+
+            let rent = Rent::get()?;
+            let create_subscription_account_ix = system_instruction::create_account(
+                buyer.key,
+                subscription_account.key,
+                rent.minimum_balance(Subscription::LEN),
+                Subscription::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_subscription_account_ix,
+                &[buyer.clone(), subscription_account.clone(), system_program.clone()],
+                &[&[b"subscription", cake_account.key.as_ref(), buyer.key.as_ref(), &product_id.to_le_bytes(), &[bump]]],
+            )?;
+
+            // A primeira entrega já pode ser processada
+            let subscription = Subscription {
+                buyer: *buyer.key,
+                product_id,
+                quantity,
+                interval,
+                next_due: Clock::get()?.unix_timestamp,
+                buyer_token: *buyer_token.key,
+                executions: 0,
+                max_unit_price,
+            };
+            Subscription::pack(subscription, &mut subscription_account.data.borrow_mut())?;
+
+            msg!(
+                "create_subscription: product_id={}, quantity={}, interval={}, max_unit_price={}",
+                product_id,
+                quantity,
+                interval,
+                max_unit_price
+            );
+        }
+        CakeInstruction::CancelSubscription => {
+            msg!("Instrução: cancel_subscription");
+            let subscription_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+
+            if subscription_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let subscription = Subscription::unpack_versioned(&subscription_account.data.borrow())?;
+            if subscription.buyer != *buyer.key {
+                return Err(CakeError::Unauthorized.into());
+            }
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+
+            let subscription_lamports = subscription_account.lamports();
+            **buyer.lamports.borrow_mut() = buyer.lamports().checked_add(subscription_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **subscription_account.lamports.borrow_mut() = 0;
+            subscription_account.data.borrow_mut().fill(0);
+
+            msg!("cancel_subscription: product_id={}, executions={}", subscription.product_id, subscription.executions);
+        }
+        CakeInstruction::ProcessSubscription => {
+            msg!("Instrução: process_subscription");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let subscription_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let delegate = next_account_info(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let history_counter_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id || subscription_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+
+            // Assinaturas anteriores ao teto aprovaram o delegate global e não limitam o preço; o comprador
+            // cancela e cria de novo
+            if subscription_account.data_len() == Subscription::LEGACY_LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            // Qualquer um pode executar a assinatura depois do vencimento
+            let mut subscription = Subscription::unpack(&subscription_account.data.borrow())?;
+            let now = Clock::get()?.unix_timestamp;
+            if now < subscription.next_due {
+                return Err(CakeError::SubscriptionNotDue.into());
+            }
+
+            let product_id = subscription.product_id;
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_subscription_account, _) =
+                find_subscription_address(cake_account.key, &subscription.buyer, product_id, program_id);
+            let (expected_delegate, delegate_bump) = find_subscription_delegate_address(subscription_account.key, program_id);
+            // A assinatura só pode ser cobrada na loja em que foi criada
+            if *product_account.key != expected_product_account
+                || *subscription_account.key != expected_subscription_account
+                || *delegate.key != expected_delegate
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+            if *buyer_token.key != subscription.buyer_token {
+                return Err(CakeError::UnexpectedAccount.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+            if !product.is_available_at(now) {
+                return Err(CakeError::OutsideAvailabilityWindow.into());
+            }
+            let quantity = subscription.quantity;
+            if quantity > product.stock {
+                return Err(CakeError::InsufficientStock.into());
+            }
+
+            let unit_price = product.unit_price_for(quantity);
+            let total_price = quantity.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+            product.record_sale(quantity, total_price, PAYMENT_MODE_TOKEN)?;
+            check_mint_decimals(payment_mint, mint_decimals)?;
+            let price_decimals = product.price_decimals_or(config.price_decimals);
+            let total_price = convert_price(total_price, price_decimals, mint_decimals)?;
+            let unit_price = convert_price(unit_price, price_decimals, mint_decimals)?;
+            if unit_price > subscription.max_unit_price {
+                return Err(CakeError::PriceSlippageExceeded.into());
+            }
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            let owner_token_data = unpack_token_account(owner_token)?;
+            if buyer_token_data.owner != subscription.buyer {
+                return Err(CakeError::WrongTokenAccountOwner.into());
+            }
+            if buyer_token_data.mint != *payment_mint.key || owner_token_data.mint != *payment_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            pay_tokens(
+                token_program,
+                buyer_token,
+                owner_token,
+                delegate,
+                payment_mint,
+                total_price,
+                &[&[b"subscription_delegate", subscription_account.key.as_ref(), &[delegate_bump]]],
+            )?;
+
+            product.stock -= quantity;
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            create_history_account(
+                payer,
+                cake_account.key,
+                &subscription.buyer,
+                history_account,
+                history_counter_account,
+                system_program,
+                program_id,
+            )?;
+
+            let history_entry = PurchaseHistory {
+                product_id,
+                quantity,
+                total_price,
+                buyer: subscription.buyer,
+                timestamp: now,
+                status: PURCHASE_STATUS_COMPLETED,
+                fee: 0,
+                refunded_quantity: 0,
+                payment_mode: PAYMENT_MODE_TOKEN,
+                rent_payer: *payer.key,
+                tip: 0,
+                donation: 0,
+                unit_price,
+                name_hash: product.name_hash(),
+                has_variant: false,
+                variant_id: 0,
+            };
+            PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+            cake_state.history_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            // Execuções atrasadas não se acumulam: o próximo vencimento conta a partir de agora
+            subscription.next_due = now.checked_add(subscription.interval).ok_or(CakeError::ArithmeticOverflow)?;
+            subscription.executions = subscription.executions.checked_add(1).ok_or(CakeError::ArithmeticOverflow)?;
+            Subscription::pack(subscription, &mut subscription_account.data.borrow_mut())?;
+
+            msg!(
+                "process_subscription: product_id={}, quantity={}, total_price={}, next_due={}",
+                product_id,
+                quantity,
+                total_price,
+                subscription.next_due
+            );
+        }
+        CakeInstruction::CreateGiftCard { code_hash, balance, expires_at } => {
+            msg!("Instrução: create_gift_card");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let gift_card_account = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let treasury_vault = next_account_info(account_iter)?;
+            let mint = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Layout: [code_hash 32][balance u64][expires_at i64]; expires_at = 0 não expira
+            if balance == 0 {
+                return Err(CakeError::InvalidGiftCard.into());
+            }
+
+            let (expected_gift_card_account, bump) = find_gift_card_address(cake_account.key, &code_hash, program_id);
+            let (expected_treasury_vault, _) = find_treasury_vault_address(cake_account.key, mint.key, program_id);
+            if *gift_card_account.key != expected_gift_card_account || *treasury_vault.key != expected_treasury_vault {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let create_gift_card_account_ix = system_instruction::create_account(
+                owner.key,
+                gift_card_account.key,
+                rent.minimum_balance(GiftCard::LEN),
+                GiftCard::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_gift_card_account_ix,
+                &[owner.clone(), gift_card_account.clone(), system_program.clone()],
+                &[&[b"gift_card", cake_account.key.as_ref(), &code_hash, &[bump]]],
+            )?;
+
+            // O saldo fica na tesouraria até ser resgatado numa venda
+            pay_tokens(token_program, owner_token, treasury_vault, owner, mint, balance, &[])?;
+
+            let gift_card = GiftCard { code_hash, mint: *mint.key, balance, expires_at };
+            GiftCard::pack(gift_card, &mut gift_card_account.data.borrow_mut())?;
+
+            msg!("create_gift_card: mint={}, balance={}, expires_at={}", mint.key, balance, expires_at);
+        }
+        CakeInstruction::AddToAllowlist { buyer } | CakeInstruction::AddToBlocklist { buyer } => {
+            let (seed, list_name): (&[u8], &str) = if matches!(instruction, CakeInstruction::AddToAllowlist { .. }) { (b"allowlist", "allowlist") } else { (b"blocklist", "blocklist") };
+            msg!("Instrução: add_to_{}", list_name);
+            let cake_account = next_account_info(account_iter)?;
+            let list_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_list_account, bump) = get_pda(&[seed, cake_account.key.as_ref(), buyer.as_ref()], program_id);
+
+            if *list_account.key != expected_list_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let create_list_account_ix = system_instruction::create_account(
+                payer.key,
+                list_account.key,
+                rent.minimum_balance(BuyerListEntry::LEN),
+                BuyerListEntry::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_list_account_ix,
+                &[payer.clone(), list_account.clone(), system_program.clone()],
+                &[&[seed, cake_account.key.as_ref(), buyer.as_ref(), &[bump]]],
+            )?;
+
+            BuyerListEntry::pack(BuyerListEntry { buyer }, &mut list_account.data.borrow_mut())?;
+
+            msg!("add_to_{}: buyer={}", list_name, buyer);
+        }
+        CakeInstruction::RemoveFromAllowlist { buyer } | CakeInstruction::RemoveFromBlocklist { buyer } => {
+            let (seed, list_name): (&[u8], &str) = if matches!(instruction, CakeInstruction::RemoveFromAllowlist { .. }) { (b"allowlist", "allowlist") } else { (b"blocklist", "blocklist") };
+            msg!("Instrução: remove_from_{}", list_name);
+            let cake_account = next_account_info(account_iter)?;
+            let list_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || list_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_list_account, _) = get_pda(&[seed, cake_account.key.as_ref(), buyer.as_ref()], program_id);
+
+            if *list_account.key != expected_list_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let list_lamports = list_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(list_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **list_account.lamports.borrow_mut() = 0;
+            list_account.data.borrow_mut().fill(0);
+
+            msg!("remove_from_{}: buyer={}", list_name, buyer);
+        }
+        CakeInstruction::SetAllowlistRequired { product_id, required } => {
+            msg!("Instrução: set_allowlist_required");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.allowlist_required = required;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_allowlist_required: product_id={}, allowlist_required={}", product_id, product.allowlist_required);
+        }
+        CakeInstruction::SetGateMint { product_id, gate_mint } => {
+            msg!("Instrução: set_gate_mint");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Pubkey::default() remove a exigência
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.gate_mint = gate_mint;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_gate_mint: product_id={}, gate_mint={}", product_id, product.gate_mint);
+        }
+        CakeInstruction::ExtendAccount { new_len } => {
+            msg!("Instrução: extend_account");
+            let cake_account = next_account_info(account_iter)?;
+            let target_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || target_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Só aumenta a conta; os bytes novos ficam zerados para os campos que vierem depois
+            let current_len = target_account.data_len();
+            let new_len = usize::try_from(new_len).map_err(|_| CakeError::InvalidAccountLength)?;
+            if new_len <= current_len {
+                return Err(CakeError::InvalidAccountLength.into());
+            }
+
+            grow_account(target_account, payer, system_program, new_len)?;
+
+            msg!("extend_account: account={}, old_len={}, new_len={}", target_account.key, current_len, new_len);
+        }
+        CakeInstruction::CloseProduct { product_id, force } => {
+            msg!("Instrução: close_product");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let product_tags_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // O product_counter não é decrementado, então o id nunca é reutilizado
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if product.stock > 0 && !force {
+                return Err(CakeError::ProductHasStock.into());
+            }
+
+            // O produto sai dos índices das suas tags (que vêm em seguida) e a PDA product_tags é fechada junto
+            let (expected_product_tags_account, _) = find_product_tags_address(cake_account.key, product_id, program_id);
+            if *product_tags_account.key != expected_product_tags_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            if !product_tags_account.data_is_empty() {
+                if product_tags_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+                let product_tags = ProductTags::unpack(&product_tags_account.data.borrow())?;
+                remove_from_tag_indexes(cake_account.key, product_id, &product_tags.tags, account_iter, program_id)?;
+
+                let product_tags_lamports = product_tags_account.lamports();
+                **owner.lamports.borrow_mut() = owner.lamports().checked_add(product_tags_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+                **product_tags_account.lamports.borrow_mut() = 0;
+                product_tags_account.data.borrow_mut().fill(0);
+            }
+
+            let product_lamports = product_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(product_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **product_account.lamports.borrow_mut() = 0;
+            product_account.data.borrow_mut().fill(0);
+
+            msg!("close_product: product_id={}, stock={}, lamports={}", product_id, product.stock, product_lamports);
+        }
+        CakeInstruction::SetHistoryRetention { history_retention } => {
+            msg!("Instrução: set_history_retention");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber a retenção
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            if history_retention < 0 {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            config.history_retention = history_retention;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_history_retention: history_retention={}", history_retention);
+        }
+        CakeInstruction::CloseHistory { history_index } => {
+            msg!("Instrução: close_history");
+            let cake_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || history_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            let history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+            check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+
+            // Compras em escrow ou em disputa ainda guardam fundos no cofre e não podem ser fechadas
+            if history_entry.holds_escrow() {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            // Owner e quem pagou o rent só fecham depois de uma retenção configurada, para que o histórico siga
+            // disponível para reembolsos; sem retenção ele fica aberto. Só um histórico final (reembolsado)
+            // pode ser fechado antes pelo rent_payer, porque não há mais o que reembolsar
+            let is_rent_payer =
+                history_entry.rent_payer != Pubkey::default() && *authority.key == history_entry.rent_payer && authority.is_signer;
+            if !is_rent_payer {
+                check_owner(&cake_state, cake_account.key, authority, accounts, program_id)?;
+            }
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let now = Clock::get()?.unix_timestamp;
+            if !(is_rent_payer && history_entry.is_final())
+                && (config.history_retention == 0 || now < history_entry.timestamp.saturating_add(config.history_retention))
+            {
+                return Err(CakeError::HistoryRetentionActive.into());
+            }
+
+            // O rent volta para quem o pagou: quando o owner fecha, o rent_payer vem em seguida e recebe os
+            // lamports. Históricos sem rent_payer gravado continuam indo para o owner
+            let owner_keeps_rent = history_entry.rent_payer == Pubkey::default() || history_entry.rent_payer == *authority.key;
+            let rent_destination = if is_rent_payer || owner_keeps_rent {
+                authority
+            } else {
+                let rent_payer = next_account_info(account_iter)?;
+                if *rent_payer.key != history_entry.rent_payer {
+                    return Err(CakeError::UnexpectedAccount.into());
+                }
+                rent_payer
+            };
+
+            let digest = solana_program::hash::hash(&history_account.data.borrow());
+            let history_lamports = history_account.lamports();
+            **rent_destination.lamports.borrow_mut() =
+                rent_destination.lamports().checked_add(history_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **history_account.lamports.borrow_mut() = 0;
+            history_account.data.borrow_mut().fill(0);
+
+            msg!(
+                "close_history: history_index={}, buyer={}, product_id={}, quantity={}, total_price={}, status={}, digest={}",
+                history_index,
+                history_entry.buyer,
+                history_entry.product_id,
+                history_entry.quantity,
+                history_entry.total_price,
+                history_entry.status,
+                digest
+            );
+        }
+        CakeInstruction::CreateGlobalStats => {
+            msg!("Instrução: create_global_stats");
+            let cake_account = next_account_info(account_iter)?;
+            let global_stats_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_global_stats_account, bump) = find_global_stats_address(program_id);
+            if *global_stats_account.key != expected_global_stats_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let rent = Rent::get()?;
+            let create_global_stats_account_ix = system_instruction::create_account(
+                payer.key,
+                global_stats_account.key,
+                rent.minimum_balance(GlobalStats::LEN),
+                GlobalStats::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_global_stats_account_ix,
+                &[payer.clone(), global_stats_account.clone(), system_program.clone()],
+                &[&[b"global_stats", &[bump]]],
+            )?;
+
+            let global_stats = GlobalStats {
+                total_orders: 0,
+                unique_buyers: 0,
+                mint_count: 0,
+                revenues: [MintRevenue::default(); MAX_STATS_MINTS],
+                buyer_filter: vec![0u8; BUYER_FILTER_BYTES],
+            };
+            GlobalStats::pack(global_stats, &mut global_stats_account.data.borrow_mut())?;
+
+            msg!("create_global_stats: account={}", global_stats_account.key);
+        }
+        CakeInstruction::MigrateState => {
+            msg!("Instrução: migrate_state");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let current_len = cake_account.data_len();
+            if current_len == CakeState::LEN {
+                msg!("migrate_state: já está no layout atual");
+                return Ok(());
+            }
+
+            // Acrescenta o discriminador e o byte is_initialized ao estado dos layouts anteriores
+            let cake_state = if current_len == CakeState::UNFLAGGED_LEN {
+                CakeState::unpack_unflagged(&cake_account.data.borrow())?
+            } else {
+                CakeState::unpack_legacy(&cake_account.data.borrow())?
+            };
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            grow_account(cake_account, payer, system_program, CakeState::LEN)?;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("migrate_state: old_len={}, new_len={}", current_len, CakeState::LEN);
+        }
+        CakeInstruction::ArchiveHistory { archive_id, history_indexes } => {
+            msg!("Instrução: archive_history");
+            let cake_account = next_account_info(account_iter)?;
+            let archive_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_archive_account, archive_bump) = find_history_archive_address(cake_account.key, archive_id, program_id);
+            if *archive_account.key != expected_archive_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Mesma regra do close_history para o owner: só com retenção configurada e já vencida
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            if config.history_retention == 0 {
+                return Err(CakeError::HistoryRetentionActive.into());
+            }
+            let now = Clock::get()?.unix_timestamp;
+
+            // O arquivo é criado antes de mover os lamports dos históricos, que não entram na CPI
+            let create_archive_account_ix = system_instruction::create_account(
+                owner.key,
+                archive_account.key,
+                Rent::get()?.minimum_balance(HistoryArchive::LEN),
+                HistoryArchive::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_archive_account_ix,
+                &[owner.clone(), archive_account.clone(), system_program.clone()],
+                &[&[b"archive", cake_account.key.as_ref(), &archive_id.to_le_bytes(), &[archive_bump]]],
+            )?;
+
+            // Depois dos históricos vêm os rent_payers que não são o owner, uma vez cada, para receberem o rent
+            let history_accounts = next_account_infos(account_iter, history_indexes.len())?;
+            let rent_payers = account_iter.as_slice();
+
+            let mut leaves = Vec::with_capacity(history_indexes.len());
+            for (history_index, history_account) in history_indexes.into_iter().zip(history_accounts) {
+                if history_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+
+                let history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+                check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+                if history_entry.holds_escrow() {
+                    return Err(CakeError::InvalidOrderStatus.into());
+                }
+                if now < history_entry.timestamp.saturating_add(config.history_retention) {
+                    return Err(CakeError::HistoryRetentionActive.into());
+                }
+
+                leaves.push(HistoryArchive::leaf(history_account.key, &history_entry));
+
+                let rent_destination = if history_entry.rent_payer == Pubkey::default() || history_entry.rent_payer == *owner.key {
+                    owner
+                } else {
+                    rent_payers
+                        .iter()
+                        .find(|account| *account.key == history_entry.rent_payer)
+                        .ok_or(ProgramError::NotEnoughAccountKeys)?
+                };
+                let history_lamports = history_account.lamports();
+                **rent_destination.lamports.borrow_mut() =
+                    rent_destination.lamports().checked_add(history_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+                **history_account.lamports.borrow_mut() = 0;
+                history_account.data.borrow_mut().fill(0);
+            }
+
+            let archive = HistoryArchive {
+                archive_id,
+                merkle_root: HistoryArchive::merkle_root(&leaves),
+                leaf_count: leaves.len() as u8,
+                archived_at: now,
+            };
+            HistoryArchive::pack(archive, &mut archive_account.data.borrow_mut())?;
+
+            msg!(
+                "archive_history: archive_id={}, histories={}, root={}",
+                archive_id,
+                archive.leaf_count,
+                solana_program::hash::Hash::new_from_array(archive.merkle_root)
+            );
+        }
+        CakeInstruction::CreateLaunch { product_id, release_at, unit_price, max_quantity } => {
+            msg!("Instrução: create_launch");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let launch_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_launch_account, launch_bump) = find_launch_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account || *launch_account.key != expected_launch_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if max_quantity == 0 {
+                return Err(CakeError::ZeroQuantity.into());
+            }
+            if release_at <= Clock::get()?.unix_timestamp {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            let create_launch_account_ix = system_instruction::create_account(
+                payer.key,
+                launch_account.key,
+                Rent::get()?.minimum_balance(Launch::LEN),
+                Launch::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_launch_account_ix,
+                &[payer.clone(), launch_account.clone(), system_program.clone()],
+                &[&[b"launch", cake_account.key.as_ref(), &product_id.to_le_bytes(), &[launch_bump]]],
+            )?;
+
+            let launch = Launch { product_id, release_at, unit_price, max_quantity, reserved: 0, cancelled: false };
+            Launch::pack(launch, &mut launch_account.data.borrow_mut())?;
+
+            msg!("create_launch: product_id={}, release_at={}, max_quantity={}", product_id, release_at, max_quantity);
+        }
+        CakeInstruction::PlacePreOrder { product_id, quantity } => {
+            msg!("Instrução: place_pre_order");
+            let cake_account = next_account_info(account_iter)?;
+            let launch_account = next_account_info(account_iter)?;
+            let pre_order_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || launch_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let (expected_launch_account, _) = find_launch_address(cake_account.key, product_id, program_id);
+            let (expected_pre_order_account, pre_order_bump) = find_pre_order_address(cake_account.key, product_id, buyer.key, program_id);
+            let (expected_vault, vault_bump) = find_pre_order_vault_address(pre_order_account.key, program_id);
+            let (expected_escrow_authority, _) = find_escrow_authority_address(program_id);
+
+            if *launch_account.key != expected_launch_account
+                || *pre_order_account.key != expected_pre_order_account
+                || *vault.key != expected_vault
+                || *escrow_authority.key != expected_escrow_authority
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut launch = Launch::unpack(&launch_account.data.borrow())?;
+            let now = Clock::get()?.unix_timestamp;
+            if launch.cancelled || now >= launch.release_at {
+                return Err(CakeError::PreOrderClosed.into());
+            }
+
+            // A blocklist e a allowlist/gate_mint do produto valem também na pré-encomenda: depois da
+            // configuração vêm a PDA da blocklist, o produto e as contas de check_product_gates
+            let blocklist_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            check_not_blocked(cake_account.key, buyer.key, blocklist_account, program_id)?;
+            if *product_account.key != find_product_address(cake_account.key, product_id, program_id).0 {
+                return Err(CakeError::WrongPda.into());
+            }
+            if product_account.owner != program_id {
+                return Err(CakeError::ProductNotFound.into());
+            }
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            check_product_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+            let reserved = launch.reserved.checked_add(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+            if quantity == 0 || reserved > launch.max_quantity {
+                return Err(CakeError::InsufficientStock.into());
+            }
+
+            let total_price = quantity.checked_mul(launch.unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+            check_mint_decimals(payment_mint, mint_decimals)?;
+            let total_price = convert_price(total_price, config.price_decimals, mint_decimals)?;
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            if buyer_token_data.mint != *payment_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            let create_pre_order_account_ix = system_instruction::create_account(
+                buyer.key,
+                pre_order_account.key,
+                Rent::get()?.minimum_balance(PreOrder::LEN),
+                PreOrder::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_pre_order_account_ix,
+                &[buyer.clone(), pre_order_account.clone(), system_program.clone()],
+                &[&[b"pre_order", cake_account.key.as_ref(), &product_id.to_le_bytes(), buyer.key.as_ref(), &[pre_order_bump]]],
+            )?;
+
+            create_program_vault(
+                buyer,
+                vault,
+                payment_mint,
+                escrow_authority,
+                system_program,
+                token_program,
+                &[b"pre_order_vault", pre_order_account.key.as_ref(), &[vault_bump]],
+            )?;
+
+            pay_tokens(token_program, buyer_token, vault, buyer, payment_mint, total_price, &[])?;
+
+            let pre_order = PreOrder {
+                product_id,
+                buyer: *buyer.key,
+                quantity,
+                total_price,
+                payment_mint: *payment_mint.key,
+                created_at: now,
+                status: ORDER_STATUS_PLACED,
+            };
+            PreOrder::pack(pre_order, &mut pre_order_account.data.borrow_mut())?;
+
+            launch.reserved = reserved;
+            Launch::pack(launch, &mut launch_account.data.borrow_mut())?;
+
+            msg!("place_pre_order: product_id={}, quantity={}, total_price={}", product_id, quantity, total_price);
+        }
+        CakeInstruction::ConvertPreOrder { product_id, buyer: buyer_key }
+        | CakeInstruction::RefundPreOrder { product_id, buyer: buyer_key } => {
+            let convert = matches!(instruction, CakeInstruction::ConvertPreOrder { .. });
+            if convert {
+                msg!("Instrução: convert_pre_order");
+            } else {
+                msg!("Instrução: refund_pre_order");
+            }
+            let cake_account = next_account_info(account_iter)?;
+            let launch_account = next_account_info(account_iter)?;
+            let pre_order_account = next_account_info(account_iter)?;
+            let authority = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || launch_account.owner != program_id || pre_order_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            // Conversão é do owner; o reembolso também pode ser pedido pelo comprador
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            let is_buyer = !convert && *authority.key == buyer_key && authority.is_signer;
+            if !is_buyer {
+                check_owner(&cake_state, cake_account.key, authority, accounts, program_id)?;
+            }
+
+            let (expected_launch_account, _) = find_launch_address(cake_account.key, product_id, program_id);
+            let (expected_pre_order_account, _) = find_pre_order_address(cake_account.key, product_id, &buyer_key, program_id);
+            let (expected_vault, _) = find_pre_order_vault_address(pre_order_account.key, program_id);
+            let (expected_escrow_authority, escrow_bump) = find_escrow_authority_address(program_id);
+
+            if *launch_account.key != expected_launch_account
+                || *pre_order_account.key != expected_pre_order_account
+                || *vault.key != expected_vault
+                || *escrow_authority.key != expected_escrow_authority
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+            if *buyer.key != buyer_key {
+                return Err(CakeError::UnexpectedAccount.into());
+            }
+
+            let mut launch = Launch::unpack(&launch_account.data.borrow())?;
+            let mut pre_order = PreOrder::unpack(&pre_order_account.data.borrow())?;
+            if pre_order.status != ORDER_STATUS_PLACED {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            let now = Clock::get()?.unix_timestamp;
+            if convert {
+                if launch.cancelled {
+                    return Err(CakeError::PreOrderClosed.into());
+                }
+                if now < launch.release_at {
+                    return Err(CakeError::LaunchNotReleased.into());
+                }
+            } else if !launch.cancelled {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            // Conversão paga o owner (ou a tesouraria); reembolso devolve ao comprador
+            let destination_token_data = unpack_token_account(destination_token)?;
+            let valid_destination = if convert {
+                is_revenue_destination(destination_token, &destination_token_data, cake_account.key, program_id)
+            } else {
+                destination_token_data.owner == pre_order.buyer
+            };
+            if destination_token_data.mint != pre_order.payment_mint {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !valid_destination {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            if convert {
+                let product_account = next_account_info(account_iter)?;
+                let history_account = next_account_info(account_iter)?;
+                let history_counter_account = next_account_info(account_iter)?;
+                let payer = next_account_info(account_iter)?;
+                let system_program = next_system_program(account_iter)?;
+
+                let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+                if product_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+                if *product_account.key != expected_product_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+
+                let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+                product.record_sale(pre_order.quantity, pre_order.total_price, PAYMENT_MODE_TOKEN)?;
+                Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+                create_history_account(
+                    payer,
+                    cake_account.key,
+                    &pre_order.buyer,
+                    history_account,
+                    history_counter_account,
+                    system_program,
+                    program_id,
+                )?;
+
+                let history_entry = PurchaseHistory {
+                    product_id,
+                    quantity: pre_order.quantity,
+                    total_price: pre_order.total_price,
+                    buyer: pre_order.buyer,
+                    timestamp: now,
+                    status: PURCHASE_STATUS_COMPLETED,
+                    fee: 0,
+                    refunded_quantity: 0,
+                    payment_mode: PAYMENT_MODE_TOKEN,
+                    rent_payer: *payer.key,
+                    tip: 0,
+                    donation: 0,
+                    // Preço da reserva, já convertido para o mint no PlacePreOrder
+                    unit_price: pre_order.total_price.checked_div(pre_order.quantity).unwrap_or(0),
+                    name_hash: product.name_hash(),
+                    has_variant: false,
+                    variant_id: 0,
+                };
+                PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+                cake_state.history_counter += 1;
+                CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+                pre_order.status = ORDER_STATUS_FULFILLED;
+            } else {
+                launch.reserved = launch.reserved.saturating_sub(pre_order.quantity);
+                Launch::pack(launch, &mut launch_account.data.borrow_mut())?;
+
+                pre_order.status = ORDER_STATUS_CANCELLED;
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            release_escrow_vault(vault, destination_token, buyer, escrow_authority, token_program, mint, pre_order.total_price, escrow_bump)?;
+
+            PreOrder::pack(pre_order, &mut pre_order_account.data.borrow_mut())?;
+
+            msg!("pre_order_status: product_id={}, buyer={}, status={}", product_id, buyer_key, pre_order.status);
+        }
+        CakeInstruction::CancelLaunch { product_id } => {
+            msg!("Instrução: cancel_launch");
+            let cake_account = next_account_info(account_iter)?;
+            let launch_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || launch_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_launch_account, _) = find_launch_address(cake_account.key, product_id, program_id);
+            if *launch_account.key != expected_launch_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let mut launch = Launch::unpack(&launch_account.data.borrow())?;
+            launch.cancelled = true;
+            Launch::pack(launch, &mut launch_account.data.borrow_mut())?;
+
+            msg!("cancel_launch: product_id={}, reserved={}", product_id, launch.reserved);
+        }
+        CakeInstruction::ScheduleFlashSale { product_id, discount_bps, start, end } => {
+            msg!("Instrução: schedule_flash_sale");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let flash_sale_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_flash_sale_account, flash_sale_bump) = find_flash_sale_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account || *flash_sale_account.key != expected_flash_sale_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if discount_bps == 0 || discount_bps > 10_000 {
+                return Err(CakeError::InvalidBps.into());
+            }
+            if start >= end || end <= Clock::get()?.unix_timestamp {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            // Reagendar sobrescreve a promoção existente
+            if flash_sale_account.data_is_empty() {
+                let create_flash_sale_account_ix = system_instruction::create_account(
+                    payer.key,
+                    flash_sale_account.key,
+                    Rent::get()?.minimum_balance(FlashSale::LEN),
+                    FlashSale::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_flash_sale_account_ix,
+                    &[payer.clone(), flash_sale_account.clone(), system_program.clone()],
+                    &[&[b"flash_sale", cake_account.key.as_ref(), &product_id.to_le_bytes(), &[flash_sale_bump]]],
+                )?;
+            } else if flash_sale_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let flash_sale = FlashSale { product_id, discount_bps, start, end };
+            FlashSale::pack(flash_sale, &mut flash_sale_account.data.borrow_mut())?;
+
+            msg!("schedule_flash_sale: product_id={}, discount_bps={}, start={}, end={}", product_id, discount_bps, start, end);
+        }
+        CakeInstruction::CancelFlashSale { product_id } => {
+            msg!("Instrução: cancel_flash_sale");
+            let cake_account = next_account_info(account_iter)?;
+            let flash_sale_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || flash_sale_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_flash_sale_account, _) = find_flash_sale_address(cake_account.key, product_id, program_id);
+            if *flash_sale_account.key != expected_flash_sale_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let flash_sale_lamports = flash_sale_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(flash_sale_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **flash_sale_account.lamports.borrow_mut() = 0;
+            flash_sale_account.data.borrow_mut().fill(0);
+
+            msg!("cancel_flash_sale: product_id={}", product_id);
+        }
+        CakeInstruction::SetAvailabilityWindow { product_id, available_from, available_until } => {
+            msg!("Instrução: set_availability_window");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if available_from != 0 && available_until != 0 && available_from > available_until {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            // Contas de layout fixo ou anteriores aos campos precisam de migrate_product/extend_account antes
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.available_from = available_from;
+            product.available_until = available_until;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_availability_window: product_id={}, from={}, until={}", product_id, available_from, available_until);
+        }
+        CakeInstruction::SetPriceDecimals { product_id, price_decimals } => {
+            msg!("Instrução: set_price_decimals");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Contas de layout fixo precisam de migrate_product antes
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            product.price_decimals = price_decimals;
+            Product::pack_versioned(&product, &mut product_account.data.borrow_mut())?;
+
+            msg!("set_price_decimals: product_id={}, price_decimals={}", product_id, price_decimals);
+        }
+        CakeInstruction::SetBuyerProfile { display_name_hash, preferred_mint, contact_hash } => {
+            msg!("Instrução: set_buyer_profile");
+            let buyer_profile_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+
+            let (expected_buyer_profile_account, buyer_profile_bump) = find_buyer_profile_address(buyer.key, program_id);
+            if *buyer_profile_account.key != expected_buyer_profile_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Atualizar mantém o gasto acumulado
+            let mut buyer_profile = if buyer_profile_account.data_is_empty() {
+                let create_buyer_profile_account_ix = system_instruction::create_account(
+                    buyer.key,
+                    buyer_profile_account.key,
+                    Rent::get()?.minimum_balance(BuyerProfile::LEN),
+                    BuyerProfile::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_buyer_profile_account_ix,
+                    &[buyer.clone(), buyer_profile_account.clone(), system_program.clone()],
+                    &[&[b"buyer_profile", buyer.key.as_ref(), &[buyer_profile_bump]]],
+                )?;
+
+                BuyerProfile {
+                    buyer: *buyer.key,
+                    display_name_hash,
+                    preferred_mint,
+                    contact_hash,
+                    total_spent: 0,
+                    total_spent_sol: 0,
+                }
+            } else {
+                if buyer_profile_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+                BuyerProfile::unpack(&buyer_profile_account.data.borrow())?
+            };
+            buyer_profile.display_name_hash = display_name_hash;
+            buyer_profile.preferred_mint = preferred_mint;
+            buyer_profile.contact_hash = contact_hash;
+            BuyerProfile::pack(buyer_profile, &mut buyer_profile_account.data.borrow_mut())?;
+
+            msg!("set_buyer_profile: buyer={}, preferred_mint={}", buyer.key, preferred_mint);
+        }
+        CakeInstruction::UpdateOrderStatus { order_id, status } => {
+            msg!("Instrução: update_order_status");
+            let cake_account = next_account_info(account_iter)?;
+            let order_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || order_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_order_account, _) = find_order_address(cake_account.key, order_id, program_id);
+            if *order_account.key != expected_order_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Os estados que movem o cofre têm instruções próprias
+            let mut order = Order::unpack_versioned(&order_account.data.borrow())?;
+            if !matches!(status, ORDER_STATUS_PREPARING | ORDER_STATUS_OUT_FOR_DELIVERY) || !order.can_transition(status) {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+            order.status = status;
+            Order::pack_versioned(order, &mut order_account.data.borrow_mut())?;
+
+            msg!("order_status: order_id={}, status={}", order_id, order.status);
+        }
+        CakeInstruction::CreateStore { store_id } => {
+            msg!("Instrução: create_store");
+            let store_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if !owner.is_signer {
+                return Err(CakeError::OwnerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            let (expected_store_account, bump) = find_store_address(store_id, program_id);
+            if *store_account.key != expected_store_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            check_uninitialized(store_account)?;
+
+            let rent = Rent::get()?;
+            let create_store_account_ix = system_instruction::create_account(
+                payer.key,
+                store_account.key,
+                rent.minimum_balance(CakeState::LEN),
+                CakeState::LEN as u64,
+                program_id,
+            );
+
+            invoke_signed(
+                &create_store_account_ix,
+                &[payer.clone(), store_account.clone(), system_program.clone()],
+                &[&[b"store", &store_id.to_le_bytes(), &[bump]]],
+            )?;
+
+            let cake_state = CakeState {
+                owner: *owner.key,
+                product_counter: 0,
+                history_counter: 0,
+                max_price_change_bps: 0,
+                paused: false,
+                order_counter: 0,
+                category_counter: 0,
+                fee_bps: 0,
+                fee_destination: Pubkey::default(),
+                escrow_timeout: 0,
+                commission_bps: 0,
+                commission_authority: Pubkey::default(),
+                commission_account: Pubkey::default(),
+                is_initialized: true,
+            };
+            CakeState::pack(cake_state, &mut store_account.data.borrow_mut())?;
+
+            create_store_revenue_account(payer, store_account.key, system_program, account_iter, program_id)?;
+
+            msg!("create_store: store_id={}, store={}, owner={}", store_id, store_account.key, owner.key);
+        }
+        CakeInstruction::SetOwnerSet { threshold, members } => {
+            msg!("Instrução: set_owner_set");
+            let cake_account = next_account_info(account_iter)?;
+            let owner_set_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            // Trocar o conjunto exige a aprovação do owner atual, que pode ser o próprio conjunto
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_owner_set_account, owner_set_bump) = find_owner_set_address(cake_account.key, program_id);
+            if *owner_set_account.key != expected_owner_set_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let owner_set = OwnerSet { store: *cake_account.key, threshold, members };
+            if !owner_set.is_valid() {
+                return Err(CakeError::InvalidOwnerSet.into());
+            }
+
+            if owner_set_account.data_is_empty() {
+                let rent = Rent::get()?;
+                let create_owner_set_account_ix = system_instruction::create_account(
+                    payer.key,
+                    owner_set_account.key,
+                    rent.minimum_balance(OwnerSet::LEN),
+                    OwnerSet::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_owner_set_account_ix,
+                    &[payer.clone(), owner_set_account.clone(), system_program.clone()],
+                    &[&[b"owner_set", cake_account.key.as_ref(), &[owner_set_bump]]],
+                )?;
+            } else if owner_set_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let member_count = owner_set.members.len();
+            OwnerSet::pack(owner_set, &mut owner_set_account.data.borrow_mut())?;
+            cake_state.owner = *owner_set_account.key;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("set_owner_set: threshold={}, members={}", threshold, member_count);
+        }
+        CakeInstruction::CreateReferral { code_hash, referrer, mint } => {
+            msg!("Instrução: create_referral");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let referral_account = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            check_payer(payer)?;
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Layout: [code_hash 32][referrer 32][mint 32]
+            let (expected_referral_account, bump) = find_referral_address(cake_account.key, &code_hash, program_id);
+            if *referral_account.key != expected_referral_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            check_uninitialized(referral_account)?;
+
+            let create_referral_account_ix = system_instruction::create_account(
+                payer.key,
+                referral_account.key,
+                Rent::get()?.minimum_balance(Referral::LEN),
+                Referral::LEN as u64,
+                program_id,
+            );
+            invoke_signed(
+                &create_referral_account_ix,
+                &[payer.clone(), referral_account.clone(), system_program.clone()],
+                &[&[b"referral", cake_account.key.as_ref(), &code_hash, &[bump]]],
+            )?;
+
+            let referral = Referral { code_hash, referrer, mint, pending: 0, total_earned: 0 };
+            Referral::pack(referral, &mut referral_account.data.borrow_mut())?;
+
+            msg!("create_referral: referrer={}, mint={}", referrer, mint);
+        }
+        CakeInstruction::SetReferralBps { referral_bps } => {
+            msg!("Instrução: set_referral_bps");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber o percentual
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            if referral_bps > 10_000 {
+                return Err(CakeError::InvalidBps.into());
+            }
+
+            config.referral_bps = referral_bps;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_referral_bps: referral_bps={}", referral_bps);
+        }
+        CakeInstruction::ClaimReferralRewards => {
+            msg!("Instrução: claim_referral_rewards");
+            let cake_account = next_account_info(account_iter)?;
+            let referral_account = next_account_info(account_iter)?;
+            let referrer = next_account_info(account_iter)?;
+            let referrer_token = next_account_info(account_iter)?;
+            let treasury_vault = next_account_info(account_iter)?;
+            let treasury_authority = next_account_info(account_iter)?;
+            let mint = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || referral_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut referral = Referral::unpack(&referral_account.data.borrow())?;
+            let (expected_referral_account, _) = find_referral_address(cake_account.key, &referral.code_hash, program_id);
+            if *referral_account.key != expected_referral_account {
+                return Err(CakeError::InvalidReferral.into());
+            }
+            if referral.referrer != *referrer.key {
+                return Err(CakeError::Unauthorized.into());
+            }
+            if !referrer.is_signer {
+                return Err(CakeError::ReferrerSignatureRequired.into());
+            }
+            if referral.pending == 0 {
+                return Err(CakeError::NoReferralRewards.into());
+            }
+
+            let (expected_treasury_vault, _) = find_treasury_vault_address(cake_account.key, &referral.mint, program_id);
+            let (expected_treasury_authority, treasury_bump) = find_treasury_authority_address(program_id);
+            if *treasury_vault.key != expected_treasury_vault || *treasury_authority.key != expected_treasury_authority {
+                return Err(CakeError::WrongPda.into());
+            }
+            if *mint.key != referral.mint {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            // O saldo é zerado antes da CPI; se a transferência falhar a transação inteira é revertida
+            let amount = referral.pending;
+            referral.pending = 0;
+            Referral::pack(referral, &mut referral_account.data.borrow_mut())?;
+
+            transfer_tokens(
+                token_program,
+                treasury_vault,
+                referrer_token,
+                treasury_authority,
+                Some(mint),
+                amount,
+                &[&[b"treasury", &[treasury_bump]]],
+            )?;
+
+            msg!("claim_referral_rewards: referrer={}, amount={}", referrer.key, amount);
+        }
+        CakeInstruction::SetTipAccount { tip_account } => {
+            msg!("Instrução: set_tip_account");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber a conta
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+
+            config.tip_account = tip_account;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_tip_account: tip_account={}", tip_account);
+        }
+        CakeInstruction::SetCharity { charity_account, round_up_unit } => {
+            msg!("Instrução: set_charity");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber a caridade
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            // Arredondar sem conta de destino deixaria o sell sempre falhando
+            if round_up_unit > 0 && charity_account == Pubkey::default() {
+                return Err(CakeError::CharityAccountRequired.into());
+            }
+
+            config.charity_account = charity_account;
+            config.round_up_unit = round_up_unit;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_charity: charity_account={}, round_up_unit={}", charity_account, round_up_unit);
+        }
+        CakeInstruction::ExecuteDelegatedPurchase { product_id, amount } => {
+            msg!("Instrução: execute_delegated_purchase");
+            process_delegated_purchase(program_id, accounts, product_id, amount, None)?;
+        }
+        CakeInstruction::ExecuteSignedOrder { order } => {
+            msg!("Instrução: execute_signed_order");
+            process_delegated_purchase(program_id, accounts, order.product_id, order.quantity, Some(&order))?;
+        }
+        CakeInstruction::GetFullQuote { product_id, amount, coupon_code, variant_id } => {
+            msg!("Instrução: get_full_quote");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let flash_sale_account = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_flash_sale_account, _) = find_flash_sale_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account || *flash_sale_account.key != expected_flash_sale_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+            check_mint_decimals(payment_mint, mint_decimals)?;
+
+            let product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+
+            // Mesma ordem do sell: preço da variante ou da faixa, promoção relâmpago e então cupom
+            let unit_price = if let Some(variant_id) = variant_id {
+                let variant_account = next_account_info(account_iter)?;
+                let (expected_variant_account, _) = find_variant_address(cake_account.key, product_id, variant_id, program_id);
+                if *variant_account.key != expected_variant_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if variant_account.owner != program_id {
+                    return Err(CakeError::IncorrectProgramId.into());
+                }
+                let variant = Variant::unpack(&variant_account.data.borrow())?;
+                if amount > variant.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                variant.price
+            } else {
+                if amount > product.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                product.unit_price_for(amount)
+            };
+            let subtotal = amount.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+            let mut total_price = subtotal;
+
+            let coupon_account = if coupon_code.is_some() { Some(next_account_info(account_iter)?) } else { None };
+            // A PDA do happy hour vem por último e é opcional, para os clientes anteriores a ela
+            let happy_hour = match account_iter.next() {
+                Some(happy_hour_account) => {
+                    let (expected_happy_hour_account, _) = find_happy_hour_address(cake_account.key, product_id, program_id);
+                    if *happy_hour_account.key != expected_happy_hour_account {
+                        return Err(CakeError::WrongPda.into());
+                    }
+                    if happy_hour_account.owner == program_id && !happy_hour_account.data_is_empty() {
+                        Some(HappyHour::unpack(&happy_hour_account.data.borrow())?)
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+
+            // Mesma regra do sell: vale o maior desconto entre a promoção relâmpago e o happy hour
+            let now = Clock::get()?.unix_timestamp;
+            let flash_sale_discount = if flash_sale_account.owner == program_id && !flash_sale_account.data_is_empty() {
+                FlashSale::unpack(&flash_sale_account.data.borrow())?.discount(total_price, now)
+            } else {
+                0
+            };
+            let happy_hour_discount = happy_hour.map_or(0, |happy_hour| happy_hour.discount(total_price, now));
+            total_price -= flash_sale_discount.max(happy_hour_discount);
+
+            if let (Some(code), Some(coupon_account)) = (coupon_code.as_deref(), coupon_account) {
+                let code_hash = solana_program::hash::hash(code).to_bytes();
+                let (expected_coupon_account, _) = find_coupon_address(cake_account.key, &code_hash, program_id);
+                if *coupon_account.key != expected_coupon_account || coupon_account.owner != program_id {
+                    return Err(CakeError::InvalidCoupon.into());
+                }
+                let coupon = Coupon::unpack(&coupon_account.data.borrow())?;
+                if coupon.code_hash != code_hash {
+                    return Err(CakeError::InvalidCoupon.into());
+                }
+                if coupon.expires_at != 0 && now > coupon.expires_at {
+                    return Err(CakeError::CouponExpired.into());
+                }
+                if coupon.max_uses != 0 && coupon.uses >= coupon.max_uses {
+                    return Err(CakeError::CouponExhausted.into());
+                }
+                total_price -= coupon.discount(total_price)?;
+            }
+
+            let price_decimals = product.price_decimals_or(config.price_decimals);
+            let subtotal = convert_price(subtotal, price_decimals, mint_decimals)?;
+            let total_price = convert_price(total_price, price_decimals, mint_decimals)?;
+            let fee = service_fee(&cake_state, total_price);
+            let quote = Quote {
+                subtotal,
+                discount: subtotal.saturating_sub(total_price),
+                total_price,
+                fee,
+                amount_due: total_price.checked_add(fee).ok_or(CakeError::ArithmeticOverflow)?,
+            };
+            set_return_data(&quote.to_bytes());
+
+            msg!("get_full_quote: product_id={}, amount={}, total_price={}, fee={}", product_id, amount, total_price, fee);
+        }
+        CakeInstruction::SetLocalization { product_id, lang_code, name, description } => {
+            msg!("Instrução: set_localization");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let localization_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            check_payer(payer)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_localization_account, localization_bump) =
+                find_localization_address(cake_account.key, product_id, &lang_code, program_id);
+            if *product_account.key != expected_product_account || *localization_account.key != expected_localization_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if !is_valid_lang_code(&lang_code) {
+                return Err(CakeError::InvalidLanguageCode.into());
+            }
+            if name.is_empty() {
+                return Err(CakeError::EmptyLocalizedName.into());
+            }
+
+            // Definir de novo substitui a tradução existente
+            if localization_account.data_is_empty() {
+                let create_localization_account_ix = system_instruction::create_account(
+                    payer.key,
+                    localization_account.key,
+                    Rent::get()?.minimum_balance(Localization::LEN),
+                    Localization::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_localization_account_ix,
+                    &[payer.clone(), localization_account.clone(), system_program.clone()],
+                    &[&[b"localization", cake_account.key.as_ref(), &product_id.to_le_bytes(), &lang_code, &[localization_bump]]],
+                )?;
+            } else if localization_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let localization = Localization { product_id, lang_code, name, description };
+            Localization::pack(localization, &mut localization_account.data.borrow_mut())?;
+
+            msg!("set_localization: product_id={}, lang_code={}", product_id, String::from_utf8_lossy(&lang_code));
+        }
+        CakeInstruction::RemoveLocalization { product_id, lang_code } => {
+            msg!("Instrução: remove_localization");
+            let cake_account = next_account_info(account_iter)?;
+            let localization_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || localization_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_localization_account, _) = find_localization_address(cake_account.key, product_id, &lang_code, program_id);
+            if *localization_account.key != expected_localization_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let localization_lamports = localization_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(localization_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **localization_account.lamports.borrow_mut() = 0;
+            localization_account.data.borrow_mut().fill(0);
+
+            msg!("remove_localization: product_id={}, lang_code={}", product_id, String::from_utf8_lossy(&lang_code));
+        }
+        CakeInstruction::SetProductTags { product_id, tags } => {
+            msg!("Instrução: set_product_tags");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let product_tags_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            check_payer(payer)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let current_tags =
+                load_or_create_product_tags(payer, cake_account.key, product_tags_account, system_program, product_id, program_id)?;
+
+            // Índices das tags removidas e depois das novas vêm após o system program; tags mantidas não mudam
+            let removed: Vec<[u8; TAG_LEN]> = current_tags.iter().filter(|tag| !tags.contains(tag)).copied().collect();
+            let added: Vec<[u8; TAG_LEN]> = tags.iter().filter(|tag| !current_tags.contains(tag)).copied().collect();
+            remove_from_tag_indexes(cake_account.key, product_id, &removed, account_iter, program_id)?;
+            add_to_tag_indexes(payer, cake_account.key, system_program, product_id, &added, account_iter, program_id)?;
+
+            let tag_count = tags.len();
+            ProductTags::pack(ProductTags { product_id, tags }, &mut product_tags_account.data.borrow_mut())?;
+
+            msg!("set_product_tags: product_id={}, tags={}", product_id, tag_count);
+        }
+        CakeInstruction::SetArbiter { arbiter, dispute_window } => {
+            msg!("Instrução: set_arbiter");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // Configs antigas devem ser migradas por set_config antes de receber o árbitro
+            let mut config = load_config(config_account, cake_account.key, program_id)?;
+            if config_account.data_len() != Config::LEN {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            // Com árbitro, um prazo zerado impediria qualquer disputa
+            if arbiter != Pubkey::default() && dispute_window <= 0 {
+                return Err(CakeError::InvalidTimeRange.into());
+            }
+
+            config.arbiter = arbiter;
+            config.dispute_window = dispute_window;
+            Config::pack(config, &mut config_account.data.borrow_mut())?;
+
+            msg!("set_arbiter: arbiter={}, dispute_window={}", arbiter, dispute_window);
+        }
+        CakeInstruction::OpenDispute { history_index } => {
+            msg!("Instrução: open_dispute");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let dispute_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || history_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            if config.arbiter == Pubkey::default() {
+                return Err(CakeError::DisputesDisabled.into());
+            }
+
+            let mut history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+            if *buyer.key != history_entry.buyer {
+                return Err(CakeError::Unauthorized.into());
+            }
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+            check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+            if history_entry.status != PURCHASE_STATUS_ESCROWED {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            let now = Clock::get()?.unix_timestamp;
+            if now > history_entry.timestamp.saturating_add(config.dispute_window) {
+                return Err(CakeError::DisputeWindowClosed.into());
+            }
+
+            // A PDA existe desde a primeira disputa, então a compra não pode ser contestada de novo
+            let (expected_dispute_account, dispute_bump) = find_dispute_address(history_account.key, program_id);
+            if *dispute_account.key != expected_dispute_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            check_uninitialized(dispute_account)?;
+
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    dispute_account.key,
+                    Rent::get()?.minimum_balance(Dispute::LEN),
+                    Dispute::LEN as u64,
+                    program_id,
+                ),
+                &[payer.clone(), dispute_account.clone(), system_program.clone()],
+                &[&[b"dispute", history_account.key.as_ref(), &[dispute_bump]]],
+            )?;
+
+            let dispute = Dispute {
+                history: *history_account.key,
+                buyer: *buyer.key,
+                opened_at: now,
+                status: DISPUTE_STATUS_OPEN,
+                arbiter: Pubkey::default(),
+                resolved_at: 0,
+            };
+            Dispute::pack(dispute, &mut dispute_account.data.borrow_mut())?;
+
+            // Com o status de disputa, confirm_delivery, release_after_timeout e refund_escrow recusam a compra
+            history_entry.status = PURCHASE_STATUS_DISPUTED;
+            PurchaseHistory::pack_versioned(history_entry, &mut history_account.data.borrow_mut())?;
+
+            CakeEvent::DisputeOpened(DisputeOpened {
+                history_index,
+                product_id: history_entry.product_id,
+                buyer: *buyer.key,
+                amount: history_entry.total_price,
+            })
+            .emit();
+        }
+        CakeInstruction::ResolveDispute { history_index, refund } => {
+            msg!("Instrução: resolve_dispute");
+            let cake_account = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let dispute_account = next_account_info(account_iter)?;
+            let arbiter = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id || history_account.owner != program_id || dispute_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            if config.arbiter == Pubkey::default() {
+                return Err(CakeError::DisputesDisabled.into());
+            }
+            if *arbiter.key != config.arbiter {
+                return Err(CakeError::Unauthorized.into());
+            }
+            if !arbiter.is_signer {
+                return Err(CakeError::ArbiterSignatureRequired.into());
+            }
+
+            let mut history_entry = PurchaseHistory::unpack_versioned(&history_account.data.borrow())?;
+            check_history_address(history_account, cake_account.key, &history_entry, history_index, program_id)?;
+            if *dispute_account.key != find_dispute_address(history_account.key, program_id).0 {
+                return Err(CakeError::WrongPda.into());
+            }
+            let mut dispute = Dispute::unpack(&dispute_account.data.borrow())?;
+            if dispute.status != DISPUTE_STATUS_OPEN || history_entry.status != PURCHASE_STATUS_DISPUTED {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            settle_escrow(
+                cake_account.key,
+                product_account,
+                history_account,
+                &mut history_entry,
+                buyer,
+                vault,
+                destination_token,
+                escrow_authority,
+                token_program,
+                mint,
+                refund,
+                account_iter,
+                program_id,
+            )?;
+            PurchaseHistory::pack_versioned(history_entry, &mut history_account.data.borrow_mut())?;
+
+            dispute.status = if refund { DISPUTE_STATUS_REFUNDED } else { DISPUTE_STATUS_RELEASED };
+            dispute.arbiter = *arbiter.key;
+            dispute.resolved_at = Clock::get()?.unix_timestamp;
+            Dispute::pack(dispute, &mut dispute_account.data.borrow_mut())?;
+
+            CakeEvent::DisputeResolved(DisputeResolved {
+                history_index,
+                product_id: history_entry.product_id,
+                buyer: history_entry.buyer,
+                arbiter: *arbiter.key,
+                status: dispute.status,
+                amount: history_entry.total_price,
+            })
+            .emit();
+        }
+        CakeInstruction::PartialFulfillOrder { order_id, quantity } => {
+            msg!("Instrução: partial_fulfill_order");
+            let cake_account = next_account_info(account_iter)?;
+            let order_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let vault = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let escrow_authority = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || order_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            check_payer(payer)?;
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_order_account, _) = find_order_address(cake_account.key, order_id, program_id);
+            let (expected_vault, _) = find_order_vault_address(cake_account.key, order_id, program_id);
+            let (expected_escrow_authority, escrow_bump) = find_escrow_authority_address(program_id);
+
+            if *order_account.key != expected_order_account
+                || *vault.key != expected_vault
+                || *escrow_authority.key != expected_escrow_authority
+            {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Só enquanto o pedido ainda pode ser retirado; a última parte sai por fulfill_order, que fecha o cofre
+            let mut order = Order::unpack_versioned(&order_account.data.borrow())?;
+            if !order.can_transition(ORDER_STATUS_FULFILLED) {
+                return Err(CakeError::InvalidOrderStatus.into());
+            }
+            if quantity >= order.remaining_quantity() {
+                return Err(CakeError::InvalidQuantity.into());
+            }
+
+            let destination_token_data = unpack_token_account(destination_token)?;
+            if destination_token_data.mint != order.payment_mint {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !is_revenue_destination(destination_token, &destination_token_data, cake_account.key, program_id) {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            let amount = order.release_amount_for(quantity);
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            let escrow_seeds: &[&[u8]] = &[b"escrow", &[escrow_bump]];
+            transfer_tokens(token_program, vault, destination_token, escrow_authority, mint, amount, &[escrow_seeds])?;
+
+            // Pedidos antigos crescem para guardar fulfilled_quantity
+            if order_account.data_len() != Order::LEN {
+                grow_account(order_account, payer, system_program, Order::LEN)?;
+            }
+            order.fulfilled_quantity += quantity;
+            Order::pack_versioned(order, &mut order_account.data.borrow_mut())?;
+
+            msg!(
+                "partial_fulfill_order: order_id={}, quantity={}, amount={}, remaining_quantity={}",
+                order_id,
+                quantity,
+                amount,
+                order.remaining_quantity()
+            );
+        }
+        CakeInstruction::SetBundle { bundle_id, price, items } => {
+            msg!("Instrução: set_bundle");
+            let cake_account = next_account_info(account_iter)?;
+            let bundle_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_bundle_account, bundle_bump) = find_bundle_address(cake_account.key, bundle_id, program_id);
+            if *bundle_account.key != expected_bundle_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            // Cada produto aparece uma vez; a quantidade por combo vai no próprio item
+            let repeated = items.iter().enumerate().any(|(i, item)| items[..i].iter().any(|other| other.product_id == item.product_id));
+            if bundle_id > MAX_BUNDLE_ID || repeated {
+                return Err(CakeError::InvalidBundle.into());
+            }
+
+            // Reconfigurar sobrescreve o combo existente
+            if bundle_account.data_is_empty() {
+                let create_bundle_account_ix = system_instruction::create_account(
+                    payer.key,
+                    bundle_account.key,
+                    Rent::get()?.minimum_balance(Bundle::LEN),
+                    Bundle::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_bundle_account_ix,
+                    &[payer.clone(), bundle_account.clone(), system_program.clone()],
+                    &[&[b"bundle", cake_account.key.as_ref(), &bundle_id.to_le_bytes(), &[bundle_bump]]],
+                )?;
+            } else if bundle_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let item_count = items.len();
+            Bundle::pack(Bundle { bundle_id, price, items }, &mut bundle_account.data.borrow_mut())?;
+
+            msg!("set_bundle: bundle_id={}, price={}, items={}", bundle_id, price, item_count);
+        }
+        CakeInstruction::CloseBundle { bundle_id } => {
+            msg!("Instrução: close_bundle");
+            let cake_account = next_account_info(account_iter)?;
+            let bundle_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || bundle_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_bundle_account, _) = find_bundle_address(cake_account.key, bundle_id, program_id);
+            if *bundle_account.key != expected_bundle_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let bundle_lamports = bundle_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(bundle_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **bundle_account.lamports.borrow_mut() = 0;
+            bundle_account.data.borrow_mut().fill(0);
+
+            msg!("close_bundle: bundle_id={}", bundle_id);
+        }
+        CakeInstruction::SellBundle { bundle_id, quantity } => {
+            msg!("Instrução: sell_bundle");
+            let cake_account = next_account_info(account_iter)?;
+            let bundle_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let history_counter_account = next_account_info(account_iter)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            if cake_account.owner != program_id || bundle_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let (expected_bundle_account, _) = find_bundle_address(cake_account.key, bundle_id, program_id);
+            if *bundle_account.key != expected_bundle_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            let bundle = Bundle::unpack(&bundle_account.data.borrow())?;
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+            let now = Clock::get()?.unix_timestamp;
+            check_not_blocked(cake_account.key, buyer.key, next_account_info(account_iter)?, program_id)?;
+
+            // Contas de produto na ordem dos itens do combo, cada uma seguida do contador por comprador
+            // quando o produto tem max_per_buyer e das contas de allowlist e gate_mint quando exigidas;
+            // se algum item falhar, nenhum estoque muda
+            let mut total_units: u64 = 0;
+            for item in &bundle.items {
+                let product_account = next_account_info(account_iter)?;
+
+                let (expected_product_account, _) = find_product_address(cake_account.key, item.product_id, program_id);
+                if *product_account.key != expected_product_account {
+                    return Err(CakeError::WrongPda.into());
+                }
+                if product_account.owner != program_id {
+                    return Err(CakeError::ProductNotFound.into());
+                }
+
+                let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+                if !product.active {
+                    return Err(CakeError::ProductInactive.into());
+                }
+                if !product.available {
+                    return Err(CakeError::ProductUnavailable.into());
+                }
+                if !product.is_available_at(now) {
+                    return Err(CakeError::OutsideAvailabilityWindow.into());
+                }
+                let units = item.quantity.checked_mul(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+                if units > product.stock {
+                    return Err(CakeError::InsufficientStock.into());
+                }
+                enforce_buyer_limit(cake_account.key, &product, units, buyer, payer, system_program, account_iter, program_id)?;
+                check_product_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+                total_units = total_units.checked_add(units).ok_or(CakeError::ArithmeticOverflow)?;
+
+                // O preço do combo não é dividido entre os produtos: só as unidades entram nas estatísticas
+                product.stock -= units;
+                product.record_sale(units, 0, PAYMENT_MODE_TOKEN)?;
+                Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+            }
+
+            check_mint_decimals(payment_mint, mint_decimals)?;
+            let unit_price = convert_price(bundle.price, config.price_decimals, mint_decimals)?;
+            let total_price = unit_price.checked_mul(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            let owner_token_data = unpack_token_account(owner_token)?;
+            if buyer_token_data.mint != *payment_mint.key || owner_token_data.mint != *payment_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            pay_tokens(token_program, buyer_token, owner_token, buyer, payment_mint, total_price, &[])?;
+
+            let fee = collect_fee(&cake_state, total_price, buyer, buyer_token, payment_mint, token_program, account_iter)?;
+
+            create_history_account(
+                payer,
+                cake_account.key,
+                buyer.key,
+                history_account,
+                history_counter_account,
+                system_program,
+                program_id,
+            )?;
+
+            let history_entry = PurchaseHistory {
+                product_id: bundle.history_product_id(),
+                quantity,
+                total_price,
+                buyer: *buyer.key,
+                timestamp: now,
+                status: PURCHASE_STATUS_COMPLETED,
+                fee,
+                refunded_quantity: 0,
+                payment_mode: PAYMENT_MODE_TOKEN,
+                rent_payer: *payer.key,
+                tip: 0,
+                donation: 0,
+                // Recibo com o preço do combo; sem nome de produto
+                unit_price,
+                name_hash: [0; 32],
+                has_variant: false,
+                variant_id: 0,
+            };
+            PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+            cake_state.history_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            msg!("sell_bundle: bundle_id={}, quantity={}, units={}, total_price={}", bundle_id, quantity, total_units, total_price);
+        }
+        CakeInstruction::SetHappyHour { product_id, windows } => {
+            msg!("Instrução: set_happy_hour");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let happy_hour_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            let (expected_happy_hour_account, happy_hour_bump) = find_happy_hour_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account || *happy_hour_account.key != expected_happy_hour_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            if !windows.iter().all(HappyHourWindow::is_valid) {
+                return Err(CakeError::InvalidHappyHourWindow.into());
+            }
+
+            // Reconfigurar substitui todas as janelas
+            if happy_hour_account.data_is_empty() {
+                let create_happy_hour_account_ix = system_instruction::create_account(
+                    payer.key,
+                    happy_hour_account.key,
+                    Rent::get()?.minimum_balance(HappyHour::LEN),
+                    HappyHour::LEN as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &create_happy_hour_account_ix,
+                    &[payer.clone(), happy_hour_account.clone(), system_program.clone()],
+                    &[&[b"happy_hour", cake_account.key.as_ref(), &product_id.to_le_bytes(), &[happy_hour_bump]]],
+                )?;
+            } else if happy_hour_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let window_count = windows.len();
+            HappyHour::pack(HappyHour { product_id, windows }, &mut happy_hour_account.data.borrow_mut())?;
+
+            msg!("set_happy_hour: product_id={}, windows={}", product_id, window_count);
+        }
+        CakeInstruction::ClearHappyHour { product_id } => {
+            msg!("Instrução: clear_happy_hour");
+            let cake_account = next_account_info(account_iter)?;
+            let happy_hour_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+
+            if cake_account.owner != program_id || happy_hour_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            let (expected_happy_hour_account, _) = find_happy_hour_address(cake_account.key, product_id, program_id);
+            if *happy_hour_account.key != expected_happy_hour_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let happy_hour_lamports = happy_hour_account.lamports();
+            **owner.lamports.borrow_mut() = owner.lamports().checked_add(happy_hour_lamports).ok_or(CakeError::ArithmeticOverflow)?;
+            **happy_hour_account.lamports.borrow_mut() = 0;
+            happy_hour_account.data.borrow_mut().fill(0);
+
+            msg!("clear_happy_hour: product_id={}", product_id);
+        }
+        CakeInstruction::WithdrawStoreRevenue { amount } => {
+            msg!("Instrução: withdraw_store_revenue");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let revenue_token = next_account_info(account_iter)?;
+            let revenue_authority = next_account_info(account_iter)?;
+            let destination_token = next_account_info(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+
+            // A autoridade de receita é uma PDA da loja, então lojas legadas também sacam
+            let (expected_revenue_authority, revenue_bump) = find_store_revenue_authority_address(cake_account.key, program_id);
+            if *revenue_authority.key != expected_revenue_authority {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let revenue_data = unpack_token_account(revenue_token)?;
+            if *revenue_token.key != store_revenue_address(cake_account.key, &revenue_data.mint, token_program.key, program_id) {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            let destination_token_data = unpack_token_account(destination_token)?;
+            if destination_token_data.mint != revenue_data.mint {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            let mint = next_mint_if_token_2022(token_program, account_iter)?;
+            transfer_tokens(
+                token_program,
+                revenue_token,
+                destination_token,
+                revenue_authority,
+                mint,
+                amount,
+                &[&[b"store_revenue", cake_account.key.as_ref(), &[revenue_bump]]],
+            )?;
+
+            msg!("withdraw_store_revenue: mint={}, amount={}", revenue_data.mint, amount);
+        }
+        CakeInstruction::ConfidentialSell { product_id, quantity, new_source_decryptable_balance } => {
+            msg!("Instrução: confidential_sell");
+            let cake_account = next_account_info(account_iter)?;
+            let product_account = next_account_info(account_iter)?;
+            let buyer = next_account_info(account_iter)?;
+            let history_account = next_account_info(account_iter)?;
+            let history_counter_account = next_account_info(account_iter)?;
+            let buyer_token = next_account_info(account_iter)?;
+            let owner_token = next_account_info(account_iter)?;
+            let payment_mint = next_account_info(account_iter)?;
+            let config_account = next_account_info(account_iter)?;
+            let confidential_payment_account = next_account_info(account_iter)?;
+            let equality_proof = next_account_info(account_iter)?;
+            let ciphertext_validity_proof = next_account_info(account_iter)?;
+            let range_proof = next_account_info(account_iter)?;
+            let amount_proof = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+            let token_program = next_token_program(account_iter)?;
+
+            if !buyer.is_signer {
+                return Err(CakeError::BuyerSignatureRequired.into());
+            }
+            check_payer(payer)?;
+
+            if cake_account.owner != program_id || product_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            // Só o Token-2022 tem transferência confidencial
+            if *token_program.key != spl_token_2022::id() {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let mut cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            if cake_state.paused {
+                return Err(CakeError::ProgramPaused.into());
+            }
+
+            let config = load_config(config_account, cake_account.key, program_id)?;
+            let mint_decimals = config.mint_decimals(payment_mint.key).ok_or(CakeError::InvalidPaymentMint)?;
+
+            let (expected_product_account, _) = find_product_address(cake_account.key, product_id, program_id);
+            if *product_account.key != expected_product_account {
+                return Err(CakeError::WrongPda.into());
+            }
+
+            let now = Clock::get()?.unix_timestamp;
+            let mut product = Product::unpack_versioned(&product_account.data.borrow())?;
+            if !product.active {
+                return Err(CakeError::ProductInactive.into());
+            }
+            if !product.available {
+                return Err(CakeError::ProductUnavailable.into());
+            }
+            if !product.is_available_at(now) {
+                return Err(CakeError::OutsideAvailabilityWindow.into());
+            }
+            if quantity > product.stock {
+                return Err(CakeError::InsufficientStock.into());
+            }
+            enforce_buyer_limit(cake_account.key, &product, quantity, buyer, payer, system_program, account_iter, program_id)?;
+            check_buyer_gates(cake_account.key, buyer.key, &product, account_iter, program_id)?;
+
+            let unit_price = product.unit_price_for(quantity);
+            let total_price = quantity.checked_mul(unit_price).ok_or(CakeError::ArithmeticOverflow)?;
+            product.record_sale(quantity, total_price, PAYMENT_MODE_CONFIDENTIAL)?;
+            check_mint_decimals(payment_mint, mint_decimals)?;
+            let price_decimals = product.price_decimals_or(config.price_decimals);
+            let total_price = convert_price(total_price, price_decimals, mint_decimals)?;
+            let unit_price = convert_price(unit_price, price_decimals, mint_decimals)?;
+
+            let buyer_token_data = unpack_token_account(buyer_token)?;
+            let owner_token_data = unpack_token_account(owner_token)?;
+            if buyer_token_data.owner != *buyer.key {
+                return Err(CakeError::WrongTokenAccountOwner.into());
+            }
+            if buyer_token_data.mint != *payment_mint.key || owner_token_data.mint != *payment_mint.key {
+                return Err(CakeError::WrongMint.into());
+            }
+            if !is_revenue_destination(owner_token, &owner_token_data, cake_account.key, program_id) {
+                return Err(CakeError::InvalidDestination.into());
+            }
+
+            // Transfer da extensão de transferência confidencial com as três provas em contas de contexto
+            // (offset 0); o Token-2022 confere as provas contra as contas de origem e destino
+            let transfer_data = spl_token_2022::extension::confidential_transfer::instruction::TransferInstructionData {
+                new_source_decryptable_available_balance: bytemuck::cast(new_source_decryptable_balance),
+                equality_proof_instruction_offset: 0,
+                ciphertext_validity_proof_instruction_offset: 0,
+                range_proof_instruction_offset: 0,
+            };
+            let mut data = spl_token_2022::instruction::TokenInstruction::ConfidentialTransferExtension.pack();
+            data.push(ConfidentialTransferInstruction::Transfer.into());
+            data.extend_from_slice(bytemuck::bytes_of(&transfer_data));
+            let transfer_ix = Instruction {
+                program_id: *token_program.key,
+                accounts: vec![
+                    AccountMeta::new(*buyer_token.key, false),
+                    AccountMeta::new_readonly(*payment_mint.key, false),
+                    AccountMeta::new(*owner_token.key, false),
+                    AccountMeta::new_readonly(*equality_proof.key, false),
+                    AccountMeta::new_readonly(*ciphertext_validity_proof.key, false),
+                    AccountMeta::new_readonly(*range_proof.key, false),
+                    AccountMeta::new_readonly(*buyer.key, true),
+                ],
+                data,
+            };
+            solana_program::program::invoke(
+                &transfer_ix,
+                &[
+                    buyer_token.clone(),
+                    payment_mint.clone(),
+                    owner_token.clone(),
+                    equality_proof.clone(),
+                    ciphertext_validity_proof.clone(),
+                    range_proof.clone(),
+                    buyer.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+
+            let (commitment_lo, commitment_hi) = check_confidential_amount(ciphertext_validity_proof, amount_proof, total_price)?;
+
+            product.stock -= quantity;
+            Product::pack_stock_and_stats(&product, &mut product_account.data.borrow_mut())?;
+
+            let history_index = create_history_account(
+                payer,
+                cake_account.key,
+                buyer.key,
+                history_account,
+                history_counter_account,
+                system_program,
+                program_id,
+            )?;
+
+            let history_entry = PurchaseHistory {
+                product_id,
+                quantity,
+                total_price,
+                buyer: *buyer.key,
+                timestamp: now,
+                status: PURCHASE_STATUS_COMPLETED,
+                fee: 0,
+                refunded_quantity: 0,
+                payment_mode: PAYMENT_MODE_CONFIDENTIAL,
+                rent_payer: *payer.key,
+                tip: 0,
+                donation: 0,
+                unit_price,
+                name_hash: product.name_hash(),
+                has_variant: false,
+                variant_id: 0,
+            };
+            PurchaseHistory::pack(history_entry, &mut history_account.data.borrow_mut())?;
+
+            let (expected_payment_account, payment_bump) = find_confidential_payment_address(history_account.key, program_id);
+            if *confidential_payment_account.key != expected_payment_account {
+                return Err(CakeError::WrongPda.into());
+            }
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    confidential_payment_account.key,
+                    Rent::get()?.minimum_balance(ConfidentialPayment::LEN),
+                    ConfidentialPayment::LEN as u64,
+                    program_id,
+                ),
+                &[payer.clone(), confidential_payment_account.clone(), system_program.clone()],
+                &[&[b"confidential_payment", history_account.key.as_ref(), &[payment_bump]]],
+            )?;
+            let payment = ConfidentialPayment { history: *history_account.key, mint: *payment_mint.key, commitment_lo, commitment_hi };
+            ConfidentialPayment::pack(payment, &mut confidential_payment_account.data.borrow_mut())?;
+
+            cake_state.history_counter += 1;
+            CakeState::pack(cake_state, &mut cake_account.data.borrow_mut())?;
+
+            CakeEvent::Sold(Sold {
+                product_id,
+                buyer: *buyer.key,
+                quantity,
+                total_price,
+                payment_mode: PAYMENT_MODE_CONFIDENTIAL,
+                payment_mint: *payment_mint.key,
+                history_index,
+            })
+            .emit();
+        }
+        CakeInstruction::ConfidentialRevenue { data } => {
+            msg!("Instrução: confidential_revenue");
+            let cake_account = next_account_info(account_iter)?;
+            let owner = next_account_info(account_iter)?;
+            let revenue_token = next_account_info(account_iter)?;
+            let revenue_authority = next_account_info(account_iter)?;
+            let token_program = next_account_info(account_iter)?;
+            let payer = next_account_info(account_iter)?;
+            let system_program = next_system_program(account_iter)?;
+
+            if cake_account.owner != program_id {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+            if *token_program.key != spl_token_2022::id() {
+                return Err(CakeError::IncorrectProgramId.into());
+            }
+
+            let cake_state = CakeState::unpack(&cake_account.data.borrow())?;
+            check_owner(&cake_state, cake_account.key, owner, accounts, program_id)?;
+            check_payer(payer)?;
+
+            let (expected_revenue_authority, revenue_bump) = find_store_revenue_authority_address(cake_account.key, program_id);
+            if *revenue_authority.key != expected_revenue_authority {
+                return Err(CakeError::WrongPda.into());
+            }
+            let revenue_data = unpack_token_account(revenue_token)?;
+            if *revenue_token.key != store_revenue_address(cake_account.key, &revenue_data.mint, token_program.key, program_id) {
+                return Err(CakeError::WrongMint.into());
+            }
+
+            // Só instruções que mexem apenas na própria conta: configurar, aplicar o saldo pendente e sacar
+            // para o saldo público, de onde o withdraw_store_revenue transfere
+            let kind = ConfidentialTransferInstruction::try_from(data[0]).map_err(|_| CakeError::UnsupportedConfidentialInstruction)?;
+            if !matches!(
+                kind,
+                ConfidentialTransferInstruction::ConfigureAccount
+                    | ConfidentialTransferInstruction::ApplyPendingBalance
+                    | ConfidentialTransferInstruction::Withdraw
+            ) {
+                return Err(CakeError::UnsupportedConfidentialInstruction.into());
+            }
+            let signer_seeds: &[&[u8]] = &[b"store_revenue", cake_account.key.as_ref(), &[revenue_bump]];
+
+            // A ATA nasce sem espaço para a extensão confidencial
+            if matches!(kind, ConfidentialTransferInstruction::ConfigureAccount) {
+                invoke_signed(
+                    &spl_token_2022::instruction::reallocate(
+                        token_program.key,
+                        revenue_token.key,
+                        payer.key,
+                        revenue_authority.key,
+                        &[],
+                        &[ExtensionType::ConfidentialTransferAccount],
+                    )?,
+                    &[revenue_token.clone(), payer.clone(), system_program.clone(), revenue_authority.clone(), token_program.clone()],
+                    &[signer_seeds],
+                )?;
+            }
+
+            let extra_accounts = account_iter.as_slice();
+            let mut metas = vec![AccountMeta::new(*revenue_token.key, false)];
+            metas.extend(extra_accounts.iter().map(|account| AccountMeta::new_readonly(*account.key, false)));
+            metas.push(AccountMeta::new_readonly(*revenue_authority.key, true));
+            let mut ix_data = spl_token_2022::instruction::TokenInstruction::ConfidentialTransferExtension.pack();
+            ix_data.extend_from_slice(&data);
+            let mut infos = vec![revenue_token.clone()];
+            infos.extend(extra_accounts.iter().cloned());
+            infos.extend([revenue_authority.clone(), token_program.clone()]);
+            invoke_signed(&Instruction { program_id: *token_program.key, accounts: metas, data: ix_data }, &infos, &[signer_seeds])?;
+
+            msg!("confidential_revenue: mint={}, instruction={:?}", revenue_data.mint, kind);
+        }
+    }
+    Ok(())
+}
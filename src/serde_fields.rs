@@ -0,0 +1,128 @@
+// Representação legível em JSON dos campos que on-chain são bytes (feature `serde`): Pubkey em base58,
+// textos com preenchimento NUL como string e hashes em hex. Usados com #[serde(with = ...)] em state.rs
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+pub mod pubkey {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(key)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+// Texto UTF-8 completado com NUL até N bytes, como metadata_uri
+pub mod nul_padded {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        let len = bytes.iter().position(|b| *b == 0).unwrap_or(N);
+        serializer.serialize_str(std::str::from_utf8(&bytes[..len]).map_err(serde::ser::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if text.len() > N || text.contains('\0') {
+            return Err(D::Error::custom(format!("texto com NUL ou maior que {} bytes", N)));
+        }
+        let mut bytes = [0u8; N];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Ok(bytes)
+    }
+}
+
+pub mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if text.len() != N * 2 {
+            return Err(D::Error::custom(format!("esperados {} dígitos hex", N * 2)));
+        }
+        let mut bytes = [0u8; N];
+        for (byte, pair) in bytes.iter_mut().zip(text.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(D::Error::custom)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(D::Error::custom)?;
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{PriceTier, Product, PurchaseHistory, MAX_PRICE_TIERS, NO_CATEGORY, PAYMENT_MODE_TOKEN, PURCHASE_STATUS_COMPLETED};
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_product_json() {
+        let mut metadata_uri = [0; 200];
+        metadata_uri[..20].copy_from_slice(b"https://cidacake.com");
+        let product = Product {
+            id: 3,
+            name: "Bolo de fubá".to_string(),
+            description: "Com goiabada".to_string(),
+            price: 800_000,
+            stock: 5,
+            active: true,
+            available: true,
+            max_per_buyer: 0,
+            category_id: NO_CATEGORY,
+            metadata_uri,
+            tier_count: 0,
+            tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+            sol_price: 0,
+            usd_price_cents: 0,
+            price_feed: Pubkey::new_unique(),
+            allowlist_required: false,
+            gate_mint: Pubkey::default(),
+            units_sold: 0,
+            revenue: 0,
+            sol_revenue: 0,
+            available_from: 0,
+            available_until: 0,
+            price_decimals: 0,
+        };
+        let mut json = serde_json::to_value(&product).unwrap();
+        assert_eq!(json["metadata_uri"], "https://cidacake.com");
+        assert_eq!(json["price_feed"], product.price_feed.to_string());
+        assert_eq!(json["gate_mint"], "11111111111111111111111111111111");
+        assert_eq!(serde_json::from_value::<Product>(json.clone()).unwrap(), product);
+
+        json["metadata_uri"] = "x".repeat(201).into();
+        assert!(serde_json::from_value::<Product>(json).is_err());
+    }
+
+    #[test]
+    fn test_purchase_history_json() {
+        let history = PurchaseHistory {
+            product_id: 3,
+            quantity: 2,
+            total_price: 1_600_000,
+            buyer: Pubkey::new_unique(),
+            timestamp: 1_700_000_000,
+            status: PURCHASE_STATUS_COMPLETED,
+            fee: 0,
+            refunded_quantity: 0,
+            payment_mode: PAYMENT_MODE_TOKEN,
+            rent_payer: Pubkey::new_unique(),
+            tip: 0,
+            donation: 0,
+            unit_price: 800_000,
+            name_hash: [0xab; 32],
+            has_variant: false,
+            variant_id: 0,
+        };
+        let json = serde_json::to_value(history).unwrap();
+        assert_eq!(json["buyer"], history.buyer.to_string());
+        assert_eq!(json["name_hash"], "ab".repeat(32));
+        assert_eq!(serde_json::from_value::<PurchaseHistory>(json).unwrap(), history);
+    }
+}
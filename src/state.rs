@@ -0,0 +1,3454 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use shank::{ShankAccount, ShankType};
+use solana_program::{
+    entrypoint::ProgramResult,
+    hash::hashv,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+};
+use spl_pod::primitives::{PodBool, PodI64, PodU64};
+
+use crate::error::CakeError;
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CakeState {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub owner: Pubkey,
+    pub product_counter: u64,
+    // Total de históricos criados; os endereços usam a sequência de cada comprador (BuyerHistoryCounter)
+    pub history_counter: u64,
+    pub max_price_change_bps: u16,
+    pub paused: bool,
+    pub order_counter: u64,
+    pub category_counter: u64,
+    pub fee_bps: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub fee_destination: Pubkey,
+    pub escrow_timeout: i64,
+    pub commission_bps: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub commission_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub commission_account: Pubkey,
+    // Gravado pelo initialize/create_store; CakeState::unpack recusa estado com o byte zerado
+    pub is_initialized: bool,
+}
+
+impl Sealed for CakeState {}
+
+impl IsInitialized for CakeState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+// Discriminadores no início das contas, calculados como no Anchor: sha256("account:<Nome>")[..8].
+// Permitem filtrar getProgramAccounts por memcmp no offset 0 e decodificar as contas com ferramentas externas
+pub const CAKE_STATE_DISCRIMINATOR: [u8; 8] = [24, 252, 37, 61, 37, 11, 247, 196];
+pub const PRODUCT_DISCRIMINATOR: [u8; 8] = [102, 76, 55, 251, 38, 73, 224, 229];
+pub const PURCHASE_HISTORY_DISCRIMINATOR: [u8; 8] = [146, 182, 21, 190, 99, 157, 221, 104];
+
+// [discriminador 8][corpo LEGACY_LEN][is_initialized u8]
+impl Pack for CakeState {
+    const LEN: usize = Self::UNFLAGGED_LEN + 1;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let (discriminator, body) = dst.split_at_mut(8);
+        discriminator.copy_from_slice(&CAKE_STATE_DISCRIMINATOR);
+        self.pack_body(body);
+        dst[Self::UNFLAGGED_LEN] = self.is_initialized as u8;
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == Self::UNFLAGGED_LEN && src[..8] == CAKE_STATE_DISCRIMINATOR {
+            return Err(CakeError::AccountNeedsMigration.into());
+        }
+        if src.len() != Self::LEN || src[..8] != CAKE_STATE_DISCRIMINATOR {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut state = Self::unpack_body(&src[8..Self::UNFLAGGED_LEN])?;
+        state.is_initialized = src[Self::UNFLAGGED_LEN] != 0;
+        Ok(state)
+    }
+}
+
+impl CakeState {
+    // Layout anterior ao discriminador; convertido por migrate_state
+    pub const LEGACY_LEN: usize = 175;
+    // Layout com discriminador e sem o byte is_initialized; também convertido por migrate_state
+    pub const UNFLAGGED_LEN: usize = 8 + Self::LEGACY_LEN;
+
+    // Os dois layouts anteriores só existiam depois do initialize, então o estado lido está inicializado
+    pub fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEGACY_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Self::unpack_body(src)
+    }
+
+    pub fn unpack_unflagged(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::UNFLAGGED_LEN || src[..8] != CAKE_STATE_DISCRIMINATOR {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Self::unpack_body(&src[8..])
+    }
+
+    fn pack_body(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[..32].copy_from_slice(self.owner.as_ref());
+        slice[32..40].copy_from_slice(&self.product_counter.to_le_bytes());
+        slice[40..48].copy_from_slice(&self.history_counter.to_le_bytes());
+        slice[48..50].copy_from_slice(&self.max_price_change_bps.to_le_bytes());
+        slice[50] = self.paused as u8;
+        slice[51..59].copy_from_slice(&self.order_counter.to_le_bytes());
+        slice[59..67].copy_from_slice(&self.category_counter.to_le_bytes());
+        slice[67..69].copy_from_slice(&self.fee_bps.to_le_bytes());
+        slice[69..101].copy_from_slice(self.fee_destination.as_ref());
+        slice[101..109].copy_from_slice(&self.escrow_timeout.to_le_bytes());
+        slice[109..111].copy_from_slice(&self.commission_bps.to_le_bytes());
+        slice[111..143].copy_from_slice(self.commission_authority.as_ref());
+        slice[143..175].copy_from_slice(self.commission_account.as_ref());
+    }
+
+    fn unpack_body(src: &[u8]) -> Result<Self, ProgramError> {
+        let owner = Pubkey::try_from(&src[..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let product_counter = u64::from_le_bytes(src[32..40].try_into().unwrap());
+        let history_counter = u64::from_le_bytes(src[40..48].try_into().unwrap());
+        let max_price_change_bps = u16::from_le_bytes(src[48..50].try_into().unwrap());
+        let paused = src[50] != 0;
+        let order_counter = u64::from_le_bytes(src[51..59].try_into().unwrap());
+        let category_counter = u64::from_le_bytes(src[59..67].try_into().unwrap());
+        let fee_bps = u16::from_le_bytes(src[67..69].try_into().unwrap());
+        let fee_destination = Pubkey::try_from(&src[69..101]).map_err(|_| CakeError::InvalidAccountData)?;
+        let escrow_timeout = i64::from_le_bytes(src[101..109].try_into().unwrap());
+        let commission_bps = u16::from_le_bytes(src[109..111].try_into().unwrap());
+        let commission_authority = Pubkey::try_from(&src[111..143]).map_err(|_| CakeError::InvalidAccountData)?;
+        let commission_account = Pubkey::try_from(&src[143..175]).map_err(|_| CakeError::InvalidAccountData)?;
+        Ok(CakeState {
+            owner,
+            product_counter,
+            history_counter,
+            max_price_change_bps,
+            paused,
+            order_counter,
+            category_counter,
+            fee_bps,
+            fee_destination,
+            escrow_timeout,
+            commission_bps,
+            commission_authority,
+            commission_account,
+            is_initialized: true,
+        })
+    }
+}
+
+pub const MAX_PRICE_TIERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceTier {
+    pub min_quantity: u64,
+    pub unit_price: u64,
+}
+
+// Contas criadas a partir deste layout: [PRODUCT_DISCRIMINATOR][PRODUCT_BORSH_VERSION][Product em Borsh],
+// com nome e descrição de tamanho variável; contas Borsh sem discriminador e de layout fixo continuam
+// legíveis e são reconhecidas pelo primeiro byte e pelo tamanho
+pub const PRODUCT_BORSH_VERSION: u8 = 1;
+pub const MAX_PRODUCT_NAME_LEN: usize = 64;
+pub const MAX_PRODUCT_DESCRIPTION_LEN: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, ShankAccount)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Product {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub stock: u64,
+    pub active: bool,
+    pub available: bool,
+    pub max_per_buyer: u64,
+    pub category_id: u64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::nul_padded"))]
+    pub metadata_uri: [u8; 200],
+    pub tier_count: u8,
+    // Tamanhos literais (= MAX_PRICE_TIERS) porque o shank não resolve constantes ao gerar o IDL
+    pub tiers: [PriceTier; 4],
+    pub sol_price: u64,
+    pub usd_price_cents: u64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub price_feed: Pubkey,
+    pub allowlist_required: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub gate_mint: Pubkey,
+    pub units_sold: u64,
+    pub revenue: u64,
+    pub sol_revenue: u64,
+    // Janela de venda em unix timestamp; zero significa sem limite
+    pub available_from: i64,
+    pub available_until: i64,
+    // Casas decimais de price, das faixas e dos cupons de valor; zero usa o price_decimals da configuração
+    pub price_decimals: u8,
+}
+
+impl Sealed for Product {}
+
+impl IsInitialized for Product {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+// Texto de campo fixo preenchido com zeros à direita
+pub(crate) fn fixed_str(src: &[u8]) -> Result<String, ProgramError> {
+    let len = src.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    String::from_utf8(src[..len].to_vec()).map_err(|_| CakeError::InvalidAccountData.into())
+}
+
+fn copy_fixed_str(dst: &mut [u8], src: &str) {
+    let len = src.len().min(dst.len());
+    dst.fill(0);
+    dst[..len].copy_from_slice(&src.as_bytes()[..len]);
+}
+
+// Layout fixo anterior ao Borsh, mantido para as contas existentes
+impl Pack for Product {
+    const LEN: usize = 548;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[..8].copy_from_slice(&self.id.to_le_bytes());
+        copy_fixed_str(&mut slice[8..40], &self.name);
+        copy_fixed_str(&mut slice[40..168], &self.description);
+        slice[168..176].copy_from_slice(&self.price.to_le_bytes());
+        slice[176..184].copy_from_slice(&self.stock.to_le_bytes());
+        slice[184] = self.active as u8;
+        slice[185] = self.available as u8;
+        slice[186..194].copy_from_slice(&self.max_per_buyer.to_le_bytes());
+        slice[194..202].copy_from_slice(&self.category_id.to_le_bytes());
+        slice[202..402].copy_from_slice(&self.metadata_uri);
+        slice[402] = self.tier_count;
+        for (i, tier) in self.tiers.iter().enumerate() {
+            let start = 403 + i * 16;
+            slice[start..start + 8].copy_from_slice(&tier.min_quantity.to_le_bytes());
+            slice[start + 8..start + 16].copy_from_slice(&tier.unit_price.to_le_bytes());
+        }
+        slice[467..475].copy_from_slice(&self.sol_price.to_le_bytes());
+        slice[475..483].copy_from_slice(&self.usd_price_cents.to_le_bytes());
+        slice[483..515].copy_from_slice(self.price_feed.as_ref());
+        slice[515] = self.allowlist_required as u8;
+        slice[516..548].copy_from_slice(self.gate_mint.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let id = u64::from_le_bytes(src[..8].try_into().unwrap());
+        let name = fixed_str(&src[8..40])?;
+        let description = fixed_str(&src[40..168])?;
+        let price = u64::from_le_bytes(src[168..176].try_into().unwrap());
+        let stock = u64::from_le_bytes(src[176..184].try_into().unwrap());
+        let active = src[184] != 0;
+        let available = src[185] != 0;
+        let max_per_buyer = u64::from_le_bytes(src[186..194].try_into().unwrap());
+        let category_id = u64::from_le_bytes(src[194..202].try_into().unwrap());
+        let mut metadata_uri = [0u8; 200];
+        metadata_uri.copy_from_slice(&src[202..402]);
+        let tier_count = src[402];
+        if tier_count as usize > MAX_PRICE_TIERS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut tiers = [PriceTier::default(); MAX_PRICE_TIERS];
+        for (i, tier) in tiers.iter_mut().enumerate() {
+            let start = 403 + i * 16;
+            tier.min_quantity = u64::from_le_bytes(src[start..start + 8].try_into().unwrap());
+            tier.unit_price = u64::from_le_bytes(src[start + 8..start + 16].try_into().unwrap());
+        }
+        let sol_price = u64::from_le_bytes(src[467..475].try_into().unwrap());
+        let usd_price_cents = u64::from_le_bytes(src[475..483].try_into().unwrap());
+        let price_feed = Pubkey::try_from(&src[483..515]).map_err(|_| CakeError::InvalidAccountData)?;
+        let allowlist_required = src[515] != 0;
+        let gate_mint = Pubkey::try_from(&src[516..548]).map_err(|_| CakeError::InvalidAccountData)?;
+        Ok(Product {
+            id,
+            name,
+            description,
+            price,
+            stock,
+            active,
+            available,
+            max_per_buyer,
+            category_id,
+            metadata_uri,
+            tier_count,
+            tiers,
+            sol_price,
+            usd_price_cents,
+            price_feed,
+            allowlist_required,
+            gate_mint,
+            units_sold: 0,
+            revenue: 0,
+            sol_revenue: 0,
+            available_from: 0,
+            available_until: 0,
+            price_decimals: 0,
+        })
+    }
+}
+
+impl Product {
+    // Tamanhos de layouts fixos anteriores; na leitura os campos ausentes ficam zerados
+    pub const LEGACY_LENS: [usize; 6] = [202, 402, 467, 475, 515, 516];
+
+    // Acumula as estatísticas de venda: revenue em unidades de preço (price_decimals) no modo token,
+    // sol_revenue em lamports no modo SOL
+    pub fn record_sale(&mut self, quantity: u64, total_price: u64, payment_mode: u8) -> ProgramResult {
+        self.units_sold = self.units_sold.checked_add(quantity).ok_or(CakeError::ArithmeticOverflow)?;
+        if payment_mode == PAYMENT_MODE_SOL {
+            self.sol_revenue = self.sol_revenue.checked_add(total_price).ok_or(CakeError::ArithmeticOverflow)?;
+        } else {
+            self.revenue = self.revenue.checked_add(total_price).ok_or(CakeError::ArithmeticOverflow)?;
+        }
+        Ok(())
+    }
+
+    // available_until é inclusivo, como o expires_at dos cupons
+    pub fn is_available_at(&self, now: i64) -> bool {
+        (self.available_from == 0 || now >= self.available_from) && (self.available_until == 0 || now <= self.available_until)
+    }
+
+    pub fn price_decimals_or(&self, config_price_decimals: u8) -> u8 {
+        if self.price_decimals == 0 {
+            config_price_decimals
+        } else {
+            self.price_decimals
+        }
+    }
+
+    // sha256 do nome, guardado no recibo da venda para identificar o produto mesmo depois de renomeado
+    pub fn name_hash(&self) -> [u8; 32] {
+        hashv(&[self.name.as_bytes()]).to_bytes()
+    }
+
+    // Faixa com maior min_quantity atendida pela quantidade; sem faixa usa o preço base
+    pub fn unit_price_for(&self, quantity: u64) -> u64 {
+        self.tiers[..self.tier_count as usize]
+            .iter()
+            .filter(|tier| quantity >= tier.min_quantity)
+            .max_by_key(|tier| tier.min_quantity)
+            .map_or(self.price, |tier| tier.unit_price)
+    }
+
+    pub fn is_fixed_layout_len(len: usize) -> bool {
+        len == Self::LEN || Self::LEGACY_LENS.contains(&len)
+    }
+
+    pub fn is_discriminated(src: &[u8]) -> bool {
+        src.len() > 8 && src[..8] == PRODUCT_DISCRIMINATOR
+    }
+
+    // Tamanho da conta no layout atual (discriminador + versão + Borsh)
+    pub fn account_len(&self) -> Result<usize, ProgramError> {
+        Ok(8 + 1 + self.try_to_vec().map_err(|_| CakeError::InvalidAccountData)?.len())
+    }
+
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if Self::is_discriminated(src) {
+            return Self::unpack_borsh(&src[8..]);
+        }
+        if src.len() == Self::LEN {
+            return Self::unpack(src);
+        }
+        if Self::LEGACY_LENS.contains(&src.len()) {
+            let mut buf = [0u8; Self::LEN];
+            buf[..src.len()].copy_from_slice(src);
+            return Self::unpack(&buf);
+        }
+        Self::unpack_borsh(src)
+    }
+
+    fn unpack_borsh(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.first() != Some(&PRODUCT_BORSH_VERSION) {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        // Campos acrescentados depois da criação da conta são lidos como zero
+        let mut buf = src[1..].to_vec();
+        buf.resize(src.len() + Self::LEN, 0);
+        Self::deserialize(&mut buf.as_slice()).map_err(|_| CakeError::InvalidAccountData.into())
+    }
+
+    // Escreve no layout atual; usado na criação e na migração, com a conta já dimensionada por account_len
+    pub fn pack_discriminated(src: &Self, dst: &mut [u8]) -> ProgramResult {
+        let (discriminator, body) = dst.split_at_mut(8);
+        discriminator.copy_from_slice(&PRODUCT_DISCRIMINATOR);
+        Self::pack_borsh(src, body)
+    }
+
+    // Mantém o layout em que a conta está; contas antigas só podem ser escritas enquanto os campos
+    // novos estiverem zerados
+    pub fn pack_versioned(src: &Self, dst: &mut [u8]) -> ProgramResult {
+        if Self::is_discriminated(dst) {
+            return Self::pack_borsh(src, &mut dst[8..]);
+        }
+        if Self::is_fixed_layout_len(dst.len()) {
+            // As estatísticas de venda, a janela de venda e as casas decimais só existem no layout Borsh
+            if src.name.len() > 32
+                || src.description.len() > 128
+                || src.units_sold != 0
+                || src.revenue != 0
+                || src.sol_revenue != 0
+                || src.available_from != 0
+                || src.available_until != 0
+                || src.price_decimals != 0
+            {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            let mut buf = [0u8; Self::LEN];
+            Self::pack(src.clone(), &mut buf)?;
+            if buf[dst.len()..].iter().any(|b| *b != 0) {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            let len = dst.len();
+            dst.copy_from_slice(&buf[..len]);
+            return Ok(());
+        }
+        Self::pack_borsh(src, dst)
+    }
+
+    // Venda e reposição só mudam estoque e estatísticas: no layout atual esses campos são gravados
+    // direto nos bytes da conta, sem serializar nome e descrição de novo
+    pub fn pack_stock_and_stats(src: &Self, dst: &mut [u8]) -> ProgramResult {
+        match ProductFixedFields::load_mut(dst) {
+            Ok(fields) => {
+                fields.stock = src.stock.into();
+                fields.units_sold = src.units_sold.into();
+                fields.revenue = src.revenue.into();
+                fields.sol_revenue = src.sol_revenue.into();
+                Ok(())
+            }
+            Err(_) => Self::pack_versioned(src, dst),
+        }
+    }
+
+    fn pack_borsh(src: &Self, dst: &mut [u8]) -> ProgramResult {
+        let data = src.try_to_vec().map_err(|_| CakeError::InvalidAccountData)?;
+        let (version, body) = dst.split_at_mut(1);
+        if data.len() > body.len() {
+            if data[body.len()..].iter().any(|b| *b != 0) {
+                return Err(CakeError::AccountNeedsMigration.into());
+            }
+            body.copy_from_slice(&data[..body.len()]);
+        } else {
+            body[..data.len()].copy_from_slice(&data);
+            body[data.len()..].fill(0);
+        }
+        version[0] = PRODUCT_BORSH_VERSION;
+        Ok(())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct PodPriceTier {
+    pub min_quantity: PodU64,
+    pub unit_price: PodU64,
+}
+
+// Visão zero-copy dos campos de tamanho fixo que seguem nome e descrição no corpo Borsh do produto.
+// Todos os campos têm alinhamento 1, então a struct é mapeada direto sobre os bytes da conta
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ProductFixedFields {
+    pub price: PodU64,
+    pub stock: PodU64,
+    pub active: PodBool,
+    pub available: PodBool,
+    pub max_per_buyer: PodU64,
+    pub category_id: PodU64,
+    pub metadata_uri: [u8; 200],
+    pub tier_count: u8,
+    pub tiers: [PodPriceTier; 4],
+    pub sol_price: PodU64,
+    pub usd_price_cents: PodU64,
+    pub price_feed: Pubkey,
+    pub allowlist_required: PodBool,
+    pub gate_mint: Pubkey,
+    pub units_sold: PodU64,
+    pub revenue: PodU64,
+    pub sol_revenue: PodU64,
+    pub available_from: PodI64,
+    pub available_until: PodI64,
+    pub price_decimals: u8,
+}
+
+impl ProductFixedFields {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    // [discriminador 8][versão 1][id 8][name_len u32][name][description_len u32][description][campos fixos]
+    fn offset(data: &[u8]) -> Result<usize, ProgramError> {
+        if !Product::is_discriminated(data) || data[8] != PRODUCT_BORSH_VERSION {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let read_len = |offset: usize| -> Result<usize, ProgramError> {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+                .ok_or_else(|| CakeError::InvalidAccountData.into())
+        };
+        let description_start = 21 + read_len(17)?;
+        Ok(description_start + 4 + read_len(description_start)?)
+    }
+
+    // Contas criadas antes dos últimos campos são mais curtas e não têm a visão; usam o Borsh
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let offset = Self::offset(data)?;
+        data.get(offset..offset + Self::LEN)
+            .and_then(|bytes| bytemuck::try_from_bytes(bytes).ok())
+            .ok_or_else(|| CakeError::AccountNeedsMigration.into())
+    }
+
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let offset = Self::offset(data)?;
+        data.get_mut(offset..offset + Self::LEN)
+            .and_then(|bytes| bytemuck::try_from_bytes_mut(bytes).ok())
+            .ok_or_else(|| CakeError::AccountNeedsMigration.into())
+    }
+}
+
+// product_id usado no histórico consolidado de um checkout com vários produtos
+pub const CART_PRODUCT_ID: u64 = u64::MAX;
+// Compras de combo gravam BUNDLE_PRODUCT_ID_FLAG | bundle_id como product_id do histórico; bundle_id vai até
+// MAX_BUNDLE_ID para não chegar em CART_PRODUCT_ID
+pub const BUNDLE_PRODUCT_ID_FLAG: u64 = 1 << 63;
+pub const MAX_BUNDLE_ID: u64 = BUNDLE_PRODUCT_ID_FLAG - 2;
+pub const MAX_BUNDLE_ITEMS: usize = 8;
+pub const MAX_CART_ITEMS: usize = 10;
+// Produtos por AddProducts; cada um custa uma PDA nas contas e um create_account em CPI
+pub const MAX_PRODUCT_BATCH: usize = 8;
+
+pub const PAYMENT_MODE_TOKEN: u8 = 0;
+pub const PAYMENT_MODE_SOL: u8 = 1;
+// Transferência confidencial do Token-2022: o valor é provado igual ao total, gravado no histórico como nas
+// outras compras, e os compromissos da transferência ficam no ConfidentialPayment
+pub const PAYMENT_MODE_CONFIDENTIAL: u8 = 2;
+
+// Versão do layout de contas do Sell, enviada no último byte dos dados:
+//   1 (dados sem o byte): authority (owner ou operador) na posição 0 e sysvar Clock após o payer
+//   2: authority na posição 0, sem a Clock (o programa usa Clock::get)
+//   3: sem authority; o comprador compra sozinho
+//   4: PDA do happy hour do produto logo após a da promoção relâmpago
+pub const SELL_LEGACY_VERSION: u8 = 1;
+pub const SELL_CLOCKLESS_VERSION: u8 = 2;
+pub const SELL_BUYER_ONLY_VERSION: u8 = 3;
+pub const SELL_VERSION: u8 = 4;
+
+// Versão do layout de contas do Checkout, enviada no byte após os itens:
+//   1 (dados sem o byte): authority (owner ou operador) na posição 0 e sysvar Clock após o payer
+//   2: sem authority nem Clock; o comprador compra sozinho, como no Sell
+pub const CHECKOUT_LEGACY_VERSION: u8 = 1;
+pub const CHECKOUT_VERSION: u8 = 2;
+
+pub const PURCHASE_STATUS_COMPLETED: u8 = 0;
+pub const PURCHASE_STATUS_REFUNDED: u8 = 1;
+pub const PURCHASE_STATUS_ESCROWED: u8 = 2;
+// Escrow contestado pelo comprador; os fundos ficam no cofre até a decisão do árbitro
+pub const PURCHASE_STATUS_DISPUTED: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize, ShankAccount)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PurchaseHistory {
+    pub product_id: u64,
+    pub quantity: u64,
+    pub total_price: u64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub buyer: Pubkey,
+    pub timestamp: i64,
+    pub status: u8,
+    pub fee: u64,
+    pub refunded_quantity: u64,
+    pub payment_mode: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::pubkey"))]
+    pub rent_payer: Pubkey,
+    // Gorjeta em unidades do mint, paga à parte para Config::tip_account; não entra em total_price
+    pub tip: u64,
+    // Arredondamento doado para Config::charity_account; também fora de total_price
+    pub donation: u64,
+    // Recibo: preço unitário de tabela na unidade cobrada, antes de descontos, e Product::name_hash no momento
+    // da venda. Zerados no checkout e nos históricos anteriores ao recibo
+    pub unit_price: u64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::hex"))]
+    pub name_hash: [u8; 32],
+    // Variante vendida, para o reembolso repor o estoque dela em vez do produto. Falso nos checkouts, no
+    // histórico paginado e nos históricos anteriores à variante
+    pub has_variant: bool,
+    pub variant_id: u8,
+}
+
+impl Sealed for PurchaseHistory {}
+
+impl IsInitialized for PurchaseHistory {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+// Visão zero-copy de uma conta PurchaseHistory: [PURCHASE_HISTORY_DISCRIMINATOR][PurchaseHistory em Borsh].
+// Os campos têm alinhamento 1 e seguem a ordem do Borsh, então a struct coincide com os bytes da conta
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct PurchaseHistoryAccount {
+    pub discriminator: [u8; 8],
+    pub product_id: PodU64,
+    pub quantity: PodU64,
+    pub total_price: PodU64,
+    pub buyer: Pubkey,
+    pub timestamp: PodI64,
+    pub status: u8,
+    pub fee: PodU64,
+    pub refunded_quantity: PodU64,
+    pub payment_mode: u8,
+    pub rent_payer: Pubkey,
+    pub tip: PodU64,
+    pub donation: PodU64,
+    pub unit_price: PodU64,
+    pub name_hash: [u8; 32],
+    pub has_variant: PodBool,
+    pub variant_id: u8,
+}
+
+impl PurchaseHistoryAccount {
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let account: &Self = bytemuck::try_from_bytes(data).map_err(|_| CakeError::InvalidAccountData)?;
+        if account.discriminator != PURCHASE_HISTORY_DISCRIMINATOR {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(account)
+    }
+
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let account: &mut Self = bytemuck::try_from_bytes_mut(data).map_err(|_| CakeError::InvalidAccountData)?;
+        if account.discriminator != PURCHASE_HISTORY_DISCRIMINATOR {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(account)
+    }
+}
+
+impl From<&PurchaseHistory> for PurchaseHistoryAccount {
+    fn from(history: &PurchaseHistory) -> Self {
+        PurchaseHistoryAccount {
+            discriminator: PURCHASE_HISTORY_DISCRIMINATOR,
+            product_id: history.product_id.into(),
+            quantity: history.quantity.into(),
+            total_price: history.total_price.into(),
+            buyer: history.buyer,
+            timestamp: history.timestamp.into(),
+            status: history.status,
+            fee: history.fee.into(),
+            refunded_quantity: history.refunded_quantity.into(),
+            payment_mode: history.payment_mode,
+            rent_payer: history.rent_payer,
+            tip: history.tip.into(),
+            donation: history.donation.into(),
+            unit_price: history.unit_price.into(),
+            name_hash: history.name_hash,
+            has_variant: history.has_variant.into(),
+            variant_id: history.variant_id,
+        }
+    }
+}
+
+impl From<&PurchaseHistoryAccount> for PurchaseHistory {
+    fn from(account: &PurchaseHistoryAccount) -> Self {
+        PurchaseHistory {
+            product_id: account.product_id.into(),
+            quantity: account.quantity.into(),
+            total_price: account.total_price.into(),
+            buyer: account.buyer,
+            timestamp: account.timestamp.into(),
+            status: account.status,
+            fee: account.fee.into(),
+            refunded_quantity: account.refunded_quantity.into(),
+            payment_mode: account.payment_mode,
+            rent_payer: account.rent_payer,
+            tip: account.tip.into(),
+            donation: account.donation.into(),
+            unit_price: account.unit_price.into(),
+            name_hash: account.name_hash,
+            has_variant: account.has_variant.into(),
+            variant_id: account.variant_id,
+        }
+    }
+}
+
+// Todos os campos têm tamanho fixo, então as contas sem discriminador coincidem byte a byte com o corpo
+// e continuam legíveis sem migração
+impl Pack for PurchaseHistory {
+    const LEN: usize = std::mem::size_of::<PurchaseHistoryAccount>();
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        *bytemuck::from_bytes_mut::<PurchaseHistoryAccount>(dst) = self.into();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        PurchaseHistoryAccount::load(src).map(Self::from)
+    }
+}
+
+impl PurchaseHistory {
+    const BODY_LEN: usize = 172;
+    // Corpo anterior ao tip e à doação; é o tamanho das entradas do histórico paginado
+    pub const BASE_BODY_LEN: usize = 114;
+    // Corpo anterior ao recibo
+    pub const TIPPED_BODY_LEN: usize = 130;
+    // Corpo anterior à variante
+    pub const RECEIPT_BODY_LEN: usize = 170;
+    // Contas sem discriminador, do layout completo anterior e de versões mais antigas
+    pub const LEGACY_LENS: [usize; 5] = [65, 73, 81, 82, 114];
+    // Contas com discriminador gravadas antes do tip, antes da doação, antes do recibo e antes da variante
+    pub const LEGACY_DISCRIMINATED_LENS: [usize; 4] =
+        [8 + Self::BASE_BODY_LEN, 8 + Self::BASE_BODY_LEN + 8, 8 + Self::TIPPED_BODY_LEN, 8 + Self::RECEIPT_BODY_LEN];
+
+    // Corpo Borsh no menor layout que comporta os campos preenchidos, então entradas e folhas antigas não mudam
+    pub fn body(&self) -> Vec<u8> {
+        let mut body = self.try_to_vec().expect("PurchaseHistory tem tamanho fixo");
+        for len in [Self::RECEIPT_BODY_LEN, Self::TIPPED_BODY_LEN, Self::BASE_BODY_LEN] {
+            if body[len..].iter().any(|b| *b != 0) {
+                break;
+            }
+            body.truncate(len);
+        }
+        body
+    }
+
+    // Mesma venda sem o recibo nem a variante, para as entradas do histórico paginado
+    pub fn without_receipt(&self) -> Self {
+        PurchaseHistory { unit_price: 0, name_hash: [0; 32], has_variant: false, variant_id: 0, ..*self }
+    }
+
+    pub fn variant(&self) -> Option<u8> {
+        self.has_variant.then_some(self.variant_id)
+    }
+
+    // Lê um corpo de qualquer layout, completando com zeros os campos que ele ainda não tinha
+    pub fn from_body(body: &[u8]) -> Result<Self, ProgramError> {
+        let mut buf = [0u8; Self::BODY_LEN];
+        buf.get_mut(..body.len()).ok_or(CakeError::InvalidAccountData)?.copy_from_slice(body);
+        Self::try_from_slice(&buf).map_err(|_| CakeError::InvalidAccountData.into())
+    }
+
+    // Combo vendido por sell_bundle; None para vendas de um produto e checkouts
+    pub fn bundle_id(&self) -> Option<u64> {
+        if self.product_id == CART_PRODUCT_ID || self.product_id & BUNDLE_PRODUCT_ID_FLAG == 0 {
+            return None;
+        }
+        Some(self.product_id & !BUNDLE_PRODUCT_ID_FLAG)
+    }
+
+    // Fundos ainda no cofre do histórico: em escrow ou em disputa
+    pub fn holds_escrow(&self) -> bool {
+        self.status == PURCHASE_STATUS_ESCROWED || self.status == PURCHASE_STATUS_DISPUTED
+    }
+
+    // Reembolsado por inteiro: nada mais muda no histórico
+    pub fn is_final(&self) -> bool {
+        self.status == PURCHASE_STATUS_REFUNDED
+    }
+
+    pub fn fulfilled_quantity(&self) -> u64 {
+        self.quantity.saturating_sub(self.refunded_quantity)
+    }
+
+    // Valor proporcional; calculado pela diferença acumulada para que a soma dos reembolsos feche em total_price
+    pub fn refund_amount_for(&self, refund_quantity: u64) -> u64 {
+        if self.quantity == 0 {
+            return 0;
+        }
+        let refunded_before = (self.total_price as u128) * (self.refunded_quantity as u128) / (self.quantity as u128);
+        let refunded_after =
+            (self.total_price as u128) * ((self.refunded_quantity + refund_quantity) as u128) / (self.quantity as u128);
+        (refunded_after - refunded_before) as u64
+    }
+
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == Self::LEN {
+            return Self::unpack(src);
+        }
+        if Self::LEGACY_DISCRIMINATED_LENS.contains(&src.len()) {
+            if src[..8] != PURCHASE_HISTORY_DISCRIMINATOR {
+                return Err(CakeError::InvalidAccountData.into());
+            }
+            return Self::from_body(&src[8..]);
+        }
+        if !Self::LEGACY_LENS.contains(&src.len()) {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Self::from_body(src)
+    }
+
+    pub fn pack_versioned(src: Self, dst: &mut [u8]) -> ProgramResult {
+        if dst.len() == Self::LEN {
+            return Self::pack(src, dst);
+        }
+        let body = if Self::LEGACY_DISCRIMINATED_LENS.contains(&dst.len()) {
+            let (discriminator, body) = dst.split_at_mut(8);
+            discriminator.copy_from_slice(&PURCHASE_HISTORY_DISCRIMINATOR);
+            body
+        } else if Self::LEGACY_LENS.contains(&dst.len()) {
+            dst
+        } else {
+            return Err(CakeError::InvalidAccountData.into());
+        };
+        let mut buf = [0u8; Self::BODY_LEN];
+        src.serialize(&mut buf.as_mut_slice()).map_err(|_| CakeError::InvalidAccountData)?;
+        if buf[body.len()..].iter().any(|b| *b != 0) {
+            return Err(CakeError::AccountNeedsMigration.into());
+        }
+        let len = body.len();
+        body.copy_from_slice(&buf[..len]);
+        Ok(())
+    }
+}
+
+pub const HISTORY_PAGE_CAPACITY: usize = 64;
+
+// Cabeçalho do histórico paginado: quantas páginas existem e quantas entradas tem a última
+#[derive(Debug, Clone, Copy, PartialEq, Default, ShankAccount)]
+pub struct HistoryPageHead {
+    pub page_count: u64,
+    pub current_len: u8,
+}
+
+impl HistoryPageHead {
+    // Página em que a próxima venda será gravada; quando a última está cheia é a página nova
+    pub fn write_page(&self) -> u64 {
+        if self.page_count == 0 || self.current_len as usize == HISTORY_PAGE_CAPACITY {
+            self.page_count
+        } else {
+            self.page_count - 1
+        }
+    }
+}
+
+impl Sealed for HistoryPageHead {}
+
+impl IsInitialized for HistoryPageHead {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for HistoryPageHead {
+    const LEN: usize = 9;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0..8].copy_from_slice(&self.page_count.to_le_bytes());
+        dst[8] = self.current_len;
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[8] as usize > HISTORY_PAGE_CAPACITY {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let page_count = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        Ok(HistoryPageHead { page_count, current_len: src[8] })
+    }
+}
+
+// Página do histórico paginado: [page u64][previous Pubkey][len u8][corpo Borsh de PurchaseHistory * HISTORY_PAGE_CAPACITY].
+// previous aponta para a página anterior (Pubkey::default() na primeira), formando uma lista a partir da última
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct HistoryPage {
+    pub page: u64,
+    pub previous: Pubkey,
+    pub entries: Vec<PurchaseHistory>,
+}
+
+impl HistoryPage {
+    pub const HEADER_LEN: usize = 41;
+
+    pub fn pack_header(page: u64, previous: &Pubkey, dst: &mut [u8]) {
+        dst[0..8].copy_from_slice(&page.to_le_bytes());
+        dst[8..40].copy_from_slice(previous.as_ref());
+        dst[40] = 0;
+    }
+
+    // Grava só a entrada nova, no primeiro espaço livre da página. As entradas têm o corpo anterior ao
+    // tip, então vendas paginadas não levam gorjeta nem doação, e o recibo e a variante ficam de fora
+    pub fn append(dst: &mut [u8], entry: &PurchaseHistory) -> ProgramResult {
+        let body = entry.without_receipt().body();
+        if dst.len() != Self::LEN || dst[40] as usize >= HISTORY_PAGE_CAPACITY || body.len() != PurchaseHistory::BASE_BODY_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let offset = Self::HEADER_LEN + dst[40] as usize * PurchaseHistory::BASE_BODY_LEN;
+        dst[offset..offset + PurchaseHistory::BASE_BODY_LEN].copy_from_slice(&body);
+        dst[40] += 1;
+        Ok(())
+    }
+}
+
+impl Sealed for HistoryPage {}
+
+impl IsInitialized for HistoryPage {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for HistoryPage {
+    const LEN: usize = Self::HEADER_LEN + HISTORY_PAGE_CAPACITY * PurchaseHistory::BASE_BODY_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        Self::pack_header(self.page, &self.previous, dst);
+        dst[Self::HEADER_LEN..].fill(0);
+        for entry in &self.entries {
+            Self::append(dst, entry).expect("HistoryPage comporta HISTORY_PAGE_CAPACITY entradas");
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[40] as usize > HISTORY_PAGE_CAPACITY {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let page = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let previous = Pubkey::try_from(&src[8..40]).map_err(|_| CakeError::InvalidAccountData)?;
+        let entries = src[Self::HEADER_LEN..]
+            .chunks_exact(PurchaseHistory::BASE_BODY_LEN)
+            .take(src[40] as usize)
+            .map(PurchaseHistory::from_body)
+            .collect::<Result<_, _>>()?;
+        Ok(HistoryPage { page, previous, entries })
+    }
+}
+
+pub const MAX_ARCHIVE_BATCH: usize = 16;
+
+// Lote de históricos fechados pelo owner, resumido na raiz merkle das folhas HistoryArchive::leaf, na ordem do lote
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct HistoryArchive {
+    pub archive_id: u64,
+    pub merkle_root: [u8; 32],
+    pub leaf_count: u8,
+    pub archived_at: i64,
+}
+
+impl HistoryArchive {
+    // sha256(endereço do histórico || PurchaseHistory::body); o corpo é o mesmo nos layouts antigos e no atual
+    pub fn leaf(history: &Pubkey, entry: &PurchaseHistory) -> [u8; 32] {
+        hashv(&[history.as_ref(), &entry.body()]).to_bytes()
+    }
+
+    // Cada nível combina pares com sha256(esquerda || direita); o último nó de um nível ímpar sobe sem alteração
+    pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level.chunks(2).map(|pair| if pair.len() == 2 { hashv(&[&pair[0], &pair[1]]).to_bytes() } else { pair[0] }).collect();
+        }
+        level.first().copied().unwrap_or_default()
+    }
+
+    // Irmãos da folha index, do nível mais baixo para o mais alto, pulando os níveis em que ela não tem par
+    pub fn proof(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            level = level.chunks(2).map(|pair| if pair.len() == 2 { hashv(&[&pair[0], &pair[1]]).to_bytes() } else { pair[0] }).collect();
+            index /= 2;
+        }
+        proof
+    }
+
+    // Confere que a folha está na posição index do lote arquivado
+    pub fn verify(&self, leaf: &[u8; 32], mut index: usize, proof: &[[u8; 32]]) -> bool {
+        let mut node = *leaf;
+        let mut level_len = self.leaf_count as usize;
+        let mut siblings = proof.iter();
+        if index >= level_len {
+            return false;
+        }
+        while level_len > 1 {
+            if index ^ 1 < level_len {
+                let Some(sibling) = siblings.next() else {
+                    return false;
+                };
+                node = if index.is_multiple_of(2) { hashv(&[&node, sibling]) } else { hashv(&[sibling, &node]) }.to_bytes();
+            }
+            level_len = level_len.div_ceil(2);
+            index /= 2;
+        }
+        siblings.next().is_none() && node == self.merkle_root
+    }
+}
+
+impl Sealed for HistoryArchive {}
+
+impl IsInitialized for HistoryArchive {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for HistoryArchive {
+    const LEN: usize = 49;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0..8].copy_from_slice(&self.archive_id.to_le_bytes());
+        dst[8..40].copy_from_slice(&self.merkle_root);
+        dst[40] = self.leaf_count;
+        dst[41..49].copy_from_slice(&self.archived_at.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(HistoryArchive {
+            archive_id: u64::from_le_bytes(src[0..8].try_into().unwrap()),
+            merkle_root: src[8..40].try_into().unwrap(),
+            leaf_count: src[40],
+            archived_at: i64::from_le_bytes(src[41..49].try_into().unwrap()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct OperatorRole {
+    pub operator: Pubkey,
+}
+
+impl Sealed for OperatorRole {}
+
+impl IsInitialized for OperatorRole {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for OperatorRole {
+    const LEN: usize = 32;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[..32].copy_from_slice(self.operator.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let operator = Pubkey::try_from(&src[..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        Ok(OperatorRole { operator })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct BuyerListEntry {
+    pub buyer: Pubkey,
+}
+
+impl Sealed for BuyerListEntry {}
+
+impl IsInitialized for BuyerListEntry {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for BuyerListEntry {
+    const LEN: usize = 32;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[..32].copy_from_slice(self.buyer.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let buyer = Pubkey::try_from(&src[..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        Ok(BuyerListEntry { buyer })
+    }
+}
+
+// Fluxo de entrega: placed → preparing → out_for_delivery → delivered (confirmado pelo comprador).
+// Fulfilled é a retirada direta e cancelled o reembolso, ambos só antes de sair para entrega
+pub const ORDER_STATUS_PLACED: u8 = 0;
+pub const ORDER_STATUS_FULFILLED: u8 = 1;
+pub const ORDER_STATUS_CANCELLED: u8 = 2;
+pub const ORDER_STATUS_PREPARING: u8 = 3;
+pub const ORDER_STATUS_OUT_FOR_DELIVERY: u8 = 4;
+pub const ORDER_STATUS_DELIVERED: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Order {
+    pub order_id: u64,
+    pub buyer: Pubkey,
+    pub product_id: u64,
+    pub quantity: u64,
+    pub total_price: u64,
+    pub payment_mint: Pubkey,
+    pub created_at: i64,
+    pub status: u8,
+    // Unidades já entregues e pagas ao owner por fulfill_partial_order; o restante continua no cofre
+    pub fulfilled_quantity: u64,
+}
+
+impl Sealed for Order {}
+
+impl IsInitialized for Order {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Order {
+    const LEN: usize = 113;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.order_id.to_le_bytes());
+        slice[8..40].copy_from_slice(self.buyer.as_ref());
+        slice[40..48].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[48..56].copy_from_slice(&self.quantity.to_le_bytes());
+        slice[56..64].copy_from_slice(&self.total_price.to_le_bytes());
+        slice[64..96].copy_from_slice(self.payment_mint.as_ref());
+        slice[96..104].copy_from_slice(&self.created_at.to_le_bytes());
+        slice[104] = self.status;
+        slice[105..113].copy_from_slice(&self.fulfilled_quantity.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let order_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let buyer = Pubkey::try_from(&src[8..40]).map_err(|_| CakeError::InvalidAccountData)?;
+        let product_id = u64::from_le_bytes(src[40..48].try_into().unwrap());
+        let quantity = u64::from_le_bytes(src[48..56].try_into().unwrap());
+        let total_price = u64::from_le_bytes(src[56..64].try_into().unwrap());
+        let payment_mint = Pubkey::try_from(&src[64..96]).map_err(|_| CakeError::InvalidAccountData)?;
+        let created_at = i64::from_le_bytes(src[96..104].try_into().unwrap());
+        let status = src[104];
+        let fulfilled_quantity = u64::from_le_bytes(src[105..113].try_into().unwrap());
+        Ok(Order { order_id, buyer, product_id, quantity, total_price, payment_mint, created_at, status, fulfilled_quantity })
+    }
+}
+
+impl Order {
+    // Pedidos criados antes da entrega parcial
+    pub const LEGACY_LEN: usize = 105;
+
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == Self::LEN {
+            return Self::unpack(src);
+        }
+        if src.len() != Self::LEGACY_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut buf = [0u8; Self::LEN];
+        buf[..src.len()].copy_from_slice(src);
+        Self::unpack(&buf)
+    }
+
+    // Pedidos antigos só guardam fulfilled_quantity depois de crescer até LEN
+    pub fn pack_versioned(src: Self, dst: &mut [u8]) -> ProgramResult {
+        if dst.len() == Self::LEN {
+            return Self::pack(src, dst);
+        }
+        if dst.len() != Self::LEGACY_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        if src.fulfilled_quantity != 0 {
+            return Err(CakeError::AccountNeedsMigration.into());
+        }
+        let mut buf = [0u8; Self::LEN];
+        src.pack_into_slice(&mut buf);
+        dst.copy_from_slice(&buf[..Self::LEGACY_LEN]);
+        Ok(())
+    }
+
+    pub fn remaining_quantity(&self) -> u64 {
+        self.quantity.saturating_sub(self.fulfilled_quantity)
+    }
+
+    // Valor proporcional; calculado pela diferença acumulada para que a soma das entregas feche em total_price
+    pub fn release_amount_for(&self, quantity: u64) -> u64 {
+        if self.quantity == 0 {
+            return 0;
+        }
+        let released_before = (self.total_price as u128) * (self.fulfilled_quantity as u128) / (self.quantity as u128);
+        let released_after =
+            (self.total_price as u128) * ((self.fulfilled_quantity + quantity) as u128) / (self.quantity as u128);
+        (released_after - released_before) as u64
+    }
+
+    // O que ainda está no cofre: pago ao owner no fulfill/entrega ou devolvido no cancelamento
+    pub fn remaining_amount(&self) -> u64 {
+        self.release_amount_for(self.remaining_quantity())
+    }
+
+    // Transições só avançam; fulfilled, cancelled e delivered são finais
+    pub fn can_transition(&self, status: u8) -> bool {
+        matches!(
+            (self.status, status),
+            (ORDER_STATUS_PLACED, ORDER_STATUS_PREPARING)
+                | (ORDER_STATUS_PREPARING, ORDER_STATUS_OUT_FOR_DELIVERY)
+                | (ORDER_STATUS_OUT_FOR_DELIVERY, ORDER_STATUS_DELIVERED)
+                | (ORDER_STATUS_PLACED | ORDER_STATUS_PREPARING, ORDER_STATUS_FULFILLED | ORDER_STATUS_CANCELLED)
+        )
+    }
+}
+
+// Lançamento de um produto ainda sem estoque: pré-encomendas até release_at, convertidas em vendas a partir dele
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Launch {
+    pub product_id: u64,
+    pub release_at: i64,
+    pub unit_price: u64,
+    pub max_quantity: u64,
+    pub reserved: u64,
+    pub cancelled: bool,
+}
+
+impl Sealed for Launch {}
+
+impl IsInitialized for Launch {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Launch {
+    const LEN: usize = 41;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8..16].copy_from_slice(&self.release_at.to_le_bytes());
+        slice[16..24].copy_from_slice(&self.unit_price.to_le_bytes());
+        slice[24..32].copy_from_slice(&self.max_quantity.to_le_bytes());
+        slice[32..40].copy_from_slice(&self.reserved.to_le_bytes());
+        slice[40] = self.cancelled as u8;
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(Launch {
+            product_id: u64::from_le_bytes(src[0..8].try_into().unwrap()),
+            release_at: i64::from_le_bytes(src[8..16].try_into().unwrap()),
+            unit_price: u64::from_le_bytes(src[16..24].try_into().unwrap()),
+            max_quantity: u64::from_le_bytes(src[24..32].try_into().unwrap()),
+            reserved: u64::from_le_bytes(src[32..40].try_into().unwrap()),
+            cancelled: src[40] != 0,
+        })
+    }
+}
+
+// Pré-encomenda de um comprador para um lançamento; o pagamento fica no cofre ["pre_order_vault", pre_order].
+// status usa os ORDER_STATUS_*: fulfilled na conversão, cancelled no reembolso
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct PreOrder {
+    pub product_id: u64,
+    pub buyer: Pubkey,
+    pub quantity: u64,
+    pub total_price: u64,
+    pub payment_mint: Pubkey,
+    pub created_at: i64,
+    pub status: u8,
+}
+
+impl Sealed for PreOrder {}
+
+impl IsInitialized for PreOrder {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for PreOrder {
+    const LEN: usize = 97;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8..40].copy_from_slice(self.buyer.as_ref());
+        slice[40..48].copy_from_slice(&self.quantity.to_le_bytes());
+        slice[48..56].copy_from_slice(&self.total_price.to_le_bytes());
+        slice[56..88].copy_from_slice(self.payment_mint.as_ref());
+        slice[88..96].copy_from_slice(&self.created_at.to_le_bytes());
+        slice[96] = self.status;
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let buyer = Pubkey::try_from(&src[8..40]).map_err(|_| CakeError::InvalidAccountData)?;
+        let quantity = u64::from_le_bytes(src[40..48].try_into().unwrap());
+        let total_price = u64::from_le_bytes(src[48..56].try_into().unwrap());
+        let payment_mint = Pubkey::try_from(&src[56..88]).map_err(|_| CakeError::InvalidAccountData)?;
+        let created_at = i64::from_le_bytes(src[88..96].try_into().unwrap());
+        let status = src[96];
+        Ok(PreOrder { product_id, buyer, quantity, total_price, payment_mint, created_at, status })
+    }
+}
+
+pub const COUPON_KIND_AMOUNT: u8 = 0;
+pub const COUPON_KIND_PERCENT_BPS: u8 = 1;
+pub const MAX_COUPON_CODE_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Coupon {
+    pub code_hash: [u8; 32],
+    pub kind: u8,
+    pub value: u64,
+    pub expires_at: i64,
+    pub max_uses: u64,
+    pub uses: u64,
+}
+
+impl Sealed for Coupon {}
+
+impl IsInitialized for Coupon {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Coupon {
+    const LEN: usize = 65;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(&self.code_hash);
+        slice[32] = self.kind;
+        slice[33..41].copy_from_slice(&self.value.to_le_bytes());
+        slice[41..49].copy_from_slice(&self.expires_at.to_le_bytes());
+        slice[49..57].copy_from_slice(&self.max_uses.to_le_bytes());
+        slice[57..65].copy_from_slice(&self.uses.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&src[0..32]);
+        let kind = src[32];
+        let value = u64::from_le_bytes(src[33..41].try_into().unwrap());
+        let expires_at = i64::from_le_bytes(src[41..49].try_into().unwrap());
+        let max_uses = u64::from_le_bytes(src[49..57].try_into().unwrap());
+        let uses = u64::from_le_bytes(src[57..65].try_into().unwrap());
+        Ok(Coupon { code_hash, kind, value, expires_at, max_uses, uses })
+    }
+}
+
+impl Coupon {
+    pub fn discount(&self, total_price: u64) -> Result<u64, ProgramError> {
+        let discount = match self.kind {
+            COUPON_KIND_AMOUNT => self.value,
+            COUPON_KIND_PERCENT_BPS => ((total_price as u128) * (self.value as u128) / 10_000) as u64,
+            _ => return Err(CakeError::InvalidCoupon.into()),
+        };
+        Ok(discount.min(total_price))
+    }
+}
+
+// Perfil opcional do comprador, criado por ele; o sell acumula o gasto quando a conta existe.
+// total_spent em unidades de preço (price_decimals) no modo token, total_spent_sol em lamports
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct BuyerProfile {
+    pub buyer: Pubkey,
+    pub display_name_hash: [u8; 32],
+    pub preferred_mint: Pubkey,
+    pub contact_hash: [u8; 32],
+    pub total_spent: u64,
+    pub total_spent_sol: u64,
+}
+
+impl Sealed for BuyerProfile {}
+
+impl IsInitialized for BuyerProfile {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for BuyerProfile {
+    const LEN: usize = 144;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.buyer.as_ref());
+        slice[32..64].copy_from_slice(&self.display_name_hash);
+        slice[64..96].copy_from_slice(self.preferred_mint.as_ref());
+        slice[96..128].copy_from_slice(&self.contact_hash);
+        slice[128..136].copy_from_slice(&self.total_spent.to_le_bytes());
+        slice[136..144].copy_from_slice(&self.total_spent_sol.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let buyer = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let display_name_hash = src[32..64].try_into().unwrap();
+        let preferred_mint = Pubkey::try_from(&src[64..96]).map_err(|_| CakeError::InvalidAccountData)?;
+        let contact_hash = src[96..128].try_into().unwrap();
+        let total_spent = u64::from_le_bytes(src[128..136].try_into().unwrap());
+        let total_spent_sol = u64::from_le_bytes(src[136..144].try_into().unwrap());
+        Ok(BuyerProfile { buyer, display_name_hash, preferred_mint, contact_hash, total_spent, total_spent_sol })
+    }
+}
+
+impl BuyerProfile {
+    // Mesmas unidades do Product::record_sale
+    pub fn record_purchase(&mut self, total_price: u64, payment_mode: u8) -> ProgramResult {
+        if payment_mode == PAYMENT_MODE_SOL {
+            self.total_spent_sol = self.total_spent_sol.checked_add(total_price).ok_or(CakeError::ArithmeticOverflow)?;
+        } else {
+            self.total_spent = self.total_spent.checked_add(total_price).ok_or(CakeError::ArithmeticOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+// Promoção relâmpago de um produto: desconto em bps aplicado pelo sell entre start (inclusive) e end (exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct FlashSale {
+    pub product_id: u64,
+    pub discount_bps: u16,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Sealed for FlashSale {}
+
+impl IsInitialized for FlashSale {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for FlashSale {
+    const LEN: usize = 26;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8..10].copy_from_slice(&self.discount_bps.to_le_bytes());
+        slice[10..18].copy_from_slice(&self.start.to_le_bytes());
+        slice[18..26].copy_from_slice(&self.end.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(FlashSale {
+            product_id: u64::from_le_bytes(src[0..8].try_into().unwrap()),
+            discount_bps: u16::from_le_bytes(src[8..10].try_into().unwrap()),
+            start: i64::from_le_bytes(src[10..18].try_into().unwrap()),
+            end: i64::from_le_bytes(src[18..26].try_into().unwrap()),
+        })
+    }
+}
+
+impl FlashSale {
+    // Fora da janela o desconto é zero
+    pub fn discount(&self, total_price: u64, now: i64) -> u64 {
+        if now < self.start || now >= self.end {
+            return 0;
+        }
+        ((total_price as u128) * (self.discount_bps.min(10_000) as u128) / 10_000) as u64
+    }
+}
+
+pub const MAX_HAPPY_HOUR_WINDOWS: usize = 4;
+pub const MINUTES_PER_DAY: u16 = 24 * 60;
+// Dias da semana em HappyHourWindow::days: bit 0 = segunda ... bit 6 = domingo
+pub const ALL_WEEKDAYS: u8 = 0x7f;
+
+// Janela semanal recorrente, em UTC: nos dias marcados em days, de start_minute (inclusive) a end_minute
+// (exclusive), contados desde 00:00. Não atravessa a meia-noite; uma janela noturna vira duas
+#[derive(Debug, Clone, Copy, PartialEq, ShankType)]
+pub struct HappyHourWindow {
+    pub days: u8,
+    pub start_minute: u16,
+    pub end_minute: u16,
+    pub discount_bps: u16,
+}
+
+impl HappyHourWindow {
+    pub const LEN: usize = 7;
+
+    pub fn is_valid(&self) -> bool {
+        self.days != 0
+            && self.days & !ALL_WEEKDAYS == 0
+            && self.start_minute < self.end_minute
+            && self.end_minute <= MINUTES_PER_DAY
+            && self.discount_bps > 0
+            && self.discount_bps <= 10_000
+    }
+
+    pub fn contains(&self, now: i64) -> bool {
+        // 01/01/1970 foi uma quinta-feira
+        let weekday = (now.div_euclid(86_400) + 3).rem_euclid(7);
+        let minute = (now.rem_euclid(86_400) / 60) as u16;
+        self.days & (1 << weekday) != 0 && minute >= self.start_minute && minute < self.end_minute
+    }
+}
+
+// Descontos recorrentes do produto ("dias úteis, 15:00–17:00 UTC, 20% off") na PDA ["happy_hour", cake, product_id]
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct HappyHour {
+    pub product_id: u64,
+    pub windows: Vec<HappyHourWindow>,
+}
+
+impl Sealed for HappyHour {}
+
+impl IsInitialized for HappyHour {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for HappyHour {
+    const LEN: usize = 9 + MAX_HAPPY_HOUR_WINDOWS * HappyHourWindow::LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8] = self.windows.len() as u8;
+        slice[9..].fill(0);
+        for (window, chunk) in self.windows.iter().zip(slice[9..].chunks_exact_mut(HappyHourWindow::LEN)) {
+            chunk[0] = window.days;
+            chunk[1..3].copy_from_slice(&window.start_minute.to_le_bytes());
+            chunk[3..5].copy_from_slice(&window.end_minute.to_le_bytes());
+            chunk[5..7].copy_from_slice(&window.discount_bps.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[8] as usize > MAX_HAPPY_HOUR_WINDOWS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let windows = src[9..9 + src[8] as usize * HappyHourWindow::LEN]
+            .chunks_exact(HappyHourWindow::LEN)
+            .map(|chunk| HappyHourWindow {
+                days: chunk[0],
+                start_minute: u16::from_le_bytes(chunk[1..3].try_into().unwrap()),
+                end_minute: u16::from_le_bytes(chunk[3..5].try_into().unwrap()),
+                discount_bps: u16::from_le_bytes(chunk[5..7].try_into().unwrap()),
+            })
+            .collect();
+        Ok(HappyHour { product_id: u64::from_le_bytes(src[0..8].try_into().unwrap()), windows })
+    }
+}
+
+impl HappyHour {
+    // Maior desconto entre as janelas abertas em now; 0 fora delas
+    pub fn active_discount_bps(&self, now: i64) -> u16 {
+        self.windows.iter().filter(|window| window.contains(now)).map(|window| window.discount_bps.min(10_000)).max().unwrap_or(0)
+    }
+
+    pub fn discount(&self, total_price: u64, now: i64) -> u64 {
+        ((total_price as u128) * (self.active_discount_bps(now) as u128) / 10_000) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankType)]
+pub struct BundleItem {
+    pub product_id: u64,
+    pub quantity: u64,
+}
+
+// Combo fixo na PDA ["bundle", cake, bundle_id]: cada unidade leva quantity de cada item por price, nas casas
+// decimais da configuração. A venda baixa o estoque de todos os itens e grava um único histórico
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct Bundle {
+    pub bundle_id: u64,
+    pub price: u64,
+    pub items: Vec<BundleItem>,
+}
+
+impl Sealed for Bundle {}
+
+impl IsInitialized for Bundle {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Bundle {
+    const LEN: usize = 17 + MAX_BUNDLE_ITEMS * 16;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.bundle_id.to_le_bytes());
+        slice[8..16].copy_from_slice(&self.price.to_le_bytes());
+        slice[16] = self.items.len() as u8;
+        slice[17..].fill(0);
+        for (item, chunk) in self.items.iter().zip(slice[17..].chunks_exact_mut(16)) {
+            chunk[..8].copy_from_slice(&item.product_id.to_le_bytes());
+            chunk[8..].copy_from_slice(&item.quantity.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[16] as usize > MAX_BUNDLE_ITEMS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let items = src[17..17 + src[16] as usize * 16]
+            .chunks_exact(16)
+            .map(|chunk| BundleItem {
+                product_id: u64::from_le_bytes(chunk[..8].try_into().unwrap()),
+                quantity: u64::from_le_bytes(chunk[8..].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Bundle {
+            bundle_id: u64::from_le_bytes(src[0..8].try_into().unwrap()),
+            price: u64::from_le_bytes(src[8..16].try_into().unwrap()),
+            items,
+        })
+    }
+}
+
+impl Bundle {
+    // product_id gravado no histórico das vendas do combo
+    pub fn history_product_id(&self) -> u64 {
+        BUNDLE_PRODUCT_ID_FLAG | self.bundle_id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct BuyerPurchaseCounter {
+    pub buyer: Pubkey,
+    pub product_id: u64,
+    pub quantity: u64,
+}
+
+impl Sealed for BuyerPurchaseCounter {}
+
+impl IsInitialized for BuyerPurchaseCounter {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for BuyerPurchaseCounter {
+    const LEN: usize = 48;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.buyer.as_ref());
+        slice[32..40].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[40..48].copy_from_slice(&self.quantity.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let buyer = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let product_id = u64::from_le_bytes(src[32..40].try_into().unwrap());
+        let quantity = u64::from_le_bytes(src[40..48].try_into().unwrap());
+        Ok(BuyerPurchaseCounter { buyer, product_id, quantity })
+    }
+}
+
+pub const MAX_MEMO_LEN: usize = 64;
+
+// Recado do comprador numa compra ("Feliz aniversário, Ana"), na PDA ["note", history]: [history 32][memo_len u8][memo 64]
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct PurchaseNote {
+    pub history: Pubkey,
+    pub memo: Vec<u8>,
+}
+
+impl Sealed for PurchaseNote {}
+
+impl IsInitialized for PurchaseNote {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for PurchaseNote {
+    const LEN: usize = 32 + 1 + MAX_MEMO_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.history.as_ref());
+        slice[32] = self.memo.len() as u8;
+        slice[33..33 + self.memo.len()].copy_from_slice(&self.memo);
+        slice[33 + self.memo.len()..Self::LEN].fill(0);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[32] as usize > MAX_MEMO_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let history = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let memo = src[33..33 + src[32] as usize].to_vec();
+        Ok(PurchaseNote { history, memo })
+    }
+}
+
+// Valor de uma compra confidencial, na PDA ["confidential_payment", history]: os compromissos de Pedersen das
+// metades baixa (16 bits) e alta (32 bits) do valor transferido, copiados da prova de validade do texto cifrado.
+// Quem tem a chave ElGamal da conta de destino abre o valor e confere os compromissos sem publicá-lo
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct ConfidentialPayment {
+    pub history: Pubkey,
+    pub mint: Pubkey,
+    pub commitment_lo: [u8; 32],
+    pub commitment_hi: [u8; 32],
+}
+
+impl Sealed for ConfidentialPayment {}
+
+impl IsInitialized for ConfidentialPayment {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for ConfidentialPayment {
+    const LEN: usize = 128;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.history.as_ref());
+        slice[32..64].copy_from_slice(self.mint.as_ref());
+        slice[64..96].copy_from_slice(&self.commitment_lo);
+        slice[96..128].copy_from_slice(&self.commitment_hi);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(ConfidentialPayment {
+            history: Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?,
+            mint: Pubkey::try_from(&src[32..64]).map_err(|_| CakeError::InvalidAccountData)?,
+            commitment_lo: src[64..96].try_into().unwrap(),
+            commitment_hi: src[96..128].try_into().unwrap(),
+        })
+    }
+}
+
+pub const DISPUTE_STATUS_OPEN: u8 = 0;
+pub const DISPUTE_STATUS_REFUNDED: u8 = 1;
+pub const DISPUTE_STATUS_RELEASED: u8 = 2;
+
+// Disputa aberta pelo comprador contra uma compra em escrow, na PDA ["dispute", history]. O árbitro da
+// configuração decide entre devolver ao comprador (REFUNDED) e liberar ao owner (RELEASED); uma por compra
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Dispute {
+    pub history: Pubkey,
+    pub buyer: Pubkey,
+    pub opened_at: i64,
+    pub status: u8,
+    // Quem decidiu e quando; zerados enquanto a disputa está aberta
+    pub arbiter: Pubkey,
+    pub resolved_at: i64,
+}
+
+impl Sealed for Dispute {}
+
+impl IsInitialized for Dispute {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Dispute {
+    const LEN: usize = 113;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.history.as_ref());
+        slice[32..64].copy_from_slice(self.buyer.as_ref());
+        slice[64..72].copy_from_slice(&self.opened_at.to_le_bytes());
+        slice[72] = self.status;
+        slice[73..105].copy_from_slice(self.arbiter.as_ref());
+        slice[105..113].copy_from_slice(&self.resolved_at.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[72] > DISPUTE_STATUS_RELEASED {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        Ok(Dispute {
+            history: Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?,
+            buyer: Pubkey::try_from(&src[32..64]).map_err(|_| CakeError::InvalidAccountData)?,
+            opened_at: i64::from_le_bytes(src[64..72].try_into().unwrap()),
+            status: src[72],
+            arbiter: Pubkey::try_from(&src[73..105]).map_err(|_| CakeError::InvalidAccountData)?,
+            resolved_at: i64::from_le_bytes(src[105..113].try_into().unwrap()),
+        })
+    }
+}
+
+// Quantos históricos o comprador já tem; a próxima compra usa a sequência count
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct BuyerHistoryCounter {
+    pub buyer: Pubkey,
+    pub count: u64,
+}
+
+impl Sealed for BuyerHistoryCounter {}
+
+impl IsInitialized for BuyerHistoryCounter {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for BuyerHistoryCounter {
+    const LEN: usize = 40;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.buyer.as_ref());
+        slice[32..40].copy_from_slice(&self.count.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let buyer = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let count = u64::from_le_bytes(src[32..40].try_into().unwrap());
+        Ok(BuyerHistoryCounter { buyer, count })
+    }
+}
+
+// Código ISO 639-1 em minúsculas ("pt", "en")
+pub const LANG_CODE_LEN: usize = 2;
+
+pub fn is_valid_lang_code(lang_code: &[u8; LANG_CODE_LEN]) -> bool {
+    lang_code.iter().all(u8::is_ascii_lowercase)
+}
+
+// Nome e descrição de um produto em outro idioma, na PDA ["localization", cake, product_id, lang_code]:
+// [product_id 8][lang_code 2][name_len u8][name 64][description_len u16][description 512]
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct Localization {
+    pub product_id: u64,
+    // Tamanho literal (= LANG_CODE_LEN) por causa do shank
+    pub lang_code: [u8; 2],
+    pub name: String,
+    pub description: String,
+}
+
+impl Sealed for Localization {}
+
+impl IsInitialized for Localization {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Localization {
+    const LEN: usize = 8 + LANG_CODE_LEN + 1 + MAX_PRODUCT_NAME_LEN + 2 + MAX_PRODUCT_DESCRIPTION_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8..10].copy_from_slice(&self.lang_code);
+        slice[10] = self.name.len() as u8;
+        slice[11..75].fill(0);
+        slice[11..11 + self.name.len()].copy_from_slice(self.name.as_bytes());
+        slice[75..77].copy_from_slice(&(self.description.len() as u16).to_le_bytes());
+        slice[77..].fill(0);
+        slice[77..77 + self.description.len()].copy_from_slice(self.description.as_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let lang_code = src[8..10].try_into().unwrap();
+        let name_len = src[10] as usize;
+        let description_len = u16::from_le_bytes(src[75..77].try_into().unwrap()) as usize;
+        if name_len > MAX_PRODUCT_NAME_LEN || description_len > MAX_PRODUCT_DESCRIPTION_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let name = String::from_utf8(src[11..11 + name_len].to_vec()).map_err(|_| CakeError::InvalidAccountData)?;
+        let description = String::from_utf8(src[77..77 + description_len].to_vec()).map_err(|_| CakeError::InvalidAccountData)?;
+        Ok(Localization { product_id, lang_code, name, description })
+    }
+}
+
+pub const NO_CATEGORY: u64 = u64::MAX;
+pub const MAX_CATEGORY_PRODUCTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Category {
+    pub id: u64,
+    pub name: [u8; 32],
+}
+
+impl Sealed for Category {}
+
+impl IsInitialized for Category {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Category {
+    const LEN: usize = 40;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.id.to_le_bytes());
+        slice[8..40].copy_from_slice(&self.name);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let mut name = [0u8; 32];
+        name.copy_from_slice(&src[8..40]);
+        Ok(Category { id, name })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct CategoryIndex {
+    pub category_id: u64,
+    pub product_ids: Vec<u64>,
+}
+
+impl Sealed for CategoryIndex {}
+
+impl IsInitialized for CategoryIndex {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for CategoryIndex {
+    const LEN: usize = 12 + MAX_CATEGORY_PRODUCTS * 8;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.category_id.to_le_bytes());
+        slice[8..12].copy_from_slice(&(self.product_ids.len() as u32).to_le_bytes());
+        slice[12..].fill(0);
+        for (i, product_id) in self.product_ids.iter().enumerate() {
+            slice[12 + i * 8..20 + i * 8].copy_from_slice(&product_id.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let category_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let count = u32::from_le_bytes(src[8..12].try_into().unwrap()) as usize;
+        if count > MAX_CATEGORY_PRODUCTS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_ids = src[12..12 + count * 8]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Ok(CategoryIndex { category_id, product_ids })
+    }
+}
+
+pub const TAG_LEN: usize = 16;
+pub const MAX_PRODUCT_TAGS: usize = 8;
+pub const MAX_TAG_PRODUCTS: usize = 64;
+
+// Tags do produto ("sem-gluten", "vegano"), na PDA ["product_tags", cake, product_id]. Cada tag é um texto
+// UTF-8 de até 16 bytes completado com zeros
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct ProductTags {
+    pub product_id: u64,
+    // Tamanhos literais (= TAG_LEN) por causa do shank
+    pub tags: Vec<[u8; 16]>,
+}
+
+impl Sealed for ProductTags {}
+
+impl IsInitialized for ProductTags {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for ProductTags {
+    const LEN: usize = 9 + MAX_PRODUCT_TAGS * TAG_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8] = self.tags.len() as u8;
+        slice[9..].fill(0);
+        for (i, tag) in self.tags.iter().enumerate() {
+            slice[9 + i * TAG_LEN..9 + (i + 1) * TAG_LEN].copy_from_slice(tag);
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN || src[8] as usize > MAX_PRODUCT_TAGS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let tags = src[9..9 + src[8] as usize * TAG_LEN].chunks_exact(TAG_LEN).map(|chunk| chunk.try_into().unwrap()).collect();
+        Ok(ProductTags { product_id, tags })
+    }
+}
+
+// Produtos com uma tag, na PDA ["tag_index", cake, tag]; AddProduct, SetProductTags e CloseProduct mantêm a lista
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct TagIndex {
+    pub tag: [u8; 16],
+    pub product_ids: Vec<u64>,
+}
+
+impl Sealed for TagIndex {}
+
+impl IsInitialized for TagIndex {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for TagIndex {
+    const LEN: usize = TAG_LEN + 4 + MAX_TAG_PRODUCTS * 8;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..16].copy_from_slice(&self.tag);
+        slice[16..20].copy_from_slice(&(self.product_ids.len() as u32).to_le_bytes());
+        slice[20..].fill(0);
+        for (i, product_id) in self.product_ids.iter().enumerate() {
+            slice[20 + i * 8..28 + i * 8].copy_from_slice(&product_id.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let tag = src[0..16].try_into().unwrap();
+        let count = u32::from_le_bytes(src[16..20].try_into().unwrap()) as usize;
+        if count > MAX_TAG_PRODUCTS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_ids = src[20..20 + count * 8].chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+        Ok(TagIndex { tag, product_ids })
+    }
+}
+
+pub const MAX_OWNER_SET_MEMBERS: usize = 10;
+
+// Conjunto de owners M-de-N da loja. Quando CakeState::owner aponta para esta PDA, as instruções
+// administrativas exigem a assinatura de pelo menos threshold membros
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct OwnerSet {
+    pub store: Pubkey,
+    pub threshold: u8,
+    pub members: Vec<Pubkey>,
+}
+
+impl Sealed for OwnerSet {}
+
+impl IsInitialized for OwnerSet {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for OwnerSet {
+    const LEN: usize = 34 + MAX_OWNER_SET_MEMBERS * 32;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.store.as_ref());
+        slice[32] = self.threshold;
+        slice[33] = self.members.len() as u8;
+        slice[34..].fill(0);
+        for (i, member) in self.members.iter().enumerate() {
+            slice[34 + i * 32..66 + i * 32].copy_from_slice(member.as_ref());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let store = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let threshold = src[32];
+        let count = src[33] as usize;
+        if count > MAX_OWNER_SET_MEMBERS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let members = src[34..34 + count * 32].chunks_exact(32).map(|chunk| Pubkey::try_from(chunk).unwrap()).collect();
+        Ok(OwnerSet { store, threshold, members })
+    }
+}
+
+impl OwnerSet {
+    // Pelo menos um membro, sem repetidos, e threshold entre 1 e o número de membros
+    pub fn is_valid(&self) -> bool {
+        let distinct = self.members.iter().enumerate().all(|(i, member)| !self.members[..i].contains(member));
+        distinct && self.members.len() <= MAX_OWNER_SET_MEMBERS && self.threshold >= 1 && self.threshold as usize <= self.members.len()
+    }
+
+    // Quantos membros estão entre os signatários da instrução
+    pub fn approvals(&self, signers: &[Pubkey]) -> usize {
+        self.members.iter().filter(|member| signers.contains(member)).count()
+    }
+
+    pub fn is_approved(&self, signers: &[Pubkey]) -> bool {
+        self.approvals(signers) >= self.threshold as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankType)]
+pub struct PurchaseIndexEntry {
+    pub buyer: Pubkey,
+    pub sequence: u64,
+}
+
+// Compras de um produto na ordem das vendas: [product_id u64][count u64][(buyer, sequence) * count].
+// Cada entrada aponta para a PDA ["history", buyer, sequence]; a conta cresce uma entrada por venda
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct ProductPurchaseIndex {
+    pub product_id: u64,
+    pub entries: Vec<PurchaseIndexEntry>,
+}
+
+impl ProductPurchaseIndex {
+    pub const HEADER_LEN: usize = 16;
+    pub const ENTRY_LEN: usize = 40;
+
+    pub fn account_len(count: usize) -> usize {
+        Self::HEADER_LEN + count * Self::ENTRY_LEN
+    }
+
+    pub fn pack_header(product_id: u64, count: u64, dst: &mut [u8]) {
+        dst[0..8].copy_from_slice(&product_id.to_le_bytes());
+        dst[8..16].copy_from_slice(&count.to_le_bytes());
+    }
+
+    fn read_header(src: &[u8]) -> Result<(u64, usize), ProgramError> {
+        if src.len() < Self::HEADER_LEN {
+            return Err(CakeError::AccountTooSmall.into());
+        }
+        let product_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let count = u64::from_le_bytes(src[8..16].try_into().unwrap());
+        Ok((product_id, usize::try_from(count).map_err(|_| CakeError::InvalidAccountData)?))
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let (product_id, count) = Self::read_header(src)?;
+        if count.checked_mul(Self::ENTRY_LEN).and_then(|len| len.checked_add(Self::HEADER_LEN)) != Some(src.len()) {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let entries = src[Self::HEADER_LEN..]
+            .chunks_exact(Self::ENTRY_LEN)
+            .map(|chunk| PurchaseIndexEntry {
+                buyer: Pubkey::try_from(&chunk[0..32]).unwrap(),
+                sequence: u64::from_le_bytes(chunk[32..40].try_into().unwrap()),
+            })
+            .collect();
+        Ok(ProductPurchaseIndex { product_id, entries })
+    }
+
+    // Grava só a entrada nova no espaço já alocado para ela (dst tem account_len(count + 1) bytes)
+    pub fn append(dst: &mut [u8], product_id: u64, entry: &PurchaseIndexEntry) -> ProgramResult {
+        let (index_product_id, count) = Self::read_header(dst)?;
+        if index_product_id != product_id || dst.len() != Self::account_len(count + 1) {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let offset = Self::account_len(count);
+        dst[offset..offset + 32].copy_from_slice(entry.buyer.as_ref());
+        dst[offset + 32..offset + 40].copy_from_slice(&entry.sequence.to_le_bytes());
+        Self::pack_header(product_id, count as u64 + 1, dst);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Variant {
+    pub product_id: u64,
+    pub variant_id: u8,
+    pub name: [u8; 32],
+    pub price: u64,
+    pub stock: u64,
+}
+
+impl Sealed for Variant {}
+
+impl IsInitialized for Variant {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Variant {
+    const LEN: usize = 57;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[8] = self.variant_id;
+        slice[9..41].copy_from_slice(&self.name);
+        slice[41..49].copy_from_slice(&self.price.to_le_bytes());
+        slice[49..57].copy_from_slice(&self.stock.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let product_id = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let variant_id = src[8];
+        let mut name = [0u8; 32];
+        name.copy_from_slice(&src[9..41]);
+        let price = u64::from_le_bytes(src[41..49].try_into().unwrap());
+        let stock = u64::from_le_bytes(src[49..57].try_into().unwrap());
+        Ok(Variant { product_id, variant_id, name, price, stock })
+    }
+}
+
+pub const MAX_ACCEPTED_MINTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, ShankType)]
+pub struct AcceptedMint {
+    pub mint: Pubkey,
+    pub decimals: u8,
+}
+
+pub const MAX_SPLIT_RECIPIENTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, ShankType)]
+pub struct SplitRecipient {
+    pub token_account: Pubkey,
+    pub share_bps: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Config {
+    pub payment_mint: Pubkey,
+    pub price_decimals: u8,
+    pub accepted_mint_count: u8,
+    // Tamanhos literais (= MAX_ACCEPTED_MINTS, MAX_SPLIT_RECIPIENTS) por causa do shank
+    pub accepted_mints: [AcceptedMint; 4],
+    pub split_count: u8,
+    pub splits: [SplitRecipient; 4],
+    pub history_retention: i64,
+    // Parte da venda (após a comissão) creditada ao referrer quando o comprador usa um código de indicação
+    pub referral_bps: u16,
+    // Conta de token que recebe as gorjetas do sell; Pubkey::default() desativa as gorjetas
+    pub tip_account: Pubkey,
+    // Arredondamento opcional do sell: o total sobe até o múltiplo de round_up_unit (em unidades do mint)
+    // e a diferença vai para charity_account; round_up_unit = 0 desativa
+    pub charity_account: Pubkey,
+    pub round_up_unit: u64,
+    // Quem decide as disputas de escrow; Pubkey::default() desativa as disputas. O comprador pode abrir
+    // uma disputa até dispute_window segundos depois da compra
+    pub arbiter: Pubkey,
+    pub dispute_window: i64,
+}
+
+impl Sealed for Config {}
+
+impl IsInitialized for Config {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Config {
+    const LEN: usize = 35 + MAX_ACCEPTED_MINTS * 33 + MAX_SPLIT_RECIPIENTS * 34 + 8 + 2 + 32 + 32 + 8 + 32 + 8;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[..32].copy_from_slice(self.payment_mint.as_ref());
+        slice[32] = self.price_decimals;
+        slice[33] = self.accepted_mint_count;
+        for (i, accepted) in self.accepted_mints.iter().enumerate() {
+            let start = 34 + i * 33;
+            slice[start..start + 32].copy_from_slice(accepted.mint.as_ref());
+            slice[start + 32] = accepted.decimals;
+        }
+        let splits_start = 34 + MAX_ACCEPTED_MINTS * 33;
+        slice[splits_start] = self.split_count;
+        for (i, split) in self.splits.iter().enumerate() {
+            let start = splits_start + 1 + i * 34;
+            slice[start..start + 32].copy_from_slice(split.token_account.as_ref());
+            slice[start + 32..start + 34].copy_from_slice(&split.share_bps.to_le_bytes());
+        }
+        let retention_start = splits_start + 1 + MAX_SPLIT_RECIPIENTS * 34;
+        slice[retention_start..retention_start + 8].copy_from_slice(&self.history_retention.to_le_bytes());
+        slice[retention_start + 8..retention_start + 10].copy_from_slice(&self.referral_bps.to_le_bytes());
+        slice[retention_start + 10..retention_start + 42].copy_from_slice(self.tip_account.as_ref());
+        slice[retention_start + 42..retention_start + 74].copy_from_slice(self.charity_account.as_ref());
+        slice[retention_start + 74..retention_start + 82].copy_from_slice(&self.round_up_unit.to_le_bytes());
+        slice[retention_start + 82..retention_start + 114].copy_from_slice(self.arbiter.as_ref());
+        slice[retention_start + 114..retention_start + 122].copy_from_slice(&self.dispute_window.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let payment_mint = Pubkey::try_from(&src[..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let price_decimals = src[32];
+        let accepted_mint_count = src[33];
+        if accepted_mint_count as usize > MAX_ACCEPTED_MINTS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut accepted_mints = [AcceptedMint::default(); MAX_ACCEPTED_MINTS];
+        for (i, accepted) in accepted_mints.iter_mut().enumerate() {
+            let start = 34 + i * 33;
+            accepted.mint = Pubkey::try_from(&src[start..start + 32]).map_err(|_| CakeError::InvalidAccountData)?;
+            accepted.decimals = src[start + 32];
+        }
+        let splits_start = 34 + MAX_ACCEPTED_MINTS * 33;
+        let split_count = src[splits_start];
+        if split_count as usize > MAX_SPLIT_RECIPIENTS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut splits = [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS];
+        for (i, split) in splits.iter_mut().enumerate() {
+            let start = splits_start + 1 + i * 34;
+            split.token_account = Pubkey::try_from(&src[start..start + 32]).map_err(|_| CakeError::InvalidAccountData)?;
+            split.share_bps = u16::from_le_bytes(src[start + 32..start + 34].try_into().unwrap());
+        }
+        let retention_start = splits_start + 1 + MAX_SPLIT_RECIPIENTS * 34;
+        let history_retention = i64::from_le_bytes(src[retention_start..retention_start + 8].try_into().unwrap());
+        let referral_bps = u16::from_le_bytes(src[retention_start + 8..retention_start + 10].try_into().unwrap());
+        let tip_account =
+            Pubkey::try_from(&src[retention_start + 10..retention_start + 42]).map_err(|_| CakeError::InvalidAccountData)?;
+        let charity_account =
+            Pubkey::try_from(&src[retention_start + 42..retention_start + 74]).map_err(|_| CakeError::InvalidAccountData)?;
+        let round_up_unit = u64::from_le_bytes(src[retention_start + 74..retention_start + 82].try_into().unwrap());
+        let arbiter =
+            Pubkey::try_from(&src[retention_start + 82..retention_start + 114]).map_err(|_| CakeError::InvalidAccountData)?;
+        let dispute_window = i64::from_le_bytes(src[retention_start + 114..retention_start + 122].try_into().unwrap());
+        Ok(Config {
+            payment_mint,
+            price_decimals,
+            accepted_mint_count,
+            accepted_mints,
+            split_count,
+            splits,
+            history_retention,
+            referral_bps,
+            tip_account,
+            charity_account,
+            round_up_unit,
+            arbiter,
+            dispute_window,
+        })
+    }
+}
+
+impl Config {
+    pub const LEGACY_LENS: [usize; 7] = [32, 166, 303, 311, 313, 345, 385];
+
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == Self::LEN {
+            return Self::unpack(src);
+        }
+        if !Self::LEGACY_LENS.contains(&src.len()) {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut buf = [0u8; Self::LEN];
+        buf[..src.len()].copy_from_slice(src);
+        Self::unpack(&buf)
+    }
+
+    // O mint canônico usa as mesmas casas decimais dos preços; os demais, as da lista
+    pub fn mint_decimals(&self, mint: &Pubkey) -> Option<u8> {
+        if *mint == self.payment_mint {
+            return Some(self.price_decimals);
+        }
+        self.accepted_mints[..self.accepted_mint_count as usize]
+            .iter()
+            .find(|accepted| accepted.mint == *mint)
+            .map(|accepted| accepted.decimals)
+    }
+
+    // Quanto falta para o total chegar ao próximo múltiplo de round_up_unit; zero se já for múltiplo
+    pub fn round_up_amount(&self, total: u64) -> u64 {
+        if self.round_up_unit == 0 {
+            return 0;
+        }
+        let remainder = total % self.round_up_unit;
+        if remainder == 0 {
+            0
+        } else {
+            self.round_up_unit - remainder
+        }
+    }
+
+    // Partes de cada destinatário; o último recebe o resto para que a soma seja exatamente o total
+    pub fn split_amounts(&self, total: u64) -> Result<[u64; MAX_SPLIT_RECIPIENTS], ProgramError> {
+        let mut amounts = [0u64; MAX_SPLIT_RECIPIENTS];
+        let count = self.split_count as usize;
+        let mut remaining = total;
+        for (i, split) in self.splits[..count].iter().enumerate() {
+            let amount = if i + 1 == count {
+                remaining
+            } else {
+                ((total as u128) * (split.share_bps as u128) / 10_000) as u64
+            };
+            remaining = remaining.checked_sub(amount).ok_or(CakeError::ArithmeticOverflow)?;
+            amounts[i] = amount;
+        }
+        Ok(amounts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Subscription {
+    pub buyer: Pubkey,
+    pub product_id: u64,
+    pub quantity: u64,
+    pub interval: i64,
+    pub next_due: i64,
+    pub buyer_token: Pubkey,
+    pub executions: u64,
+    // Teto do preço unitário, em unidades do mint da buyer_token, aceito pelo comprador na criação; a cobrança
+    // acima dele falha, então o owner não esvazia a aprovação subindo o preço
+    pub max_unit_price: u64,
+}
+
+impl Sealed for Subscription {}
+
+impl IsInitialized for Subscription {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Subscription {
+    const LEN: usize = 112;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(self.buyer.as_ref());
+        slice[32..40].copy_from_slice(&self.product_id.to_le_bytes());
+        slice[40..48].copy_from_slice(&self.quantity.to_le_bytes());
+        slice[48..56].copy_from_slice(&self.interval.to_le_bytes());
+        slice[56..64].copy_from_slice(&self.next_due.to_le_bytes());
+        slice[64..96].copy_from_slice(self.buyer_token.as_ref());
+        slice[96..104].copy_from_slice(&self.executions.to_le_bytes());
+        slice[104..112].copy_from_slice(&self.max_unit_price.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let buyer = Pubkey::try_from(&src[0..32]).map_err(|_| CakeError::InvalidAccountData)?;
+        let product_id = u64::from_le_bytes(src[32..40].try_into().unwrap());
+        let quantity = u64::from_le_bytes(src[40..48].try_into().unwrap());
+        let interval = i64::from_le_bytes(src[48..56].try_into().unwrap());
+        let next_due = i64::from_le_bytes(src[56..64].try_into().unwrap());
+        let buyer_token = Pubkey::try_from(&src[64..96]).map_err(|_| CakeError::InvalidAccountData)?;
+        let executions = u64::from_le_bytes(src[96..104].try_into().unwrap());
+        let max_unit_price = u64::from_le_bytes(src[104..112].try_into().unwrap());
+        Ok(Subscription { buyer, product_id, quantity, interval, next_due, buyer_token, executions, max_unit_price })
+    }
+}
+
+impl Subscription {
+    // Assinaturas criadas antes do teto de preço; só podem ser canceladas
+    pub const LEGACY_LEN: usize = 104;
+
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == Self::LEN {
+            return Self::unpack(src);
+        }
+        if src.len() != Self::LEGACY_LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut buf = [0u8; Self::LEN];
+        buf[..src.len()].copy_from_slice(src);
+        Self::unpack(&buf)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct GiftCard {
+    pub code_hash: [u8; 32],
+    pub mint: Pubkey,
+    pub balance: u64,
+    pub expires_at: i64,
+}
+
+impl Sealed for GiftCard {}
+
+impl IsInitialized for GiftCard {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for GiftCard {
+    const LEN: usize = 80;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(&self.code_hash);
+        slice[32..64].copy_from_slice(self.mint.as_ref());
+        slice[64..72].copy_from_slice(&self.balance.to_le_bytes());
+        slice[72..80].copy_from_slice(&self.expires_at.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&src[0..32]);
+        let mint = Pubkey::try_from(&src[32..64]).map_err(|_| CakeError::InvalidAccountData)?;
+        let balance = u64::from_le_bytes(src[64..72].try_into().unwrap());
+        let expires_at = i64::from_le_bytes(src[72..80].try_into().unwrap());
+        Ok(GiftCard { code_hash, mint, balance, expires_at })
+    }
+}
+
+// Código de indicação na PDA ["referral", store, sha256(código)]. As recompensas das vendas com o código
+// ficam no cofre da tesouraria do mint; pending é o que o referrer ainda pode sacar
+#[derive(Debug, Clone, Copy, PartialEq, ShankAccount)]
+pub struct Referral {
+    pub code_hash: [u8; 32],
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub pending: u64,
+    pub total_earned: u64,
+}
+
+impl Sealed for Referral {}
+
+impl IsInitialized for Referral {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for Referral {
+    const LEN: usize = 112;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..32].copy_from_slice(&self.code_hash);
+        slice[32..64].copy_from_slice(self.referrer.as_ref());
+        slice[64..96].copy_from_slice(self.mint.as_ref());
+        slice[96..104].copy_from_slice(&self.pending.to_le_bytes());
+        slice[104..112].copy_from_slice(&self.total_earned.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&src[0..32]);
+        let referrer = Pubkey::try_from(&src[32..64]).map_err(|_| CakeError::InvalidAccountData)?;
+        let mint = Pubkey::try_from(&src[64..96]).map_err(|_| CakeError::InvalidAccountData)?;
+        let pending = u64::from_le_bytes(src[96..104].try_into().unwrap());
+        let total_earned = u64::from_le_bytes(src[104..112].try_into().unwrap());
+        Ok(Referral { code_hash, referrer, mint, pending, total_earned })
+    }
+}
+
+impl Referral {
+    pub fn reward(net_price: u64, referral_bps: u16) -> u64 {
+        ((net_price as u128) * (referral_bps as u128) / 10_000) as u64
+    }
+}
+
+pub const MAX_STATS_MINTS: usize = 8;
+pub const BUYER_FILTER_BYTES: usize = 1024;
+pub const BUYER_FILTER_HASHES: usize = 3;
+
+// Receita acumulada de um mint; Pubkey::default() representa SOL (lamports)
+#[derive(Debug, Clone, Copy, PartialEq, Default, ShankType)]
+pub struct MintRevenue {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, ShankAccount)]
+pub struct GlobalStats {
+    pub total_orders: u64,
+    pub unique_buyers: u64,
+    pub mint_count: u8,
+    // Tamanho literal (= MAX_STATS_MINTS) por causa do shank
+    pub revenues: [MintRevenue; 8],
+    pub buyer_filter: Vec<u8>,
+}
+
+impl Sealed for GlobalStats {}
+
+impl IsInitialized for GlobalStats {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Pack for GlobalStats {
+    const LEN: usize = 17 + MAX_STATS_MINTS * 40 + BUYER_FILTER_BYTES;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let slice = dst;
+        slice[0..8].copy_from_slice(&self.total_orders.to_le_bytes());
+        slice[8..16].copy_from_slice(&self.unique_buyers.to_le_bytes());
+        slice[16] = self.mint_count;
+        for (i, revenue) in self.revenues.iter().enumerate() {
+            let start = 17 + i * 40;
+            slice[start..start + 32].copy_from_slice(revenue.mint.as_ref());
+            slice[start + 32..start + 40].copy_from_slice(&revenue.amount.to_le_bytes());
+        }
+        let filter_start = 17 + MAX_STATS_MINTS * 40;
+        slice[filter_start..].copy_from_slice(&self.buyer_filter);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let total_orders = u64::from_le_bytes(src[0..8].try_into().unwrap());
+        let unique_buyers = u64::from_le_bytes(src[8..16].try_into().unwrap());
+        let mint_count = src[16];
+        if mint_count as usize > MAX_STATS_MINTS {
+            return Err(CakeError::InvalidAccountData.into());
+        }
+        let mut revenues = [MintRevenue::default(); MAX_STATS_MINTS];
+        for (i, revenue) in revenues.iter_mut().enumerate() {
+            let start = 17 + i * 40;
+            revenue.mint = Pubkey::try_from(&src[start..start + 32]).map_err(|_| CakeError::InvalidAccountData)?;
+            revenue.amount = u64::from_le_bytes(src[start + 32..start + 40].try_into().unwrap());
+        }
+        let filter_start = 17 + MAX_STATS_MINTS * 40;
+        let buyer_filter = src[filter_start..].to_vec();
+        Ok(GlobalStats { total_orders, unique_buyers, mint_count, revenues, buyer_filter })
+    }
+}
+
+impl GlobalStats {
+    // Filtro de Bloom sobre o hash do comprador: falsos positivos podem subcontar compradores
+    // novos, mas um comprador repetido nunca é contado duas vezes
+    pub fn record_buyer(&mut self, buyer: &Pubkey) -> Result<bool, ProgramError> {
+        let digest = solana_program::hash::hash(buyer.as_ref()).to_bytes();
+        let mut seen = true;
+        for chunk in digest.chunks_exact(4).take(BUYER_FILTER_HASHES) {
+            let bit = u32::from_le_bytes(chunk.try_into().unwrap()) as usize % (BUYER_FILTER_BYTES * 8);
+            let mask = 1u8 << (bit % 8);
+            if self.buyer_filter[bit / 8] & mask == 0 {
+                seen = false;
+                self.buyer_filter[bit / 8] |= mask;
+            }
+        }
+        if !seen {
+            self.unique_buyers = self.unique_buyers.checked_add(1).ok_or(CakeError::ArithmeticOverflow)?;
+        }
+        Ok(!seen)
+    }
+
+    pub fn add_revenue(&mut self, mint: &Pubkey, amount: u64) -> ProgramResult {
+        let count = self.mint_count as usize;
+        let index = match self.revenues[..count].iter().position(|revenue| revenue.mint == *mint) {
+            Some(index) => index,
+            None if count < MAX_STATS_MINTS => {
+                self.revenues[count].mint = *mint;
+                self.mint_count += 1;
+                count
+            }
+            None => return Err(CakeError::InvalidPaymentMint.into()),
+        };
+        self.revenues[index].amount = self.revenues[index].amount.checked_add(amount).ok_or(CakeError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pubkey() -> impl Strategy<Value = Pubkey> {
+        any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+    }
+
+    fn bytes<const N: usize>() -> impl Strategy<Value = [u8; N]> {
+        prop::collection::vec(any::<u8>(), N).prop_map(|bytes| bytes.try_into().unwrap())
+    }
+
+    fn price_tiers() -> impl Strategy<Value = [PriceTier; MAX_PRICE_TIERS]> {
+        prop::array::uniform4((any::<u64>(), any::<u64>()).prop_map(|(min_quantity, unit_price)| PriceTier { min_quantity, unit_price }))
+    }
+
+    fn cake_state() -> impl Strategy<Value = CakeState> {
+        (
+            (pubkey(), any::<u64>(), any::<u64>(), any::<u16>(), any::<bool>(), any::<u64>(), any::<u64>()),
+            (any::<u16>(), pubkey(), any::<i64>(), any::<u16>(), pubkey(), pubkey()),
+        )
+            .prop_map(
+                |(
+                    (owner, product_counter, history_counter, max_price_change_bps, paused, order_counter, category_counter),
+                    (fee_bps, fee_destination, escrow_timeout, commission_bps, commission_authority, commission_account),
+                )| CakeState {
+                    owner,
+                    product_counter,
+                    history_counter,
+                    max_price_change_bps,
+                    paused,
+                    order_counter,
+                    category_counter,
+                    fee_bps,
+                    fee_destination,
+                    escrow_timeout,
+                    commission_bps,
+                    commission_authority,
+                    commission_account,
+                    is_initialized: true,
+                },
+            )
+    }
+
+    // Textos e campos gerados conforme o layout: no fixo os textos cabem nos campos sem zeros à direita
+    // e as estatísticas, a janela de venda e as casas decimais ficam zeradas, no Borsh qualquer valor é aceito
+    fn product(name: &'static str, description: &'static str, with_stats: bool) -> impl Strategy<Value = Product> {
+        let stats = if with_stats {
+            (any::<u64>(), any::<u64>(), any::<u64>(), any::<i64>(), any::<i64>(), any::<u8>()).boxed()
+        } else {
+            Just((0, 0, 0, 0, 0, 0)).boxed()
+        };
+        (
+            (any::<u64>(), name, description, any::<u64>(), any::<u64>(), any::<bool>(), any::<bool>(), any::<u64>(), any::<u64>()),
+            (bytes::<200>(), 0..=MAX_PRICE_TIERS as u8, price_tiers(), any::<u64>(), any::<u64>(), pubkey(), any::<bool>(), pubkey()),
+            stats,
+        )
+            .prop_map(
+                |(
+                    (id, name, description, price, stock, active, available, max_per_buyer, category_id),
+                    (metadata_uri, tier_count, tiers, sol_price, usd_price_cents, price_feed, allowlist_required, gate_mint),
+                    (units_sold, revenue, sol_revenue, available_from, available_until, price_decimals),
+                )| Product {
+                    id,
+                    name,
+                    description,
+                    price,
+                    stock,
+                    active,
+                    available,
+                    max_per_buyer,
+                    category_id,
+                    metadata_uri,
+                    tier_count,
+                    tiers,
+                    sol_price,
+                    usd_price_cents,
+                    price_feed,
+                    allowlist_required,
+                    gate_mint,
+                    units_sold,
+                    revenue,
+                    sol_revenue,
+                    available_from,
+                    available_until,
+                    price_decimals,
+                },
+            )
+    }
+
+    fn purchase_history() -> impl Strategy<Value = PurchaseHistory> {
+        (
+            (
+                any::<u64>(),
+                any::<u64>(),
+                any::<u64>(),
+                pubkey(),
+                any::<i64>(),
+                any::<u8>(),
+                any::<u64>(),
+                any::<u64>(),
+                any::<u8>(),
+                pubkey(),
+                any::<u64>(),
+                any::<u64>(),
+            ),
+            (any::<u64>(), any::<[u8; 32]>(), any::<bool>(), any::<u8>()),
+        )
+            .prop_map(
+                |(
+                    (
+                        product_id,
+                        quantity,
+                        total_price,
+                        buyer,
+                        timestamp,
+                        status,
+                        fee,
+                        refunded_quantity,
+                        payment_mode,
+                        rent_payer,
+                        tip,
+                        donation,
+                    ),
+                    (unit_price, name_hash, has_variant, variant_id),
+                )| {
+                    PurchaseHistory {
+                        product_id,
+                        quantity,
+                        total_price,
+                        buyer,
+                        timestamp,
+                        status,
+                        fee,
+                        refunded_quantity,
+                        payment_mode,
+                        rent_payer,
+                        tip,
+                        donation,
+                        unit_price,
+                        name_hash,
+                        has_variant,
+                        variant_id,
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn test_cake_state_round_trip(state in cake_state()) {
+            let mut data = [0u8; CakeState::LEN];
+            CakeState::pack(state, &mut data).unwrap();
+            prop_assert_eq!(&data[..8], &CAKE_STATE_DISCRIMINATOR[..]);
+            prop_assert_eq!(CakeState::unpack(&data).unwrap(), state);
+            // O corpo é o layout anterior ao discriminador, e os layouts anteriores ao byte is_initialized
+            // continuam legíveis para a migração
+            prop_assert_eq!(CakeState::unpack_legacy(&data[8..CakeState::UNFLAGGED_LEN]).unwrap(), state);
+            prop_assert_eq!(CakeState::unpack_unflagged(&data[..CakeState::UNFLAGGED_LEN]).unwrap(), state);
+            prop_assert!(CakeState::unpack(&data[..CakeState::UNFLAGGED_LEN]).is_err());
+
+            // Estado com o byte zerado não é lido como loja
+            data[CakeState::UNFLAGGED_LEN] = 0;
+            prop_assert!(CakeState::unpack(&data).is_err());
+            prop_assert!(!CakeState::unpack_unchecked(&data).unwrap().is_initialized);
+        }
+
+        #[test]
+        fn test_product_borsh_round_trip(product in product("\\PC{0,64}", "\\PC{0,128}", true)) {
+            let mut data = vec![0u8; product.account_len().unwrap()];
+            Product::pack_discriminated(&product, &mut data).unwrap();
+            prop_assert!(Product::is_discriminated(&data));
+            prop_assert_eq!(data[8], PRODUCT_BORSH_VERSION);
+            prop_assert_eq!(&Product::unpack_versioned(&data).unwrap(), &product);
+
+            // pack_versioned mantém o layout da conta
+            let mut repacked = vec![0u8; data.len()];
+            repacked[..8].copy_from_slice(&PRODUCT_DISCRIMINATOR);
+            Product::pack_versioned(&product, &mut repacked).unwrap();
+            prop_assert_eq!(repacked, data);
+        }
+
+        #[test]
+        fn test_product_fixed_fields_view(
+            product in product("\\PC{0,64}", "\\PC{0,128}", true),
+            stock in any::<u64>(),
+            units_sold in any::<u64>(),
+            revenue in any::<u64>(),
+        ) {
+            let mut data = vec![0u8; product.account_len().unwrap()];
+            Product::pack_discriminated(&product, &mut data).unwrap();
+            let fields = ProductFixedFields::load(&data).unwrap();
+            prop_assert_eq!(u64::from(fields.price), product.price);
+            prop_assert_eq!(u64::from(fields.stock), product.stock);
+            prop_assert_eq!(bool::from(fields.available), product.available);
+            prop_assert_eq!(fields.metadata_uri, product.metadata_uri);
+            prop_assert_eq!(u64::from(fields.tiers[3].unit_price), product.tiers[3].unit_price);
+            prop_assert_eq!(fields.gate_mint, product.gate_mint);
+            prop_assert_eq!(u64::from(fields.sol_revenue), product.sol_revenue);
+            prop_assert_eq!(i64::from(fields.available_until), product.available_until);
+            prop_assert_eq!(fields.price_decimals, product.price_decimals);
+
+            // Gravar só estoque e estatísticas equivale a reserializar o produto inteiro
+            let updated = Product { stock, units_sold, revenue, ..product };
+            let mut expected = vec![0u8; data.len()];
+            Product::pack_discriminated(&updated, &mut expected).unwrap();
+            Product::pack_stock_and_stats(&updated, &mut data).unwrap();
+            prop_assert_eq!(data, expected);
+        }
+
+        #[test]
+        fn test_product_fixed_layout_round_trip(product in product("[a-zA-Z0-9 ]{0,32}", "[a-zA-Z0-9 ]{0,128}", false)) {
+            let mut data = [0u8; Product::LEN];
+            Product::pack(product.clone(), &mut data).unwrap();
+            prop_assert_eq!(&Product::unpack(&data).unwrap(), &product);
+            prop_assert_eq!(&Product::unpack_versioned(&data).unwrap(), &product);
+
+            let mut repacked = [0u8; Product::LEN];
+            Product::pack_versioned(&product, &mut repacked).unwrap();
+            prop_assert_eq!(repacked, data);
+        }
+
+        #[test]
+        fn test_product_fixed_layout_rejects_stats(product in product("[a-z]{0,32}", "[a-z]{0,128}", true)) {
+            prop_assume!(
+                product.units_sold != 0
+                    || product.revenue != 0
+                    || product.sol_revenue != 0
+                    || product.available_from != 0
+                    || product.available_until != 0
+                    || product.price_decimals != 0
+            );
+            let mut data = [0u8; Product::LEN];
+            prop_assert_eq!(Product::pack_versioned(&product, &mut data), Err(CakeError::AccountNeedsMigration.into()));
+        }
+
+        #[test]
+        fn test_purchase_history_round_trip(history in purchase_history()) {
+            let mut data = [0u8; PurchaseHistory::LEN];
+            PurchaseHistory::pack(history, &mut data).unwrap();
+            prop_assert_eq!(&data[..8], &PURCHASE_HISTORY_DISCRIMINATOR[..]);
+            prop_assert_eq!(&data[8..], &history.try_to_vec().unwrap()[..]);
+            prop_assert_eq!(PurchaseHistory::from(PurchaseHistoryAccount::load(&data).unwrap()), history);
+            prop_assert_eq!(PurchaseHistory::unpack(&data).unwrap(), history);
+            prop_assert_eq!(PurchaseHistory::unpack_versioned(&data).unwrap(), history);
+
+            // Contas anteriores ao tip, com e sem discriminador, coincidem com o corpo sem gorjeta e doação
+            let base = PurchaseHistory { tip: 0, donation: 0, ..history.without_receipt() };
+            let mut legacy = [0u8; PurchaseHistory::BASE_BODY_LEN];
+            PurchaseHistory::pack_versioned(base, &mut legacy).unwrap();
+            prop_assert_eq!(&legacy[..], &base.body()[..]);
+            prop_assert_eq!(PurchaseHistory::unpack_versioned(&legacy).unwrap(), base);
+            for len in PurchaseHistory::LEGACY_DISCRIMINATED_LENS {
+                let mut discriminated = vec![0u8; len];
+                PurchaseHistory::pack_versioned(base, &mut discriminated).unwrap();
+                prop_assert_eq!(&discriminated[..8], &PURCHASE_HISTORY_DISCRIMINATOR[..]);
+                prop_assert_eq!(&discriminated[8..8 + legacy.len()], &legacy[..]);
+                prop_assert_eq!(PurchaseHistory::unpack_versioned(&discriminated).unwrap(), base);
+            }
+            if history.tip != 0 || history.donation != 0 {
+                prop_assert_eq!(PurchaseHistory::pack_versioned(history, &mut legacy), Err(CakeError::AccountNeedsMigration.into()));
+            }
+            // As contas com gorjeta e sem doação guardam a gorjeta
+            let tipped = PurchaseHistory { donation: 0, ..history.without_receipt() };
+            let mut discriminated = vec![0u8; PurchaseHistory::LEGACY_DISCRIMINATED_LENS[1]];
+            PurchaseHistory::pack_versioned(tipped, &mut discriminated).unwrap();
+            prop_assert_eq!(PurchaseHistory::unpack_versioned(&discriminated).unwrap(), tipped);
+            // As anteriores ao recibo guardam tudo menos ele, e o corpo volta ao tamanho delas
+            let unreceipted = history.without_receipt();
+            let mut discriminated = vec![0u8; PurchaseHistory::LEGACY_DISCRIMINATED_LENS[2]];
+            PurchaseHistory::pack_versioned(unreceipted, &mut discriminated).unwrap();
+            prop_assert_eq!(PurchaseHistory::unpack_versioned(&discriminated).unwrap(), unreceipted);
+            prop_assert!(unreceipted.body().len() <= PurchaseHistory::TIPPED_BODY_LEN);
+            if history.unit_price != 0 || history.name_hash != [0; 32] {
+                prop_assert!(history.body().len() >= PurchaseHistory::RECEIPT_BODY_LEN);
+                prop_assert_eq!(
+                    PurchaseHistory::pack_versioned(history, &mut discriminated),
+                    Err(CakeError::AccountNeedsMigration.into())
+                );
+            }
+            // As anteriores à variante guardam o recibo; uma venda de variante pede a migração
+            let unvaried = PurchaseHistory { has_variant: false, variant_id: 0, ..history };
+            let mut discriminated = vec![0u8; PurchaseHistory::LEGACY_DISCRIMINATED_LENS[3]];
+            PurchaseHistory::pack_versioned(unvaried, &mut discriminated).unwrap();
+            prop_assert_eq!(PurchaseHistory::unpack_versioned(&discriminated).unwrap(), unvaried);
+            prop_assert!(unvaried.body().len() <= PurchaseHistory::RECEIPT_BODY_LEN);
+            if history.has_variant || history.variant_id != 0 {
+                prop_assert_eq!(history.body().len(), PurchaseHistory::LEN - 8);
+                prop_assert_eq!(
+                    PurchaseHistory::pack_versioned(history, &mut discriminated),
+                    Err(CakeError::AccountNeedsMigration.into())
+                );
+            }
+        }
+
+        #[test]
+        fn test_config_round_up_amount(total in any::<u32>(), round_up_unit in 0u64..1_000_000) {
+            let mut config = Config::unpack_versioned(&[0u8; 32]).unwrap();
+            config.round_up_unit = round_up_unit;
+            let donation = config.round_up_amount(total as u64);
+            if round_up_unit == 0 {
+                prop_assert_eq!(donation, 0);
+            } else {
+                prop_assert!(donation < round_up_unit);
+                prop_assert_eq!((total as u64 + donation) % round_up_unit, 0);
+            }
+        }
+
+        #[test]
+        fn test_buyer_history_counter_round_trip(buyer in pubkey(), count in any::<u64>()) {
+            let counter = BuyerHistoryCounter { buyer, count };
+            let mut data = [0u8; BuyerHistoryCounter::LEN];
+            BuyerHistoryCounter::pack(counter, &mut data).unwrap();
+            prop_assert_eq!(BuyerHistoryCounter::unpack(&data).unwrap(), counter);
+        }
+
+        #[test]
+        fn test_referral_round_trip(
+            code_hash in bytes::<32>(),
+            referrer in pubkey(),
+            mint in pubkey(),
+            pending in any::<u64>(),
+            total_earned in any::<u64>(),
+        ) {
+            let referral = Referral { code_hash, referrer, mint, pending, total_earned };
+            let mut data = [0u8; Referral::LEN];
+            Referral::pack(referral, &mut data).unwrap();
+            prop_assert_eq!(Referral::unpack(&data).unwrap(), referral);
+        }
+
+        #[test]
+        fn test_purchase_note_round_trip(history in pubkey(), memo in prop::collection::vec(any::<u8>(), 0..=MAX_MEMO_LEN)) {
+            let note = PurchaseNote { history, memo };
+            let mut data = [0xffu8; PurchaseNote::LEN];
+            PurchaseNote::pack(note.clone(), &mut data).unwrap();
+            prop_assert_eq!(PurchaseNote::unpack(&data).unwrap(), note);
+        }
+
+        #[test]
+        fn test_dispute_round_trip(
+            history in pubkey(),
+            buyer in pubkey(),
+            opened_at in any::<i64>(),
+            status in DISPUTE_STATUS_OPEN..=DISPUTE_STATUS_RELEASED,
+            arbiter in pubkey(),
+            resolved_at in any::<i64>(),
+        ) {
+            let dispute = Dispute { history, buyer, opened_at, status, arbiter, resolved_at };
+            let mut data = [0u8; Dispute::LEN];
+            Dispute::pack(dispute, &mut data).unwrap();
+            prop_assert_eq!(Dispute::unpack(&data).unwrap(), dispute);
+            data[72] = DISPUTE_STATUS_RELEASED + 1;
+            prop_assert!(Dispute::unpack(&data).is_err());
+        }
+
+        #[test]
+        fn test_confidential_payment_round_trip(
+            history in pubkey(),
+            mint in pubkey(),
+            commitment_lo in any::<[u8; 32]>(),
+            commitment_hi in any::<[u8; 32]>(),
+        ) {
+            let payment = ConfidentialPayment { history, mint, commitment_lo, commitment_hi };
+            let mut data = [0u8; ConfidentialPayment::LEN];
+            ConfidentialPayment::pack(payment, &mut data).unwrap();
+            prop_assert_eq!(ConfidentialPayment::unpack(&data).unwrap(), payment);
+        }
+
+        #[test]
+        fn test_config_dispute_fields(arbiter in pubkey(), dispute_window in any::<i64>()) {
+            // Configs anteriores à disputa são lidas com o árbitro desativado
+            let legacy = Config::unpack_versioned(&[0u8; 385]).unwrap();
+            prop_assert_eq!((legacy.arbiter, legacy.dispute_window), (Pubkey::default(), 0));
+
+            let config = Config { arbiter, dispute_window, ..legacy };
+            let mut data = [0u8; Config::LEN];
+            Config::pack(config, &mut data).unwrap();
+            prop_assert_eq!(Config::unpack(&data).unwrap(), config);
+        }
+
+        #[test]
+        fn test_product_tags_round_trip(
+            product_id in any::<u64>(),
+            tags in prop::collection::vec(any::<[u8; TAG_LEN]>(), 0..=MAX_PRODUCT_TAGS),
+            product_ids in prop::collection::vec(any::<u64>(), 0..=MAX_TAG_PRODUCTS),
+        ) {
+            let product_tags = ProductTags { product_id, tags: tags.clone() };
+            let mut data = [0xffu8; ProductTags::LEN];
+            ProductTags::pack(product_tags.clone(), &mut data).unwrap();
+            prop_assert_eq!(ProductTags::unpack(&data).unwrap(), product_tags);
+
+            let index = TagIndex { tag: tags.first().copied().unwrap_or_default(), product_ids };
+            let mut data = [0xffu8; TagIndex::LEN];
+            TagIndex::pack(index.clone(), &mut data).unwrap();
+            prop_assert_eq!(TagIndex::unpack(&data).unwrap(), index);
+        }
+
+        #[test]
+        fn test_localization_round_trip(
+            product_id in any::<u64>(),
+            lang_code in "[a-z]{2}",
+            name in "[a-zA-Z0-9 ]{1,64}",
+            description in "\\PC{0,128}",
+        ) {
+            let lang_code: [u8; LANG_CODE_LEN] = lang_code.as_bytes().try_into().unwrap();
+            prop_assert!(is_valid_lang_code(&lang_code));
+            let localization = Localization { product_id, lang_code, name, description };
+            let mut data = [0xffu8; Localization::LEN];
+            Localization::pack(localization.clone(), &mut data).unwrap();
+            prop_assert_eq!(Localization::unpack(&data).unwrap(), localization);
+        }
+
+        #[test]
+        fn test_product_purchase_index_append(product_id in any::<u64>(), entries in prop::collection::vec((pubkey(), any::<u64>()), 0..8)) {
+            let mut data = vec![0u8; ProductPurchaseIndex::HEADER_LEN];
+            ProductPurchaseIndex::pack_header(product_id, 0, &mut data);
+            let entries: Vec<PurchaseIndexEntry> = entries.into_iter().map(|(buyer, sequence)| PurchaseIndexEntry { buyer, sequence }).collect();
+            for entry in &entries {
+                prop_assert!(ProductPurchaseIndex::append(&mut data, product_id, entry).is_err());
+                data.resize(data.len() + ProductPurchaseIndex::ENTRY_LEN, 0);
+                ProductPurchaseIndex::append(&mut data, product_id, entry).unwrap();
+            }
+            prop_assert_eq!(ProductPurchaseIndex::unpack(&data).unwrap(), ProductPurchaseIndex { product_id, entries });
+            prop_assert!(ProductPurchaseIndex::unpack(&data[..data.len() - 1]).is_err());
+        }
+
+        #[test]
+        fn test_history_page_append(previous in pubkey(), entries in prop::collection::vec(purchase_history(), 0..HISTORY_PAGE_CAPACITY + 2)) {
+            let mut data = vec![0u8; HistoryPage::LEN];
+            HistoryPage::pack_header(7, &previous, &mut data);
+            // As entradas da página não têm espaço para a gorjeta
+            let tipped = PurchaseHistory { tip: 1, ..purchase_history_default() };
+            prop_assert!(HistoryPage::append(&mut data, &tipped).is_err());
+            let entries: Vec<PurchaseHistory> =
+                entries.into_iter().map(|entry| PurchaseHistory { tip: 0, donation: 0, ..entry }).collect();
+            for (i, entry) in entries.iter().enumerate() {
+                prop_assert_eq!(HistoryPage::append(&mut data, entry).is_ok(), i < HISTORY_PAGE_CAPACITY);
+            }
+            // O recibo não cabe na entrada e é descartado
+            let stored = entries.iter().take(HISTORY_PAGE_CAPACITY).map(PurchaseHistory::without_receipt).collect::<Vec<_>>();
+            let page = HistoryPage::unpack(&data).unwrap();
+            prop_assert_eq!(&page, &HistoryPage { page: 7, previous, entries: stored });
+
+            let mut repacked = vec![0u8; HistoryPage::LEN];
+            HistoryPage::pack(page, &mut repacked).unwrap();
+            prop_assert_eq!(repacked, data);
+        }
+
+        #[test]
+        fn test_history_archive_proofs(histories in prop::collection::vec((pubkey(), purchase_history()), 1..=MAX_ARCHIVE_BATCH)) {
+            let leaves: Vec<[u8; 32]> = histories.iter().map(|(address, history)| HistoryArchive::leaf(address, history)).collect();
+            let archive = HistoryArchive {
+                archive_id: 1,
+                merkle_root: HistoryArchive::merkle_root(&leaves),
+                leaf_count: leaves.len() as u8,
+                archived_at: 0,
+            };
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = HistoryArchive::proof(&leaves, index);
+                prop_assert!(archive.verify(leaf, index, &proof));
+                prop_assert!(!archive.verify(leaf, index + leaves.len(), &proof));
+                let mut tampered = *leaf;
+                tampered[0] ^= 1;
+                prop_assert!(!archive.verify(&tampered, index, &proof));
+            }
+        }
+
+        #[test]
+        fn test_launch_and_pre_order_round_trip(
+            (product_id, release_at, unit_price, max_quantity, reserved, cancelled) in
+                (any::<u64>(), any::<i64>(), any::<u64>(), any::<u64>(), any::<u64>(), any::<bool>()),
+            (buyer, quantity, total_price, payment_mint, created_at, status) in
+                (pubkey(), any::<u64>(), any::<u64>(), pubkey(), any::<i64>(), any::<u8>()),
+        ) {
+            let launch = Launch { product_id, release_at, unit_price, max_quantity, reserved, cancelled };
+            let mut data = [0u8; Launch::LEN];
+            Launch::pack(launch, &mut data).unwrap();
+            prop_assert_eq!(Launch::unpack(&data).unwrap(), launch);
+
+            let pre_order = PreOrder { product_id, buyer, quantity, total_price, payment_mint, created_at, status };
+            let mut data = [0u8; PreOrder::LEN];
+            PreOrder::pack(pre_order, &mut data).unwrap();
+            prop_assert_eq!(PreOrder::unpack(&data).unwrap(), pre_order);
+        }
+
+        #[test]
+        fn test_product_availability_window(available_from in -1_000i64..1_000, available_until in -1_000i64..1_000, now in -2_000i64..2_000) {
+            let product = Product { available_from, available_until, ..Product::unpack(&[0u8; Product::LEN]).unwrap() };
+            let expected = (available_from == 0 || now >= available_from) && (available_until == 0 || now <= available_until);
+            prop_assert_eq!(product.is_available_at(now), expected);
+            let unbounded = Product { available_from: 0, available_until: 0, ..product };
+            prop_assert!(unbounded.is_available_at(now));
+        }
+
+        #[test]
+        fn test_product_price_decimals(price_decimals in any::<u8>(), config_price_decimals in any::<u8>()) {
+            let product = Product { price_decimals, ..Product::unpack(&[0u8; Product::LEN]).unwrap() };
+            let expected = if price_decimals == 0 { config_price_decimals } else { price_decimals };
+            prop_assert_eq!(product.price_decimals_or(config_price_decimals), expected);
+        }
+
+        #[test]
+        fn test_buyer_profile_round_trip(
+            (buyer, display_name_hash, preferred_mint, contact_hash) in (pubkey(), bytes::<32>(), pubkey(), bytes::<32>()),
+            (total_spent, total_spent_sol, total_price) in (any::<u64>(), any::<u64>(), any::<u64>()),
+        ) {
+            let mut profile = BuyerProfile { buyer, display_name_hash, preferred_mint, contact_hash, total_spent, total_spent_sol };
+            let mut data = [0u8; BuyerProfile::LEN];
+            BuyerProfile::pack(profile, &mut data).unwrap();
+            prop_assert_eq!(BuyerProfile::unpack(&data).unwrap(), profile);
+
+            let result = profile.record_purchase(total_price, PAYMENT_MODE_SOL);
+            prop_assert_eq!(result.is_ok(), total_spent_sol.checked_add(total_price).is_some());
+            prop_assert_eq!(profile.total_spent, total_spent);
+        }
+
+        #[test]
+        fn test_owner_set_threshold(
+            store in pubkey(),
+            members in prop::collection::hash_set(pubkey(), 1..=MAX_OWNER_SET_MEMBERS),
+            threshold in 1u8..=MAX_OWNER_SET_MEMBERS as u8,
+            signed in 0usize..=MAX_OWNER_SET_MEMBERS,
+        ) {
+            let owner_set = OwnerSet { store, threshold, members: members.into_iter().collect() };
+            let mut data = vec![0u8; OwnerSet::LEN];
+            OwnerSet::pack(owner_set.clone(), &mut data).unwrap();
+            prop_assert_eq!(OwnerSet::unpack(&data).unwrap(), owner_set.clone());
+            prop_assert_eq!(owner_set.is_valid(), threshold as usize <= owner_set.members.len());
+
+            // Assinaturas repetidas e de quem não é membro não contam
+            let signed = signed.min(owner_set.members.len());
+            let mut signers = owner_set.members[..signed].to_vec();
+            signers.extend_from_slice(&owner_set.members[..signed]);
+            signers.push(store);
+            prop_assert_eq!(owner_set.approvals(&signers), signed);
+            prop_assert_eq!(owner_set.is_approved(&signers), signed >= threshold as usize);
+
+            let mut duplicated = owner_set.clone();
+            duplicated.members.push(duplicated.members[0]);
+            prop_assert!(!duplicated.is_valid());
+        }
+
+        #[test]
+        fn test_flash_sale_window(
+            discount_bps in 0u16..=10_000,
+            start in -1_000i64..1_000,
+            duration in 1i64..1_000,
+            now in -2_000i64..3_000,
+            total_price in any::<u64>(),
+        ) {
+            let flash_sale = FlashSale { product_id: 1, discount_bps, start, end: start + duration };
+            let mut data = [0u8; FlashSale::LEN];
+            FlashSale::pack(flash_sale, &mut data).unwrap();
+            prop_assert_eq!(FlashSale::unpack(&data).unwrap(), flash_sale);
+
+            let discount = flash_sale.discount(total_price, now);
+            prop_assert!(discount <= total_price);
+            if now < start || now >= start + duration {
+                prop_assert_eq!(discount, 0);
+            } else {
+                prop_assert_eq!(discount as u128, total_price as u128 * discount_bps as u128 / 10_000);
+            }
+        }
+
+        #[test]
+        fn test_wrong_length_buffers_are_rejected(len in 0usize..2 * Product::LEN, fill in any::<u8>()) {
+            let data = vec![fill; len];
+            if len != CakeState::LEN {
+                prop_assert!(CakeState::unpack(&data).is_err());
+                prop_assert!(CakeState::pack(cake_state_default(), &mut vec![0u8; len]).is_err());
+            }
+            if len != CakeState::LEGACY_LEN {
+                prop_assert!(CakeState::unpack_legacy(&data).is_err());
+            }
+            if len != Product::LEN {
+                prop_assert!(Product::unpack(&data).is_err());
+            }
+            if len != PurchaseHistory::LEN {
+                prop_assert!(PurchaseHistory::unpack(&data).is_err());
+            }
+            if len != PurchaseHistory::LEN
+                && !PurchaseHistory::LEGACY_DISCRIMINATED_LENS.contains(&len)
+                && !PurchaseHistory::LEGACY_LENS.contains(&len)
+            {
+                prop_assert!(PurchaseHistory::unpack_versioned(&data).is_err());
+                prop_assert!(PurchaseHistory::pack_versioned(purchase_history_default(), &mut vec![0u8; len]).is_err());
+            }
+        }
+    }
+
+    fn cake_state_default() -> CakeState {
+        CakeState::unpack_legacy(&[0u8; CakeState::LEGACY_LEN]).unwrap()
+    }
+
+    fn purchase_history_default() -> PurchaseHistory {
+        PurchaseHistory::unpack_versioned(&[0u8; PurchaseHistory::BASE_BODY_LEN]).unwrap()
+    }
+
+    #[test]
+    fn test_discriminator_mismatch_is_rejected() {
+        let mut data = [0u8; CakeState::LEN];
+        CakeState::pack(cake_state_default(), &mut data).unwrap();
+        data[0] ^= 1;
+        assert!(CakeState::unpack(&data).is_err());
+
+        let mut data = [0u8; PurchaseHistory::LEN];
+        PurchaseHistory::pack(purchase_history_default(), &mut data).unwrap();
+        data[7] ^= 1;
+        assert!(PurchaseHistory::unpack(&data).is_err());
+        assert!(PurchaseHistory::unpack_versioned(&data).is_err());
+    }
+
+    #[test]
+    fn test_legacy_subscription_has_no_price_cap() {
+        let subscription = Subscription {
+            buyer: Pubkey::new_unique(),
+            product_id: 1,
+            quantity: 2,
+            interval: 3,
+            next_due: 4,
+            buyer_token: Pubkey::new_unique(),
+            executions: 5,
+            max_unit_price: 6,
+        };
+        let mut data = [0u8; Subscription::LEN];
+        Subscription::pack(subscription, &mut data).unwrap();
+        assert_eq!(Subscription::unpack_versioned(&data).unwrap(), subscription);
+        let legacy = Subscription::unpack_versioned(&data[..Subscription::LEGACY_LEN]).unwrap();
+        assert_eq!(legacy, Subscription { max_unit_price: 0, ..subscription });
+        assert!(Subscription::unpack(&data[..Subscription::LEGACY_LEN]).is_err());
+    }
+
+    #[test]
+    fn test_order_transitions_only_move_forward() {
+        let order = |status| Order {
+            order_id: 0,
+            buyer: Pubkey::default(),
+            product_id: 0,
+            quantity: 1,
+            total_price: 1,
+            payment_mint: Pubkey::default(),
+            created_at: 0,
+            status,
+            fulfilled_quantity: 0,
+        };
+        let allowed = [
+            (ORDER_STATUS_PLACED, ORDER_STATUS_PREPARING),
+            (ORDER_STATUS_PLACED, ORDER_STATUS_FULFILLED),
+            (ORDER_STATUS_PLACED, ORDER_STATUS_CANCELLED),
+            (ORDER_STATUS_PREPARING, ORDER_STATUS_OUT_FOR_DELIVERY),
+            (ORDER_STATUS_PREPARING, ORDER_STATUS_FULFILLED),
+            (ORDER_STATUS_PREPARING, ORDER_STATUS_CANCELLED),
+            (ORDER_STATUS_OUT_FOR_DELIVERY, ORDER_STATUS_DELIVERED),
+        ];
+        for from in 0..=ORDER_STATUS_DELIVERED + 1 {
+            for to in 0..=ORDER_STATUS_DELIVERED + 1 {
+                assert_eq!(order(from).can_transition(to), allowed.contains(&(from, to)), "{} -> {}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_partial_fulfillment() {
+        let mut order = Order {
+            order_id: 3,
+            buyer: Pubkey::new_unique(),
+            product_id: 1,
+            quantity: 12,
+            total_price: 1_000_001,
+            payment_mint: Pubkey::new_unique(),
+            created_at: 1_700_000_000,
+            status: ORDER_STATUS_PREPARING,
+            fulfilled_quantity: 0,
+        };
+        // As entregas parciais e o restante somam exatamente o valor do cofre
+        let mut released = 0;
+        for quantity in [8, 1, 2] {
+            released += order.release_amount_for(quantity);
+            order.fulfilled_quantity += quantity;
+        }
+        assert_eq!(order.remaining_quantity(), 1);
+        assert_eq!(released + order.remaining_amount(), order.total_price);
+
+        let mut data = [0u8; Order::LEN];
+        Order::pack_versioned(order, &mut data).unwrap();
+        assert_eq!(Order::unpack_versioned(&data).unwrap(), order);
+
+        // Pedido antigo: lido com fulfilled_quantity zero e sem espaço para gravá-lo
+        let mut legacy = [0u8; Order::LEGACY_LEN];
+        assert_eq!(Order::pack_versioned(order, &mut legacy), Err(CakeError::AccountNeedsMigration.into()));
+        order.fulfilled_quantity = 0;
+        Order::pack_versioned(order, &mut legacy).unwrap();
+        assert_eq!(Order::unpack_versioned(&legacy).unwrap(), order);
+        assert_eq!(order.remaining_amount(), order.total_price);
+    }
+
+    #[test]
+    fn test_bundle_round_trip_and_history_marker() {
+        let items = vec![BundleItem { product_id: 1, quantity: 2 }, BundleItem { product_id: 7, quantity: 1 }];
+        let bundle = Bundle { bundle_id: 5, price: 12_000_000, items };
+        let mut data = [0u8; Bundle::LEN];
+        Bundle::pack(bundle.clone(), &mut data).unwrap();
+        assert_eq!(Bundle::unpack(&data).unwrap(), bundle);
+        data[16] = MAX_BUNDLE_ITEMS as u8 + 1;
+        assert!(Bundle::unpack(&data).is_err());
+
+        let mut entry = purchase_history_default();
+        assert_eq!(entry.bundle_id(), None);
+        entry.product_id = bundle.history_product_id();
+        assert_eq!(entry.bundle_id(), Some(5));
+        entry.product_id = CART_PRODUCT_ID;
+        assert_eq!(entry.bundle_id(), None);
+        entry.product_id = Bundle { bundle_id: MAX_BUNDLE_ID, price: 1, items: Vec::new() }.history_product_id();
+        assert_eq!(entry.bundle_id(), Some(MAX_BUNDLE_ID));
+    }
+
+    #[test]
+    fn test_happy_hour_windows() {
+        // Dias úteis, 15:00–17:00 UTC, 20% off
+        let weekdays = HappyHourWindow { days: 0x1f, start_minute: 15 * 60, end_minute: 17 * 60, discount_bps: 2_000 };
+        // Domingo o dia todo, 10% off
+        let sunday = HappyHourWindow { days: 1 << 6, start_minute: 0, end_minute: MINUTES_PER_DAY, discount_bps: 1_000 };
+        let happy_hour = HappyHour { product_id: 2, windows: vec![weekdays, sunday] };
+        assert!(weekdays.is_valid() && sunday.is_valid());
+
+        // 2023-11-14 (terça) 00:00 UTC
+        let tuesday = 1_699_920_000;
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 15 * 3_600), 2_000);
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 17 * 3_600 - 1), 2_000);
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 17 * 3_600), 0);
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 14 * 3_600), 0);
+        assert_eq!(happy_hour.discount(1_000_000, tuesday + 16 * 3_600), 200_000);
+        // Sábado fora das duas janelas; domingo com a janela do dia inteiro
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 4 * 86_400 + 16 * 3_600), 0);
+        assert_eq!(happy_hour.active_discount_bps(tuesday + 5 * 86_400 + 16 * 3_600), 1_000);
+
+        let mut data = [0u8; HappyHour::LEN];
+        HappyHour::pack(happy_hour.clone(), &mut data).unwrap();
+        assert_eq!(HappyHour::unpack(&data).unwrap(), happy_hour);
+
+        assert!(!HappyHourWindow { days: 0, ..weekdays }.is_valid());
+        assert!(!HappyHourWindow { days: 0x80, ..weekdays }.is_valid());
+        assert!(!HappyHourWindow { end_minute: 15 * 60, ..weekdays }.is_valid());
+        assert!(!HappyHourWindow { end_minute: MINUTES_PER_DAY + 1, ..weekdays }.is_valid());
+        assert!(!HappyHourWindow { discount_bps: 10_001, ..weekdays }.is_valid());
+    }
+
+    #[test]
+    fn test_product_rejects_unknown_version() {
+        let product = Product::unpack(&[0u8; Product::LEN]).unwrap();
+        let mut data = vec![0u8; product.account_len().unwrap()];
+        Product::pack_discriminated(&product, &mut data).unwrap();
+        data[8] = PRODUCT_BORSH_VERSION + 1;
+        assert!(Product::unpack_versioned(&data).is_err());
+
+        // Tamanho que não é de nenhum layout fixo e sem versão Borsh
+        assert!(Product::unpack_versioned(&[0u8; Product::LEN + 1]).is_err());
+        assert!(Product::unpack_versioned(&[]).is_err());
+    }
+}
@@ -0,0 +1,458 @@
+// Validação comum a todas as instruções, feita pelo process_instruction antes do processador: dados não
+// vazios e do tamanho exato do layout, textos em UTF-8 sem NUL no meio, quantidades e preços maiores que
+// zero e nenhuma conta do programa gravável repetida
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{
+    error::CakeError,
+    instruction::CakeInstruction,
+    state::{HappyHourWindow, TAG_LEN},
+};
+
+pub fn check_instruction_data(data: &[u8]) -> ProgramResult {
+    if data.is_empty() {
+        return Err(CakeError::EmptyInstructionData.into());
+    }
+    Ok(())
+}
+
+// Texto de um campo fixo ou com tamanho: o preenchimento com NUL no fim é descartado, mas um NUL antes de
+// texto ainda é erro
+pub fn clean_text(src: &[u8]) -> Result<String, ProgramError> {
+    let len = src.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    if src[..len].contains(&0) {
+        return Err(CakeError::InteriorNul.into());
+    }
+    String::from_utf8(src[..len].to_vec()).map_err(|_| CakeError::InvalidUtf8.into())
+}
+
+// Primeira fronteira de campo que alcança len. Layouts com campos opcionais no fim aceitam as versões antigas
+// mais curtas, desde que terminem numa fronteira; passar da última fronteira é byte sobrando
+fn field_boundary(len: usize, base: usize, fields: &[usize]) -> usize {
+    let mut boundary = base;
+    for field in fields {
+        if boundary >= len {
+            break;
+        }
+        boundary += field;
+    }
+    boundary
+}
+
+// [len u8][bytes]; len = 0 significa ausente
+fn prefixed_len(bytes: &Option<Vec<u8>>) -> usize {
+    1 + bytes.as_ref().map_or(0, Vec::len)
+}
+
+// Tamanho exato do layout de cada instrução, calculado dos campos sem montar o pack
+fn expected_len(instruction: &CakeInstruction, len: usize) -> usize {
+    match instruction {
+        CakeInstruction::Initialize
+        | CakeInstruction::Pause
+        | CakeInstruction::Resume
+        | CakeInstruction::CreateTreasury
+        | CakeInstruction::CancelSubscription
+        | CakeInstruction::ProcessSubscription
+        | CakeInstruction::CreateGlobalStats
+        | CakeInstruction::MigrateState
+        | CakeInstruction::ClaimReferralRewards => 1,
+        // Formato longo: [name_len u8][name][description_len u16][description], tags opcionais no fim
+        CakeInstruction::AddProduct { name, description, tags, .. } if len > 185 => {
+            188 + name.len() + description.len() + if tags.is_empty() { 0 } else { 1 + tags.len() * TAG_LEN }
+        }
+        CakeInstruction::AddProduct { .. } => field_boundary(len, 177, &[8]),
+        CakeInstruction::Sell { coupon_code, gift_code, memo, referral_code, .. } => {
+            let (coupon, gift) = (prefixed_len(coupon_code), prefixed_len(gift_code));
+            let (memo, referral) = (prefixed_len(memo), prefixed_len(referral_code));
+            field_boundary(len, 17, &[coupon, 2, 1, 1, 1, gift, 1, 2, 1, 1, memo, referral, 8, 1, 1, 1, 8])
+        }
+        CakeInstruction::Restock { variant_id, .. } => 17 + variant_id.is_some() as usize,
+        CakeInstruction::SetConfig { accepted_mints, .. } if len > 33 => 35 + accepted_mints.len() * 33,
+        CakeInstruction::SetConfig { .. } => 33,
+        CakeInstruction::SetMaxPriceChange { .. } | CakeInstruction::SetReferralBps { .. } => 3,
+        CakeInstruction::DeactivateProduct { .. }
+        | CakeInstruction::Refund { .. }
+        | CakeInstruction::FulfillOrder { .. }
+        | CakeInstruction::CancelOrder { .. }
+        | CakeInstruction::MigrateProduct { .. }
+        | CakeInstruction::ConfirmDelivery { .. }
+        | CakeInstruction::ReleaseAfterTimeout { .. }
+        | CakeInstruction::RefundEscrow { .. }
+        | CakeInstruction::SetEscrowTimeout { .. }
+        | CakeInstruction::WithdrawTreasury { .. }
+        | CakeInstruction::ExtendAccount { .. }
+        | CakeInstruction::SetHistoryRetention { .. }
+        | CakeInstruction::CloseHistory { .. }
+        | CakeInstruction::CancelLaunch { .. }
+        | CakeInstruction::CancelFlashSale { .. }
+        | CakeInstruction::ConfirmOrderDelivery { .. }
+        | CakeInstruction::CreateStore { .. }
+        | CakeInstruction::OpenDispute { .. }
+        | CakeInstruction::ReleaseExpiredEscrow { .. }
+        | CakeInstruction::CloseBundle { .. }
+        | CakeInstruction::ClearHappyHour { .. }
+        | CakeInstruction::WithdrawStoreRevenue { .. } => 9,
+        CakeInstruction::SetAvailability { .. }
+        | CakeInstruction::SetAllowlistRequired { .. }
+        | CakeInstruction::CloseProduct { .. }
+        | CakeInstruction::UpdateOrderStatus { .. }
+        | CakeInstruction::SetPriceDecimals { .. }
+        | CakeInstruction::ResolveDispute { .. } => 10,
+        CakeInstruction::RemoveLocalization { lang_code, .. } => 9 + lang_code.len(),
+        CakeInstruction::GetQuote { .. }
+        | CakeInstruction::UpdateStock { .. }
+        | CakeInstruction::UpdatePrice { .. }
+        | CakeInstruction::PlaceOrder { .. }
+        | CakeInstruction::SetMaxPerBuyer { .. }
+        | CakeInstruction::SetProductCategory { .. }
+        | CakeInstruction::PartialRefund { .. }
+        | CakeInstruction::SetSolPrice { .. }
+        | CakeInstruction::PlacePreOrder { .. }
+        | CakeInstruction::ExecuteDelegatedPurchase { .. }
+        | CakeInstruction::PartialFulfillOrder { .. }
+        | CakeInstruction::SellBundle { .. } => 17,
+        CakeInstruction::SetAvailabilityWindow { .. } => 25,
+        CakeInstruction::ScheduleFlashSale { .. } => 27,
+        CakeInstruction::AddOperator { .. }
+        | CakeInstruction::RemoveOperator { .. }
+        | CakeInstruction::AddToAllowlist { .. }
+        | CakeInstruction::RemoveFromAllowlist { .. }
+        | CakeInstruction::AddToBlocklist { .. }
+        | CakeInstruction::RemoveFromBlocklist { .. }
+        | CakeInstruction::SetTipAccount { .. }
+        | CakeInstruction::CreateSubscription { .. }
+        | CakeInstruction::CreateLaunch { .. } => 33,
+        CakeInstruction::CreateCategory { name } => 1 + name.len(),
+        CakeInstruction::SetFee { .. } | CakeInstruction::SetCommission { .. } => 35,
+        CakeInstruction::SetArbiter { .. }
+        | CakeInstruction::SetCharity { .. }
+        | CakeInstruction::ExecuteSignedOrder { .. }
+        | CakeInstruction::ConvertPreOrder { .. }
+        | CakeInstruction::RefundPreOrder { .. }
+        | CakeInstruction::SetGateMint { .. } => 41,
+        CakeInstruction::SetUsdPrice { .. } => 49,
+        CakeInstruction::CreateGiftCard { code_hash, .. } => 17 + code_hash.len(),
+        CakeInstruction::CreateCoupon { code_hash, .. } => 26 + code_hash.len(),
+        CakeInstruction::AddVariant { name, .. } => 26 + name.len(),
+        CakeInstruction::SetMetadataUri { metadata_uri, .. } => 9 + metadata_uri.len(),
+        CakeInstruction::SetBuyerProfile { display_name_hash, contact_hash, .. } => 33 + display_name_hash.len() + contact_hash.len(),
+        CakeInstruction::CreateReferral { code_hash, .. } => 65 + code_hash.len(),
+        CakeInstruction::ConfidentialSell { new_source_decryptable_balance, .. } => 17 + new_source_decryptable_balance.len(),
+        CakeInstruction::Checkout { items, .. } => field_boundary(len, 2 + items.len() * 16, &[1]),
+        CakeInstruction::SetPriceTiers { tiers, .. } => 10 + tiers.len() * 16,
+        CakeInstruction::SetRevenueSplit { splits } => 2 + splits.len() * 34,
+        CakeInstruction::ArchiveHistory { history_indexes, .. } => 10 + history_indexes.len() * 8,
+        CakeInstruction::SetOwnerSet { members, .. } => 3 + members.len() * 32,
+        CakeInstruction::SetBundle { items, .. } => 18 + items.len() * 16,
+        CakeInstruction::SetHappyHour { windows, .. } => 10 + windows.len() * HappyHourWindow::LEN,
+        CakeInstruction::SetProductTags { tags, .. } => 10 + tags.len() * TAG_LEN,
+        CakeInstruction::AddProducts { products } => {
+            2 + products.iter().map(|product| 27 + product.name.len() + product.description.len()).sum::<usize>()
+        }
+        CakeInstruction::GetFullQuote { coupon_code, .. } => 19 + prefixed_len(coupon_code),
+        CakeInstruction::SetLocalization { lang_code, name, description, .. } => 12 + lang_code.len() + name.len() + description.len(),
+        CakeInstruction::ConfidentialRevenue { data } => 1 + data.len(),
+    }
+}
+
+pub fn check_data_len(instruction: &CakeInstruction, data: &[u8]) -> ProgramResult {
+    let expected = expected_len(instruction, data.len());
+    if data.len() > expected {
+        return Err(CakeError::InstructionDataTooLong.into());
+    }
+    if data.len() < expected {
+        return Err(CakeError::InstructionDataTooShort.into());
+    }
+    Ok(())
+}
+
+// Nomes fixos guardados como bytes nas contas
+fn check_fixed_text(src: &[u8]) -> ProgramResult {
+    clean_text(src).map(|_| ())
+}
+
+// Tags não vazias, em texto válido e sem repetição
+fn check_tags(tags: &[[u8; TAG_LEN]]) -> ProgramResult {
+    for (i, tag) in tags.iter().enumerate() {
+        if clean_text(tag)?.is_empty() || tags[..i].contains(tag) {
+            return Err(CakeError::InvalidTag.into());
+        }
+    }
+    Ok(())
+}
+
+fn check_quantity(quantity: u64) -> ProgramResult {
+    if quantity == 0 {
+        return Err(CakeError::ZeroQuantity.into());
+    }
+    Ok(())
+}
+
+fn check_price(price: u64) -> ProgramResult {
+    if price == 0 {
+        return Err(CakeError::ZeroPrice.into());
+    }
+    Ok(())
+}
+
+// sol_price e usd_price_cents ficam de fora: neles 0 desativa o preço alternativo
+pub fn check_fields(instruction: &CakeInstruction) -> ProgramResult {
+    match instruction {
+        CakeInstruction::AddProduct { price, tags, .. } => {
+            check_price(*price)?;
+            check_tags(tags)
+        }
+        CakeInstruction::AddProducts { products } => products.iter().try_for_each(|product| check_price(product.price)),
+        CakeInstruction::UpdatePrice { new_price, .. } => check_price(*new_price),
+        CakeInstruction::AddVariant { name, price, .. } => {
+            check_fixed_text(name)?;
+            check_price(*price)
+        }
+        CakeInstruction::CreateCategory { name } => check_fixed_text(name),
+        CakeInstruction::SetProductTags { tags, .. } => check_tags(tags),
+        CakeInstruction::SetPriceTiers { tiers, .. } => tiers.iter().try_for_each(|tier| check_price(tier.unit_price)),
+        CakeInstruction::CreateLaunch { unit_price, max_quantity, .. } => {
+            check_price(*unit_price)?;
+            check_quantity(*max_quantity)
+        }
+        CakeInstruction::GetQuote { amount, .. }
+        | CakeInstruction::GetFullQuote { amount, .. }
+        | CakeInstruction::Sell { amount, .. }
+        | CakeInstruction::Restock { amount, .. }
+        | CakeInstruction::ExecuteDelegatedPurchase { amount, .. } => check_quantity(*amount),
+        CakeInstruction::PlaceOrder { quantity, .. }
+        | CakeInstruction::PlacePreOrder { quantity, .. }
+        | CakeInstruction::PartialRefund { quantity, .. }
+        | CakeInstruction::PartialFulfillOrder { quantity, .. }
+        | CakeInstruction::SellBundle { quantity, .. }
+        | CakeInstruction::ConfidentialSell { quantity, .. } => check_quantity(*quantity),
+        CakeInstruction::CreateSubscription { quantity, max_unit_price, .. } => {
+            check_quantity(*quantity)?;
+            check_price(*max_unit_price)
+        }
+        CakeInstruction::Checkout { items, .. } => items.iter().try_for_each(|item| check_quantity(item.quantity)),
+        CakeInstruction::SetBundle { price, items, .. } => {
+            check_price(*price)?;
+            items.iter().try_for_each(|item| check_quantity(item.quantity))
+        }
+        CakeInstruction::ExecuteSignedOrder { order } => check_quantity(order.quantity),
+        _ => Ok(()),
+    }
+}
+
+// Uma conta do programa gravável passada duas vezes seria lida e gravada como se fossem duas (o mesmo
+// produto duas vezes no checkout, por exemplo). Cofres e comprador = pagador podem se repetir
+pub fn check_unique_accounts(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let mut seen: Vec<&Pubkey> = Vec::with_capacity(accounts.len());
+    for account in accounts.iter().filter(|account| account.is_writable && account.owner == program_id) {
+        if seen.contains(&account.key) {
+            return Err(CakeError::DuplicateAccount.into());
+        }
+        seen.push(account.key);
+    }
+    Ok(())
+}
+
+pub fn validate_instruction(instruction: &CakeInstruction, data: &[u8], accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    check_data_len(instruction, data)?;
+    check_fields(instruction)?;
+    check_unique_accounts(accounts, program_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        instruction::{CartItem, NewProduct},
+        state::{AcceptedMint, CHECKOUT_VERSION, SELL_VERSION},
+    };
+
+    fn err(error: CakeError) -> ProgramError {
+        error.into()
+    }
+
+    #[test]
+    fn test_check_instruction_data() {
+        assert_eq!(check_instruction_data(&[]), Err(err(CakeError::EmptyInstructionData)));
+        assert_eq!(check_instruction_data(&[0]), Ok(()));
+        assert_eq!(crate::process_instruction(&Pubkey::new_unique(), &[], &[]), Err(err(CakeError::EmptyInstructionData)));
+    }
+
+    #[test]
+    fn test_check_data_len() {
+        let instruction = CakeInstruction::GetQuote { product_id: 1, amount: 2 };
+        let mut data = instruction.pack();
+        assert_eq!(check_data_len(&instruction, &data), Ok(()));
+        data.push(0);
+        assert_eq!(check_data_len(&instruction, &data), Err(err(CakeError::InstructionDataTooLong)));
+
+        // Layout antigo do AddProduct, sem max_per_buyer, ainda passa
+        let add_product = CakeInstruction::AddProduct {
+            name: "Bolo".to_string(),
+            description: String::new(),
+            price: 1,
+            stock: 1,
+            max_per_buyer: 0,
+            tags: Vec::new(),
+        };
+        let data = add_product.pack();
+        assert_eq!(check_data_len(&add_product, &data[..177]), Ok(()));
+
+        // Textos curtos no formato longo: [name_len][name][description_len u16][description]
+        let mut long = data.clone();
+        long.push(4);
+        long.extend_from_slice(b"Bolo");
+        long.extend_from_slice(&0u16.to_le_bytes());
+        assert_eq!(check_data_len(&CakeInstruction::unpack(&long).unwrap(), &long), Ok(()));
+        long.push(0);
+        assert_eq!(check_data_len(&CakeInstruction::unpack(&long).unwrap(), &long), Err(err(CakeError::InstructionDataTooLong)));
+
+        // Terminar no meio do max_per_buyer não é um layout antigo
+        let cut = &data[..180];
+        assert_eq!(check_data_len(&CakeInstruction::unpack(cut).unwrap(), cut), Err(err(CakeError::InstructionDataTooShort)));
+    }
+
+    #[test]
+    fn test_check_data_len_sell() {
+        let sell = CakeInstruction::Sell {
+            product_id: 1,
+            amount: 2,
+            coupon_code: Some(b"BOLO10".to_vec()),
+            variant_id: Some(3),
+            escrow: false,
+            payment_mode: 0,
+            create_ata: false,
+            gift_code: None,
+            mint_receipt: false,
+            product_bump: None,
+            paged_history: false,
+            version: SELL_VERSION,
+            memo: Some(b"Feliz aniversario".to_vec()),
+            referral_code: None,
+            tip: 5,
+            round_up: false,
+            sync_native: false,
+            close_wsol: false,
+            max_total_price: 0,
+        };
+        let data = sell.pack();
+        assert_eq!(check_data_len(&sell, &data), Ok(()));
+
+        // Versões antigas terminam na fronteira de um campo: só o cupom, ou cupom e variante
+        for len in [17, 24, 26] {
+            assert_eq!(check_data_len(&CakeInstruction::unpack(&data[..len]).unwrap(), &data[..len]), Ok(()));
+        }
+        // Um byte da variante sozinho seria lido como escrow
+        let cut = &data[..25];
+        assert_eq!(check_data_len(&CakeInstruction::unpack(cut).unwrap(), cut), Err(err(CakeError::InstructionDataTooShort)));
+    }
+
+    #[test]
+    fn test_expected_len_matches_pack() {
+        let product = NewProduct { name: "Bolo".to_string(), description: "Cenoura".to_string(), price: 1, stock: 1, max_per_buyer: 0 };
+        let instructions = [
+            CakeInstruction::Initialize,
+            CakeInstruction::Restock { product_id: 1, amount: 2, variant_id: Some(3) },
+            CakeInstruction::SetConfig {
+                payment_mint: Pubkey::new_unique(),
+                price_decimals: 6,
+                accepted_mints: vec![AcceptedMint::default(); 2],
+            },
+            CakeInstruction::Checkout { items: vec![CartItem { product_id: 1, quantity: 1 }; 3], version: CHECKOUT_VERSION },
+            CakeInstruction::CreateLaunch { product_id: 1, release_at: 2, unit_price: 3, max_quantity: 4 },
+            CakeInstruction::AddProducts { products: vec![product] },
+            CakeInstruction::GetFullQuote { product_id: 1, amount: 2, coupon_code: Some(b"BOLO".to_vec()), variant_id: None },
+            CakeInstruction::SetLocalization { product_id: 1, lang_code: *b"en", name: "Cake".to_string(), description: String::new() },
+            CakeInstruction::SetProductTags { product_id: 1, tags: vec![[b'a'; TAG_LEN]] },
+            CakeInstruction::ConfidentialRevenue { data: vec![1, 2, 3] },
+        ];
+        for instruction in &instructions {
+            let data = instruction.pack();
+            assert_eq!(expected_len(instruction, data.len()), data.len(), "{:?}", instruction);
+        }
+    }
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(clean_text(b"Bolo\0\0\0"), Ok("Bolo".to_string()));
+        assert_eq!(clean_text(&[0; 8]), Ok(String::new()));
+        assert_eq!(clean_text(b"Bo\0lo\0"), Err(err(CakeError::InteriorNul)));
+        assert_eq!(clean_text(&[0xff, 0xfe]), Err(err(CakeError::InvalidUtf8)));
+
+        let add_product = CakeInstruction::AddProduct {
+            name: "Bolo".to_string(),
+            description: String::new(),
+            price: 1,
+            stock: 1,
+            max_per_buyer: 0,
+            tags: Vec::new(),
+        };
+        let mut data = add_product.pack();
+        data[3] = 0;
+        assert_eq!(CakeInstruction::unpack(&data), Err(err(CakeError::InteriorNul)));
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let zero_quantity = [
+            CakeInstruction::GetQuote { product_id: 0, amount: 0 },
+            CakeInstruction::PlaceOrder { product_id: 0, quantity: 0 },
+            CakeInstruction::Restock { product_id: 0, amount: 0, variant_id: None },
+            CakeInstruction::Checkout {
+                items: vec![CartItem { product_id: 0, quantity: 1 }, CartItem { product_id: 1, quantity: 0 }],
+                version: CHECKOUT_VERSION,
+            },
+            CakeInstruction::ExecuteDelegatedPurchase { product_id: 0, amount: 0 },
+        ];
+        for instruction in &zero_quantity {
+            assert_eq!(check_fields(instruction), Err(err(CakeError::ZeroQuantity)));
+        }
+
+        let new_product = NewProduct { name: "Bolo".to_string(), description: String::new(), price: 0, stock: 1, max_per_buyer: 0 };
+        let zero_price = [
+            CakeInstruction::UpdatePrice { product_id: 0, new_price: 0 },
+            CakeInstruction::AddVariant { product_id: 0, variant_id: 1, name: [b'P'; 32], price: 0, stock: 1 },
+            CakeInstruction::AddProducts { products: vec![new_product] },
+        ];
+        for instruction in &zero_price {
+            assert_eq!(check_fields(instruction), Err(err(CakeError::ZeroPrice)));
+        }
+
+        let mut name = [0u8; 32];
+        name[1] = b'P';
+        assert_eq!(check_fields(&CakeInstruction::CreateCategory { name }), Err(err(CakeError::InteriorNul)));
+
+        let tag = |text: &[u8]| {
+            let mut tag = [0u8; TAG_LEN];
+            tag[..text.len()].copy_from_slice(text);
+            tag
+        };
+        let set_tags = |tags: Vec<[u8; TAG_LEN]>| check_fields(&CakeInstruction::SetProductTags { product_id: 0, tags });
+        assert_eq!(set_tags(vec![tag(b"vegano"), tag(b"sem-gluten")]), Ok(()));
+        assert_eq!(set_tags(vec![tag(b"vegano"), tag(b"vegano")]), Err(err(CakeError::InvalidTag)));
+        assert_eq!(set_tags(vec![tag(b"")]), Err(err(CakeError::InvalidTag)));
+        assert_eq!(set_tags(vec![tag(b"ve\0gano")]), Err(err(CakeError::InteriorNul)));
+
+        // 0 desativa o preço em SOL
+        assert_eq!(check_fields(&CakeInstruction::SetSolPrice { product_id: 0, sol_price: 0 }), Ok(()));
+        assert_eq!(check_fields(&CakeInstruction::GetQuote { product_id: 0, amount: 1 }), Ok(()));
+    }
+
+    #[test]
+    fn test_check_unique_accounts() {
+        let program_id = Pubkey::new_unique();
+        let product = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let system_program = solana_system_interface::program::id();
+        let (mut lamports, mut data) = ([1u64; 4], [[0u8; 0]; 4]);
+        let [l0, l1, l2, l3] = &mut lamports;
+        let [d0, d1, d2, d3] = &mut data;
+        let product_a = AccountInfo::new(&product, false, true, l0, d0, &program_id, false, 0);
+        let product_b = AccountInfo::new(&product, false, true, l1, d1, &program_id, false, 0);
+        let vault_a = AccountInfo::new(&vault, false, true, l2, d2, &system_program, false, 0);
+        let vault_b = AccountInfo::new(&vault, false, true, l3, d3, &system_program, false, 0);
+
+        assert_eq!(check_unique_accounts(&[product_a.clone(), vault_a.clone(), vault_b], &program_id), Ok(()));
+        assert_eq!(check_unique_accounts(&[product_a, vault_a, product_b], &program_id), Err(err(CakeError::DuplicateAccount)));
+    }
+}
@@ -0,0 +1,57 @@
+// Decodificação das contas no navegador (feature `wasm`): recebe os bytes crus de getAccountInfo e devolve
+// um objeto JS com os mesmos campos do serde. u64/i64 viram BigInt para não perder precisão acima de 2^53
+use serde::Serialize;
+use solana_program::{program_error::ProgramError, program_pack::Pack};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    error::CakeError,
+    state::{CakeState, Product, PurchaseHistory},
+};
+
+// Erros do programa saem com a mensagem de CakeError em vez de "custom program error"
+fn error_message(err: ProgramError) -> String {
+    match err {
+        ProgramError::Custom(code) => match CakeError::from_code(code) {
+            Some(error) => format!("{:?}: {}", error, error),
+            None => err.to_string(),
+        },
+        _ => err.to_string(),
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+    value.serialize(&serializer).map_err(|err| JsError::new(&err.to_string()))
+}
+
+fn decode<T: Serialize>(value: Result<T, ProgramError>) -> Result<JsValue, JsError> {
+    to_js(&value.map_err(|err| JsError::new(&error_message(err)))?)
+}
+
+#[wasm_bindgen(js_name = decodeCakeState)]
+pub fn decode_cake_state(data: &[u8]) -> Result<JsValue, JsError> {
+    decode(CakeState::unpack(data))
+}
+
+// Aceita o layout atual e os de tamanho fixo anteriores ao discriminador
+#[wasm_bindgen(js_name = decodeProduct)]
+pub fn decode_product(data: &[u8]) -> Result<JsValue, JsError> {
+    decode(Product::unpack_versioned(data))
+}
+
+#[wasm_bindgen(js_name = decodePurchaseHistory)]
+pub fn decode_purchase_history(data: &[u8]) -> Result<JsValue, JsError> {
+    decode(PurchaseHistory::unpack_versioned(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(error_message(CakeError::WrongPda.into()), "WrongPda: Conta não é a PDA esperada");
+        assert_eq!(error_message(ProgramError::AccountDataTooSmall), ProgramError::AccountDataTooSmall.to_string());
+    }
+}
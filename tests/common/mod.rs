@@ -0,0 +1,63 @@
+// Contas montadas à mão e o baseline de compute units, compartilhados pelos testes de integração. Cada
+// teste usa só parte dos helpers
+#![allow(dead_code)]
+
+use solana_sdk::{account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+
+pub const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compute_units_baseline.txt");
+
+pub fn account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account { lamports: Rent::default().minimum_balance(data.len()), data, owner, executable: false, rent_epoch: 0 }
+}
+
+pub fn packed<T: Pack>(value: T) -> Vec<u8> {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).unwrap();
+    data
+}
+
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    account(
+        packed(spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }),
+        spl_token::id(),
+    )
+}
+
+// Os testes que medem o binário SBF ficam em #[ignore]; rodá-los sem o binário é erro, não um teste que
+// passa sem medir nada
+pub fn require_sbf() {
+    if std::env::var_os("SBF_OUT_DIR").is_none() && std::env::var_os("BPF_OUT_DIR").is_none() {
+        panic!("binário SBF ausente (SBF_OUT_DIR); rode com cargo test-sbf --features client");
+    }
+}
+
+// Linhas "nome teto"; # começa comentário
+pub fn read_baseline() -> Vec<(String, u64)> {
+    let contents = std::fs::read_to_string(BASELINE_PATH).unwrap_or_default();
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, budget) = line.split_once(char::is_whitespace).expect("linha do baseline sem teto");
+            (name.to_string(), budget.trim().parse().expect("teto do baseline não é um número"))
+        })
+        .collect()
+}
+
+pub fn baseline_budget(name: &str) -> u64 {
+    read_baseline()
+        .into_iter()
+        .find(|(baseline_name, _)| baseline_name == name)
+        .map(|(_, budget)| budget)
+        .unwrap_or_else(|| panic!("{} sem teto no baseline", name))
+}
@@ -0,0 +1,228 @@
+// Consumo de compute units por instrução no binário SBF, comparado com os tetos de
+// tests/compute_units_baseline.txt. O cargo test-sbf liga a feature test-sbf e o teste roda (é o que o CI
+// faz em .github/workflows/compute-units.yml); no `cargo test` comum ele aparece como ignorado. Com
+// CIDACAKE_CU_BASELINE=update o arquivo é regravado com o consumo medido mais a folga de BASELINE_MARGIN_BPS
+mod common;
+
+use cidacake_program::{
+    client::{self, SellAccounts, SellOptions, Store},
+    pda::*,
+    state::*,
+};
+use common::{account, packed, read_baseline, require_sbf, token_account, BASELINE_PATH};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    program_option::COption,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    signer::keypair::keypair_from_seed,
+    transaction::Transaction,
+};
+
+const BASELINE_MARGIN_BPS: u64 = 1_000;
+
+// Chaves fixas: os bumps das PDAs (e o custo do find_program_address) não mudam entre execuções
+fn keypair(seed: u8) -> Keypair {
+    keypair_from_seed(&[seed; 32]).unwrap()
+}
+
+// Simula para ler o consumo e depois executa, para que a próxima instrução veja o estado atualizado
+async fn measure(context: &mut ProgramTestContext, ix: Instruction, signers: &[&Keypair]) -> u64 {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, blockhash);
+
+    let simulation = context.banks_client.simulate_transaction(tx.clone()).await.unwrap();
+    simulation.result.unwrap().unwrap();
+    let units = simulation.simulation_details.unwrap().units_consumed;
+    context.banks_client.process_transaction(tx).await.unwrap();
+    units
+}
+
+fn write_baseline(measured: &[(&str, u64)]) {
+    let mut contents = String::from(
+        "# Teto de compute units por instrução, conferido por tests/compute_units.rs. Regrave com\n\
+         # CIDACAKE_CU_BASELINE=update cargo test-sbf --features client --test compute_units\n\
+         # e justifique no commit qualquer teto que subir\n",
+    );
+    for (name, units) in measured {
+        contents.push_str(&format!("{} {}\n", name, units + units * BASELINE_MARGIN_BPS / 10_000));
+    }
+    std::fs::write(BASELINE_PATH, contents).unwrap();
+}
+
+async fn measure_all() -> Vec<(&'static str, u64)> {
+    let program_id = Pubkey::new_from_array([7; 32]);
+    let owner = keypair(1);
+    let buyer = keypair(2);
+    let cake = Pubkey::new_from_array([3; 32]);
+    let mint = Pubkey::new_from_array([4; 32]);
+    let buyer_token = Pubkey::new_from_array([5; 32]);
+    let owner_token = spl_associated_token_account::get_associated_token_address(&owner.pubkey(), &mint);
+    let revenue_token = store_revenue_address(&cake, &mint, &spl_token::id(), &program_id);
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, None);
+    program_test.prefer_bpf(true);
+
+    let state = CakeState {
+        owner: owner.pubkey(),
+        product_counter: 1,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout: 0,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    };
+    program_test.add_account(cake, account(packed(state), program_id));
+
+    let config = Config {
+        payment_mint: mint,
+        price_decimals: 6,
+        accepted_mint_count: 0,
+        accepted_mints: [AcceptedMint::default(); MAX_ACCEPTED_MINTS],
+        split_count: 0,
+        splits: [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS],
+        history_retention: 0,
+        referral_bps: 0,
+        tip_account: Pubkey::default(),
+        charity_account: Pubkey::default(),
+        round_up_unit: 0,
+        arbiter: Pubkey::default(),
+        dispute_window: 0,
+    };
+    program_test.add_account(find_config_address(&cake, &program_id).0, account(packed(config), program_id));
+
+    let product = Product {
+        id: 0,
+        name: "Bolo de cenoura".to_string(),
+        description: String::new(),
+        price: 1_000_000,
+        stock: 100,
+        active: true,
+        available: true,
+        max_per_buyer: 0,
+        category_id: NO_CATEGORY,
+        metadata_uri: [0; 200],
+        tier_count: 0,
+        tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+        sol_price: 0,
+        usd_price_cents: 0,
+        price_feed: Pubkey::default(),
+        allowlist_required: false,
+        gate_mint: Pubkey::default(),
+        units_sold: 0,
+        revenue: 0,
+        sol_revenue: 0,
+        available_from: 0,
+        available_until: 0,
+        price_decimals: 0,
+    };
+    let mut product_data = vec![0; product.account_len().unwrap()];
+    Product::pack_discriminated(&product, &mut product_data).unwrap();
+    program_test.add_account(find_product_address(&cake, 0, &program_id).0, account(product_data, program_id));
+
+    let mint_data = packed(spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    });
+    program_test.add_account(mint, account(mint_data, spl_token::id()));
+    program_test.add_account(buyer_token, token_account(mint, buyer.pubkey(), 100_000_000));
+    program_test.add_account(owner_token, token_account(mint, owner.pubkey(), 10_000_000));
+    let revenue_authority = find_store_revenue_authority_address(&cake, &program_id).0;
+    program_test.add_account(revenue_token, token_account(mint, revenue_authority, 0));
+
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+    let store = Store { cake, state, config };
+    let sell_accounts = SellAccounts {
+        buyer: buyer.pubkey(),
+        payer: Some(payer),
+        buyer_token,
+        owner_token: revenue_token,
+        token_program: spl_token::id(),
+        payment_mint: mint,
+        gate_token: None,
+        price_feed: None,
+    };
+    let mut measured = Vec::new();
+
+    let ix = client::create_store_ix(&program_id, 1, &owner.pubkey(), &payer, &mint, &spl_token::id());
+    measured.push(("create_store", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::add_product_ix(&program_id, &store, &payer, "Bolo de fubá", "Com goiabada", 800_000, 10, 0, &[]);
+    measured.push(("add_product", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::set_config_ix(&program_id, &store, &payer, &mint, 6);
+    measured.push(("set_config", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::update_price_ix(&program_id, &store, 0, 1_200_000);
+    measured.push(("update_price", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::update_stock_ix(&program_id, &store, 0, 5);
+    measured.push(("update_stock", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::restock_ix(&program_id, &store, &owner.pubkey(), 0, 10, None);
+    measured.push(("restock", measure(&mut context, ix, &[&owner]).await));
+
+    let ix = client::get_quote_ix(&program_id, &store, 0, 2);
+    measured.push(("get_quote", measure(&mut context, ix, &[]).await));
+
+    // A primeira compra cria histórico, contador, índice e perfil; a segunda só grava
+    let ix = client::sell_ix(&program_id, &store, &product, 2, 0, &sell_accounts, &SellOptions::default());
+    measured.push(("sell", measure(&mut context, ix, &[&buyer]).await));
+
+    let ix = client::sell_ix(&program_id, &store, &product, 1, 1, &sell_accounts, &SellOptions::default());
+    measured.push(("sell_repeat", measure(&mut context, ix, &[&buyer]).await));
+
+    let history_address = find_buyer_history_address(&cake, &buyer.pubkey(), 0, &program_id).0;
+    let history_account = context.banks_client.get_account(history_address).await.unwrap().unwrap();
+    let history = PurchaseHistory::unpack_versioned(&history_account.data).unwrap();
+    let ix = client::refund_ix(&program_id, &store, 0, &history, &buyer_token, &spl_token::id(), &mint);
+    measured.push(("refund", measure(&mut context, ix, &[&owner]).await));
+
+    measured
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "test-sbf"), ignore = "mede o binário SBF: cargo test-sbf --features client")]
+async fn test_compute_unit_budgets() {
+    require_sbf();
+
+    let measured = measure_all().await;
+    if std::env::var("CIDACAKE_CU_BASELINE").as_deref() == Ok("update") {
+        write_baseline(&measured);
+        return;
+    }
+
+    let baseline = read_baseline();
+    let mut failures = Vec::new();
+    for (name, units) in &measured {
+        match baseline.iter().find(|(baseline_name, _)| baseline_name == name) {
+            Some((_, budget)) => {
+                println!("{:<14} {:>7} CU (teto {})", name, units, budget);
+                if units > budget {
+                    failures.push(format!("{} consumiu {} CU (teto {})", name, units, budget));
+                }
+            }
+            None => failures.push(format!("{} sem teto no baseline ({} CU medidos)", name, units)),
+        }
+    }
+    for (name, _) in &baseline {
+        if !measured.iter().any(|(measured_name, _)| measured_name == name) {
+            failures.push(format!("{} está no baseline mas não é medida", name));
+        }
+    }
+    assert!(failures.is_empty(), "compute units fora do baseline:\n{}", failures.join("\n"));
+}
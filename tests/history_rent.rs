@@ -0,0 +1,259 @@
+// Fechamento e arquivamento de históricos devolvem o rent a quem o pagou: o rent_payer gravado no histórico,
+// que pode ser outra carteira além do comprador e do owner. Usa o processador nativo, como signer_checks
+mod common;
+
+use cidacake_program::{instruction::CakeInstruction, pda::*, process_instruction, state::*, CakeError};
+use common::account;
+use solana_program_test::{processor, BanksClientError, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_system_interface::program as system_program;
+
+fn cake_state(owner: Pubkey) -> Vec<u8> {
+    let state = CakeState {
+        owner,
+        product_counter: 1,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout: 0,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    };
+    let mut data = vec![0; CakeState::LEN];
+    CakeState::pack(state, &mut data).unwrap();
+    data
+}
+
+fn config(history_retention: i64) -> Vec<u8> {
+    let config = Config {
+        payment_mint: Pubkey::new_unique(),
+        price_decimals: 6,
+        accepted_mint_count: 0,
+        accepted_mints: [AcceptedMint::default(); 4],
+        split_count: 0,
+        splits: [SplitRecipient::default(); 4],
+        history_retention,
+        referral_bps: 0,
+        tip_account: Pubkey::default(),
+        charity_account: Pubkey::default(),
+        round_up_unit: 0,
+        arbiter: Pubkey::default(),
+        dispute_window: 0,
+    };
+    let mut data = vec![0; Config::LEN];
+    Config::pack(config, &mut data).unwrap();
+    data
+}
+
+// Compra de 1970, então qualquer retenção configurada já passou
+fn history(buyer: Pubkey, rent_payer: Pubkey, status: u8) -> Vec<u8> {
+    let history = PurchaseHistory {
+        product_id: 0,
+        quantity: 1,
+        total_price: 1_000_000,
+        buyer,
+        timestamp: 0,
+        status,
+        fee: 0,
+        refunded_quantity: if status == PURCHASE_STATUS_REFUNDED { 1 } else { 0 },
+        payment_mode: PAYMENT_MODE_TOKEN,
+        rent_payer,
+        tip: 0,
+        donation: 0,
+        unit_price: 1_000_000,
+        name_hash: [0; 32],
+        has_variant: false,
+        variant_id: 0,
+    };
+    let mut data = vec![0; PurchaseHistory::LEN];
+    PurchaseHistory::pack(history, &mut data).unwrap();
+    data
+}
+
+struct Store {
+    program_id: Pubkey,
+    cake: Pubkey,
+    owner: Keypair,
+    program_test: ProgramTest,
+}
+
+fn store(history_retention: i64) -> Store {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Keypair::new();
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(owner.pubkey()), program_id));
+    program_test.add_account(find_config_address(&cake, &program_id).0, account(config(history_retention), program_id));
+    program_test.add_account(owner.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    Store { program_id, cake, owner, program_test }
+}
+
+impl Store {
+    // Grava o histórico de índice 0 do comprador e devolve o endereço
+    fn add_history(&mut self, buyer: Pubkey, rent_payer: Pubkey, status: u8) -> Pubkey {
+        let address = find_buyer_history_address(&self.cake, &buyer, 0, &self.program_id).0;
+        self.program_test.add_account(address, account(history(buyer, rent_payer, status), self.program_id));
+        address
+    }
+}
+
+fn close_history_ix(store: &Store, history: Pubkey, authority: Pubkey, rent_payer: Option<Pubkey>) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(history, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new_readonly(find_config_address(&store.cake, &store.program_id).0, false),
+    ];
+    accounts.extend(rent_payer.map(|rent_payer| AccountMeta::new(rent_payer, false)));
+    Instruction::new_with_bytes(store.program_id, &CakeInstruction::CloseHistory { history_index: 0 }.pack(), accounts)
+}
+
+// Executa a instrução e devolve o saldo de cada conta de `watch` depois dela
+async fn run(store: Store, ix: Instruction, signers: &[&Keypair], watch: &[Pubkey]) -> Result<Vec<u64>, TransactionError> {
+    let context = store.program_test.start_with_context().await;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.map_err(|error| match error {
+        BanksClientError::TransactionError(error) => error,
+        error => panic!("{error}"),
+    })?;
+    let mut balances = Vec::with_capacity(watch.len());
+    for key in watch {
+        balances.push(context.banks_client.get_balance(*key).await.unwrap());
+    }
+    Ok(balances)
+}
+
+fn custom(error: CakeError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+fn history_rent() -> u64 {
+    Rent::default().minimum_balance(PurchaseHistory::LEN)
+}
+
+#[tokio::test]
+async fn test_owner_close_refunds_separate_rent_payer() {
+    let mut store = store(60);
+    let buyer = Pubkey::new_unique();
+    let rent_payer = Pubkey::new_unique();
+    let history = store.add_history(buyer, rent_payer, PURCHASE_STATUS_COMPLETED);
+    store.program_test.add_account(rent_payer, Account::new(1_000_000, 0, &system_program::id()));
+
+    let owner = store.owner.insecure_clone();
+    let ix = close_history_ix(&store, history, owner.pubkey(), Some(rent_payer));
+    let balances = run(store, ix, &[&owner], &[history, rent_payer, owner.pubkey()]).await.unwrap();
+
+    // O rent vai para o rent_payer, não para o owner que fechou nem para o comprador
+    assert_eq!(balances, [0, 1_000_000 + history_rent(), 1_000_000_000]);
+}
+
+#[tokio::test]
+async fn test_owner_close_requires_rent_payer_account() {
+    let mut store = store(60);
+    let history = store.add_history(Pubkey::new_unique(), Pubkey::new_unique(), PURCHASE_STATUS_COMPLETED);
+
+    let owner = store.owner.insecure_clone();
+    let ix = close_history_ix(&store, history, owner.pubkey(), None);
+    let error = run(store, ix, &[&owner], &[]).await.unwrap_err();
+    assert_eq!(error, TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys));
+}
+
+#[tokio::test]
+async fn test_owner_close_rejects_wrong_rent_payer() {
+    let mut store = store(60);
+    let history = store.add_history(Pubkey::new_unique(), Pubkey::new_unique(), PURCHASE_STATUS_COMPLETED);
+
+    let owner = store.owner.insecure_clone();
+    let ix = close_history_ix(&store, history, owner.pubkey(), Some(owner.pubkey()));
+    assert_eq!(run(store, ix, &[&owner], &[]).await.unwrap_err(), custom(CakeError::UnexpectedAccount));
+}
+
+#[tokio::test]
+async fn test_rent_payer_close_requires_retention() {
+    let mut store = store(0);
+    let buyer = Keypair::new();
+    let history = store.add_history(buyer.pubkey(), buyer.pubkey(), PURCHASE_STATUS_COMPLETED);
+
+    // Sem retenção, o comprador que pagou o rent não apaga uma compra ainda reembolsável
+    let ix = close_history_ix(&store, history, buyer.pubkey(), None);
+    assert_eq!(run(store, ix, &[&buyer], &[]).await.unwrap_err(), custom(CakeError::HistoryRetentionActive));
+}
+
+#[tokio::test]
+async fn test_rent_payer_closes_refunded_history() {
+    let mut store = store(0);
+    let buyer = Keypair::new();
+    let history = store.add_history(buyer.pubkey(), buyer.pubkey(), PURCHASE_STATUS_REFUNDED);
+    store.program_test.add_account(buyer.pubkey(), Account::new(1_000_000, 0, &system_program::id()));
+
+    let ix = close_history_ix(&store, history, buyer.pubkey(), None);
+    let balances = run(store, ix, &[&buyer], &[history, buyer.pubkey()]).await.unwrap();
+    assert_eq!(balances, [0, 1_000_000 + history_rent()]);
+}
+
+fn archive_history_ix(store: &Store, histories: &[Pubkey], rent_payers: &[Pubkey]) -> Instruction {
+    let data = CakeInstruction::ArchiveHistory { archive_id: 1, history_indexes: vec![0; histories.len()] };
+    let mut accounts = vec![
+        AccountMeta::new_readonly(store.cake, false),
+        AccountMeta::new(find_history_archive_address(&store.cake, 1, &store.program_id).0, false),
+        AccountMeta::new(store.owner.pubkey(), true),
+        AccountMeta::new_readonly(find_config_address(&store.cake, &store.program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(histories.iter().map(|history| AccountMeta::new(*history, false)));
+    accounts.extend(rent_payers.iter().map(|rent_payer| AccountMeta::new(*rent_payer, false)));
+    Instruction::new_with_bytes(store.program_id, &data.pack(), accounts)
+}
+
+#[tokio::test]
+async fn test_archive_refunds_mixed_rent_payers() {
+    let mut store = store(60);
+    let owner = store.owner.insecure_clone();
+    let buyer = Pubkey::new_unique();
+    let sponsor = Pubkey::new_unique();
+    // Um histórico pago pelo owner, dois pelo comprador e um por outra carteira
+    let histories = [
+        store.add_history(Pubkey::new_unique(), owner.pubkey(), PURCHASE_STATUS_COMPLETED),
+        store.add_history(buyer, buyer, PURCHASE_STATUS_COMPLETED),
+        store.add_history(Pubkey::new_unique(), buyer, PURCHASE_STATUS_REFUNDED),
+        store.add_history(Pubkey::new_unique(), sponsor, PURCHASE_STATUS_COMPLETED),
+    ];
+    store.program_test.add_account(buyer, Account::new(1_000_000, 0, &system_program::id()));
+    store.program_test.add_account(sponsor, Account::new(1_000_000, 0, &system_program::id()));
+
+    let ix = archive_history_ix(&store, &histories, &[buyer, sponsor]);
+    let balances = run(store, ix, &[&owner], &[buyer, sponsor, histories[0], histories[3]]).await.unwrap();
+    assert_eq!(balances, [1_000_000 + 2 * history_rent(), 1_000_000 + history_rent(), 0, 0]);
+}
+
+#[tokio::test]
+async fn test_archive_requires_every_rent_payer() {
+    let mut store = store(60);
+    let owner = store.owner.insecure_clone();
+    let buyer = Pubkey::new_unique();
+    let histories = [
+        store.add_history(buyer, buyer, PURCHASE_STATUS_COMPLETED),
+        store.add_history(Pubkey::new_unique(), Pubkey::new_unique(), PURCHASE_STATUS_COMPLETED),
+    ];
+
+    // Falta o rent_payer do segundo histórico: nada é fechado
+    let ix = archive_history_ix(&store, &histories, &[buyer]);
+    let error = run(store, ix, &[&owner], &[]).await.unwrap_err();
+    assert_eq!(error, TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys));
+}
@@ -0,0 +1,156 @@
+// Contas serializadas a partir de valores fixos e comparadas byte a byte com tests/golden/*.hex. Uma
+// diferença quer dizer que o layout on-chain mudou e as contas já gravadas na mainnet passariam a ser
+// lidas errado. Mudança intencional (com migração): CIDACAKE_GOLDEN=update cargo test --test layout_golden
+use cidacake_program::state::*;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+// 32 bytes por linha, para o diff apontar a região que mudou
+fn to_hex(data: &[u8]) -> String {
+    data.chunks(32).map(|line| line.iter().map(|b| format!("{:02x}", b)).collect::<String>() + "\n").collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    digits.chunks(2).map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).expect("golden com hex inválido")).collect()
+}
+
+fn check_golden(name: &str, data: &[u8]) {
+    let path = format!("{}/{}.hex", GOLDEN_DIR, name);
+    if std::env::var("CIDACAKE_GOLDEN").as_deref() == Ok("update") {
+        std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+        std::fs::write(&path, to_hex(data)).unwrap();
+        return;
+    }
+    let golden = from_hex(&std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err)));
+    if let Some(offset) = golden.iter().zip(data).position(|(expected, actual)| expected != actual) {
+        panic!(
+            "layout de {} mudou: primeiro byte diferente no offset {} ({:#04x} no golden, {:#04x} agora)",
+            name, offset, golden[offset], data[offset]
+        );
+    }
+    assert_eq!(data.len(), golden.len(), "layout de {} mudou de tamanho", name);
+}
+
+fn cake_state() -> CakeState {
+    CakeState {
+        owner: key(1),
+        product_counter: 12,
+        history_counter: 345,
+        max_price_change_bps: 2_500,
+        paused: true,
+        order_counter: 67,
+        category_counter: 8,
+        fee_bps: 150,
+        fee_destination: key(2),
+        escrow_timeout: 86_400,
+        commission_bps: 75,
+        commission_authority: key(3),
+        commission_account: key(4),
+        is_initialized: true,
+    }
+}
+
+fn product() -> Product {
+    let mut metadata_uri = [0; 200];
+    metadata_uri[..27].copy_from_slice(b"https://cidacake.com/bolo/7");
+    let mut tiers = [PriceTier::default(); MAX_PRICE_TIERS];
+    tiers[0] = PriceTier { min_quantity: 10, unit_price: 900_000 };
+    tiers[1] = PriceTier { min_quantity: 50, unit_price: 800_000 };
+    Product {
+        id: 7,
+        name: "Bolo de cenoura".to_string(),
+        description: "Com cobertura de chocolate".to_string(),
+        price: 1_000_000,
+        stock: 42,
+        active: true,
+        available: true,
+        max_per_buyer: 3,
+        category_id: 2,
+        metadata_uri,
+        tier_count: 2,
+        tiers,
+        sol_price: 5_000_000,
+        usd_price_cents: 1_250,
+        price_feed: key(5),
+        allowlist_required: true,
+        gate_mint: key(6),
+        units_sold: 0,
+        revenue: 0,
+        sol_revenue: 0,
+        available_from: 0,
+        available_until: 0,
+        price_decimals: 0,
+    }
+}
+
+fn purchase_history() -> PurchaseHistory {
+    PurchaseHistory {
+        product_id: 7,
+        quantity: 2,
+        total_price: 2_000_000,
+        buyer: key(8),
+        timestamp: 1_700_000_000,
+        status: PURCHASE_STATUS_ESCROWED,
+        fee: 30_000,
+        refunded_quantity: 1,
+        payment_mode: PAYMENT_MODE_TOKEN,
+        rent_payer: key(9),
+        tip: 10_000,
+        donation: 500,
+        unit_price: 1_000_000,
+        name_hash: [10; 32],
+        has_variant: true,
+        variant_id: 3,
+    }
+}
+
+#[test]
+fn test_cake_state_layout() {
+    let mut data = vec![0; CakeState::LEN];
+    CakeState::pack(cake_state(), &mut data).unwrap();
+    check_golden("cake_state", &data);
+    assert_eq!(CakeState::unpack(&data).unwrap(), cake_state());
+}
+
+// Layout atual: discriminador, versão e corpo Borsh
+#[test]
+fn test_product_layout() {
+    let product = Product { units_sold: 9, revenue: 9_000_000, available_from: 1_700_000_000, price_decimals: 6, ..product() };
+    let mut data = vec![0; product.account_len().unwrap()];
+    Product::pack_discriminated(&product, &mut data).unwrap();
+    check_golden("product", &data);
+    assert_eq!(Product::unpack_versioned(&data).unwrap(), product);
+}
+
+// Contas de tamanho fixo criadas antes do discriminador continuam sendo lidas e escritas no lugar
+#[test]
+fn test_product_fixed_layout() {
+    let mut data = vec![0; Product::LEN];
+    Product::pack(product(), &mut data).unwrap();
+    check_golden("product_fixed", &data);
+    assert_eq!(Product::unpack_versioned(&data).unwrap(), product());
+}
+
+#[test]
+fn test_purchase_history_layout() {
+    let mut data = vec![0; PurchaseHistory::LEN];
+    PurchaseHistory::pack(purchase_history(), &mut data).unwrap();
+    check_golden("purchase_history", &data);
+    assert_eq!(PurchaseHistory::unpack_versioned(&data).unwrap(), purchase_history());
+}
+
+// Contas com recibo e sem a variante continuam sendo lidas e escritas no lugar
+#[test]
+fn test_purchase_history_receipt_layout() {
+    let history = PurchaseHistory { has_variant: false, variant_id: 0, ..purchase_history() };
+    let mut data = vec![0; 8 + PurchaseHistory::RECEIPT_BODY_LEN];
+    PurchaseHistory::pack_versioned(history, &mut data).unwrap();
+    check_golden("purchase_history_receipt", &data);
+    assert_eq!(PurchaseHistory::unpack_versioned(&data).unwrap(), history);
+}
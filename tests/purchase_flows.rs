@@ -0,0 +1,306 @@
+// Fluxos de compra de ponta a ponta no processador nativo, como store_revenue: reembolso de uma variante,
+// limite de usos do cupom, liberação do escrow vencido e cobrança de assinatura depois de uma alta de preço
+mod common;
+
+use cidacake_program::{
+    client::{self, SellAccounts, SellOptions, Store},
+    pda::*,
+    process_instruction,
+    state::*,
+    CakeError,
+};
+use common::{account, packed, token_account};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_system_interface::program as system_program;
+
+const PRICE: u64 = 1_000_000;
+
+fn state(owner: Pubkey, escrow_timeout: i64) -> CakeState {
+    CakeState {
+        owner,
+        product_counter: 1,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    }
+}
+
+fn config(payment_mint: Pubkey) -> Config {
+    Config {
+        payment_mint,
+        price_decimals: 6,
+        accepted_mint_count: 0,
+        accepted_mints: [AcceptedMint::default(); MAX_ACCEPTED_MINTS],
+        split_count: 0,
+        splits: [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS],
+        history_retention: 0,
+        referral_bps: 0,
+        tip_account: Pubkey::default(),
+        charity_account: Pubkey::default(),
+        round_up_unit: 0,
+        arbiter: Pubkey::default(),
+        dispute_window: 0,
+    }
+}
+
+fn product() -> Product {
+    Product {
+        id: 0,
+        name: "Bolo de cenoura".to_string(),
+        description: String::new(),
+        price: PRICE,
+        stock: 100,
+        active: true,
+        available: true,
+        max_per_buyer: 0,
+        category_id: NO_CATEGORY,
+        metadata_uri: [0; 200],
+        tier_count: 0,
+        tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+        sol_price: 0,
+        usd_price_cents: 0,
+        price_feed: Pubkey::default(),
+        allowlist_required: false,
+        gate_mint: Pubkey::default(),
+        units_sold: 0,
+        revenue: 0,
+        sol_revenue: 0,
+        available_from: 0,
+        available_until: 0,
+        price_decimals: 0,
+    }
+}
+
+// Loja com um produto, a conta de receita vazia e um comprador com SOL para o rent e saldo no mint de pagamento
+struct Shop {
+    program_id: Pubkey,
+    owner: Keypair,
+    buyer: Keypair,
+    mint: Pubkey,
+    buyer_token: Pubkey,
+    revenue: Pubkey,
+    store: Store,
+}
+
+fn setup(escrow_timeout: i64) -> (Shop, ProgramTest) {
+    let program_id = Pubkey::new_unique();
+    let owner = Keypair::new();
+    let buyer = Keypair::new();
+    let cake = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token = Pubkey::new_unique();
+    let revenue = store_revenue_address(&cake, &mint, &spl_token::id(), &program_id);
+    let store = Store { cake, state: state(owner.pubkey(), escrow_timeout), config: config(mint) };
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(packed(store.state), program_id));
+    program_test.add_account(find_config_address(&cake, &program_id).0, account(packed(store.config), program_id));
+    let product = product();
+    let mut product_data = vec![0; product.account_len().unwrap()];
+    Product::pack_discriminated(&product, &mut product_data).unwrap();
+    program_test.add_account(find_product_address(&cake, 0, &program_id).0, account(product_data, program_id));
+
+    let mint_data = packed(spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    });
+    program_test.add_account(mint, account(mint_data, spl_token::id()));
+    program_test.add_account(buyer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    program_test.add_account(buyer_token, token_account(mint, buyer.pubkey(), 100_000_000));
+    program_test.add_account(revenue, token_account(mint, find_store_revenue_authority_address(&cake, &program_id).0, 0));
+    (Shop { program_id, owner, buyer, mint, buyer_token, revenue, store }, program_test)
+}
+
+impl Shop {
+    fn sell_ix(&self, context: &ProgramTestContext, amount: u64, history_sequence: u64, options: &SellOptions) -> Instruction {
+        let accounts = SellAccounts {
+            buyer: self.buyer.pubkey(),
+            payer: Some(context.payer.pubkey()),
+            buyer_token: self.buyer_token,
+            owner_token: self.revenue,
+            token_program: spl_token::id(),
+            payment_mint: self.mint,
+            gate_token: None,
+            price_feed: None,
+        };
+        client::sell_ix(&self.program_id, &self.store, &product(), amount, history_sequence, &accounts, options)
+    }
+
+    async fn history(&self, context: &mut ProgramTestContext, sequence: u64) -> PurchaseHistory {
+        let address = find_buyer_history_address(&self.store.cake, &self.buyer.pubkey(), sequence, &self.program_id).0;
+        PurchaseHistory::unpack_versioned(&account_data(context, address).await).unwrap()
+    }
+
+    async fn product_stock(&self, context: &mut ProgramTestContext) -> u64 {
+        let address = find_product_address(&self.store.cake, 0, &self.program_id).0;
+        Product::unpack_versioned(&account_data(context, address).await).unwrap().stock
+    }
+}
+
+// Busca um blockhash novo a cada envio: uma transação idêntica a outra que já falhou seria recusada como repetida
+async fn send(context: &mut ProgramTestContext, ix: Instruction, signers: &[&Keypair]) -> Result<(), TransactionError> {
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.map_err(|error| match error {
+        BanksClientError::TransactionError(error) => error,
+        error => panic!("{error}"),
+    })
+}
+
+fn cake_error(error: CakeError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+async fn account_data(context: &mut ProgramTestContext, address: Pubkey) -> Vec<u8> {
+    context.banks_client.get_account(address).await.unwrap().expect("conta").data
+}
+
+async fn token_amount(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    spl_token::state::Account::unpack(&account_data(context, address).await).unwrap().amount
+}
+
+async fn advance_clock(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+#[tokio::test]
+async fn test_variant_refund_restores_variant_stock() {
+    let (shop, mut program_test) = setup(0);
+    let variant_address = find_variant_address(&shop.store.cake, 0, 1, &shop.program_id).0;
+    let variant = Variant { product_id: 0, variant_id: 1, name: [0; 32], price: 500_000, stock: 5 };
+    program_test.add_account(variant_address, account(packed(variant), shop.program_id));
+    let mut context = program_test.start_with_context().await;
+
+    let options = SellOptions { variant_id: Some(1), ..SellOptions::default() };
+    let ix = shop.sell_ix(&context, 2, 0, &options);
+    send(&mut context, ix, &[&shop.buyer]).await.unwrap();
+
+    let history = shop.history(&mut context, 0).await;
+    assert_eq!((history.variant(), history.total_price), (Some(1), 1_000_000));
+    assert_eq!(Variant::unpack(&account_data(&mut context, variant_address).await).unwrap().stock, 3);
+    assert_eq!(shop.product_stock(&mut context).await, 100);
+
+    let ix = client::refund_ix(&shop.program_id, &shop.store, 0, &history, &shop.buyer_token, &spl_token::id(), &shop.mint);
+    send(&mut context, ix, &[&shop.owner]).await.unwrap();
+
+    assert_eq!(shop.history(&mut context, 0).await.status, PURCHASE_STATUS_REFUNDED);
+    assert_eq!(Variant::unpack(&account_data(&mut context, variant_address).await).unwrap().stock, 5);
+    assert_eq!(shop.product_stock(&mut context).await, 100);
+    assert_eq!(token_amount(&mut context, shop.revenue).await, 0);
+    assert_eq!(token_amount(&mut context, shop.buyer_token).await, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_coupon_stops_at_max_uses() {
+    let (shop, mut program_test) = setup(0);
+    let code = b"BOLO10".to_vec();
+    let code_hash = solana_sdk::hash::hash(&code).to_bytes();
+    let coupon = Coupon { code_hash, kind: COUPON_KIND_PERCENT_BPS, value: 1_000, expires_at: 0, max_uses: 1, uses: 0 };
+    let coupon_address = find_coupon_address(&shop.store.cake, &code_hash, &shop.program_id).0;
+    program_test.add_account(coupon_address, account(packed(coupon), shop.program_id));
+    let mut context = program_test.start_with_context().await;
+
+    let options = SellOptions { coupon_code: Some(code), ..SellOptions::default() };
+    let ix = shop.sell_ix(&context, 1, 0, &options);
+    send(&mut context, ix, &[&shop.buyer]).await.unwrap();
+    assert_eq!(Coupon::unpack(&account_data(&mut context, coupon_address).await).unwrap().uses, 1);
+    assert_eq!(token_amount(&mut context, shop.revenue).await, 900_000);
+
+    let ix = shop.sell_ix(&context, 1, 1, &options);
+    assert_eq!(send(&mut context, ix, &[&shop.buyer]).await.unwrap_err(), cake_error(CakeError::CouponExhausted));
+    assert_eq!(Coupon::unpack(&account_data(&mut context, coupon_address).await).unwrap().uses, 1);
+}
+
+#[tokio::test]
+async fn test_expired_escrow_releases_to_store() {
+    let (shop, program_test) = setup(3_600);
+    let mut context = program_test.start_with_context().await;
+
+    let ix = shop.sell_ix(&context, 1, 0, &SellOptions { escrow: true, ..SellOptions::default() });
+    send(&mut context, ix, &[&shop.buyer]).await.unwrap();
+    let history = shop.history(&mut context, 0).await;
+    assert_eq!(history.status, PURCHASE_STATUS_ESCROWED);
+
+    // Qualquer um envia a liberação, sem assinar, mas só depois do prazo
+    let caller = Pubkey::new_unique();
+    let ix =
+        client::release_expired_escrow_ix(&shop.program_id, &shop.store, &caller, 0, &history, &shop.revenue, &spl_token::id(), &shop.mint);
+    assert_eq!(send(&mut context, ix.clone(), &[]).await.unwrap_err(), cake_error(CakeError::EscrowNotExpired));
+
+    advance_clock(&mut context, 3_600).await;
+    send(&mut context, ix, &[]).await.unwrap();
+
+    assert_eq!(shop.history(&mut context, 0).await.status, PURCHASE_STATUS_COMPLETED);
+    assert_eq!(token_amount(&mut context, shop.revenue).await, PRICE);
+    let history_address = find_buyer_history_address(&shop.store.cake, &shop.buyer.pubkey(), 0, &shop.program_id).0;
+    let vault = find_history_vault_address(&history_address, &shop.program_id).0;
+    assert!(context.banks_client.get_account(vault).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_subscription_stops_above_max_unit_price() {
+    let (shop, program_test) = setup(0);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+    let buyer = shop.buyer.pubkey();
+    let interval = 86_400;
+
+    let ix = client::create_subscription_ix(&shop.program_id, &shop.store, &buyer, &shop.buyer_token, 0, 1, interval, PRICE);
+    send(&mut context, ix, &[&shop.buyer]).await.unwrap();
+    let ix = client::approve_subscription_delegate_ix(
+        &shop.program_id,
+        &shop.store,
+        &buyer,
+        0,
+        &shop.buyer_token,
+        &shop.mint,
+        &spl_token::id(),
+        10 * PRICE,
+        6,
+    )
+    .unwrap();
+    send(&mut context, ix, &[&shop.buyer]).await.unwrap();
+
+    let subscription_address = find_subscription_address(&shop.store.cake, &buyer, 0, &shop.program_id).0;
+    let subscription = Subscription::unpack(&account_data(&mut context, subscription_address).await).unwrap();
+    let ix = client::process_subscription_ix(&shop.program_id, &shop.store, &subscription, 0, &payer, &shop.mint, &spl_token::id());
+    send(&mut context, ix, &[]).await.unwrap();
+    assert_eq!(token_amount(&mut context, shop.revenue).await, PRICE);
+
+    // A alta passa do teto aceito na criação: a próxima cobrança falha mesmo com a aprovação ainda cobrindo o valor
+    let ix = client::update_price_ix(&shop.program_id, &shop.store, 0, PRICE + PRICE / 2);
+    send(&mut context, ix, &[&shop.owner]).await.unwrap();
+    advance_clock(&mut context, interval).await;
+    let ix = client::process_subscription_ix(&shop.program_id, &shop.store, &subscription, 1, &payer, &shop.mint, &spl_token::id());
+    assert_eq!(send(&mut context, ix, &[]).await.unwrap_err(), cake_error(CakeError::PriceSlippageExceeded));
+
+    let subscription = Subscription::unpack(&account_data(&mut context, subscription_address).await).unwrap();
+    assert_eq!(subscription.executions, 1);
+    assert_eq!(token_amount(&mut context, shop.revenue).await, PRICE);
+}
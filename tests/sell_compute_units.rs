@@ -0,0 +1,180 @@
+// Teto de compute units do sell no caminho comum (SPL Token, sem extensões), com e sem product_bump. Usa
+// o teto "sell" de tests/compute_units_baseline.txt e mede o binário SBF: roda com a feature test-sbf, que o
+// `cargo test-sbf --features client --test sell_compute_units` liga, e no CI junto com tests/compute_units.rs
+mod common;
+
+use cidacake_program::{instruction::CakeInstruction, pda::*, state::*};
+use common::{account, baseline_budget, packed, require_sbf, token_account};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_option::COption,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_system_interface::program as system_program;
+
+async fn sell_units(product_bump: bool) -> u64 {
+    let program_id = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let buyer = Keypair::new();
+    let cake = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token = Pubkey::new_unique();
+    let revenue_token = store_revenue_address(&cake, &mint, &spl_token::id(), &program_id);
+    let (product_address, bump) = find_product_address(&cake, 0, &program_id);
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, None);
+    program_test.prefer_bpf(true);
+
+    let state = CakeState {
+        owner,
+        product_counter: 1,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout: 0,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    };
+    program_test.add_account(cake, account(packed(state), program_id));
+
+    let config = Config {
+        payment_mint: mint,
+        price_decimals: 6,
+        accepted_mint_count: 0,
+        accepted_mints: [AcceptedMint::default(); MAX_ACCEPTED_MINTS],
+        split_count: 0,
+        splits: [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS],
+        history_retention: 0,
+        referral_bps: 0,
+        tip_account: Pubkey::default(),
+        charity_account: Pubkey::default(),
+        round_up_unit: 0,
+        arbiter: Pubkey::default(),
+        dispute_window: 0,
+    };
+    program_test.add_account(find_config_address(&cake, &program_id).0, account(packed(config), program_id));
+
+    let product = Product {
+        id: 0,
+        name: "Bolo de cenoura".to_string(),
+        description: String::new(),
+        price: 1_000_000,
+        stock: 100,
+        active: true,
+        available: true,
+        max_per_buyer: 0,
+        category_id: NO_CATEGORY,
+        metadata_uri: [0; 200],
+        tier_count: 0,
+        tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+        sol_price: 0,
+        usd_price_cents: 0,
+        price_feed: Pubkey::default(),
+        allowlist_required: false,
+        gate_mint: Pubkey::default(),
+        units_sold: 0,
+        revenue: 0,
+        sol_revenue: 0,
+        available_from: 0,
+        available_until: 0,
+        price_decimals: 0,
+    };
+    let mut product_data = vec![0; product.account_len().unwrap()];
+    Product::pack_discriminated(&product, &mut product_data).unwrap();
+    program_test.add_account(product_address, account(product_data, program_id));
+
+    let mint_data = packed(spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    });
+    program_test.add_account(mint, account(mint_data, spl_token::id()));
+    program_test.add_account(buyer_token, token_account(mint, buyer.pubkey(), 10_000_000));
+    let revenue_authority = find_store_revenue_authority_address(&cake, &program_id).0;
+    program_test.add_account(revenue_token, token_account(mint, revenue_authority, 0));
+
+    let context = program_test.start_with_context().await;
+    let data = CakeInstruction::Sell {
+        product_id: 0,
+        amount: 2,
+        coupon_code: None,
+        variant_id: None,
+        escrow: false,
+        payment_mode: PAYMENT_MODE_TOKEN,
+        create_ata: false,
+        gift_code: None,
+        mint_receipt: false,
+        product_bump: product_bump.then_some(bump),
+        paged_history: false,
+        version: SELL_VERSION,
+        memo: None,
+        referral_code: None,
+        tip: 0,
+        round_up: false,
+        sync_native: false,
+        close_wsol: false,
+        max_total_price: 0,
+    };
+    let ix = Instruction::new_with_bytes(
+        program_id,
+        &data.pack(),
+        vec![
+            AccountMeta::new(cake, false),
+            AccountMeta::new(product_address, false),
+            AccountMeta::new(buyer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(find_buyer_history_address(&cake, &buyer.pubkey(), 0, &program_id).0, false),
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(buyer_token, false),
+            AccountMeta::new(revenue_token, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(find_config_address(&cake, &program_id).0, false),
+            AccountMeta::new(find_buyer_history_counter_address(&cake, &buyer.pubkey(), &program_id).0, false),
+            AccountMeta::new(find_purchase_index_address(&cake, 0, &program_id).0, false),
+            AccountMeta::new_readonly(find_blocklist_address(&cake, &buyer.pubkey(), &program_id).0, false),
+            AccountMeta::new(find_global_stats_address(&program_id).0, false),
+            AccountMeta::new_readonly(find_flash_sale_address(&cake, 0, &program_id).0, false),
+            AccountMeta::new_readonly(find_happy_hour_address(&cake, 0, &program_id).0, false),
+            AccountMeta::new(find_buyer_profile_address(&buyer.pubkey(), &program_id).0, false),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &buyer],
+        context.last_blockhash,
+    );
+
+    let simulation = context.banks_client.simulate_transaction(tx).await.unwrap();
+    simulation.result.unwrap().unwrap();
+    simulation.simulation_details.unwrap().units_consumed
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "test-sbf"), ignore = "mede o binário SBF: cargo test-sbf --features client")]
+async fn test_sell_compute_units() {
+    require_sbf();
+
+    // O mesmo teto do sell no compute_units: o sell precisa caber no limite padrão por instrução mesmo
+    // com as extensões opcionais somadas ao caminho comum
+    let limit = baseline_budget("sell");
+    let with_bump = sell_units(true).await;
+    let without_bump = sell_units(false).await;
+    println!("sell: {} CU com product_bump, {} CU sem", with_bump, without_bump);
+
+    assert!(with_bump <= limit, "sell consumiu {} CU (limite {})", with_bump, limit);
+    assert!(without_bump <= limit, "sell consumiu {} CU (limite {})", without_bump, limit);
+    assert!(with_bump < without_bump);
+}
@@ -0,0 +1,276 @@
+// Owner, autoridade de estoque, comprador e pagador precisam assinar; sem assinatura cada um falha com o
+// próprio erro. Também cobre PDAs já criadas, o rent e a reinicialização do estado no initialize e
+// contas de programa trocadas. Usa o processador nativo, então roda com `cargo test` sem o binário SBF
+use cidacake_program::{instruction::CakeInstruction, pda::*, process_instruction, state::*, CakeError};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_system_interface::program as system_program;
+
+fn account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account { lamports: Rent::default().minimum_balance(data.len()), data, owner, executable: false, rent_epoch: 0 }
+}
+
+fn cake_state(owner: Pubkey) -> Vec<u8> {
+    let state = CakeState {
+        owner,
+        product_counter: 1,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout: 0,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    };
+    let mut data = vec![0; CakeState::LEN];
+    CakeState::pack(state, &mut data).unwrap();
+    data
+}
+
+// Executa a instrução assinada só pelo payer e pelos `signers` e devolve o erro da transação
+async fn run(program_test: ProgramTest, ix: Instruction, signers: &[&Keypair]) -> TransactionError {
+    let context = program_test.start_with_context().await;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap_err().unwrap()
+}
+
+fn custom(error: CakeError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+// owner acompanha se assina a instrução
+fn initialize_ix(program_id: Pubkey, cake: Pubkey, owner: (Pubkey, bool), payer: Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        program_id,
+        &CakeInstruction::Initialize.pack(),
+        vec![
+            AccountMeta::new(cake, false),
+            AccountMeta::new_readonly(owner.0, owner.1),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// owner e payer acompanham se assinam a instrução
+fn add_product_ix(program_id: Pubkey, cake: Pubkey, owner: (Pubkey, bool), payer: (Pubkey, bool)) -> Instruction {
+    let data = CakeInstruction::AddProduct {
+        name: "Bolo de Cenoura".to_string(),
+        description: String::new(),
+        price: 1_000_000,
+        stock: 10,
+        max_per_buyer: 0,
+        tags: Vec::new(),
+    };
+    Instruction::new_with_bytes(
+        program_id,
+        &data.pack(),
+        vec![
+            AccountMeta::new(cake, false),
+            AccountMeta::new(find_product_address(&cake, 1, &program_id).0, false),
+            AccountMeta::new_readonly(owner.0, owner.1),
+            AccountMeta::new(payer.0, payer.1),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn test_initialize_requires_owner_signature() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(vec![0; CakeState::LEN], program_id));
+
+    let payer = Keypair::new();
+    program_test.add_account(payer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    let ix = initialize_ix(program_id, cake, (owner, false), payer.pubkey());
+
+    assert_eq!(run(program_test, ix, &[&payer]).await, custom(CakeError::OwnerSignatureRequired));
+}
+
+#[tokio::test]
+async fn test_initialize_rejects_initialized_state() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let attacker = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(Pubkey::new_unique()), program_id));
+
+    let payer = Keypair::new();
+    program_test.add_account(payer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    let ix = initialize_ix(program_id, cake, (attacker.pubkey(), true), payer.pubkey());
+
+    assert_eq!(run(program_test, ix, &[&attacker, &payer]).await, custom(CakeError::AlreadyInitialized));
+}
+
+#[tokio::test]
+async fn test_add_product_requires_owner_signature() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(owner), program_id));
+
+    let payer = Keypair::new();
+    program_test.add_account(payer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    let ix = add_product_ix(program_id, cake, (owner, false), (payer.pubkey(), true));
+
+    assert_eq!(run(program_test, ix, &[&payer]).await, custom(CakeError::OwnerSignatureRequired));
+}
+
+#[tokio::test]
+async fn test_initialize_requires_rent_exemption() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    let mut cake_account = account(vec![0; CakeState::LEN], program_id);
+    cake_account.lamports = 1;
+    program_test.add_account(cake, cake_account);
+
+    let payer = Keypair::new();
+    program_test.add_account(payer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    let ix = initialize_ix(program_id, cake, (owner.pubkey(), true), payer.pubkey());
+
+    assert_eq!(run(program_test, ix, &[&owner, &payer]).await, custom(CakeError::NotRentExempt));
+}
+
+#[tokio::test]
+async fn test_add_product_requires_payer_signature() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(owner.pubkey()), program_id));
+
+    let ix = add_product_ix(program_id, cake, (owner.pubkey(), true), (Pubkey::new_unique(), false));
+    assert_eq!(run(program_test, ix, &[&owner]).await, custom(CakeError::PayerSignatureRequired));
+}
+
+#[tokio::test]
+async fn test_add_product_rejects_initialized_product() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(owner.pubkey()), program_id));
+    program_test.add_account(find_product_address(&cake, 1, &program_id).0, account(vec![1; 64], program_id));
+
+    let payer = Keypair::new();
+    program_test.add_account(payer.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    let ix = add_product_ix(program_id, cake, (owner.pubkey(), true), (payer.pubkey(), true));
+    assert_eq!(run(program_test, ix, &[&owner, &payer]).await, custom(CakeError::AccountAlreadyInitialized));
+}
+
+#[tokio::test]
+async fn test_restock_requires_authority_signature() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let product = find_product_address(&cake, 0, &program_id).0;
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(owner), program_id));
+    program_test.add_account(product, account(vec![0; 8], program_id));
+
+    let ix = Instruction::new_with_bytes(
+        program_id,
+        &CakeInstruction::Restock { product_id: 0, amount: 5, variant_id: None }.pack(),
+        vec![AccountMeta::new_readonly(cake, false), AccountMeta::new(product, false), AccountMeta::new_readonly(owner, false)],
+    );
+
+    assert_eq!(run(program_test, ix, &[]).await, custom(CakeError::AuthoritySignatureRequired));
+}
+
+// Sell com pagamento em token; as contas que não são lidas antes da falha podem ser quaisquer
+fn sell_ix(program_id: Pubkey, cake: Pubkey, payer: Pubkey, buyer: (Pubkey, bool), token_program: Pubkey) -> Instruction {
+    let data = CakeInstruction::Sell {
+        product_id: 0,
+        amount: 1,
+        coupon_code: None,
+        variant_id: None,
+        escrow: false,
+        payment_mode: PAYMENT_MODE_TOKEN,
+        create_ata: false,
+        gift_code: None,
+        mint_receipt: false,
+        product_bump: None,
+        paged_history: false,
+        version: SELL_VERSION,
+        memo: None,
+        referral_code: None,
+        tip: 0,
+        round_up: false,
+        sync_native: false,
+        close_wsol: false,
+        max_total_price: 0,
+    };
+    Instruction::new_with_bytes(
+        program_id,
+        &data.pack(),
+        vec![
+            AccountMeta::new(cake, false),
+            AccountMeta::new(find_product_address(&cake, 0, &program_id).0, false),
+            AccountMeta::new(buyer.0, buyer.1),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(find_buyer_history_address(&cake, &buyer.0, 0, &program_id).0, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(find_config_address(&cake, &program_id).0, false),
+            AccountMeta::new(find_buyer_history_counter_address(&cake, &buyer.0, &program_id).0, false),
+            AccountMeta::new(find_purchase_index_address(&cake, 0, &program_id).0, false),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn test_sell_requires_buyer_signature() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let payer = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(Pubkey::new_unique()), program_id));
+
+    let ix = sell_ix(program_id, cake, payer.pubkey(), (Pubkey::new_unique(), false), spl_token::id());
+    assert_eq!(run(program_test, ix, &[&payer]).await, custom(CakeError::BuyerSignatureRequired));
+}
+
+#[tokio::test]
+async fn test_sell_rejects_unknown_token_program() {
+    let program_id = Pubkey::new_unique();
+    let cake = Pubkey::new_unique();
+    let payer = Keypair::new();
+
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    program_test.add_account(cake, account(cake_state(Pubkey::new_unique()), program_id));
+
+    let ix = sell_ix(program_id, cake, payer.pubkey(), (Pubkey::new_unique(), false), Pubkey::new_unique());
+    assert_eq!(run(program_test, ix, &[&payer]).await, custom(CakeError::IncorrectProgramId));
+}
@@ -0,0 +1,159 @@
+// A conta de receita da loja é a ATA da PDA ["store_revenue", loja]: initialize e create_store a criam, e o
+// owner saca dela também numa loja legada. Usa o processador nativo, como signer_checks
+mod common;
+
+use cidacake_program::{
+    client::{self, Store},
+    pda::*,
+    process_instruction,
+    state::*,
+    CakeError,
+};
+use common::{account, packed, token_account};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+fn state(owner: Pubkey) -> CakeState {
+    CakeState {
+        owner,
+        product_counter: 0,
+        history_counter: 0,
+        max_price_change_bps: 0,
+        paused: false,
+        order_counter: 0,
+        category_counter: 0,
+        fee_bps: 0,
+        fee_destination: Pubkey::default(),
+        escrow_timeout: 0,
+        commission_bps: 0,
+        commission_authority: Pubkey::default(),
+        commission_account: Pubkey::default(),
+        is_initialized: true,
+    }
+}
+
+fn config(payment_mint: Pubkey) -> Config {
+    Config {
+        payment_mint,
+        price_decimals: 6,
+        accepted_mint_count: 0,
+        accepted_mints: [AcceptedMint::default(); 4],
+        split_count: 0,
+        splits: [SplitRecipient::default(); 4],
+        history_retention: 0,
+        referral_bps: 0,
+        tip_account: Pubkey::default(),
+        charity_account: Pubkey::default(),
+        round_up_unit: 0,
+        arbiter: Pubkey::default(),
+        dispute_window: 0,
+    }
+}
+
+struct Setup {
+    program_id: Pubkey,
+    mint: Pubkey,
+    owner: Keypair,
+    program_test: ProgramTest,
+}
+
+fn setup() -> Setup {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("cidacake_program", program_id, processor!(process_instruction));
+    let mint_data = packed(spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    });
+    program_test.add_account(mint, account(mint_data, spl_token::id()));
+    Setup { program_id, mint, owner: Keypair::new(), program_test }
+}
+
+async fn send(context: &mut ProgramTestContext, ix: Instruction, signers: &[&Keypair]) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.map_err(|error| match error {
+        BanksClientError::TransactionError(error) => error,
+        error => panic!("{error}"),
+    })
+}
+
+async fn token_data(context: &mut ProgramTestContext, address: Pubkey) -> spl_token::state::Account {
+    let account = context.banks_client.get_account(address).await.unwrap().expect("conta de token");
+    spl_token::state::Account::unpack(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_create_store_creates_revenue_account() {
+    let Setup { program_id, mint, owner, program_test } = setup();
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let ix = client::create_store_ix(&program_id, 7, &owner.pubkey(), &payer, &mint, &spl_token::id());
+    send(&mut context, ix, &[&owner]).await.unwrap();
+
+    let store = find_store_address(7, &program_id).0;
+    let revenue = token_data(&mut context, store_revenue_address(&store, &mint, &spl_token::id(), &program_id)).await;
+    assert_eq!((revenue.mint, revenue.owner), (mint, find_store_revenue_authority_address(&store, &program_id).0));
+}
+
+#[tokio::test]
+async fn test_initialize_creates_revenue_account() {
+    let Setup { program_id, mint, owner, mut program_test } = setup();
+    let cake = Pubkey::new_unique();
+    program_test.add_account(cake, account(vec![0; CakeState::LEN], program_id));
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let ix = client::initialize_ix(&program_id, &cake, &owner.pubkey(), &payer, &mint, &spl_token::id());
+    send(&mut context, ix, &[&owner]).await.unwrap();
+
+    let revenue = token_data(&mut context, store_revenue_address(&cake, &mint, &spl_token::id(), &program_id)).await;
+    assert_eq!(revenue.owner, find_store_revenue_authority_address(&cake, &program_id).0);
+}
+
+#[tokio::test]
+async fn test_legacy_store_withdraws_revenue() {
+    let Setup { program_id, mint, owner, mut program_test } = setup();
+    let cake = Pubkey::new_unique();
+    let revenue = store_revenue_address(&cake, &mint, &spl_token::id(), &program_id);
+    let destination = Pubkey::new_unique();
+    program_test.add_account(cake, account(packed(state(owner.pubkey())), program_id));
+    program_test.add_account(revenue, token_account(mint, find_store_revenue_authority_address(&cake, &program_id).0, 1_000));
+    program_test.add_account(destination, token_account(mint, owner.pubkey(), 0));
+    let mut context = program_test.start_with_context().await;
+
+    let store = Store { cake, state: state(owner.pubkey()), config: config(mint) };
+    let ix = client::withdraw_store_revenue_ix(&program_id, &store, &destination, &mint, &spl_token::id(), 400);
+    send(&mut context, ix, &[&owner]).await.unwrap();
+
+    assert_eq!(token_data(&mut context, revenue).await.amount, 600);
+    assert_eq!(token_data(&mut context, destination).await.amount, 400);
+}
+
+#[tokio::test]
+async fn test_withdraw_rejects_other_authority() {
+    let Setup { program_id, mint, owner, mut program_test } = setup();
+    let cake = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    program_test.add_account(cake, account(packed(state(owner.pubkey())), program_id));
+    program_test.add_account(destination, token_account(mint, owner.pubkey(), 0));
+    let mut context = program_test.start_with_context().await;
+
+    let store = Store { cake, state: state(owner.pubkey()), config: config(mint) };
+    let mut ix = client::withdraw_store_revenue_ix(&program_id, &store, &destination, &mint, &spl_token::id(), 400);
+    ix.accounts[3].pubkey = Pubkey::new_unique();
+    let error = send(&mut context, ix, &[&owner]).await.unwrap_err();
+    assert_eq!(error, TransactionError::InstructionError(0, InstructionError::Custom(CakeError::WrongPda as u32)));
+}